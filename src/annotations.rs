@@ -0,0 +1,91 @@
+//! Annotations attached to games
+//!
+//! Coaching and review work marks stretches of a game ("missed punish", "good edgeguard") to come
+//! back to or share. Annotations are kept in a `<hash>.annotations.json` file next to the game's
+//! Arrow file, where `<hash>` is the SHA-256 of the source replay rather than of the converted
+//! artifact, so they're found again whatever options the replay is next converted with, and from
+//! any copy of it. Sharing a game's annotations is copying that file into another output directory.
+//!
+//! Annotation files aren't deleted along with the Arrow files next to them.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{atomic, lock};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    /// First and last frame index (as stored in the replay) annotated, inclusive
+    pub start: i32,
+    pub end: i32,
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Annotations {
+    /// SHA-256 of the replay annotated
+    game: String,
+    annotations: Vec<Annotation>,
+}
+
+/// Path of the annotations of the replay with SHA-256 `game`, for Arrow files in `dir`.
+pub fn path_for(dir: &Path, game: &str) -> PathBuf {
+    dir.join(format!("{game}.annotations.json"))
+}
+
+/// Annotations in the file at `path`, in the order they were added (none if there's no file).
+pub fn read(path: &Path) -> io::Result<Vec<Annotation>> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let annotations: Annotations = serde_json::from_slice(&data)?;
+    Ok(annotations.annotations)
+}
+
+/// Read, change, and rewrite the annotations of `game` at `path` while holding its lock, so
+/// processes annotating the same game don't lose each other's changes.
+fn update<T>(path: &Path, game: &str, f: impl FnOnce(&mut Vec<Annotation>) -> T) -> io::Result<T> {
+    let _lock = lock::FileLock::exclusive(path)?;
+    let mut annotations = read(path)?;
+    let result = f(&mut annotations);
+    let annotations = Annotations {
+        game: game.to_string(),
+        annotations,
+    };
+    atomic::write_json(path, &annotations, true)?;
+    Ok(result)
+}
+
+/// Add an annotation of `game` to the file at `path`, returning how many the game has.
+pub fn add(path: &Path, game: &str, annotation: Annotation) -> io::Result<usize> {
+    if annotation.start > annotation.end {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("annotation ends (frame {}) before it starts (frame {})", annotation.end, annotation.start),
+        ));
+    }
+    update(path, game, |annotations| {
+        annotations.push(annotation);
+        annotations.len()
+    })
+}
+
+/// Remove the annotations of `game` with the given label (all of them when `None`) from the file
+/// at `path`, returning how many were removed.
+pub fn remove(path: &Path, game: &str, label: Option<&str>) -> io::Result<usize> {
+    if !path.exists() {
+        return Ok(0);
+    }
+    update(path, game, |annotations| {
+        let before = annotations.len();
+        annotations.retain(|a| label.is_some_and(|l| a.label != l));
+        before - annotations.len()
+    })
+}
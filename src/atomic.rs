@@ -0,0 +1,55 @@
+//! Atomic file writes
+//!
+//! Every file this library produces is written to a temporary name in the destination directory
+//! and renamed into place once complete. Readers (including other Julia processes) therefore only
+//! ever see either the previous file or the finished new one, never a half-written one left by a
+//! crash or a concurrent writer.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::json;
+
+/// Temporary sibling of `path`, unique within and across processes.
+fn temp_path(path: &Path) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{name}.{}.{n}.tmp", process::id()))
+}
+
+/// Create `path` by writing to a temporary file with `f` and renaming it into place on success.
+pub fn write<E: From<io::Error>>(
+    path: &Path,
+    f: impl FnOnce(&mut fs::File) -> Result<(), E>,
+) -> Result<(), E> {
+    let tmp = temp_path(path);
+    let result = (|| {
+        let mut file = fs::File::create(&tmp)?;
+        f(&mut file)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Atomic counterpart of [fs::write].
+pub fn write_bytes(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    write(path, |file| file.write_all(bytes))
+}
+
+/// Atomically write `value` as [canonical](crate::json) JSON, pretty-printed or not.
+pub fn write_json<T: serde::Serialize + ?Sized>(path: &Path, value: &T, pretty: bool) -> io::Result<()> {
+    write(path, |file| {
+        let mut w = io::BufWriter::new(file);
+        json::to_writer(&mut w, value, pretty)?;
+        w.flush()
+    })
+}
@@ -0,0 +1,129 @@
+//! Reading a directory of replays in one call
+//!
+//! Tournament organizers process thousands of replays at once, and looping over `read_slippi` in
+//! Julia pays for a round trip per file. A batch finds the replays in a directory, reads them all,
+//! and holds the resulting games until Julia takes them, one handle at a time, by the batch's ID.
+//! Games left untaken are dropped (and their Arrow files released) when the batch is freed.
+//!
+//! A batch read tolerantly also keeps the files it skipped, in the same table batch jobs write to
+//! their errors files, so a corpus with a few broken replays is still read end to end.
+//!
+//! Replays are independent, so they're parsed and converted on a pool of threads (as many as
+//! configured for batch work). The work never touches the Julia runtime; only handing the games
+//! back does, which keeps Julia's garbage collector out of the way of the parsers.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+
+use crate::Game;
+use crate::errors::FileError;
+
+struct Batch {
+    /// Games in path order; taken games leave a `None` behind.
+    games: Vec<Option<Game>>,
+    /// Files skipped in tolerant reads
+    errors: Vec<FileError>,
+}
+
+fn batches() -> &'static Mutex<HashMap<u64, Batch>> {
+    static BATCHES: OnceLock<Mutex<HashMap<u64, Batch>>> = OnceLock::new();
+    BATCHES.get_or_init(Default::default)
+}
+
+/// Whether the file name `name` matches `pattern`, where `*` matches any run of characters and `?`
+/// any single one.
+fn matches(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.split_first(), name.split_first()) {
+        (None, _) => name.is_empty(),
+        (Some(('*', rest)), _) => (0..=name.len()).any(|i| matches(rest, &name[i..])),
+        (Some((p, rest)), Some((c, name))) => (*p == '?' || p == c) && matches(rest, name),
+        (Some(_), None) => false,
+    }
+}
+
+/// Paths of the files in `dir` (and below it, if `recursive`) whose names match `pattern`, sorted.
+pub fn paths(dir: &Path, recursive: bool, pattern: &str) -> io::Result<Vec<PathBuf>> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let mut paths = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                if recursive {
+                    dirs.push(path);
+                }
+                continue;
+            }
+            let name: Vec<char> = path.file_name().unwrap_or_default().to_string_lossy().chars().collect();
+            if matches(&pattern, &name) {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Apply `f` to every item on `threads` threads, returning the results in the order of the items.
+pub fn map<I: Sync, T: Send>(items: &[I], threads: usize, f: impl Fn(&I) -> T + Sync) -> Vec<T> {
+    let next = AtomicUsize::new(0);
+    let work = || {
+        let mut done = Vec::new();
+        loop {
+            let i = next.fetch_add(1, Ordering::Relaxed);
+            let Some(item) = items.get(i) else {
+                return done;
+            };
+            done.push((i, f(item)));
+        }
+    };
+    let mut results: Vec<(usize, T)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.clamp(1, items.len().max(1)))
+            .map(|_| scope.spawn(work))
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+            .collect()
+    });
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Hold `games` until they're taken, along with the `errors` of the files skipped, returning the
+/// batch's ID.
+pub fn insert(games: Vec<Game>, errors: Vec<FileError>) -> u64 {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let games = games.into_iter().map(Some).collect();
+    batches().lock().unwrap().insert(id, Batch { games, errors });
+    id
+}
+
+/// Number of games read into a batch, taken or not.
+pub fn len(id: u64) -> Option<usize> {
+    batches().lock().unwrap().get(&id).map(|b| b.games.len())
+}
+
+/// Files skipped when reading a batch.
+pub fn errors(id: u64) -> Option<Vec<FileError>> {
+    batches().lock().unwrap().get(&id).map(|b| b.errors.clone())
+}
+
+/// Take game `i` (0-based) of a batch, if it exists and hasn't been taken yet.
+pub fn take(id: u64, i: usize) -> Option<Game> {
+    batches().lock().unwrap().get_mut(&id)?.games.get_mut(i)?.take()
+}
+
+/// Drop a batch and any games still in it, returning whether it existed.
+pub fn free(id: u64) -> bool {
+    // Dropping games may delete their Arrow files, so it's done outside the lock.
+    let games = batches().lock().unwrap().remove(&id);
+    games.is_some()
+}
@@ -0,0 +1,37 @@
+//! Parse worker for sandboxed reads
+//!
+//! Parses one replay in a process of its own, so a crash or runaway allocation on a pathological
+//! file takes down only this process. The library starts it (see `set_sandbox`) and speaks a
+//! minimal protocol over its standard streams:
+//!
+//! - stdin: one line of JSON, `{"len": <bytes>, "skip_frames": <bool>}`, then the replay's bytes
+//! - stdout: one line of JSON, `{"ok": true}` or `{"ok": false, "error": "<message>"}`
+//!
+//! Anything else (no answer, or a nonzero exit) means the parse crashed.
+
+use std::io::{self, BufRead, Read, Write};
+
+use peppi::io::slippi::de::Opts as SlippiReadOpts;
+use serde_json::{Value, json};
+
+fn main() -> io::Result<()> {
+    let mut stdin = io::stdin().lock();
+    let mut header = String::new();
+    stdin.read_line(&mut header)?;
+    let header: Value = serde_json::from_str(&header)?;
+    let len = header["len"].as_u64().unwrap_or(0);
+    let opts = SlippiReadOpts {
+        skip_frames: header["skip_frames"].as_bool().unwrap_or(false),
+        ..Default::default()
+    };
+
+    let mut data = Vec::new();
+    stdin.take(len).read_to_end(&mut data)?;
+    let response = match peppi::io::slippi::read(&mut io::Cursor::new(&data), Some(&opts)) {
+        Ok(_) => json!({ "ok": true }),
+        Err(e) => json!({ "ok": false, "error": e.to_string() }),
+    };
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{response}")?;
+    stdout.flush()
+}
@@ -0,0 +1,501 @@
+//! Replay catalog (index) of a directory of `.slp` files
+//!
+//! The catalog is a JSON file holding one [Entry] per replay, built from each game's start block and
+//! metadata without parsing frames. Analytics over the catalog, like time bucketing, run natively
+//! so dashboards don't need to load the whole catalog into Julia.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::{fs, io};
+
+use peppi::game::Port;
+use peppi::game::immutable::Game as SlippiGame;
+use peppi::io::slippi::de::Opts as SlippiReadOpts;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::atomic;
+use crate::clock;
+use crate::dataset::to_hex;
+use crate::errors::{self, FileError};
+use crate::ids::{Character, Stage};
+use crate::metrics;
+use crate::outcome;
+use crate::skill;
+
+/// A single row of the catalog.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub path: String,
+    /// `startAt` from the metadata, as an ISO-8601 string
+    pub start_time: Option<String>,
+    /// `lastFrame` from the metadata
+    pub last_frame: Option<i64>,
+    pub stage: u16,
+    pub stage_name: Stage,
+    pub console_nick: Option<String>,
+    pub played_on: Option<String>,
+    pub players: Vec<EntryPlayer>,
+    /// Per-game seed for reproducible augmentation and shuffling, from the file's SHA-256 (0 in
+    /// catalogs built before it was recorded)
+    #[serde(default)]
+    pub seed: u64,
+    /// Start time corrected for its setup's clock drift, set by [clock::apply](crate::clock::apply)
+    #[serde(default)]
+    pub corrected_start_time: Option<String>,
+    /// Hex SHA-256 of the file, as a game's `get_source_hash` (empty in catalogs built before it
+    /// was recorded)
+    #[serde(default)]
+    pub hash: String,
+    /// Curation tags, set by [tags::add](crate::tags::add)
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// A player within a catalog [Entry].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntryPlayer {
+    pub port: Port,
+    /// External character ID
+    pub character: u8,
+    pub character_name: Character,
+    pub name: Option<String>,
+    pub code: Option<String>,
+    /// Rating from the ratings file, set by [skill::annotate](crate::skill::annotate)
+    #[serde(default)]
+    pub rating: Option<f64>,
+    /// Skill bracket matching the rating
+    #[serde(default)]
+    pub bracket: Option<String>,
+    /// Canonical player ID, set by [identity::resolve](crate::identity::resolve)
+    #[serde(default)]
+    pub player_id: Option<String>,
+    /// Placement from 1, recorded or inferred (see [outcome](crate::outcome))
+    #[serde(default)]
+    pub placement: Option<usize>,
+    /// Whether the placement was recorded rather than inferred
+    #[serde(default)]
+    pub placement_recorded: bool,
+}
+
+impl EntryPlayer {
+    /// Key identifying the player across games: their resolved player ID, else their connect code,
+    /// else their display name.
+    pub fn key(&self) -> Option<&str> {
+        self.player_id.as_deref().or(self.code.as_deref()).or(self.name.as_deref())
+    }
+}
+
+/// Conditions on catalog entries; every condition that's set must hold.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Filter {
+    /// Any player is one of these external character IDs
+    pub characters: Option<Vec<u8>>,
+    pub stages: Option<Vec<u16>>,
+    /// Any player has one of these player IDs, connect codes, or display names
+    pub players: Option<Vec<String>>,
+    /// Every player is rated, with the lowest bracket among them one of these (e.g. `["Master"]`)
+    pub brackets: Option<Vec<String>>,
+    /// The game has one of these tags
+    pub tags: Option<Vec<String>>,
+    /// The game started at or after this time (e.g. `2023-04-01T18:00:00Z`, or `2023-04-01` for
+    /// the start of the day), corrected for clock drift when it has been
+    pub since: Option<Time>,
+    /// The game started before this time
+    pub until: Option<Time>,
+}
+
+/// A time bounding a [Filter]'s period: an ISO-8601 timestamp, or a date alone for midnight UTC.
+/// Times are checked as filters are read, so a saved filter always has valid ones.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Time {
+    text: String,
+    /// Seconds since the Unix epoch
+    seconds: f64,
+}
+
+impl TryFrom<String> for Time {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        let seconds = match text.len() {
+            10 => clock::parse(&format!("{text}T00:00:00Z")),
+            _ => clock::parse(&text),
+        };
+        match seconds {
+            Some(seconds) => Ok(Time { text, seconds }),
+            None => Err(format!("invalid time: {text}")),
+        }
+    }
+}
+
+impl From<Time> for String {
+    fn from(time: Time) -> Self {
+        time.text
+    }
+}
+
+impl Filter {
+    pub fn matches(&self, entry: &Entry) -> bool {
+        let any_player = |f: &dyn Fn(&EntryPlayer) -> bool| entry.players.iter().any(f);
+        self.characters.as_ref().is_none_or(|cs| any_player(&|p| cs.contains(&p.character)))
+            && self.stages.as_ref().is_none_or(|ss| ss.contains(&entry.stage))
+            && self.players.as_ref().is_none_or(|ks| {
+                any_player(&|p| {
+                    [p.player_id.as_deref(), p.code.as_deref(), p.name.as_deref()]
+                        .into_iter()
+                        .flatten()
+                        .any(|k| ks.iter().any(|wanted| wanted == k))
+                })
+            })
+            && self.brackets.as_ref().is_none_or(|bs| {
+                skill::game_bracket(entry).is_some_and(|b| bs.iter().any(|wanted| wanted == b))
+            })
+            && self.tags.as_ref().is_none_or(|ts| entry.tags.iter().any(|t| ts.contains(t)))
+            && self.in_period(entry)
+    }
+
+    /// Whether `entry` started within `since` and `until`. Games without a start time only match
+    /// when neither is set.
+    fn in_period(&self, entry: &Entry) -> bool {
+        if self.since.is_none() && self.until.is_none() {
+            return true;
+        }
+        let start_time = entry.corrected_start_time.as_deref().or(entry.start_time.as_deref());
+        let Some(start) = start_time.and_then(clock::parse) else {
+            return false;
+        };
+        self.since.as_ref().is_none_or(|since| start >= since.seconds)
+            && self.until.as_ref().is_none_or(|until| start < until.seconds)
+    }
+}
+
+/// Length of the buckets computed by [buckets].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Day,
+    /// Monday through Sunday
+    Week,
+    Month,
+}
+
+impl FromStr for Period {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "day" => Ok(Period::Day),
+            "week" => Ok(Period::Week),
+            "month" => Ok(Period::Month),
+            _ => Err(format!("unknown period: {s} (expected day, week, or month)")),
+        }
+    }
+}
+
+/// Per-bucket activity computed by [buckets].
+#[derive(Debug, Serialize)]
+pub struct Bucket {
+    /// `YYYY-MM-DD` for days, the Monday of the week for weeks, or `YYYY-MM` for months
+    pub bucket: String,
+    pub games: usize,
+    /// Games played per player, keyed by [EntryPlayer::key]
+    pub players: BTreeMap<String, usize>,
+}
+
+/// Collect the `.slp` files below `dir`, sorted by path.
+pub fn replay_paths(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            paths.extend(replay_paths(&path)?);
+        } else if path.extension().is_some_and(|e| e == "slp") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Build catalog entries for every replay below `dir`, skipping (and reporting) files that fail to
+/// parse.
+pub fn build(dir: &Path) -> io::Result<(Vec<Entry>, Vec<FileError>)> {
+    let opts = SlippiReadOpts {
+        skip_frames: true,
+        ..Default::default()
+    };
+    let mut entries = Vec::new();
+    let mut failed = Vec::new();
+    for path in replay_paths(dir)? {
+        match errors::read_replay(&path, Some(&opts), false) {
+            Ok((data, game)) => entries.push(entry(&path, &game, &data)),
+            Err(e) => {
+                metrics::record_error();
+                failed.push(e);
+            }
+        }
+    }
+    Ok((entries, failed))
+}
+
+/// Seed derived from the first 8 bytes of the SHA-256 `digest` of a replay's contents, so it's the
+/// same for copies of the same replay wherever they're stored.
+pub fn seed(digest: &[u8]) -> u64 {
+    u64::from_le_bytes(digest[..8].try_into().unwrap())
+}
+
+fn entry(path: &Path, game: &SlippiGame, data: &[u8]) -> Entry {
+    let digest = Sha256::digest(data);
+    let metadata = game.metadata.as_ref();
+    let metadata_str = |key: &str| metadata?.get(key)?.as_str().map(str::to_string);
+    Entry {
+        path: path.to_string_lossy().into_owned(),
+        start_time: metadata_str("startAt"),
+        last_frame: metadata.and_then(|m| m.get("lastFrame")).and_then(|v| v.as_i64()),
+        stage: game.start.stage,
+        stage_name: Stage::from_id(game.start.stage),
+        console_nick: metadata_str("consoleNick"),
+        played_on: metadata_str("playedOn"),
+        players: players(game),
+        seed: seed(&digest),
+        corrected_start_time: None,
+        hash: to_hex(&digest),
+        tags: Vec::new(),
+    }
+}
+
+/// The players of a game, named from the start block or the metadata, with their placements.
+pub fn players(game: &SlippiGame) -> Vec<EntryPlayer> {
+    let metadata = game.metadata.as_ref();
+    // Without frames, as when building a catalog, only recorded placements are known.
+    let outcome = (!game.frames.id.is_empty()).then(|| outcome::decide(game.end.as_ref(), &game.frames));
+    let recorded = game.end.as_ref().and_then(|e| e.players.as_ref());
+    game.start
+        .players
+        .iter()
+        .map(|p| {
+            // Names were only added to the start block in 3.9, so fall back to the metadata.
+            let names = metadata
+                .and_then(|m| m.get("players"))
+                .and_then(|ps| ps.get((p.port as u8).to_string()))
+                .and_then(|m| m.get("names"));
+            let name_str = |key: &str| names?.get(key)?.as_str().map(str::to_string);
+            let standing = outcome.iter().flat_map(|o| &o.players).find(|s| s.port == p.port);
+            let recorded = recorded.and_then(|r| r.iter().find(|r| r.port == p.port));
+            EntryPlayer {
+                port: p.port,
+                character: p.character,
+                character_name: Character::from_id(p.character),
+                name: p.netplay.as_ref().map(|n| n.name.clone()).or_else(|| name_str("netplay")),
+                code: p.netplay.as_ref().map(|n| n.code.clone()).or_else(|| name_str("code")),
+                rating: None,
+                bracket: None,
+                player_id: None,
+                placement: standing
+                    .map(|s| s.placement)
+                    .or(recorded.map(|r| r.placement as usize + 1)),
+                placement_recorded: recorded.is_some(),
+            }
+        })
+        .collect()
+}
+
+/// Carry what was added to the `previous` catalog of the same replays since it was built (tags,
+/// corrected start times, and players' IDs, ratings, and inferred placements) over to the freshly
+/// built `entries`, matching games by hash (or by path, for entries built before hashes were
+/// recorded) and players by port. Returns the number of entries carried over to.
+pub fn carry_over(entries: &mut [Entry], previous: &[Entry]) -> usize {
+    let mut by_hash = BTreeMap::new();
+    let mut by_path = BTreeMap::new();
+    for old in previous {
+        match old.hash.as_str() {
+            "" => by_path.insert(old.path.as_str(), old),
+            hash => by_hash.insert(hash, old),
+        };
+    }
+    let mut carried = 0;
+    for entry in entries {
+        let old = by_hash.get(entry.hash.as_str()).or_else(|| by_path.get(entry.path.as_str()));
+        let Some(old) = old else {
+            continue;
+        };
+        entry.tags = old.tags.clone();
+        entry.corrected_start_time = old.corrected_start_time.clone();
+        for player in &mut entry.players {
+            let Some(old) = old.players.iter().find(|p| p.port == player.port) else {
+                continue;
+            };
+            player.player_id = old.player_id.clone();
+            player.rating = old.rating;
+            player.bracket = old.bracket.clone();
+            if !player.placement_recorded {
+                player.placement = old.placement;
+            }
+        }
+        carried += 1;
+    }
+    carried
+}
+
+pub fn write(entries: &[Entry], path: &Path) -> io::Result<()> {
+    atomic::write_json(path, entries, false)
+}
+
+pub fn read(path: &Path) -> io::Result<Vec<Entry>> {
+    let file = io::BufReader::new(fs::File::open(path)?);
+    Ok(serde_json::from_reader(file)?)
+}
+
+/// Count games and per-player activity per `period` ("day", "week", or "month").
+///
+/// Entries without a parseable start time are left out.
+pub fn buckets(entries: &[Entry], period: Period) -> Vec<Bucket> {
+    let mut buckets: BTreeMap<String, Bucket> = BTreeMap::new();
+    for entry in entries {
+        let Some(label) = entry.start_time.as_deref().and_then(|t| bucket_label(t, period)) else {
+            continue;
+        };
+        let bucket = buckets.entry(label.clone()).or_insert_with(|| Bucket {
+            bucket: label,
+            games: 0,
+            players: BTreeMap::new(),
+        });
+        bucket.games += 1;
+        for key in entry.players.iter().filter_map(EntryPlayer::key) {
+            *bucket.players.entry(key.to_string()).or_default() += 1;
+        }
+    }
+    buckets.into_values().collect()
+}
+
+fn bucket_label(start_time: &str, period: Period) -> Option<String> {
+    let date = start_time.get(..10)?;
+    let year: i64 = date.get(..4)?.parse().ok()?;
+    let month: i64 = date.get(5..7)?.parse().ok()?;
+    let day: i64 = date.get(8..10)?.parse().ok()?;
+    match period {
+        Period::Day => Some(date.to_string()),
+        Period::Month => Some(date[..7].to_string()),
+        Period::Week => {
+            let days = days_from_civil(year, month, day);
+            // 1970-01-01 was a Thursday, three days after a Monday
+            let monday = days - (days + 3).rem_euclid(7);
+            let (y, m, d) = civil_from_days(monday);
+            Some(format!("{y:04}-{m:02}-{d:02}"))
+        }
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date (Howard Hinnant's algorithm).
+pub fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Inverse of [days_from_civil].
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A catalog entry for `path` on Battlefield, with a Fox on consecutive ports per connect code.
+    pub(crate) fn entry(path: &str, codes: &[&str]) -> Entry {
+        let ports = [Port::P1, Port::P2, Port::P3, Port::P4];
+        Entry {
+            path: path.to_string(),
+            start_time: None,
+            last_frame: None,
+            stage: 31,
+            stage_name: Stage::from_id(31),
+            console_nick: None,
+            played_on: None,
+            players: codes
+                .iter()
+                .zip(ports)
+                .map(|(code, port)| EntryPlayer {
+                    port,
+                    character: 2,
+                    character_name: Character::from_id(2),
+                    name: None,
+                    code: Some(code.to_string()),
+                    rating: None,
+                    bracket: None,
+                    player_id: None,
+                    placement: None,
+                    placement_recorded: false,
+                })
+                .collect(),
+            seed: 0,
+            corrected_start_time: None,
+            hash: String::new(),
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn rebuilding_carries_annotations_over_by_hash() {
+        let mut old = entry("old/1.slp", &["A#1", "B#2"]);
+        old.hash = "ab".repeat(32);
+        old.tags = vec!["money-match".to_string()];
+        old.players[0].player_id = Some("a".to_string());
+        old.players[1].placement = Some(2);
+        let mut legacy = entry("2.slp", &["C#3"]);
+        legacy.tags = vec!["bad-netcode".to_string()];
+
+        let mut moved = entry("new/1.slp", &["A#1", "B#2"]);
+        moved.hash = old.hash.clone();
+        let mut unhashed = entry("2.slp", &["C#3"]);
+        unhashed.hash = "cd".repeat(32);
+        let mut entries = vec![moved, unhashed, entry("3.slp", &["D#4"])];
+        assert_eq!(carry_over(&mut entries, &[old, legacy]), 2);
+        assert_eq!(entries[0].tags, ["money-match"]);
+        assert_eq!(entries[0].players[0].player_id.as_deref(), Some("a"));
+        assert_eq!(entries[0].players[1].placement, Some(2));
+        assert_eq!(entries[1].tags, ["bad-netcode"]);
+        assert!(entries[2].tags.is_empty());
+    }
+
+    #[test]
+    fn seed_is_from_the_start_of_the_hash() {
+        let digest = Sha256::digest(b"replay");
+        assert_eq!(format!("{:016x}", seed(&digest).swap_bytes()), to_hex(&digest)[..16]);
+    }
+
+    #[test]
+    fn reads_entries_from_before_seeds() {
+        let mut json = serde_json::to_value(entry("1.slp", &["A#1"])).unwrap();
+        json.as_object_mut().unwrap().remove("seed");
+        let entry: Entry = serde_json::from_value(json).unwrap();
+        assert_eq!(entry.seed, 0);
+    }
+
+    #[test]
+    fn buckets_weeks_from_monday() {
+        let mut a = entry("1.slp", &["A#1"]);
+        a.start_time = Some("2024-03-03T20:00:00Z".to_string());
+        let mut b = entry("2.slp", &["A#1"]);
+        b.start_time = Some("2024-03-04T20:00:00Z".to_string());
+        let labels: Vec<_> = buckets(&[a, b], Period::Week).into_iter().map(|b| b.bucket).collect();
+        assert_eq!(labels, ["2024-02-26", "2024-03-04"]);
+        assert!("year".parse::<Period>().is_err());
+    }
+}
@@ -0,0 +1,40 @@
+//! Frames through the Arrow C Data Interface
+//!
+//! Writing frames to an Arrow IPC file and memory-mapping it costs a trip through the filesystem,
+//! which in-memory workflows (batch analysis, notebooks) pay for every game. The C Data Interface
+//! hands the frames' buffers over as they are instead: the caller allocates an `ArrowSchema` and an
+//! `ArrowArray` struct, we fill them in, and the buffers stay alive until the caller invokes the
+//! structs' `release` callbacks. Nothing is copied on either side.
+//!
+//! The frames are exported as a struct array described by a `frame` field, the same column the
+//! Arrow IPC files hold.
+
+use std::io;
+
+use arrow2::array::{Array, StructArray};
+use arrow2::datatypes::Field;
+use arrow2::ffi::{self, ArrowArray, ArrowSchema};
+
+/// Size in bytes of the `ArrowArray` struct the caller must allocate.
+pub const ARRAY_SIZE: usize = std::mem::size_of::<ArrowArray>();
+/// Size in bytes of the `ArrowSchema` struct the caller must allocate.
+pub const SCHEMA_SIZE: usize = std::mem::size_of::<ArrowSchema>();
+
+/// Export `frames` into the caller-allocated structs at the addresses `array` and `schema`, which
+/// the caller then owns and must release.
+///
+/// # Safety
+///
+/// Both addresses must point to writable memory of at least [ARRAY_SIZE] and [SCHEMA_SIZE] bytes,
+/// suitably aligned. Whatever they hold is overwritten without being released.
+pub unsafe fn export(frames: StructArray, array: usize, schema: usize) -> io::Result<()> {
+    if array == 0 || schema == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "null ArrowArray or ArrowSchema pointer"));
+    }
+    let field = Field::new("frame", frames.data_type().clone(), false);
+    unsafe {
+        std::ptr::write(schema as *mut ArrowSchema, ffi::export_field_to_c(&field));
+        std::ptr::write(array as *mut ArrowArray, ffi::export_array_to_c(frames.boxed()));
+    }
+    Ok(())
+}
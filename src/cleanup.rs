@@ -0,0 +1,57 @@
+//! Removing Arrow files once no game needs them
+//!
+//! Arrow files written to the output directory exist only to back the [Game](crate::Game)s read
+//! from them, so when the configuration asks for it they're deleted once the last game using one
+//! is closed or garbage collected. This is opt-in, since the output directory doubles as a cache
+//! other processes may share. Games read from the same replay with the same options share a file,
+//! so each file is counted by the games using it.
+//!
+//! The count is per process: when several processes share an output directory, one may delete a
+//! file another has just reused, so they shouldn't delete their files. Deleting never waits on the
+//! file's lock, since it runs from finalizers: a file locked by another process is being written
+//! or reused, so it's left alone. Files written where the caller asked (see
+//! [read_slippi_to](crate::read_slippi_to)) are never deleted.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::{config, lock};
+
+fn users() -> &'static Mutex<HashMap<PathBuf, usize>> {
+    static USERS: OnceLock<Mutex<HashMap<PathBuf, usize>>> = OnceLock::new();
+    USERS.get_or_init(Default::default)
+}
+
+/// Count a new game backed by the Arrow file at `path`.
+pub fn acquire(path: &Path) {
+    *users().lock().unwrap().entry(path.to_path_buf()).or_default() += 1;
+}
+
+/// Stop counting a game backed by the Arrow file at `path`, deleting the file if it was the last.
+pub fn release(path: &Path) {
+    {
+        let mut users = users().lock().unwrap();
+        let Some(count) = users.get_mut(path) else {
+            return;
+        };
+        *count -= 1;
+        if *count > 0 {
+            return;
+        }
+        users.remove(path);
+    }
+    if !config::get().delete_arrow_files {
+        return;
+    }
+    // The lock keeps other processes from reusing the file while it's removed, and games are
+    // counted while it's held, so one read from the file in the meantime is seen here.
+    let Ok(Some(_lock)) = lock::FileLock::try_exclusive(path) else {
+        return;
+    };
+    if !users().lock().unwrap().contains_key(path) {
+        // Best-effort: this runs from finalizers, and fails on Windows while the file is mapped.
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,359 @@
+//! Correcting clock drift between the setups of a tournament
+//!
+//! A replay's start time comes from the clock of the console or PC that recorded it, and at a
+//! tournament every setup's clock is off by its own amount, however far it was last set wrong.
+//! Sorting an archive by start time then interleaves sets wrongly. Offsets between setups (by
+//! console nickname) are estimated from games that must have happened in a known order:
+//!
+//! - A player's consecutive games on different setups: they can't start the second before the
+//!   first is over.
+//! - Bracket-order hints: pairs of replays, the first known to have been played before the second.
+//!
+//! Each such pair bounds how far the later game's setup can be behind the earlier one's. A bound
+//! one way only says which side of it the skew is on, not how big it is, so two setups' skew is
+//! only measured once they're bounded both ways: it's then the one closest to zero satisfying all
+//! their bounds (or halfway between contradicting ones).
+//!
+//! Setups are anchored to true time by references: replays whose true start time is known (from a
+//! stream VOD or the bracket's match reports, say). Without any, they're lined up with the setup
+//! with the most games instead. The rest are placed from the anchors through the measured skews
+//! best supported by observations; setups no measured skew reaches are left uncorrected.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::catalog::{self, Entry};
+
+/// Frames before frame 0 in every game, which count towards its duration.
+const COUNTDOWN_FRAMES: i64 = 123;
+
+/// Estimated offset of one setup's clock.
+#[derive(Debug, Serialize)]
+pub struct SetupOffset {
+    /// Console nickname
+    pub setup: String,
+    pub games: usize,
+    /// Seconds to add to the setup's start times to line them up with true time if anchored, else
+    /// with the reference setup's
+    pub offset: f64,
+    /// Ordered pairs of games tying the setup to the others
+    pub observations: usize,
+    /// Whether the offset was measured: the setup has references, or a chain of measured skews
+    /// reaches it from an anchor
+    pub corrected: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Drift {
+    /// Setup the others are lined up with, when there are no references
+    pub reference: Option<String>,
+    /// Whether offsets line setups up with true time, from references
+    pub anchored: bool,
+    pub setups: Vec<SetupOffset>,
+}
+
+/// Seconds since the Unix epoch of an ISO-8601 timestamp like `2023-04-01T18:22:07Z` (fractional
+/// seconds and UTC offsets allowed).
+pub fn parse(timestamp: &str) -> Option<f64> {
+    let date = timestamp.get(..10)?;
+    let year: i64 = date.get(..4)?.parse().ok()?;
+    let month: i64 = date.get(5..7)?.parse().ok()?;
+    let day: i64 = date.get(8..10)?.parse().ok()?;
+    let time = timestamp.get(11..)?;
+    let hours: i64 = time.get(..2)?.parse().ok()?;
+    let minutes: i64 = time.get(3..5)?.parse().ok()?;
+    let rest = time.get(6..)?;
+    let zone = rest.find(['Z', '+', '-']).unwrap_or(rest.len());
+    let seconds: f64 = rest[..zone].parse().ok()?;
+    let offset = match &rest[zone..] {
+        "" | "Z" => 0,
+        z => {
+            let sign = if z.starts_with('-') { -1 } else { 1 };
+            let h: i64 = z.get(1..3)?.parse().ok()?;
+            let m: i64 = z.get(z.len() - 2..)?.parse().ok()?;
+            sign * (h * 3600 + m * 60)
+        }
+    };
+    let days = catalog::days_from_civil(year, month, day);
+    Some((days * 86400 + hours * 3600 + minutes * 60 - offset) as f64 + seconds)
+}
+
+/// UTC ISO-8601 timestamp of `seconds` since the Unix epoch, to the second.
+pub fn format(seconds: f64) -> String {
+    format_fraction(seconds, 0)
+}
+
+/// UTC ISO-8601 timestamp of `seconds` since the Unix epoch, with `digits` digits of fractional
+/// seconds (at most 9).
+pub fn format_fraction(seconds: f64, digits: usize) -> String {
+    let digits = digits.min(9);
+    let scale = 10i64.pow(digits as u32);
+    let ticks = (seconds * scale as f64).round() as i64;
+    let seconds = ticks.div_euclid(scale);
+    let (year, month, day) = catalog::civil_from_days(seconds.div_euclid(86400));
+    let time = seconds.rem_euclid(86400);
+    let fraction = match digits {
+        0 => String::new(),
+        _ => format!(".{:0digits$}", ticks.rem_euclid(scale)),
+    };
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}{fraction}Z",
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+/// Start and end, in seconds since the Unix epoch, of a game starting at `start_time` and ending on
+/// frame `last_frame` (ending as it starts if that's unknown).
+pub fn span(start_time: &str, last_frame: Option<i64>) -> Option<(f64, f64)> {
+    let start = parse(start_time)?;
+    let frames = last_frame.map_or(0, |f| f + COUNTDOWN_FRAMES + 1);
+    Some((start, start + frames as f64 / 60.0))
+}
+
+/// A game placed in time by its setup's clock.
+struct Game<'a> {
+    setup: &'a str,
+    start: f64,
+    end: f64,
+}
+
+/// Bounds on the skew between two setups from the games ordered across them.
+#[derive(Default)]
+struct Bounds {
+    /// Smallest gap from a game on the first setup to a later one on the second
+    forward: Option<f64>,
+    /// Smallest gap from a game on the second setup to a later one on the first
+    backward: Option<f64>,
+    observations: usize,
+}
+
+impl Bounds {
+    /// Whether games are ordered across the setups both ways, bounding their skew on both sides.
+    fn measured(&self) -> bool {
+        self.forward.is_some() && self.backward.is_some()
+    }
+
+    /// Seconds the second setup's clock is ahead of the first's.
+    fn skew(&self) -> f64 {
+        // Gaps can't be negative once corrected: `forward - skew >= 0` and `backward + skew >= 0`.
+        let lo = self.backward.map_or(f64::NEG_INFINITY, |b| -b);
+        let hi = self.forward.unwrap_or(f64::INFINITY);
+        if lo <= hi { 0f64.clamp(lo, hi) } else { (lo + hi) / 2.0 }
+    }
+}
+
+/// Estimate the offsets of the setups of `entries`, with `hints` of replays (by path) known to have
+/// been played in order, and `references` of replays (by path) with their true start times. Games
+/// without a console nickname or start time are ignored.
+pub fn estimate(
+    entries: &[Entry],
+    hints: &[(String, String)],
+    references: &[(String, String)],
+) -> Drift {
+    let games: Vec<Option<Game>> = entries
+        .iter()
+        .map(|e| {
+            let (start, end) = span(e.start_time.as_deref()?, e.last_frame)?;
+            Some(Game {
+                setup: e.console_nick.as_deref()?,
+                start,
+                end,
+            })
+        })
+        .collect();
+
+    // Pairs of games (by index) played one after the other
+    let mut ordered: Vec<(usize, usize)> = Vec::new();
+    let mut by_player: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, entry) in entries.iter().enumerate().filter(|(i, _)| games[*i].is_some()) {
+        for key in entry.players.iter().filter_map(|p| p.key()) {
+            by_player.entry(key).or_default().push(i);
+        }
+    }
+    let start = |i: usize| games[i].as_ref().map_or(0.0, |g| g.start);
+    for mut played in by_player.into_values() {
+        played.sort_by(|&a, &b| start(a).total_cmp(&start(b)));
+        ordered.extend(played.windows(2).map(|w| (w[0], w[1])));
+    }
+    let index: HashMap<&str, usize> =
+        entries.iter().enumerate().map(|(i, e)| (e.path.as_str(), i)).collect();
+    for (a, b) in hints {
+        if let (Some(&a), Some(&b)) = (index.get(a.as_str()), index.get(b.as_str())) {
+            ordered.push((a, b));
+        }
+    }
+
+    let mut bounds: BTreeMap<(&str, &str), Bounds> = BTreeMap::new();
+    for (a, b) in ordered {
+        let (Some(first), Some(second)) = (&games[a], &games[b]) else {
+            continue;
+        };
+        if first.setup == second.setup {
+            continue;
+        }
+        let gap = second.start - first.end;
+        let (key, forward) = match first.setup < second.setup {
+            true => ((first.setup, second.setup), true),
+            false => ((second.setup, first.setup), false),
+        };
+        let b = bounds.entry(key).or_default();
+        let side = if forward { &mut b.forward } else { &mut b.backward };
+        *side = Some(side.map_or(gap, |g| g.min(gap)));
+        b.observations += 1;
+    }
+
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for game in games.iter().flatten() {
+        *counts.entry(game.setup).or_default() += 1;
+    }
+
+    // Setups with references are anchored by how far ahead their clocks were, on average.
+    let mut ahead: BTreeMap<&str, Vec<f64>> = BTreeMap::new();
+    for (path, time) in references {
+        let game = index.get(path.as_str()).and_then(|&i| games[i].as_ref());
+        if let (Some(game), Some(time)) = (game, parse(time)) {
+            ahead.entry(game.setup).or_default().push(game.start - time);
+        }
+    }
+    let mut clocks: HashMap<&str, f64> = ahead
+        .iter()
+        .map(|(&setup, ahead)| (setup, ahead.iter().sum::<f64>() / ahead.len() as f64))
+        .collect();
+    // Without any, the reference is the setup with the most games (the first by name among ties).
+    let reference = match clocks.is_empty() {
+        true => counts.iter().max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0))).map(|(s, _)| *s),
+        false => None,
+    };
+    clocks.extend(reference.map(|r| (r, 0.0)));
+
+    // Place setups outwards from the anchors, always through the best-observed measured pair.
+    loop {
+        let next = bounds
+            .iter()
+            .filter(|((a, b), pair)| {
+                pair.measured() && clocks.contains_key(a) != clocks.contains_key(b)
+            })
+            .max_by_key(|(_, b)| b.observations);
+        let Some((&(a, b), pair)) = next else {
+            break;
+        };
+        match clocks.get(a) {
+            Some(&clock) => clocks.insert(b, clock + pair.skew()),
+            None => clocks.insert(a, clocks[b] - pair.skew()),
+        };
+    }
+
+    let setups = counts
+        .iter()
+        .map(|(&setup, &games)| SetupOffset {
+            setup: setup.to_string(),
+            games,
+            offset: clocks.get(setup).map_or(0.0, |clock| -clock),
+            observations: bounds
+                .iter()
+                .filter(|((a, b), _)| *a == setup || *b == setup)
+                .map(|(_, b)| b.observations)
+                .sum(),
+            corrected: clocks.contains_key(setup),
+        })
+        .collect();
+    Drift {
+        reference: reference.map(str::to_string),
+        anchored: !ahead.is_empty(),
+        setups,
+    }
+}
+
+/// Set the corrected start time of every game on a corrected setup, returning how many were set.
+pub fn apply(entries: &mut [Entry], drift: &Drift) -> usize {
+    let offsets: HashMap<&str, f64> = drift
+        .setups
+        .iter()
+        .filter(|s| s.corrected)
+        .map(|s| (s.setup.as_str(), s.offset))
+        .collect();
+    let mut corrected = 0;
+    for entry in entries {
+        let offset = entry.console_nick.as_deref().and_then(|s| offsets.get(s));
+        let start = entry.start_time.as_deref().and_then(parse);
+        entry.corrected_start_time = match (offset, start) {
+            (Some(offset), Some(start)) => Some(format(start + offset)),
+            _ => None,
+        };
+        corrected += entry.corrected_start_time.is_some() as usize;
+    }
+    corrected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::tests::entry;
+
+    /// 2023-04-01T18:00:00Z
+    const BASE: f64 = 1_680_372_000.0;
+
+    /// A 100-second game of X#1 on `setup`, starting `at` seconds past [BASE] by its clock.
+    fn game(path: &str, setup: &str, at: f64) -> Entry {
+        let mut entry = entry(path, &["X#1", format!("{path}#2").as_str()]);
+        entry.console_nick = Some(setup.to_string());
+        entry.start_time = Some(format(BASE + at));
+        entry.last_frame = Some(6000 - COUNTDOWN_FRAMES - 1);
+        entry
+    }
+
+    fn offset<'a>(drift: &'a Drift, setup: &str) -> &'a SetupOffset {
+        drift.setups.iter().find(|s| s.setup == setup).unwrap()
+    }
+
+    /// X#1 plays on setup A, then B, then A again, B's clock being 300 seconds ahead.
+    fn alternating() -> Vec<Entry> {
+        vec![game("1", "A", 0.0), game("2", "B", 200.0 + 300.0), game("3", "A", 550.0)]
+    }
+
+    #[test]
+    fn timestamps_round_trip() {
+        assert_eq!(parse("2023-04-01T18:00:00Z"), Some(BASE));
+        assert_eq!(parse("2023-04-01T20:00:00.5+02:00"), Some(BASE + 0.5));
+        assert_eq!(parse("2023-04-01"), None);
+        assert_eq!(format(BASE), "2023-04-01T18:00:00Z");
+        assert_eq!(format_fraction(BASE + 0.25, 3), "2023-04-01T18:00:00.250Z");
+        assert_eq!(format_fraction(BASE - 0.5, 1), "2023-04-01T17:59:59.5Z");
+    }
+
+    #[test]
+    fn skew_is_measured_from_games_both_ways() {
+        let drift = estimate(&alternating(), &[], &[]);
+        assert_eq!(drift.reference.as_deref(), Some("A"));
+        assert!(!drift.anchored);
+        let b = offset(&drift, "B");
+        assert!(b.corrected);
+        // Bounded to 50..=400 seconds ahead, and taken as close to zero as the bounds allow.
+        assert_eq!(b.offset, -50.0);
+    }
+
+    #[test]
+    fn one_sided_bounds_leave_setups_uncorrected() {
+        let mut entries = alternating();
+        entries.truncate(2);
+        let drift = estimate(&entries, &[], &[]);
+        let b = offset(&drift, "B");
+        assert!(!b.corrected);
+        assert_eq!(b.observations, 1);
+        assert_eq!(apply(&mut entries, &drift), 1);
+        assert!(entries[1].corrected_start_time.is_none());
+    }
+
+    #[test]
+    fn references_anchor_offsets() {
+        let references = [("1".to_string(), format(BASE - 60.0))];
+        let drift = estimate(&alternating(), &[], &references);
+        assert!(drift.anchored);
+        assert_eq!(drift.reference, None);
+        assert_eq!(offset(&drift, "A").offset, -60.0);
+        assert_eq!(offset(&drift, "B").offset, -110.0);
+    }
+}
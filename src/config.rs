@@ -0,0 +1,119 @@
+//! Library-wide configuration
+//!
+//! Settings start out from environment variables, read the first time the configuration is used,
+//! and can be changed at any point from Julia. This lets batch jobs configure the library through
+//! their job scripts without code changes:
+//!
+//! - `PEPPI_JL_OUTPUT_DIR`: directory Arrow files are written to (default: the temp directory)
+//! - `PEPPI_JL_DELETE_ARROW`: delete Arrow files once the games using them are gone when set to
+//!   `1` (default: keep them)
+//! - `PEPPI_JL_CACHE`: directory for cached artifacts (default: none)
+//! - `PEPPI_JL_THREADS`: number of threads for batch work (default: available parallelism)
+//!
+//! Export options that have no environment variable, like the follower policy, dictionary emission, or
+//! units, are set from Julia only, one at a time or together through a [profile](crate::profiles).
+
+use std::env;
+use std::path::PathBuf;
+use std::sync::{OnceLock, RwLock};
+use std::thread;
+
+use serde::Serialize;
+
+use crate::export::FollowerPolicy;
+use crate::ipc::Compression;
+use crate::limits::Limits;
+use crate::live::Overflow;
+use crate::nulls::NullPolicy;
+use crate::sandbox::Sandbox;
+use crate::units::Units;
+use crate::win_probability::Model;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Config {
+    pub output_dir: PathBuf,
+    /// Delete Arrow files in the output directory once the games backed by them are closed
+    pub delete_arrow_files: bool,
+    /// Compression of the Arrow IPC files written
+    pub compression: Compression,
+    pub cache_dir: Option<PathBuf>,
+    pub threads: usize,
+    /// Layout of Ice Climbers follower columns in flattened exports
+    pub follower_policy: FollowerPolicy,
+    /// Write a data dictionary next to every export
+    pub emit_dictionary: bool,
+    /// Units exported frames are converted to
+    pub units: Units,
+    /// Add stage-relative features to exports
+    pub stage_features: bool,
+    /// Add relative features between players to exports
+    pub relative_features: bool,
+    /// Model scoring the win probability column added to exports, when enabled
+    pub win_probability: Option<Model>,
+    /// How nulls in exported frame columns are written
+    pub null_policy: NullPolicy,
+    /// Patterns of the columns exports keep (all of them when `None`)
+    pub columns: Option<Vec<String>>,
+    /// Times a batch job retries reading a file that failed
+    pub retries: usize,
+    /// Directory files that still fail in batch jobs are copied into
+    pub quarantine_dir: Option<PathBuf>,
+    /// Limits on the replays read, for untrusted input
+    pub limits: Limits,
+    /// Worker process replays are parsed in first, when sandboxed
+    pub sandbox: Option<Sandbox>,
+    /// Events a live session queues for Julia before applying `live_overflow`
+    pub live_capacity: usize,
+    pub live_overflow: Overflow,
+    /// Directory live sessions record the games they receive to
+    pub live_record_dir: Option<PathBuf>,
+}
+
+impl Config {
+    fn from_env() -> Self {
+        let var = |key: &str| env::var(key).ok().filter(|v| !v.is_empty());
+        Config {
+            output_dir: var("PEPPI_JL_OUTPUT_DIR").map_or_else(env::temp_dir, PathBuf::from),
+            delete_arrow_files: var("PEPPI_JL_DELETE_ARROW").is_some_and(|v| v == "1"),
+            compression: Compression::None,
+            cache_dir: var("PEPPI_JL_CACHE").map(PathBuf::from),
+            threads: var("PEPPI_JL_THREADS")
+                .and_then(|v| v.parse().ok())
+                .filter(|&n| n > 0)
+                .unwrap_or_else(default_threads),
+            follower_policy: FollowerPolicy::Separate,
+            emit_dictionary: false,
+            units: Units::default(),
+            stage_features: false,
+            relative_features: false,
+            win_probability: None,
+            null_policy: NullPolicy::Null,
+            columns: None,
+            retries: 0,
+            quarantine_dir: None,
+            limits: Limits::default(),
+            sandbox: None,
+            live_capacity: 65536,
+            live_overflow: Overflow::Block,
+            live_record_dir: None,
+        }
+    }
+}
+
+fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+fn config() -> &'static RwLock<Config> {
+    static CONFIG: OnceLock<RwLock<Config>> = OnceLock::new();
+    CONFIG.get_or_init(|| RwLock::new(Config::from_env()))
+}
+
+/// A snapshot of the current configuration.
+pub fn get() -> Config {
+    config().read().unwrap().clone()
+}
+
+pub fn update(f: impl FnOnce(&mut Config)) {
+    f(&mut config().write().unwrap())
+}
@@ -0,0 +1,58 @@
+//! Content addressing of converted artifacts
+//!
+//! Cached artifacts are named by a hash of the source replay's contents and of every option that
+//! affects them, rather than by the source's file name. The same replay converted the same way
+//! gets the same name wherever it's stored, so caches deduplicate copies across directories and
+//! can be shared between users, while converting with different options never reuses a stale file.
+
+use serde::Serialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::dataset::to_hex;
+use crate::features;
+use crate::nulls::NullPolicy;
+use crate::units::{self, Units};
+use crate::win_probability::{self, Model};
+
+/// Hex SHA-256 of the source replay itself, identifying it whatever it's converted with.
+pub fn source_hash(source: &[u8]) -> String {
+    to_hex(&Sha256::digest(source))
+}
+
+/// Hex SHA-256 identifying the artifact converted from `source` with `options`.
+pub fn hash(source: &[u8], options: &impl Serialize) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(Sha256::digest(source));
+    hasher.update(serde_json::to_vec(options).unwrap_or_default());
+    to_hex(&hasher.finalize())
+}
+
+/// Options determining the contents of exported frames, including the versions of the code
+/// converting them.
+pub fn frame_options(
+    units: Units,
+    stage_features: bool,
+    relative_features: bool,
+    win_probability: Option<&Model>,
+    null_policy: NullPolicy,
+    columns: Option<&[String]>,
+) -> serde_json::Value {
+    let mut options = json!({
+        "columns": columns,
+        "units": units,
+        "units_version": units::VERSION,
+        "stage_features": stage_features,
+        "relative_features": relative_features,
+        "features_version": features::VERSION,
+    });
+    // Only named when used, leaving the options of exports without it as they were
+    if let Some(model) = win_probability {
+        options["win_probability"] = json!(model);
+        options["win_probability_version"] = json!(win_probability::VERSION);
+    }
+    if null_policy != NullPolicy::Null {
+        options["null_policy"] = json!(null_policy);
+    }
+    options
+}
@@ -0,0 +1,64 @@
+//! Field coverage of a replay's frames
+//!
+//! Before committing to an analysis over an archive, it's worth knowing how much of the data it
+//! needs is actually there. Fields can be missing because the Slippi version that recorded a replay
+//! predates them, or null on some frames, like follower fields for ports without Ice Climbers or
+//! item fields on frames without items. A coverage report lists every frame column a replay of the
+//! latest version would have, whether this replay's version records it, and the share of frames on
+//! which it isn't null.
+
+use arrow2::array::StructArray;
+use peppi::io::slippi::Version;
+use serde::Serialize;
+
+use crate::{dictionary, export, warnings};
+
+#[derive(Debug, Serialize)]
+pub struct Column {
+    /// Dotted path of the column
+    pub name: String,
+    /// Slippi version that introduced the field
+    pub introduced: &'static str,
+    /// Whether the replay's version records the field
+    pub recorded: bool,
+    /// Share of frames on which the column isn't null, from 0 to 1
+    pub non_null: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Coverage {
+    /// Slippi version that recorded the replay
+    pub version: String,
+    pub frames: usize,
+    /// Columns the replay's version doesn't record
+    pub missing: usize,
+    pub columns: Vec<Column>,
+}
+
+/// Coverage of every frame column for `frames` recorded by Slippi `version`.
+pub fn report(frames: &StructArray, version: Version) -> Coverage {
+    let (filled, missing) = warnings::fill(frames, version, &["*".to_string()]);
+    let len = filled.len();
+    let columns = export::leaves(&filled)
+        .into_iter()
+        .map(|(name, array)| {
+            let recorded = !missing.iter().any(|w| w.field == name);
+            let non_null = match len {
+                0 => 0.0,
+                _ => 1.0 - array.null_count() as f64 / len as f64,
+            };
+            Column {
+                introduced: dictionary::introduced_in(&name),
+                name,
+                recorded,
+                non_null,
+            }
+        })
+        .collect();
+    Coverage {
+        version: warnings::version_string(version),
+        frames: len,
+        missing: missing.len(),
+        columns,
+    }
+}
@@ -0,0 +1,87 @@
+//! Audio cues for sound-reactive overlays
+//!
+//! Overlays and audio tooling that react to a replay as it plays back only need the moments that
+//! make a sound, timed to the frame. Cues are:
+//!
+//! - `hit`: damage dealt (phantom hits aside), graded `light`, `medium`, or `heavy` by damage
+//! - `death`: a stock lost
+//! - `shield_break`: a shield broken
+//!
+//! Each cue carries its time in seconds from the start of the replay (the first frame of the
+//! countdown), so it can be scheduled against playback without knowing how frames are numbered,
+//! and an intensity from 0 to 1 for scaling effects.
+
+use std::ops::RangeInclusive;
+
+use peppi::frame::immutable::Frame;
+use peppi::game::Port;
+use serde::Serialize;
+
+use crate::{FIRST_FRAME, hits, stalls, windows};
+
+/// Damage up to which a hit is light, and up to which it's medium.
+const LIGHT_DAMAGE: f32 = 8.0;
+const MEDIUM_DAMAGE: f32 = 15.0;
+/// Damage at which a hit's intensity reaches 1.
+const FULL_INTENSITY_DAMAGE: f32 = 30.0;
+/// Shield break action states (`ShieldBreakFly` through `ShieldBreakDownD`).
+const SHIELD_BREAK_STATES: RangeInclusive<u16> = 205..=208;
+
+#[derive(Debug, Serialize)]
+pub struct Cue {
+    pub kind: &'static str,
+    /// `light`, `medium`, or `heavy` for hits
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strength: Option<&'static str>,
+    /// Frame index, as stored in the replay
+    pub frame: i32,
+    /// Seconds from the start of the replay
+    pub time: f64,
+    /// Player the cue happened to
+    pub port: Port,
+    pub intensity: f32,
+}
+
+fn cue(kind: &'static str, strength: Option<&'static str>, frame: i32, port: Port, intensity: f32) -> Cue {
+    Cue {
+        kind,
+        strength,
+        frame,
+        time: (frame - FIRST_FRAME) as f64 / stalls::FPS,
+        port,
+        intensity,
+    }
+}
+
+/// Every cue of the game, ordered by frame and then by port.
+pub fn detect(frames: &Frame) -> Vec<Cue> {
+    let mut cues: Vec<Cue> = hits::detect(frames)
+        .iter()
+        .filter(|h| !h.phantom)
+        .map(|h| {
+            let strength = match h.damage {
+                d if d <= LIGHT_DAMAGE => "light",
+                d if d <= MEDIUM_DAMAGE => "medium",
+                _ => "heavy",
+            };
+            let intensity = (h.damage / FULL_INTENSITY_DAMAGE).min(1.0);
+            cue("hit", Some(strength), h.frame, h.victim, intensity)
+        })
+        .collect();
+
+    let deaths = windows::anchors(frames, "death").unwrap_or_default();
+    cues.extend(deaths.iter().map(|d| cue("death", None, d.frame, d.port, 1.0)));
+
+    for port in &frames.ports {
+        let state = &port.leader.post.state;
+        let broke = |i| SHIELD_BREAK_STATES.contains(&state.value(i));
+        for i in 1..frames.id.len() {
+            if broke(i) && !broke(i - 1) {
+                cues.push(cue("shield_break", None, frames.id.value(i), port.port, 1.0));
+            }
+        }
+    }
+
+    cues.sort_by_key(|c| (c.frame, c.port as u8));
+    cues
+}
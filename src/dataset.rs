@@ -0,0 +1,154 @@
+//! Dataset manifests and integrity signatures
+//!
+//! A dataset directory is described by a `manifest.json` listing the size and SHA-256 of every file
+//! in it. The manifest can be signed with an ed25519 key, so a shared dataset can be checked against
+//! its publisher's public key before it's trusted.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{atomic, json};
+
+pub const MANIFEST: &str = "manifest.json";
+pub const SIGNATURE: &str = "manifest.json.sig";
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Path relative to the dataset directory, with `/` separators
+    pub path: String,
+    pub size: u64,
+    /// Lowercase hex SHA-256 of the file's contents
+    pub sha256: String,
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub fn from_hex(s: &str) -> io::Result<Vec<u8>> {
+    let s = s.trim();
+    // Checked first, since slicing would panic inside a multi-byte character, and
+    // `from_str_radix` takes a sign.
+    if !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid("invalid hex string"));
+    }
+    if s.len() % 2 != 0 {
+        return Err(invalid("odd-length hex string"));
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|_| invalid("invalid hex string")))
+        .collect()
+}
+
+/// List every file below `dir` (other than the manifest and its signature), sorted by path.
+pub fn manifest(dir: &Path) -> io::Result<Vec<ManifestEntry>> {
+    let mut entries = Vec::new();
+    collect(dir, dir, &mut entries)?;
+    entries.retain(|e| e.path != MANIFEST && e.path != SIGNATURE);
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+fn collect(root: &Path, dir: &Path, entries: &mut Vec<ManifestEntry>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect(root, &path, entries)?;
+            continue;
+        }
+        let data = fs::read(&path)?;
+        let relative = path.strip_prefix(root).expect("path is below root");
+        entries.push(ManifestEntry {
+            path: relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+            size: data.len() as u64,
+            sha256: to_hex(&Sha256::digest(&data)),
+        });
+    }
+    Ok(())
+}
+
+/// Parse a hex-encoded ed25519 secret key.
+pub fn signing_key(hex: &str) -> Result<SigningKey, String> {
+    let key: [u8; 32] = from_hex(hex)
+        .map_err(|e| format!("secret key: {e}"))?
+        .try_into()
+        .map_err(|_| "secret key must be 32 bytes".to_string())?;
+    Ok(SigningKey::from_bytes(&key))
+}
+
+/// Parse a hex-encoded ed25519 public key.
+pub fn verifying_key(hex: &str) -> Result<VerifyingKey, String> {
+    let key: [u8; 32] = from_hex(hex)
+        .map_err(|e| format!("public key: {e}"))?
+        .try_into()
+        .map_err(|_| "public key must be 32 bytes".to_string())?;
+    VerifyingKey::from_bytes(&key).map_err(|e| format!("public key: {e}"))
+}
+
+/// Write the manifest for `dir`, signing it when given a secret key.
+pub fn write_manifest(dir: &Path, secret_key: Option<&SigningKey>) -> io::Result<usize> {
+    let entries = manifest(dir)?;
+    let mut bytes = Vec::new();
+    json::to_writer(&mut bytes, &entries, true)?;
+    atomic::write_bytes(&dir.join(MANIFEST), &bytes)?;
+
+    if let Some(secret_key) = secret_key {
+        let signature = secret_key.sign(&bytes);
+        atomic::write_bytes(&dir.join(SIGNATURE), to_hex(&signature.to_bytes()).as_bytes())?;
+    }
+    Ok(entries.len())
+}
+
+/// Check that the manifest in `dir` was signed by `public_key` and that every file still matches
+/// it.
+///
+/// Returns `Ok(false)` when the dataset has been tampered with, and an error when it can't be
+/// checked at all (missing or malformed manifest or signature).
+pub fn verify(dir: &Path, public_key: &VerifyingKey) -> io::Result<bool> {
+    let bytes = fs::read(dir.join(MANIFEST))?;
+    let signature: [u8; 64] = from_hex(&fs::read_to_string(dir.join(SIGNATURE))?)?
+        .try_into()
+        .map_err(|_| invalid("signature must be 64 bytes"))?;
+    if public_key.verify(&bytes, &Signature::from_bytes(&signature)).is_err() {
+        return Ok(false);
+    }
+
+    let signed: Vec<ManifestEntry> = serde_json::from_slice(&bytes)?;
+    Ok(signed == manifest(dir)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_round_trips_and_rejects_non_digits() {
+        assert_eq!(from_hex(&to_hex(&[0, 0xab, 0xff])).unwrap(), [0, 0xab, 0xff]);
+        assert_eq!(from_hex(" 0A ").unwrap(), [0x0a]);
+        assert!(from_hex("abc").is_err());
+        assert!(from_hex("+1").is_err());
+        // Two bytes, but one character: slicing it would panic.
+        assert!(from_hex("é").is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_keys() {
+        assert!(signing_key(&"ab".repeat(32)).is_ok());
+        assert!(signing_key(&"ab".repeat(31)).is_err());
+        assert!(verifying_key("zz").is_err());
+    }
+}
@@ -0,0 +1,132 @@
+//! Human-readable game summaries
+//!
+//! Julia `show` methods and REPL sessions want a glance at a game, not its JSON. The summary is put
+//! together here, so it reads the same wherever it's shown: the stage, how long the game ran, who
+//! won, then a line per player with their character, name, where they finished, and their hits.
+//!
+//! The result is the game's [Outcome], as `game_result` reports it, so the two never disagree: the
+//! winner (or the player placed first, outside singles), a tie, who quit out, or for games without
+//! a proper end, who was ahead.
+
+use std::fmt::Write;
+
+use peppi::frame::immutable::Frame;
+use peppi::game::Port;
+
+use crate::catalog::EntryPlayer;
+use crate::hits::Hit;
+use crate::ids::Stage;
+use crate::outcome::{Outcome, Standing};
+
+/// Melee runs at 60 frames per second.
+const FPS: i32 = 60;
+
+fn port_name(port: Port) -> String {
+    format!("P{}", port as u8 + 1)
+}
+
+/// The result part of a summary, e.g. "P1 wins" or "P2 quit out, P1 wins".
+fn result(outcome: &Outcome) -> String {
+    let first: Vec<Port> =
+        outcome.players.iter().filter(|p| p.placement == 1).map(|p| p.port).collect();
+    let leader = match first.as_slice() {
+        &[port] => Some(port),
+        _ => None,
+    };
+    match outcome.result {
+        "lras" => {
+            let quitter = outcome.lras_initiator.map_or_else(|| "a player".to_string(), port_name);
+            match outcome.winner {
+                Some(winner) => format!("{quitter} quit out, {} wins", port_name(winner)),
+                None => format!("{quitter} quit out"),
+            }
+        }
+        "tie" => "tie".to_string(),
+        "incomplete" => match leader {
+            Some(leader) => format!("{} ahead", port_name(leader)),
+            None => "no winner".to_string(),
+        },
+        _ => match outcome.winner.or(leader) {
+            Some(winner) => format!("{} wins", port_name(winner)),
+            None => "no winner".to_string(),
+        },
+    }
+}
+
+/// Summarize a game on `stage` between `players`, from its frames, hits, and outcome.
+pub fn describe(
+    players: &[EntryPlayer],
+    stage: u16,
+    frames: &Frame,
+    hits: &[Hit],
+    outcome: &Outcome,
+) -> String {
+    // Game time starts at frame 0, when players can first move.
+    let last = frames.id.len().checked_sub(1);
+    let frames_played = last.map_or(0, |i| frames.id.value(i) + 1).max(0);
+    let seconds = frames_played / FPS;
+    let stage = Stage::from_id(stage);
+    let mut s = format!("{stage}, {}:{:02}, {}", seconds / 60, seconds % 60, result(outcome));
+
+    for &Standing { port, stocks, percent, .. } in &outcome.players {
+        let player = players.iter().find(|p| p.port == port);
+        let _ = write!(s, "\n{}", port_name(port));
+        if let Some(player) = player {
+            let _ = write!(s, " {}", player.character_name);
+            if let Some(name) = &player.name {
+                let _ = write!(s, " \"{name}\"");
+            }
+            if let Some(code) = &player.code {
+                let _ = write!(s, " ({code})");
+            }
+        }
+        let landed: Vec<&Hit> = hits.iter().filter(|h| h.attacker == Some(port) && h.victim != port).collect();
+        let dealt: f32 = landed.iter().map(|h| h.damage).sum();
+        let _ = write!(
+            s,
+            ": {stocks} stock{}, {percent:.1}% | {} hit{}, {dealt:.1}% dealt",
+            if stocks == 1 { "" } else { "s" },
+            landed.len(),
+            if landed.len() == 1 { "" } else { "s" },
+        );
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(result: &'static str, winner: Option<Port>, placements: &[usize]) -> Outcome {
+        let ports = [Port::P1, Port::P2, Port::P3, Port::P4];
+        let players = ports
+            .iter()
+            .zip(placements)
+            .map(|(&port, &placement)| Standing {
+                port,
+                stocks: 0,
+                percent: 0.0,
+                placement,
+                placement_recorded: false,
+            })
+            .collect();
+        Outcome {
+            result,
+            winner,
+            loser: None,
+            lras_initiator: (result == "lras").then_some(Port::P2),
+            players,
+        }
+    }
+
+    #[test]
+    fn results_follow_the_outcome() {
+        assert_eq!(result(&outcome("ko", Some(Port::P2), &[2, 1])), "P2 wins");
+        assert_eq!(result(&outcome("timeout", None, &[2, 1, 3, 4])), "P2 wins");
+        assert_eq!(result(&outcome("tie", None, &[1, 1])), "tie");
+        assert_eq!(result(&outcome("lras", Some(Port::P1), &[1, 2])), "P2 quit out, P1 wins");
+        assert_eq!(result(&outcome("lras", None, &[1, 4, 2, 3])), "P2 quit out");
+        assert_eq!(result(&outcome("incomplete", None, &[1, 2])), "P1 ahead");
+        assert_eq!(result(&outcome("incomplete", None, &[1, 1])), "no winner");
+    }
+}
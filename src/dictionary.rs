@@ -0,0 +1,173 @@
+//! Data dictionaries describing exported columns
+//!
+//! When enabled, every export writes a `<output>.dictionary.json` next to it (`dictionary.json` for
+//! exports to a directory), listing each column's name, type, unit, the Slippi event it comes from,
+//! and the Slippi version that introduced it. Shared datasets can then be understood without this
+//! library's source at hand.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use arrow2::array::Array;
+use serde::Serialize;
+
+use crate::atomic;
+use crate::config;
+use crate::units::{FacingUnits, PositionUnits, Units};
+
+#[derive(Debug, Serialize)]
+pub struct Column {
+    pub name: String,
+    pub data_type: String,
+    pub unit: Option<&'static str>,
+    pub source: &'static str,
+    /// Slippi version the column was introduced in
+    pub introduced: &'static str,
+}
+
+/// Dictionary of an export, one list of columns per table.
+#[derive(Debug, Serialize)]
+pub struct Dictionary {
+    pub frames: Vec<Column>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hits: Vec<Column>,
+}
+
+/// Path of the dictionary accompanying the export at `path`.
+pub fn path_for(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".dictionary.json");
+    path.with_file_name(name)
+}
+
+/// Write the dictionary of an export holding the given frame columns, and the hit table if `hits`,
+/// to `path`.
+pub fn write(path: &Path, frames: &[(String, Box<dyn Array>)], hits: bool) -> io::Result<()> {
+    let dictionary = Dictionary {
+        frames: self::frames(frames),
+        hits: if hits { self::hits() } else { Vec::new() },
+    };
+    atomic::write_json(path, &dictionary, true)
+}
+
+/// Unit of a frame field, by its name within its event.
+fn unit(field: &str, units: Units) -> Option<&'static str> {
+    let leaf = field.rsplit('.').next().unwrap_or(field);
+    Some(match field.split('.').next().unwrap_or(field) {
+        "position" if units.positions == PositionUnits::Stage => "blast zones at -1 and 1",
+        "position" => "game units",
+        "velocities" | "velocity" => "game units per frame",
+        "percent" => "percent",
+        "shield" => "shield health",
+        "hitlag" | "state_age" | "timer" => "frames",
+        "direction" if units.facing == FacingUnits::Bool => "true when facing right",
+        "direction" => "facing (-1 left, 1 right)",
+        "joystick" | "cstick" => "stick position in [-1, 1]",
+        "triggers" | "triggers_physical" => "trigger position in [0, 1]",
+        "ledge_distance" | "surface_height" | "dx" | "dy" | "separation" => "game units",
+        "percent_diff" => "percent",
+        "stock_diff" => "stocks",
+        "win_probability" => "probability",
+        _ if leaf.starts_with("blast_") => "game units",
+        _ if leaf == "stocks" => "stocks",
+        _ => return None,
+    })
+}
+
+/// Slippi version a frame field was introduced in, by its name within its event.
+fn introduced(source: &str, field: &str) -> &'static str {
+    let top = field.split('.').next().unwrap_or(field);
+    match (source, top) {
+        ("pre", "raw_analog_x") => "1.2.0",
+        ("pre", "percent") => "1.4.0",
+        ("pre", "raw_analog_y") => "3.15.0",
+        ("post", "state_age") => "0.2.0",
+        ("post", "state_flags" | "misc_as" | "airborne" | "ground" | "jumps" | "l_cancel") => "2.0.0",
+        ("post", "hurtbox_state") => "2.1.0",
+        ("post", "velocities") => "3.5.0",
+        ("post", "hitlag") => "3.8.0",
+        ("post", "animation_index") => "3.11.0",
+        ("post", "last_hit_by_instance" | "instance_id") => "3.16.0",
+        ("item", "misc") => "3.2.0",
+        ("item", "owner") => "3.6.0",
+        ("item", "instance_id") => "3.16.0",
+        ("item", _) => "3.0.0",
+        ("start", _) => "2.2.0",
+        ("end", _) => "3.0.0",
+        _ => "0.1.0",
+    }
+}
+
+fn event_name(source: &str) -> &'static str {
+    match source {
+        "features" | "win_probability" => "Derived",
+        "pre" => "Pre-Frame Update",
+        "post" => "Post-Frame Update",
+        "item" => "Item Update",
+        "start" => "Frame Start",
+        "end" => "Frame Bookend",
+        _ => "Frame",
+    }
+}
+
+/// Event and field a flattened frame column comes from: `ports.P1.leader.post.position.x` comes
+/// from the `post` event's `position.x` field.
+fn source_field(name: &str) -> (&str, String) {
+    let parts: Vec<&str> = name.split('.').collect();
+    match parts.as_slice() {
+        ["ports", _, _, source, ..] => (*source, parts[4..].join(".")),
+        ["features", .., field] => ("features", field.to_string()),
+        ["win_probability", ..] => ("win_probability", "win_probability".to_string()),
+        [source, ..] => (*source, parts[1..].join(".")),
+        [] => ("", String::new()),
+    }
+}
+
+/// Slippi version that introduced a flattened frame column, named as by
+/// [export::flatten](crate::export::flatten).
+pub fn introduced_in(name: &str) -> &'static str {
+    let (source, field) = source_field(name);
+    introduced(source, &field)
+}
+
+/// Describe flattened frame columns, named as by [export::flatten](crate::export::flatten).
+pub fn frames(columns: &[(String, Box<dyn Array>)]) -> Vec<Column> {
+    let units = config::get().units;
+    columns
+        .iter()
+        .map(|(name, array)| {
+            let (source, field) = source_field(name);
+            Column {
+                name: name.clone(),
+                data_type: format!("{:?}", array.data_type()),
+                unit: unit(&field, units),
+                source: event_name(source),
+                introduced: introduced(source, &field),
+            }
+        })
+        .collect()
+}
+
+/// Describe the hit table columns written by the table exporters.
+pub fn hits() -> Vec<Column> {
+    let column = |name: &str, data_type: &str, unit, introduced| Column {
+        name: name.to_string(),
+        data_type: data_type.to_string(),
+        unit,
+        source: "Post-Frame Update (derived)",
+        introduced,
+    };
+    vec![
+        column("frame", "Int32", Some("frame index"), "0.1.0"),
+        column("victim", "UInt8", Some("port index"), "0.1.0"),
+        column("attacker", "UInt8", Some("port index"), "0.1.0"),
+        column("damage", "Float32", Some("percent"), "0.1.0"),
+        column("percent", "Float32", Some("percent"), "0.1.0"),
+        column("hitlag", "Float32", Some("frames"), "3.8.0"),
+        column("trade", "Boolean", None, "0.1.0"),
+        column("trade_winner", "UInt8", Some("port index"), "0.1.0"),
+        column("phantom", "Boolean", None, "0.1.0"),
+        column("meteor", "Boolean", None, "3.8.0"),
+        column("meteor_cancel", "UInt32", Some("frames"), "3.8.0"),
+    ]
+}
@@ -0,0 +1,67 @@
+//! Typed access to a game's Game End block
+//!
+//! Who won, and how the game ended, otherwise takes parsing the end block's JSON and matching its
+//! placements up with the players. An [EndInfo] holds the block as an opaque Julia object with one
+//! getter per field, ports numbered from 1 as everywhere else. Placements are recorded from Slippi
+//! 3.13.0, and the LRAS initiator from 2.0.0.
+
+use jlrs::{
+    data::managed::string::{JuliaString, StringRet},
+    prelude::*,
+    weak_handle_unchecked,
+};
+use peppi::game::End;
+
+/// The Game End block of a game, exposed to Julia.
+#[derive(OpaqueType)]
+pub struct EndInfo {
+    end: End,
+}
+
+impl EndInfo {
+    pub fn new(end: End) -> Self {
+        EndInfo { end }
+    }
+
+    /// How the game ended: 0 unresolved, 1 time, 2 game, 3 resolved, 7 no contest
+    pub fn method(&self) -> u8 {
+        self.end.method as u8
+    }
+
+    /// Name of the end method, e.g. `Game` or `NoContest`
+    pub fn method_name(&self) -> StringRet {
+        let handle = unsafe { weak_handle_unchecked!() };
+        JuliaString::new(handle, format!("{:?}", self.end.method)).leak()
+    }
+
+    /// Port of the player who quit out with L+R+A+Start, 0 if nobody did, or -1 if not recorded
+    pub fn lras_initiator(&self) -> i8 {
+        match self.end.lras_initiator {
+            Some(Some(port)) => port as i8 + 1,
+            Some(None) => 0,
+            None => -1,
+        }
+    }
+
+    /// Whether per-player placements were recorded
+    pub fn has_placements(&self) -> bool {
+        self.end.players.is_some()
+    }
+
+    /// Placement of the player on `port` (from 1), from 1 for the winner, or -1 if not recorded
+    pub fn placement(&self, port: u8) -> i8 {
+        let players = self.end.players.iter().flatten();
+        let mut found = players.filter(|p| p.port as u8 + 1 == port);
+        found.next().map_or(-1, |p| p.placement as i8 + 1)
+    }
+
+    /// Port of the sole winner, or 0 if placements weren't recorded or first place was shared
+    pub fn winner(&self) -> u8 {
+        let players = self.end.players.iter().flatten();
+        let first: Vec<_> = players.filter(|p| p.placement == 0).collect();
+        match first.as_slice() {
+            [winner] => winner.port as u8 + 1,
+            _ => 0,
+        }
+    }
+}
@@ -0,0 +1,268 @@
+//! Per-file error reports for batch jobs
+//!
+//! Batch jobs skip the files they fail on rather than aborting, and record each failure in an
+//! errors file next to their output (`<output>.errors.json`): the file's path, a coarse class of
+//! error, the error message, and for malformed replays the byte offset where the event stream stops
+//! making sense. Triage can then be scripted over the table rather than done from printed logs.
+//!
+//! Reads that fail are retried (up to the configured number of retries, for files on flaky network
+//! storage or still being written) after a delay doubling with each attempt, and files that still
+//! fail are copied into the quarantine directory when one is configured, so problem files are
+//! collected for inspection. Copies are named after the file and a hash of its full path, so files
+//! of the same name from different directories don't replace each other. Dry runs don't quarantine.
+//!
+//! Replays read directly from Julia fail with a [ReadError] instead, thrown as an exception whose
+//! message starts with the kind of failure.
+
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{fs, io, thread};
+
+use peppi::game::immutable::Game as SlippiGame;
+use peppi::io::slippi::de::Opts as SlippiReadOpts;
+use peppi::io::slippi::{MAX_SUPPORTED_VERSION, Version};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{atomic, config, limits, raw};
+
+/// A single row of an errors file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileError {
+    pub path: String,
+    /// `io` (the file couldn't be read), `parse` (it isn't a valid replay), `limit` (it's over
+    /// the configured limits), or `unsupported` (it's valid but can't be processed with the
+    /// requested options)
+    pub class: String,
+    pub message: String,
+    /// Offset from the start of the file of the first event that couldn't be walked
+    pub offset: Option<usize>,
+    /// Number of times reading the file was attempted
+    #[serde(default)]
+    pub attempts: usize,
+    /// Path of the file's copy in the quarantine directory, if it was quarantined
+    #[serde(default)]
+    pub quarantined: Option<String>,
+}
+
+impl FileError {
+    pub fn io(path: &Path, error: impl Display) -> Self {
+        FileError {
+            path: path.to_string_lossy().into_owned(),
+            class: "io".to_string(),
+            message: error.to_string(),
+            offset: None,
+            attempts: 1,
+            quarantined: None,
+        }
+    }
+
+    pub fn parse(path: &Path, data: &[u8], error: impl Display) -> Self {
+        FileError {
+            path: path.to_string_lossy().into_owned(),
+            class: "parse".to_string(),
+            message: error.to_string(),
+            offset: raw::error_offset(data),
+            attempts: 1,
+            quarantined: None,
+        }
+    }
+
+    pub fn limit(path: &Path, error: impl Display) -> Self {
+        FileError {
+            path: path.to_string_lossy().into_owned(),
+            class: "limit".to_string(),
+            message: error.to_string(),
+            offset: None,
+            attempts: 1,
+            quarantined: None,
+        }
+    }
+
+    pub fn unsupported(path: &Path, error: impl Display) -> Self {
+        FileError {
+            path: path.to_string_lossy().into_owned(),
+            class: "unsupported".to_string(),
+            message: error.to_string(),
+            offset: None,
+            attempts: 1,
+            quarantined: None,
+        }
+    }
+
+    /// Row for a replay read as from Julia, which failed with `error`.
+    pub fn read(path: &Path, error: &ReadError) -> Self {
+        match error {
+            ReadError::NotFound(_) | ReadError::Io(_) => FileError::io(path, error),
+            ReadError::Limit(_) => FileError::limit(path, error),
+            ReadError::UnsupportedVersion { .. } => FileError::unsupported(path, error),
+            // The replay's bytes are gone by now, so the offset isn't found.
+            ReadError::Truncated(_) | ReadError::Invalid(_) => FileError {
+                class: "parse".to_string(),
+                ..FileError::io(path, error)
+            },
+        }
+    }
+}
+
+/// Why a replay couldn't be read.
+#[derive(Debug)]
+pub enum ReadError {
+    NotFound(PathBuf),
+    Io(io::Error),
+    /// The file ends partway through the replay
+    Truncated(String),
+    /// The replay was recorded by a newer Slippi than this build supports
+    UnsupportedVersion { version: [u8; 3], message: String },
+    /// Any other malformed replay
+    Invalid(String),
+    /// The file is over one of the configured [limits](crate::limits)
+    Limit(String),
+}
+
+impl ReadError {
+    pub fn io(path: &Path, error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::NotFound => ReadError::NotFound(path.to_path_buf()),
+            _ => ReadError::Io(error),
+        }
+    }
+
+    /// Classify a failure to parse the replay in `data`.
+    pub fn parse(data: &[u8], error: impl Display) -> Self {
+        let message = error.to_string();
+        match raw::game_version(data) {
+            Some(v) if Version(v[0], v[1], v[2]) > MAX_SUPPORTED_VERSION => {
+                ReadError::UnsupportedVersion { version: v, message }
+            }
+            _ if raw::truncated(data) => ReadError::Truncated(message),
+            _ => ReadError::Invalid(message),
+        }
+    }
+}
+
+impl Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::NotFound(path) => write!(f, "file not found: {}", path.display()),
+            ReadError::Io(e) => write!(f, "I/O error: {e}"),
+            ReadError::Truncated(message) => write!(f, "truncated replay: {message}"),
+            ReadError::UnsupportedVersion { version: [major, minor, build], message } => write!(
+                f,
+                "unsupported version: recorded by Slippi {major}.{minor}.{build}, newer than the \
+                 latest supported ({}.{}.{}): {message}",
+                MAX_SUPPORTED_VERSION.0, MAX_SUPPORTED_VERSION.1, MAX_SUPPORTED_VERSION.2,
+            ),
+            ReadError::Invalid(message) => write!(f, "invalid replay: {message}"),
+            ReadError::Limit(message) => write!(f, "limit exceeded: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {}
+
+/// Delay before the first retry of a failed read, doubled for each one after it.
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Longest delay between retries.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Read and parse a replay for a batch job, retrying and then quarantining it as configured.
+/// Nothing is quarantined for a `dry_run`.
+pub fn read_replay(
+    path: &Path,
+    opts: Option<&SlippiReadOpts>,
+    dry_run: bool,
+) -> Result<(Vec<u8>, SlippiGame), FileError> {
+    let config = config::get();
+    let limits = config.limits;
+    let attempt = || {
+        let data = limits::read(path, &limits).map_err(|e| match e {
+            ReadError::Limit(_) => FileError::limit(path, e),
+            e => FileError::io(path, e),
+        })?;
+        limits::check(&data, &limits).map_err(|e| FileError::limit(path, e))?;
+        match peppi::io::slippi::read(&mut io::Cursor::new(&data), opts) {
+            Ok(game) => Ok((data, game)),
+            Err(e) => Err(FileError::parse(path, &data, e)),
+        }
+    };
+
+    let mut result = attempt();
+    let mut attempts = 1;
+    let mut delay = RETRY_DELAY;
+    while result.is_err() && attempts <= config.retries {
+        thread::sleep(delay);
+        delay = (delay * 2).min(MAX_RETRY_DELAY);
+        result = attempt();
+        attempts += 1;
+    }
+    result.map_err(|mut error| {
+        error.attempts = attempts;
+        if let Some(dir) = config.quarantine_dir.as_ref().filter(|_| !dry_run) {
+            error.quarantined = quarantine(path, dir).ok();
+        }
+        error
+    })
+}
+
+/// Name of the quarantined copy of the file at `path`: its stem, a hash of the full path, and its
+/// extension (`game.slp` becomes `game.1f2e3d4c5b6a7988.slp`).
+fn quarantine_name(path: &Path) -> PathBuf {
+    let digest = Sha256::digest(path.to_string_lossy().as_bytes());
+    let hash: String = digest[..8].iter().map(|b| format!("{b:02x}")).collect();
+    let mut name = path.file_stem().unwrap_or_default().to_os_string();
+    name.push(format!(".{hash}"));
+    if let Some(extension) = path.extension() {
+        name.push(".");
+        name.push(extension);
+    }
+    name.into()
+}
+
+/// Copy a failing file into the quarantine directory `dir`, returning the copy's path.
+fn quarantine(path: &Path, dir: &Path) -> io::Result<String> {
+    fs::create_dir_all(dir)?;
+    let copy = dir.join(quarantine_name(path));
+    fs::copy(path, &copy)?;
+    Ok(copy.to_string_lossy().into_owned())
+}
+
+/// Path of the errors file of a batch job writing to `output`.
+pub fn path_for(output: &Path) -> PathBuf {
+    let mut name = output.file_name().unwrap_or_default().to_os_string();
+    name.push(".errors.json");
+    output.with_file_name(name)
+}
+
+/// Write the errors of a batch job writing to `output`, replacing those of any previous run.
+pub fn write(output: &Path, errors: &[FileError]) -> io::Result<()> {
+    atomic::write_json(&path_for(output), errors, true)
+}
+
+/// Read the errors of the last batch job that wrote to `output`, if it recorded any.
+pub fn read(output: &Path) -> io::Result<Vec<FileError>> {
+    match fs::File::open(path_for(output)) {
+        Ok(file) => Ok(serde_json::from_reader(io::BufReader::new(file))?),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quarantined_copies_of_files_with_the_same_name_differ() {
+        let a = quarantine_name(Path::new("a/Game_1.slp"));
+        let b = quarantine_name(Path::new("b/Game_1.slp"));
+        assert_ne!(a, b);
+        assert_eq!(a, quarantine_name(Path::new("a/Game_1.slp")));
+        let a = a.to_string_lossy();
+        assert!(a.starts_with("Game_1.") && a.ends_with(".slp"));
+        assert_eq!(a.len(), "Game_1..slp".len() + 16);
+        assert!(quarantine_name(Path::new("a/README")).to_string_lossy().starts_with("README."));
+    }
+}
@@ -0,0 +1,208 @@
+//! Excitement scores for ranking highlights
+//!
+//! Highlight pipelines go through a tournament's worth of replays looking for the moments worth a
+//! clip. An excitement score turns each game into a timeline that can be ranked: every frame adds
+//! the excitement of what happened on it, and the score is a running total that decays with a
+//! half-life of two seconds, so a flurry of action builds up and quiet play lets it fall.
+//!
+//! - Damage bursts: every point of damage dealt (phantom hits aside) adds 1.
+//! - Stock swings: a lost stock adds 40, doubled when it costs the player the lead or a tie.
+//! - Close calls: getting knocked back within 20 units of a blast zone and living adds 30. Stages
+//!   without known geometry have none.
+//!
+//! A game is scored per segment of a chosen length, as the mean of the timeline over the segment,
+//! and as a whole. The weights are hand-picked, so scores are for ranking, not for reading on their
+//! own.
+
+use std::path::Path;
+
+use peppi::frame::immutable::Frame;
+use serde::Serialize;
+
+use crate::catalog::Entry;
+use crate::errors::{self, FileError};
+use crate::{batch, config, hits, stages};
+
+/// Frames over which the running score halves.
+const HALF_LIFE: f64 = 120.0;
+const DAMAGE: f64 = 1.0;
+const STOCK: f64 = 40.0;
+const CLOSE_CALL: f64 = 30.0;
+/// Distance from a blast zone within which knockback counts as a close call, in game units.
+const CLOSE_CALL_DISTANCE: f32 = 20.0;
+/// Frames after a close call within which losing a stock means it wasn't survived.
+const SURVIVAL_WINDOW: usize = 60;
+
+#[derive(Debug, Serialize)]
+pub struct Segment {
+    /// First and last frame index (as stored in the replay) of the segment
+    pub start: i32,
+    pub end: i32,
+    pub score: f64,
+    /// Damage dealt in the segment, phantom hits aside
+    pub damage: f32,
+    pub stocks_lost: usize,
+    pub close_calls: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Excitement {
+    /// Mean of the timeline over the whole game
+    pub score: f64,
+    /// Highest point of the timeline, and the frame index it's reached on
+    pub peak: f64,
+    pub peak_frame: i32,
+    pub segments: Vec<Segment>,
+}
+
+/// What happened on one row of the frame arrays.
+#[derive(Default, Clone, Copy)]
+struct Moment {
+    damage: f32,
+    stocks_lost: usize,
+    swings: usize,
+    close_calls: usize,
+}
+
+impl Moment {
+    fn excitement(&self) -> f64 {
+        DAMAGE * self.damage as f64
+            + STOCK * (self.stocks_lost + self.swings) as f64
+            + CLOSE_CALL * self.close_calls as f64
+    }
+}
+
+fn moments(frames: &Frame, stage: u16) -> Vec<Moment> {
+    let len = frames.id.len();
+    let mut moments = vec![Moment::default(); len];
+    for hit in hits::detect(frames).iter().filter(|h| !h.phantom) {
+        moments[hit.index].damage += hit.damage;
+    }
+
+    let stocks = |i: usize| -> Vec<u8> { frames.ports.iter().map(|p| p.leader.post.stocks.value(i)).collect() };
+    for i in 1..len {
+        let (before, after) = (stocks(i - 1), stocks(i));
+        let lead = before.iter().copied().max().unwrap_or(0);
+        for (b, a) in before.iter().zip(&after) {
+            if a < b {
+                moments[i].stocks_lost += 1;
+                // The player was leading or tied, and isn't any more.
+                let swung = *b == lead && after.iter().any(|other| other > a);
+                moments[i].swings += swung as usize;
+            }
+        }
+    }
+
+    if let Some(geometry) = stages::geometry(stage) {
+        let zones = geometry.blast_zones;
+        for port in &frames.ports {
+            let post = &port.leader.post;
+            let mut close = false;
+            for i in 0..len {
+                let (x, y) = (post.position.x.value(i), post.position.y.value(i));
+                let edge = (x - zones.left)
+                    .min(zones.right - x)
+                    .min(zones.top - y)
+                    .min(y - zones.bottom);
+                let was_close = close;
+                close = hits::DAMAGE_STATES.contains(&post.state.value(i)) && edge <= CLOSE_CALL_DISTANCE;
+                if close && !was_close {
+                    let window = i..(i + SURVIVAL_WINDOW).min(len);
+                    let survived = window.all(|j| post.stocks.value(j) == post.stocks.value(i));
+                    moments[i].close_calls += survived as usize;
+                }
+            }
+        }
+    }
+    moments
+}
+
+/// Score a game on `stage` in segments of `segment_frames` frames (the whole game in one when 0).
+pub fn score(frames: &Frame, stage: u16, segment_frames: usize) -> Excitement {
+    let moments = moments(frames, stage);
+    let decay = 0.5f64.powf(1.0 / HALF_LIFE);
+    let mut level = 0.0;
+    let timeline: Vec<f64> = moments
+        .iter()
+        .map(|m| {
+            level = level * decay + m.excitement();
+            level
+        })
+        .collect();
+
+    let mean = |values: &[f64]| values.iter().sum::<f64>() / values.len().max(1) as f64;
+    let segment_frames = if segment_frames == 0 {
+        timeline.len().max(1)
+    } else {
+        segment_frames
+    };
+    let segments = timeline
+        .chunks(segment_frames)
+        .zip(moments.chunks(segment_frames))
+        .enumerate()
+        .map(|(n, (levels, moments))| {
+            let start = n * segment_frames;
+            Segment {
+                start: frames.id.value(start),
+                end: frames.id.value(start + levels.len() - 1),
+                score: mean(levels),
+                damage: moments.iter().map(|m| m.damage).sum(),
+                stocks_lost: moments.iter().map(|m| m.stocks_lost).sum(),
+                close_calls: moments.iter().map(|m| m.close_calls).sum(),
+            }
+        })
+        .collect();
+
+    let peak = timeline.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1));
+    Excitement {
+        score: mean(&timeline),
+        peak: peak.map_or(0.0, |(_, &p)| p),
+        peak_frame: peak.map_or(0, |(i, _)| frames.id.value(i)),
+        segments,
+    }
+}
+
+/// A segment of a game in a catalog, for ranking across games.
+#[derive(Debug, Serialize)]
+pub struct Highlight {
+    pub path: String,
+    #[serde(flatten)]
+    pub segment: Segment,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Ranking {
+    pub highlights: Vec<Highlight>,
+    pub failed: Vec<FileError>,
+}
+
+/// The `top` most exciting segments of `segment_frames` frames (whole games when 0) across the games
+/// of a catalog, best first, scored on `threads` threads (as many as configured for batch work when
+/// 0).
+pub fn rank(entries: &[Entry], segment_frames: usize, top: usize, threads: usize) -> Ranking {
+    let threads = if threads == 0 { config::get().threads } else { threads };
+    let scored = batch::map(entries, threads, |entry| -> Result<Vec<Segment>, FileError> {
+        let (_, game) = errors::read_replay(Path::new(&entry.path), None, false)?;
+        let version = game.start.slippi.version;
+        let frames = game
+            .frames
+            .into_struct_array(version, &crate::port_occupancy(&game.start));
+        let frames = Frame::from_struct_array(frames, version);
+        Ok(score(&frames, game.start.stage, segment_frames).segments)
+    });
+
+    let mut highlights = Vec::new();
+    let mut failed = Vec::new();
+    for (entry, segments) in entries.iter().zip(scored) {
+        match segments {
+            Ok(segments) => highlights.extend(segments.into_iter().map(|segment| Highlight {
+                path: entry.path.clone(),
+                segment,
+            })),
+            Err(e) => failed.push(e),
+        }
+    }
+    highlights.sort_by(|a, b| b.segment.score.total_cmp(&a.segment.score));
+    highlights.truncate(top);
+    Ranking { highlights, failed }
+}
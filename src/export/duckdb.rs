@@ -0,0 +1,132 @@
+//! DuckDB sink, appending frame and hit tables straight into a database file
+//!
+//! Tables are created on first use with column types matching the Arrow data types, and every row
+//! is tagged with a `game` column so many games can share one database. Appending to a `frames`
+//! table with other columns (written under another follower policy, say) is an error.
+
+use std::error::Error;
+
+use arrow2::array::{Array, BooleanArray, PrimitiveArray, StructArray};
+use arrow2::datatypes::DataType;
+use duckdb::types::Value;
+use duckdb::{Connection, appender_params_from_iter, params};
+
+use crate::config;
+use crate::hits::Hit;
+
+fn sql_type(data_type: &DataType) -> Option<&'static str> {
+    Some(match data_type {
+        DataType::Int8 => "TINYINT",
+        DataType::Int16 => "SMALLINT",
+        DataType::Int32 => "INTEGER",
+        DataType::Int64 => "BIGINT",
+        DataType::UInt8 => "UTINYINT",
+        DataType::UInt16 => "USMALLINT",
+        DataType::UInt32 => "UINTEGER",
+        DataType::UInt64 => "UBIGINT",
+        DataType::Float32 => "FLOAT",
+        DataType::Float64 => "DOUBLE",
+        DataType::Boolean => "BOOLEAN",
+        _ => return None,
+    })
+}
+
+fn value(array: &dyn Array, i: usize) -> Value {
+    macro_rules! primitive {
+        ($t:ty, $variant:ident) => {
+            Value::$variant(
+                array
+                    .as_any()
+                    .downcast_ref::<PrimitiveArray<$t>>()
+                    .expect("data type matches array")
+                    .value(i),
+            )
+        };
+    }
+
+    if !array.is_valid(i) {
+        return Value::Null;
+    }
+    match array.data_type() {
+        DataType::Int8 => primitive!(i8, TinyInt),
+        DataType::Int16 => primitive!(i16, SmallInt),
+        DataType::Int32 => primitive!(i32, Int),
+        DataType::Int64 => primitive!(i64, BigInt),
+        DataType::UInt8 => primitive!(u8, UTinyInt),
+        DataType::UInt16 => primitive!(u16, USmallInt),
+        DataType::UInt32 => primitive!(u32, UInt),
+        DataType::UInt64 => primitive!(u64, UBigInt),
+        DataType::Float32 => primitive!(f32, Float),
+        DataType::Float64 => primitive!(f64, Double),
+        DataType::Boolean => Value::Boolean(
+            array.as_any().downcast_ref::<BooleanArray>().expect("data type matches array").value(i),
+        ),
+        _ => Value::Null,
+    }
+}
+
+/// Append a game's frames and hit table to the `frames` and `hits` tables of the DuckDB database at
+/// `path`, creating the database and tables if needed.
+pub fn append(
+    path: &str,
+    game: &str,
+    frames: &StructArray,
+    hits: &[Hit],
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let conn = Connection::open(path)?;
+
+    // Columns DuckDB has no single type for (like per-frame item lists) are left out.
+    let columns: Vec<_> = super::flatten(frames, config::get().follower_policy)
+        .into_iter()
+        .filter_map(|(name, array)| Some((name, sql_type(array.data_type())?, array)))
+        .collect();
+    let definitions: Vec<String> = columns
+        .iter()
+        .map(|(name, sql_type, _)| format!("\"{name}\" {sql_type}"))
+        .collect();
+    conn.execute_batch(&format!(
+        "CREATE TABLE IF NOT EXISTS frames (game VARCHAR, {});
+         CREATE TABLE IF NOT EXISTS hits (game VARCHAR, frame INTEGER, victim UTINYINT,
+             attacker UTINYINT, damage FLOAT, percent FLOAT, hitlag FLOAT, trade BOOLEAN,
+             trade_winner UTINYINT, phantom BOOLEAN, meteor BOOLEAN, meteor_cancel UINTEGER);",
+        definitions.join(", ")
+    ))?;
+    let expected: Vec<String> = std::iter::once("game".to_string())
+        .chain(columns.iter().map(|(name, _, _)| name.clone()))
+        .collect();
+    let found: Vec<String> = conn
+        .prepare(
+            "SELECT column_name FROM information_schema.columns WHERE table_name = 'frames' \
+             ORDER BY ordinal_position",
+        )?
+        .query_map([], |row| row.get(0))?
+        .collect::<duckdb::Result<_>>()?;
+    super::check_columns("frames", &found, &expected)?;
+
+    let mut appender = conn.appender("frames")?;
+    for i in 0..frames.len() {
+        let row = std::iter::once(Value::Text(game.to_string()))
+            .chain(columns.iter().map(|(_, _, array)| value(array.as_ref(), i)));
+        appender.append_row(appender_params_from_iter(row))?;
+    }
+    appender.flush()?;
+
+    let mut appender = conn.appender("hits")?;
+    for hit in hits {
+        appender.append_row(params![
+            game,
+            hit.frame,
+            hit.victim as u8,
+            hit.attacker.map(|p| p as u8),
+            hit.damage,
+            hit.percent,
+            hit.hitlag,
+            hit.trade,
+            hit.trade_winner.map(|p| p as u8),
+            hit.phantom,
+            hit.meteor,
+            hit.meteor_cancel,
+        ])?;
+    }
+    Ok(appender.flush()?)
+}
@@ -0,0 +1,81 @@
+//! HDF5 export, for research groups that standardize on HDF5 from MATLAB or Python
+//!
+//! Each game is written to its own group, holding a `frames` group with one dataset per flattened
+//! frame column and a `hits` group with one dataset per hit table column. Null values are written
+//! as whatever the Arrow buffer holds in their slot, since HDF5 datasets have no validity mask.
+
+use arrow2::array::{Array, BooleanArray, PrimitiveArray, StructArray};
+use arrow2::datatypes::DataType;
+use hdf5::{Group, H5Type, Result};
+
+use crate::config;
+use crate::hits::Hit;
+
+fn write_dataset<T: H5Type>(group: &Group, name: &str, values: &[T]) -> Result<()> {
+    group
+        .new_dataset::<T>()
+        .shape(values.len())
+        .create(name)?
+        .write(values)
+}
+
+fn write_column(group: &Group, name: &str, array: &dyn Array) -> Result<()> {
+    macro_rules! primitive {
+        ($t:ty) => {
+            write_dataset(
+                group,
+                name,
+                array
+                    .as_any()
+                    .downcast_ref::<PrimitiveArray<$t>>()
+                    .expect("data type matches array")
+                    .values(),
+            )
+        };
+    }
+
+    match array.data_type() {
+        DataType::Int8 => primitive!(i8),
+        DataType::Int16 => primitive!(i16),
+        DataType::Int32 => primitive!(i32),
+        DataType::Int64 => primitive!(i64),
+        DataType::UInt8 => primitive!(u8),
+        DataType::UInt16 => primitive!(u16),
+        DataType::UInt32 => primitive!(u32),
+        DataType::UInt64 => primitive!(u64),
+        DataType::Float32 => primitive!(f32),
+        DataType::Float64 => primitive!(f64),
+        DataType::Boolean => {
+            let array = array.as_any().downcast_ref::<BooleanArray>().expect("data type matches array");
+            let values: Vec<u8> = array.values_iter().map(u8::from).collect();
+            write_dataset(group, name, &values)
+        }
+        // Item lists and other nested types don't map onto a single dataset.
+        _ => Ok(()),
+    }
+}
+
+/// Write a game's frames and hit table to `group_name` within the HDF5 file at `path`, creating
+/// the file if needed.
+pub fn write(path: &str, group_name: &str, frames: &StructArray, hits: &[Hit]) -> Result<()> {
+    let file = hdf5::File::append(path)?;
+    let game = file.create_group(group_name)?;
+
+    let frames_group = game.create_group("frames")?;
+    for (name, column) in super::flatten(frames, config::get().follower_policy) {
+        write_column(&frames_group, &name, column.as_ref())?;
+    }
+
+    let hits_group = game.create_group("hits")?;
+    let column = |f: fn(&Hit) -> f32| hits.iter().map(f).collect::<Vec<_>>();
+    write_dataset(&hits_group, "frame", &hits.iter().map(|h| h.frame).collect::<Vec<_>>())?;
+    write_dataset(&hits_group, "victim", &hits.iter().map(|h| h.victim as u8).collect::<Vec<_>>())?;
+    // Hits without a known attacker are written as -1.
+    let attacker: Vec<i8> = hits.iter().map(|h| h.attacker.map_or(-1, |p| p as i8)).collect();
+    write_dataset(&hits_group, "attacker", &attacker)?;
+    write_dataset(&hits_group, "damage", &column(|h| h.damage))?;
+    write_dataset(&hits_group, "percent", &column(|h| h.percent))?;
+    write_dataset(&hits_group, "trade", &hits.iter().map(|h| h.trade as u8).collect::<Vec<_>>())?;
+    write_dataset(&hits_group, "phantom", &hits.iter().map(|h| h.phantom as u8).collect::<Vec<_>>())?;
+    Ok(())
+}
@@ -0,0 +1,145 @@
+//! Exporters writing frame and stats tables to formats other than Arrow IPC
+//!
+//! Most destinations don't understand nested structs, so frames are first flattened into one column
+//! per leaf field, named by joining the field path with dots (e.g. `ports.P1.leader.post.percent`).
+//!
+//! Only Ice Climbers have follower (Nana) data, so left alone the set of columns would depend on
+//! the characters played. A [FollowerPolicy] fixes the layout instead, so that tensors built from
+//! many games line up.
+
+#[cfg(any(feature = "duckdb", feature = "postgres"))]
+use std::io;
+use std::str::FromStr;
+
+use arrow2::array::{Array, StructArray, new_null_array};
+use serde::Serialize;
+
+#[cfg(feature = "duckdb")]
+pub mod duckdb;
+#[cfg(feature = "hdf5")]
+pub mod hdf5;
+pub mod parquet;
+pub mod postgres;
+
+/// How follower (Nana) columns are laid out in flattened exports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FollowerPolicy {
+    /// Every port gets a block of `ports.Pn.follower.*` columns after its leader block, null when
+    /// the port has no follower.
+    Separate,
+    /// As `Separate`, but each follower column directly follows the matching leader column.
+    Merged,
+    /// Follower columns are left out.
+    Dropped,
+}
+
+impl FromStr for FollowerPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "separate" => Ok(FollowerPolicy::Separate),
+            "merged" => Ok(FollowerPolicy::Merged),
+            "dropped" => Ok(FollowerPolicy::Dropped),
+            _ => Err(format!("unknown follower policy: {s}")),
+        }
+    }
+}
+
+/// Check that the columns `found` in an existing `table` are the `expected` ones, in order, so a
+/// game flattened differently (under another follower policy or column selection) isn't appended
+/// to it.
+#[cfg(any(feature = "duckdb", feature = "postgres"))]
+fn check_columns(table: &str, found: &[String], expected: &[String]) -> io::Result<()> {
+    if found == expected {
+        return Ok(());
+    }
+    let missing = expected.iter().find(|c| !found.contains(c));
+    let extra = found.iter().find(|c| !expected.contains(c));
+    let detail = match (missing, extra) {
+        (Some(c), _) => format!("it has no column {c}"),
+        (None, Some(c)) => format!("it has an extra column {c}"),
+        (None, None) => "its columns are in another order".to_string(),
+    };
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("table {table} has a different schema: {detail}"),
+    ))
+}
+
+/// Flatten nested struct arrays into `(name, array)` pairs for every leaf column, laying out
+/// follower columns according to `policy`.
+pub fn flatten(array: &StructArray, policy: FollowerPolicy) -> Vec<(String, Box<dyn Array>)> {
+    let (followers, others): (Vec<_>, Vec<_>) = leaves(array)
+        .into_iter()
+        .partition(|(name, _)| matches!(port_field(name), Some((_, "follower", _))));
+    if policy == FollowerPolicy::Dropped {
+        return others;
+    }
+
+    let mut followers: Vec<_> = followers.into_iter().map(Some).collect();
+    let mut columns = Vec::new();
+    // Follower columns for the current port's leader block, under `Separate`
+    let mut block = Vec::new();
+    let mut block_port = None;
+    for (name, array) in others {
+        let leader = match port_field(&name) {
+            Some((port, "leader", field)) => Some((port.to_string(), field.to_string())),
+            _ => None,
+        };
+        let port = leader.as_ref().map(|(port, _)| port.clone());
+        if port != block_port {
+            columns.append(&mut block);
+            block_port = port;
+        }
+        let Some((port, field)) = leader else {
+            columns.push((name, array));
+            continue;
+        };
+
+        // Pair the leader column with its follower column, or an all-null stand-in.
+        let follower_name = format!("ports.{port}.follower.{field}");
+        let follower = followers
+            .iter_mut()
+            .find(|f| f.as_ref().is_some_and(|(n, _)| *n == follower_name))
+            .and_then(Option::take)
+            .unwrap_or_else(|| {
+                let nulls = new_null_array(array.data_type().clone(), array.len());
+                (follower_name, nulls)
+            });
+        columns.push((name, array));
+        match policy {
+            FollowerPolicy::Merged => columns.push(follower),
+            _ => block.push(follower),
+        }
+    }
+    columns.append(&mut block);
+    columns
+}
+
+/// Split a column named `ports.<port>.<role>.<field>` into its port, role, and field.
+fn port_field(name: &str) -> Option<(&str, &str, &str)> {
+    let mut parts = name.strip_prefix("ports.")?.splitn(3, '.');
+    Some((parts.next()?, parts.next()?, parts.next()?))
+}
+
+/// Every leaf column of nested struct arrays, exactly as nested (no follower policy applied).
+pub fn leaves(array: &StructArray) -> Vec<(String, Box<dyn Array>)> {
+    let mut columns = Vec::new();
+    flatten_into(array, "", &mut columns);
+    columns
+}
+
+fn flatten_into(array: &StructArray, prefix: &str, columns: &mut Vec<(String, Box<dyn Array>)>) {
+    for (field, values) in array.fields().iter().zip(array.values()) {
+        let name = match prefix {
+            "" => field.name.clone(),
+            _ => format!("{prefix}.{}", field.name),
+        };
+        match values.as_any().downcast_ref::<StructArray>() {
+            Some(inner) => flatten_into(inner, &name, columns),
+            None => columns.push((name, values.clone())),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Parquet writer for frame data
+//!
+//! Parquet is what DuckDB, Spark, and most data lake tooling read natively. It understands nested
+//! structs, so unlike the table exporters the frames keep their shape: one top-level column per
+//! field of the frames struct (`id`, `ports`, ...), with ports nested below.
+//!
+//! Rows are split into row groups of a chosen size, the unit readers skip or parallelize over.
+//!
+//! Files are stamped with the [schema version](crate::schema) they were written with.
+
+use std::fs;
+use std::str::FromStr;
+
+use arrow2::array::{Array, StructArray};
+use arrow2::chunk::Chunk;
+use arrow2::compute::concatenate::concatenate;
+use arrow2::datatypes::{DataType, Metadata, Schema};
+use arrow2::error::Result;
+use arrow2::io::parquet::read;
+use arrow2::io::parquet::write::{
+    CompressionOptions, Encoding, FileWriter, RowGroupIterator, Version, WriteOptions, transverse,
+};
+
+/// Compression of Parquet pages.
+#[derive(Debug, Clone, Copy)]
+pub struct Compression(CompressionOptions);
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(Compression(match s {
+            "" | "none" => CompressionOptions::Uncompressed,
+            "snappy" => CompressionOptions::Snappy,
+            "gzip" => CompressionOptions::Gzip(None),
+            // Files written with the deprecated LZ4 framing are rewritten with LZ4 raw.
+            "lz4" | "lz4raw" => CompressionOptions::Lz4Raw,
+            "zstd" => CompressionOptions::Zstd(None),
+            _ => return Err(format!("unknown Parquet compression: {s}")),
+        }))
+    }
+}
+
+/// Write `frames` to `file` as Parquet, in row groups of `row_group_size` rows (one row group when
+/// 0), with `metadata` as its schema's metadata.
+pub fn write(
+    file: &mut fs::File,
+    frames: &StructArray,
+    compression: Compression,
+    row_group_size: usize,
+    metadata: Metadata,
+) -> Result<()> {
+    let schema = Schema::from(frames.fields().to_vec()).with_metadata(metadata);
+    let options = WriteOptions {
+        write_statistics: true,
+        compression: compression.0,
+        version: Version::V2,
+        data_pagesize_limit: None,
+    };
+    let encodings = schema
+        .fields
+        .iter()
+        .map(|f| transverse(&f.data_type, |_| Encoding::Plain))
+        .collect();
+
+    let len = frames.len();
+    let size = match row_group_size {
+        0 => len.max(1),
+        n => n,
+    };
+    let chunks = (0..len).step_by(size).map(|start| {
+        let columns = frames.values().iter().map(|c| c.sliced(start, size.min(len - start))).collect();
+        Chunk::try_new(columns)
+    });
+    let row_groups = RowGroupIterator::try_new(chunks, &schema, options, encodings)?;
+
+    let mut writer = FileWriter::try_new(file, schema, options)?;
+    for group in row_groups {
+        writer.write(group?)?;
+    }
+    writer.end(None)?;
+    Ok(())
+}
+
+/// Read a Parquet file written by [write], returning its frames, its schema's metadata, and the
+/// compression and row group size it was written with.
+pub fn read(file: &mut fs::File) -> Result<(StructArray, Metadata, Compression, usize)> {
+    let metadata = read::read_metadata(file)?;
+    let schema = read::infer_schema(&metadata)?;
+    let column = metadata.row_groups.first().and_then(|g| g.columns().first());
+    let compression = column
+        .and_then(|c| format!("{:?}", c.compression()).to_lowercase().parse().ok())
+        .unwrap_or(Compression(CompressionOptions::Uncompressed));
+    let row_group_size = metadata.row_groups.first().map_or(0, |g| g.num_rows());
+
+    let fields = schema.fields.clone();
+    let mut columns: Vec<Vec<Box<dyn Array>>> = vec![Vec::new(); fields.len()];
+    for chunk in read::FileReader::new(file, metadata.row_groups, schema.clone(), None, None, None) {
+        for (i, column) in chunk?.into_arrays().into_iter().enumerate() {
+            columns[i].push(column);
+        }
+    }
+    let values = columns
+        .iter()
+        .zip(&fields)
+        .map(|(chunks, field)| match chunks.as_slice() {
+            [] => Ok(arrow2::array::new_empty_array(field.data_type.clone())),
+            chunks => concatenate(&chunks.iter().map(|c| c.as_ref()).collect::<Vec<_>>()),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let frames = StructArray::new(DataType::Struct(fields), values, None);
+    Ok((frames, schema.metadata, compression, row_group_size))
+}
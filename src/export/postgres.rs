@@ -0,0 +1,201 @@
+//! PostgreSQL bulk-load export
+//!
+//! Tables are written in `COPY ... (FORMAT csv)` form, either to files (alongside a `schema.sql`
+//! with matching `CREATE TABLE` statements) for loading with `\copy`, or streamed straight into a
+//! database through `COPY FROM STDIN` (with the `postgres` feature). Both are far faster than
+//! inserting rows one at a time. Copying into a `frames` table with other columns (written under
+//! another follower policy, say) is an error.
+
+#[cfg(feature = "postgres")]
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use arrow2::array::{Array, BooleanArray, PrimitiveArray, StructArray};
+use arrow2::datatypes::DataType;
+#[cfg(feature = "postgres")]
+use postgres::{Client, NoTls};
+
+use crate::atomic;
+use crate::config;
+use crate::hits::Hit;
+
+/// Postgres has no unsigned types, so each is widened to the next signed type up.
+fn sql_type(data_type: &DataType) -> Option<&'static str> {
+    Some(match data_type {
+        DataType::Int8 | DataType::Int16 | DataType::UInt8 => "SMALLINT",
+        DataType::Int32 | DataType::UInt16 => "INTEGER",
+        DataType::Int64 | DataType::UInt32 => "BIGINT",
+        DataType::UInt64 => "NUMERIC(20)",
+        DataType::Float32 => "REAL",
+        DataType::Float64 => "DOUBLE PRECISION",
+        DataType::Boolean => "BOOLEAN",
+        _ => return None,
+    })
+}
+
+fn float(v: f64) -> String {
+    match v {
+        f64::INFINITY => "Infinity".to_string(),
+        f64::NEG_INFINITY => "-Infinity".to_string(),
+        _ => v.to_string(),
+    }
+}
+
+/// CSV cell for row `i` of `array` (empty for null, as `COPY` expects).
+fn cell(array: &dyn Array, i: usize) -> String {
+    macro_rules! primitive {
+        ($t:ty) => {
+            array.as_any().downcast_ref::<PrimitiveArray<$t>>().expect("data type matches array").value(i)
+        };
+    }
+
+    if !array.is_valid(i) {
+        return String::new();
+    }
+    match array.data_type() {
+        DataType::Int8 => primitive!(i8).to_string(),
+        DataType::Int16 => primitive!(i16).to_string(),
+        DataType::Int32 => primitive!(i32).to_string(),
+        DataType::Int64 => primitive!(i64).to_string(),
+        DataType::UInt8 => primitive!(u8).to_string(),
+        DataType::UInt16 => primitive!(u16).to_string(),
+        DataType::UInt32 => primitive!(u32).to_string(),
+        DataType::UInt64 => primitive!(u64).to_string(),
+        DataType::Float32 => float(primitive!(f32) as f64),
+        DataType::Float64 => float(primitive!(f64)),
+        DataType::Boolean => array
+            .as_any()
+            .downcast_ref::<BooleanArray>()
+            .expect("data type matches array")
+            .value(i)
+            .to_string(),
+        _ => String::new(),
+    }
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\"\""))
+}
+
+fn opt<T: ToString>(v: Option<T>) -> String {
+    v.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// `CREATE TABLE` statements for the `frames` and `hits` tables, and the names of the frame columns
+/// they cover with their arrays.
+fn schema(frames: &StructArray) -> (String, Vec<(String, Box<dyn Array>)>) {
+    let mut definitions = vec!["game TEXT".to_string()];
+    let mut columns = Vec::new();
+    for (name, array) in super::flatten(frames, config::get().follower_policy) {
+        // Columns Postgres has no single type for (like per-frame item lists) are left out.
+        if let Some(sql_type) = sql_type(array.data_type()) {
+            definitions.push(format!("{} {sql_type}", quote(&name)));
+            columns.push((name, array));
+        }
+    }
+    let sql = format!(
+        "CREATE TABLE IF NOT EXISTS frames ({});\n\
+         CREATE TABLE IF NOT EXISTS hits (game TEXT, frame INTEGER, victim SMALLINT, \
+         attacker SMALLINT, damage REAL, percent REAL, hitlag REAL, trade BOOLEAN, \
+         trade_winner SMALLINT, phantom BOOLEAN, meteor BOOLEAN, meteor_cancel BIGINT);\n",
+        definitions.join(", ")
+    );
+    (sql, columns)
+}
+
+fn write_frames<W: Write>(
+    mut w: W,
+    game: &str,
+    columns: &[(String, Box<dyn Array>)],
+    len: usize,
+) -> io::Result<()> {
+    let game = quote(game);
+    let mut line = String::new();
+    for i in 0..len {
+        line.clear();
+        line.push_str(&game);
+        for (_, array) in columns {
+            line.push(',');
+            line.push_str(&cell(array.as_ref(), i));
+        }
+        line.push('\n');
+        w.write_all(line.as_bytes())?;
+    }
+    Ok(())
+}
+
+fn write_hits<W: Write>(mut w: W, game: &str, hits: &[Hit]) -> io::Result<()> {
+    let game = quote(game);
+    for hit in hits {
+        let mut line = String::new();
+        let _ = writeln!(
+            line,
+            "{game},{},{},{},{},{},{},{},{},{},{},{}",
+            hit.frame,
+            hit.victim as u8,
+            opt(hit.attacker.map(|p| p as u8)),
+            float(hit.damage as f64),
+            float(hit.percent as f64),
+            opt(hit.hitlag.map(|h| float(h as f64))),
+            hit.trade,
+            opt(hit.trade_winner.map(|p| p as u8)),
+            hit.phantom,
+            opt(hit.meteor),
+            opt(hit.meteor_cancel),
+        );
+        w.write_all(line.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Write `schema.sql`, `frames.csv`, and `hits.csv` to `dir`, ready for `\copy ... (FORMAT csv)`.
+pub fn write_copy_files(dir: &Path, game: &str, frames: &StructArray, hits: &[Hit]) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let (sql, columns) = schema(frames);
+    atomic::write_bytes(&dir.join("schema.sql"), sql.as_bytes())?;
+    atomic::write(&dir.join("frames.csv"), |file| {
+        let mut w = io::BufWriter::new(file);
+        write_frames(&mut w, game, &columns, frames.len())?;
+        w.flush()
+    })?;
+    atomic::write(&dir.join("hits.csv"), |file| {
+        let mut w = io::BufWriter::new(file);
+        write_hits(&mut w, game, hits)?;
+        w.flush()
+    })
+}
+
+/// Create the `frames` and `hits` tables if needed, and `COPY` a game's rows into them over the
+/// connection described by `url`.
+#[cfg(feature = "postgres")]
+pub fn copy(url: &str, game: &str, frames: &StructArray, hits: &[Hit]) -> Result<(), Box<dyn Error>> {
+    let mut client = Client::connect(url, NoTls)?;
+    let (sql, columns) = schema(frames);
+    client.batch_execute(&sql)?;
+    let expected: Vec<String> = std::iter::once("game".to_string())
+        .chain(columns.iter().map(|(name, _)| name.clone()))
+        .collect();
+    let found: Vec<String> = client
+        .query(
+            "SELECT column_name::text FROM information_schema.columns \
+             WHERE table_schema = current_schema() AND table_name = 'frames' \
+             ORDER BY ordinal_position",
+            &[],
+        )?
+        .iter()
+        .map(|row| row.get(0))
+        .collect();
+    super::check_columns("frames", &found, &expected)?;
+
+    let mut writer = client.copy_in("COPY frames FROM STDIN (FORMAT csv)")?;
+    write_frames(&mut writer, game, &columns, frames.len())?;
+    writer.finish()?;
+
+    let mut writer = client.copy_in("COPY hits FROM STDIN (FORMAT csv)")?;
+    write_hits(&mut writer, game, hits)?;
+    writer.finish()?;
+    Ok(())
+}
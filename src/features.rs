@@ -0,0 +1,160 @@
+//! Derived feature columns for exported frames
+//!
+//! Raw positions say little on their own; most models care where a character is relative to the
+//! stage and to each other. When enabled, exports gain a `features` struct column computed from the
+//! raw frames (leaders only, since followers aren't controlled independently).
+//!
+//! Stage-relative features get one block per port (e.g. `features.P1.ledge_distance`):
+//!
+//! - `ledge_distance`: distance to the nearer ledge
+//! - `surface_height`: height above the highest surface below the character (null offstage)
+//! - `blast_left`, `blast_right`, `blast_top`, `blast_bottom`: distance to each blast zone
+//!
+//! These are null on stages without known geometry.
+//!
+//! Relative features get one block per pair of ports, `a` being the lower port (e.g.
+//! `features.P1_P2.separation`):
+//!
+//! - `dx`, `dy`: position of `b` relative to `a`
+//! - `separation`: distance between the two
+//! - `a_facing_b`, `b_facing_a`: whether each faces the other
+//! - `percent_diff`, `stock_diff`: `a`'s percent and stocks minus `b`'s
+//!
+//! Distances are in game units, whatever units the exported positions are in.
+//!
+//! Arrow has no empty structs, so features that would come out with no columns (relative features
+//! alone in a game of one player) are an error rather than an empty `features` column.
+
+use arrow2::array::{Array, BooleanArray, PrimitiveArray, StructArray};
+use arrow2::datatypes::{DataType, Field};
+use peppi::frame::immutable::Frame;
+
+use crate::stages;
+
+/// Version of the derived features, bumped whenever a change alters their values.
+pub const VERSION: u32 = 1;
+
+/// Append the `features` column to `exported`, computed from the game's raw `frames` on `stage`,
+/// with stage-relative and/or relative features.
+pub fn append(
+    exported: &StructArray,
+    frames: &Frame,
+    stage: u16,
+    stage_relative: bool,
+    relative: bool,
+) -> Result<StructArray, String> {
+    let mut blocks = Vec::new();
+    if stage_relative {
+        blocks.extend(stage_blocks(frames, stage)?);
+    }
+    if relative {
+        blocks.extend(relative_blocks(frames)?);
+    }
+
+    let features = struct_array(blocks)?;
+    let mut fields = exported.fields().to_vec();
+    let mut values = exported.values().to_vec();
+    fields.push(Field::new("features", features.data_type().clone(), false));
+    values.push(features.boxed());
+    Ok(StructArray::new(DataType::Struct(fields), values, exported.validity().cloned()))
+}
+
+fn stage_blocks(frames: &Frame, stage: u16) -> Result<Vec<(String, Box<dyn Array>)>, String> {
+    let geometry = stages::geometry(stage);
+    frames
+        .ports
+        .iter()
+        .map(|port| {
+            let position = &port.leader.post.position;
+            let column = |f: &dyn Fn(stages::Geometry, f32, f32) -> Option<f32>| -> Box<dyn Array> {
+                let values = (0..frames.id.len()).map(|i| {
+                    let (x, y) = (position.x.value(i), position.y.value(i));
+                    geometry.and_then(|g| f(g, x, y))
+                });
+                PrimitiveArray::<f32>::from_trusted_len_iter(values).boxed()
+            };
+            let columns = vec![
+                ("ledge_distance".to_string(), column(&|g, x, y| Some(g.ledge_distance(x, y)))),
+                ("surface_height".to_string(), column(&|g, x, y| g.height_above_surface(x, y))),
+                ("blast_left".to_string(), column(&|g, x, _| Some(x - g.blast_zones.left))),
+                ("blast_right".to_string(), column(&|g, x, _| Some(g.blast_zones.right - x))),
+                ("blast_top".to_string(), column(&|g, _, y| Some(g.blast_zones.top - y))),
+                ("blast_bottom".to_string(), column(&|g, _, y| Some(y - g.blast_zones.bottom))),
+            ];
+            Ok((format!("{:?}", port.port), struct_array(columns)?.boxed()))
+        })
+        .collect()
+}
+
+fn relative_blocks(frames: &Frame) -> Result<Vec<(String, Box<dyn Array>)>, String> {
+    let len = frames.id.len();
+    let mut blocks = Vec::new();
+    for (i, a) in frames.ports.iter().enumerate() {
+        for b in &frames.ports[i + 1..] {
+            let (a_post, b_post) = (&a.leader.post, &b.leader.post);
+            let dx = |j| b_post.position.x.value(j) - a_post.position.x.value(j);
+            let dy = |j| b_post.position.y.value(j) - a_post.position.y.value(j);
+            // Facing right is a positive direction, so facing the other means matching the sign
+            // of the offset towards them.
+            let facing = |direction: f32, offset: f32| direction * offset > 0.0;
+
+            let f32_column = |f: &dyn Fn(usize) -> f32| -> Box<dyn Array> {
+                PrimitiveArray::<f32>::from_trusted_len_values_iter((0..len).map(f)).boxed()
+            };
+            let bool_column = |f: &dyn Fn(usize) -> bool| -> Box<dyn Array> {
+                BooleanArray::from_trusted_len_values_iter((0..len).map(f)).boxed()
+            };
+            let columns = vec![
+                ("dx".to_string(), f32_column(&dx)),
+                ("dy".to_string(), f32_column(&dy)),
+                ("separation".to_string(), f32_column(&|j| dx(j).hypot(dy(j)))),
+                (
+                    "a_facing_b".to_string(),
+                    bool_column(&|j| facing(a_post.direction.value(j), dx(j))),
+                ),
+                (
+                    "b_facing_a".to_string(),
+                    bool_column(&|j| facing(b_post.direction.value(j), -dx(j))),
+                ),
+                (
+                    "percent_diff".to_string(),
+                    f32_column(&|j| a_post.percent.value(j) - b_post.percent.value(j)),
+                ),
+                (
+                    "stock_diff".to_string(),
+                    PrimitiveArray::<i16>::from_trusted_len_values_iter(
+                        (0..len).map(|j| a_post.stocks.value(j) as i16 - b_post.stocks.value(j) as i16),
+                    )
+                    .boxed(),
+                ),
+            ];
+            blocks.push((format!("{:?}_{:?}", a.port, b.port), struct_array(columns)?.boxed()));
+        }
+    }
+    Ok(blocks)
+}
+
+/// A struct array of `columns`, of which there must be at least one: Arrow has no empty structs.
+pub fn struct_array(columns: Vec<(String, Box<dyn Array>)>) -> Result<StructArray, String> {
+    if columns.is_empty() {
+        return Err("no columns for a struct".to_string());
+    }
+    let (fields, values): (Vec<Field>, Vec<Box<dyn Array>>) = columns
+        .into_iter()
+        .map(|(name, values)| (Field::new(name, values.data_type().clone(), true), values))
+        .unzip();
+    Ok(StructArray::new(DataType::Struct(fields), values, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn structs_need_a_column() {
+        assert!(struct_array(Vec::new()).is_err());
+        let column = PrimitiveArray::<f32>::from_vec(vec![1.0]).boxed();
+        let array = struct_array(vec![("x".to_string(), column)]).unwrap();
+        assert_eq!(array.fields()[0].name, "x");
+    }
+}
@@ -0,0 +1,107 @@
+//! Follower (Nana) frames aligned with the leader's
+//!
+//! Ice Climbers' follower has frame data of her own, nested in each port's `follower` struct, and
+//! none at all on frames where she's dead, since Slippi stops sending it. A follower table pulls it
+//! out with one row per frame of the game, so it lines up with the leader's columns row for row:
+//!
+//! - `frame`: the frame index
+//! - `alive`: whether Nana is alive on the frame: her data was recorded, and she isn't in a death
+//!   action state
+//! - `pre.*` and `post.*`: her frame data, flattened as in other exports, and null wherever she
+//!   isn't alive
+//!
+//! `alive` is the mask of her rows: the null policy applies to her recorded data as in other
+//! exports, but the rows she isn't alive on stay null whatever the policy, rather than passing
+//! for data as sentinels or values carried over from before she died. Since the policy fills the
+//! frames where she wasn't recorded too, `alive` is read from the frames as recorded.
+//!
+//! Only ports playing Ice Climbers have a follower.
+
+use std::fs;
+use std::ops::RangeInclusive;
+
+use arrow2::array::{Array, BooleanArray, PrimitiveArray, StructArray};
+use arrow2::bitmap::Bitmap;
+use arrow2::chunk::Chunk;
+use arrow2::datatypes::{DataType, Field, Metadata, Schema};
+use arrow2::error::{Error, Result};
+use arrow2::io::ipc::write::FileWriter;
+use peppi::game::Port;
+
+use crate::{export, ipc};
+
+/// Death action states (`DeadDown` through `DeadUpFallHitCameraIce`).
+const DEAD_STATES: RangeInclusive<u16> = 0..=10;
+
+fn field<'a>(array: &'a StructArray, name: &str) -> Option<&'a StructArray> {
+    let i = array.fields().iter().position(|f| f.name == name)?;
+    array.values()[i].as_any().downcast_ref()
+}
+
+fn follower(frames: &StructArray, port: Port) -> Result<&StructArray> {
+    field(frames, "ports")
+        .and_then(|ports| field(ports, &format!("{port:?}")))
+        .and_then(|p| field(p, "follower"))
+        .ok_or_else(|| Error::InvalidArgumentError(format!("no follower on port {port:?}")))
+}
+
+/// Whether Nana is alive on each of the `recorded` frames of `port` (before any export).
+fn alive_mask(recorded: &StructArray, port: Port) -> Result<Bitmap> {
+    let follower = follower(recorded, port)?;
+    let columns = export::leaves(follower);
+    let states = columns
+        .iter()
+        .find(|(name, _)| name == "post.state")
+        .and_then(|(_, a)| a.as_any().downcast_ref::<PrimitiveArray<u16>>())
+        .ok_or_else(|| Error::InvalidArgumentError(format!("no follower state on port {port:?}")))?;
+    Ok((0..follower.len())
+        .map(|i| follower.is_valid(i) && states.get(i).is_some_and(|s| !DEAD_STATES.contains(&s)))
+        .collect())
+}
+
+/// The follower table of `port` in the exported `frames`, and the number of frames Nana is alive
+/// on, which is read from the `recorded` frames they were exported from.
+pub fn table(
+    frames: &StructArray,
+    recorded: &StructArray,
+    port: Port,
+) -> Result<(StructArray, usize)> {
+    let follower = follower(frames, port)?;
+    let frame = frames
+        .fields()
+        .iter()
+        .position(|f| f.name == "id")
+        .map(|i| frames.values()[i].clone())
+        .ok_or_else(|| Error::InvalidArgumentError("no frame index".to_string()))?;
+
+    let columns = export::leaves(follower);
+    let alive = alive_mask(recorded, port)?;
+    if alive.len() != follower.len() {
+        return Err(Error::InvalidArgumentError("recorded frames don't match".to_string()));
+    }
+
+    let mut fields = vec![
+        Field::new("frame", DataType::Int32, false),
+        Field::new("alive", DataType::Boolean, false),
+    ];
+    let alive_frames = alive.len() - alive.unset_bits();
+    let mut arrays = vec![frame, BooleanArray::new(DataType::Boolean, alive.clone(), None).boxed()];
+    for (name, array) in columns {
+        let validity = match array.validity() {
+            Some(validity) => validity & &alive,
+            None => alive.clone(),
+        };
+        fields.push(Field::new(name, array.data_type().clone(), true));
+        arrays.push(array.with_validity(Some(validity)));
+    }
+    Ok((StructArray::new(DataType::Struct(fields), arrays, None), alive_frames))
+}
+
+/// Write a follower table as an Arrow IPC file with the given schema `metadata`, one column per
+/// field.
+pub fn write(file: &mut fs::File, follower: &StructArray, metadata: Metadata) -> Result<()> {
+    let schema = Schema::from(follower.fields().to_vec()).with_metadata(metadata);
+    let mut writer = FileWriter::try_new(file, schema, None, ipc::write_options())?;
+    writer.write(&Chunk::new(follower.values().to_vec()), None)?;
+    writer.finish()
+}
@@ -0,0 +1,174 @@
+//! Grab detection over a game's columnar frame data
+//!
+//! A grab starts when a player enters a held action state and ends on the first frame they leave
+//! it. Each grab records how quickly the victim mashed out relative to the escape timer Melee
+//! starts them with, how many pummels they took, and for throws, how the victim's DI compares to
+//! the optimal (perpendicular to the launch) direction.
+
+use std::ops::RangeInclusive;
+
+use peppi::frame::immutable::{Frame, PortData};
+use peppi::game::Port;
+use serde::Serialize;
+
+use crate::hits::THROWN_STATES;
+
+/// Version of grab detection, bumped whenever a change alters detected grabs.
+pub const VERSION: u32 = 1;
+
+/// Held action states (`CapturePulledHi` through `CaptureDamageLw`).
+const HELD_STATES: RangeInclusive<u16> = 223..=228;
+/// Pummel action states (`CaptureDamageHi` and `CaptureDamageLw`).
+const PUMMEL_STATES: [u16; 2] = [225, 228];
+/// Grab release action states (`CaptureCut` and `CaptureJump`).
+const ESCAPE_STATES: RangeInclusive<u16> = 229..=230;
+/// Grabbing action states (`Catch` through `CatchCut`).
+const GRABBING_STATES: RangeInclusive<u16> = 212..=218;
+
+/// Frames on the escape timer at 0%.
+const GRAB_BASE_FRAMES: f32 = 90.0;
+/// Frames added to the escape timer per percent of damage.
+const GRAB_PERCENT_FRAMES: f32 = 1.7;
+/// Joystick distance from center below which the victim is treated as not holding DI.
+const DI_DEADZONE: f32 = 0.2875;
+
+/// A single row of the grab table.
+#[derive(Debug, Clone, Serialize)]
+pub struct Grab {
+    /// Frame index (as stored in the replay) on which the victim was grabbed
+    pub frame: i32,
+    pub victim: Port,
+    /// Port of the player holding a grabbing action state when the grab started
+    pub grabber: Option<Port>,
+    /// Frames the victim spent held
+    pub frames_held: u32,
+    /// Frames the escape timer started at, based on the victim's percent when grabbed
+    pub expected_frames: f32,
+    pub pummels: u32,
+    /// One of `throw_forward`, `throw_back`, `throw_up`, `throw_down`, `escape`, or `other`
+    pub outcome: &'static str,
+    /// Launch angle of the throw in degrees (Slippi 3.5+)
+    pub launch_angle: Option<f32>,
+    /// Angle in degrees between the victim's DI and the nearest optimal DI direction
+    pub di_offset: Option<f32>,
+}
+
+/// Detect all grabs in the game, ordered by frame and then by victim port.
+pub fn detect(frames: &Frame) -> Vec<Grab> {
+    let mut grabs = Vec::new();
+    for port in &frames.ports {
+        let states = &port.leader.post.state;
+        let mut i = 0;
+        while i < states.len() {
+            let held = |j: usize| HELD_STATES.contains(&states.value(j));
+            if !held(i) || (i > 0 && held(i - 1)) {
+                i += 1;
+                continue;
+            }
+            let end = (i..states.len()).find(|&j| !held(j)).unwrap_or(states.len());
+            grabs.push(grab(frames, port, i, end));
+            i = end;
+        }
+    }
+    grabs.sort_by_key(|g| (g.frame, g.victim as u8));
+    grabs
+}
+
+/// Build the grab whose victim was held on rows `start..end`.
+fn grab(frames: &Frame, victim: &PortData, start: usize, end: usize) -> Grab {
+    let post = &victim.leader.post;
+    let grabber = frames
+        .ports
+        .iter()
+        .find(|p| p.port != victim.port && GRABBING_STATES.contains(&p.leader.post.state.value(start)))
+        .map(|p| p.port);
+    let pummels = (start..end)
+        .filter(|&j| {
+            PUMMEL_STATES.contains(&post.state.value(j))
+                && (j == start || !PUMMEL_STATES.contains(&post.state.value(j - 1)))
+        })
+        .count() as u32;
+
+    let release = (end < post.state.len()).then(|| post.state.value(end));
+    let outcome = match release {
+        Some(239) => "throw_forward",
+        Some(240) => "throw_back",
+        Some(241) => "throw_up",
+        Some(242 | 243) => "throw_down",
+        Some(s) if ESCAPE_STATES.contains(&s) => "escape",
+        _ => "other",
+    };
+    let (launch_angle, di_offset) = match release {
+        Some(s) if THROWN_STATES.contains(&s) => throw_di(victim, end),
+        _ => (None, None),
+    };
+
+    Grab {
+        frame: frames.id.value(start),
+        victim: victim.port,
+        grabber,
+        frames_held: (end - start) as u32,
+        expected_frames: GRAB_BASE_FRAMES + GRAB_PERCENT_FRAMES * post.percent.value(start),
+        pummels,
+        outcome,
+        launch_angle,
+        di_offset,
+    }
+}
+
+/// Launch angle and DI offset of a throw that started on row `thrown`.
+///
+/// The victim is launched on the first frame after the throw with knockback, and their DI is the
+/// stick position on the frame before it. Either value is `None` when the replay predates
+/// velocities, or when the victim is left in the thrown state for the rest of the game.
+fn throw_di(victim: &PortData, thrown: usize) -> (Option<f32>, Option<f32>) {
+    let post = &victim.leader.post;
+    let Some(velocities) = &post.velocities else {
+        return (None, None);
+    };
+    let launch = (thrown..velocities.knockback_x.len()).find(|&j| {
+        velocities.knockback_x.value(j) != 0.0 || velocities.knockback_y.value(j) != 0.0
+    });
+    let Some(launch) = launch else {
+        return (None, None);
+    };
+
+    let angle = velocities
+        .knockback_y
+        .value(launch)
+        .atan2(velocities.knockback_x.value(launch))
+        .to_degrees()
+        .rem_euclid(360.0);
+
+    let pre = &victim.leader.pre;
+    let (x, y) = (pre.joystick.x.value(launch - 1), pre.joystick.y.value(launch - 1));
+    let di_offset = (x.hypot(y) >= DI_DEADZONE).then(|| {
+        let di = y.atan2(x).to_degrees().rem_euclid(360.0);
+        // Optimal survival DI is perpendicular to the launch, on either side
+        let off = (di - angle).rem_euclid(180.0);
+        (off - 90.0).abs()
+    });
+
+    (Some(angle), di_offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::action_state_name;
+
+    fn names(states: impl IntoIterator<Item = u16>) -> Vec<String> {
+        states.into_iter().map(|s| action_state_name(2, s)).collect()
+    }
+
+    #[test]
+    fn state_constants_match_their_names() {
+        assert_eq!(names(PUMMEL_STATES), ["CaptureDamageHi", "CaptureDamageLw"]);
+        let held = names([*HELD_STATES.start(), *HELD_STATES.end()]);
+        assert_eq!(held, ["CapturePulledHi", "CaptureDamageLw"]);
+        assert_eq!(names(ESCAPE_STATES), ["CaptureCut", "CaptureJump"]);
+        assert_eq!(names([*GRABBING_STATES.start(), *GRABBING_STATES.end()]), ["Catch", "CatchCut"]);
+        // The low grab's pull isn't a pummel, though it sits between the two.
+        assert_eq!(names([226]), ["CapturePulledLw"]);
+    }
+}
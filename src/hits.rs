@@ -0,0 +1,233 @@
+//! Hit detection over a game's columnar frame data
+//!
+//! A hit is recorded whenever a player's post-frame percent goes up from one frame to the next.
+//! The attacker is taken from the victim's `last_hit_by` field, and the victim's hitlag (Slippi
+//! 3.8+) is used to decide whether two players hit each other closely enough to count as a trade.
+//!
+//! Phantom hits deal damage without putting the victim into hitlag or knockback. They are flagged
+//! rather than dropped, so damage statistics can choose whether to count them.
+//!
+//! Survival techniques are measured per hit from the victim's hitlag frames: every smash DI input
+//! made during hitlag is listed by direction, and meteor smashes are checked for a jump or special
+//! that cancels them out of knockback.
+
+use std::ops::RangeInclusive;
+
+use peppi::frame::immutable::{Frame, Pre, Post};
+use peppi::game::Port;
+use serde::Serialize;
+
+/// Version of hit detection, bumped whenever a change alters detected hits, so stored hit tables
+/// are recomputed.
+pub const VERSION: u32 = 1;
+
+/// Number of frames after a trade during which losing a stock decides who lost the trade.
+const TRADE_STOCK_WINDOW: usize = 120;
+
+/// Knockback action states (`DamageHi1` through `DamageFlyRoll`).
+pub(crate) const DAMAGE_STATES: RangeInclusive<u16> = 75..=91;
+/// Grabbed action states (`CapturePulledHi` through `CaptureFoot`).
+const CAPTURE_STATES: RangeInclusive<u16> = 223..=232;
+/// Thrown action states (`ThrownF` through `ThrownLwWomen`).
+pub(crate) const THROWN_STATES: RangeInclusive<u16> = 239..=243;
+/// Aerial jump action states (`JumpAerialF` and `JumpAerialB`).
+const AERIAL_JUMP_STATES: RangeInclusive<u16> = 27..=28;
+/// First character-specific action state; up-specials that cancel meteors live above it.
+const SPECIAL_STATES_START: u16 = 341;
+
+/// Launch angles (in degrees) that make a hit meteor-cancellable.
+const METEOR_ANGLES: RangeInclusive<f32> = 260.0..=280.0;
+/// Joystick distance from center past which a new direction counts as a smash DI input.
+const SDI_THRESHOLD: f32 = 0.7;
+/// Names of the eight stick directions, counter-clockwise from right.
+const DIRECTIONS: [&str; 8] = [
+    "right", "up_right", "up", "up_left", "left", "down_left", "down", "down_right",
+];
+
+/// A single row of the hit table.
+#[derive(Debug, Clone, Serialize)]
+pub struct Hit {
+    /// Frame index (as stored in the replay) on which the damage was applied
+    pub frame: i32,
+    pub victim: Port,
+    /// Port named by the victim's `last_hit_by`, if any
+    pub attacker: Option<Port>,
+    pub damage: f32,
+    /// Victim's percent after the hit
+    pub percent: f32,
+    /// Victim's remaining hitlag frames on the hit frame (Slippi 3.8+)
+    pub hitlag: Option<f32>,
+    /// Whether the victim hit the attacker back within the hitlag window
+    pub trade: bool,
+    /// Winner of the trade, by stock outcome and then by damage dealt (None for an even trade)
+    pub trade_winner: Option<Port>,
+    /// Whether the damage came without the hitlag and knockback of a real hit
+    pub phantom: bool,
+    /// Directions of the victim's smash DI inputs during hitlag (Slippi 3.8+)
+    pub sdi: Option<Vec<&'static str>>,
+    /// Whether the victim was launched at a meteor-cancellable angle (Slippi 3.8+)
+    pub meteor: Option<bool>,
+    /// Frames after hitlag at which the victim cancelled the meteor, if they did
+    pub meteor_cancel: Option<u32>,
+    /// Row of the frame arrays this hit was found on
+    #[serde(skip)]
+    pub(crate) index: usize,
+}
+
+/// Detect all hits in the game, ordered by frame and then by victim port.
+pub fn detect(frames: &Frame) -> Vec<Hit> {
+    let mut hits = Vec::new();
+    for port in &frames.ports {
+        let post = &port.leader.post;
+        for i in 1..frames.id.len() {
+            let damage = post.percent.value(i) - post.percent.value(i - 1);
+            if damage <= 0.0 {
+                continue;
+            }
+            let hitlag_end = hitlag_end(post, i);
+            let meteor = hitlag_end.map(|end| is_meteor(post, end));
+            hits.push(Hit {
+                frame: frames.id.value(i),
+                victim: port.port,
+                attacker: Port::try_from(post.last_hit_by.value(i)).ok(),
+                damage,
+                percent: post.percent.value(i),
+                hitlag: post.hitlag.as_ref().map(|h| h.value(i)),
+                trade: false,
+                trade_winner: None,
+                phantom: is_phantom(post, i),
+                sdi: hitlag_end.map(|end| sdi_inputs(&port.leader.pre, i, end)),
+                meteor,
+                meteor_cancel: hitlag_end.filter(|_| meteor == Some(true)).and_then(|end| meteor_cancel(post, end)),
+                index: i,
+            });
+        }
+    }
+    hits.sort_by_key(|h| (h.frame, h.victim as u8));
+    mark_trades(frames, &mut hits);
+    hits
+}
+
+/// Whether damage taken on row `i` looks like a phantom hit.
+///
+/// From Slippi 3.8 a phantom hit is damage with no hitlag on the hit frame or the one after it.
+/// Older replays don't record hitlag, so the victim's action state is the only evidence: a phantom
+/// hit leaves them outside of any knockback, grabbed, or thrown state.
+fn is_phantom(post: &Post, i: usize) -> bool {
+    let next = (i + 1).min(post.state.len() - 1);
+    let knocked_back = [i, next].iter().any(|&j| {
+        let state = post.state.value(j);
+        DAMAGE_STATES.contains(&state)
+            || CAPTURE_STATES.contains(&state)
+            || THROWN_STATES.contains(&state)
+    });
+    match &post.hitlag {
+        Some(hitlag) => hitlag.value(i) <= 0.0 && hitlag.value(next) <= 0.0 && !knocked_back,
+        None => !knocked_back,
+    }
+}
+
+/// First row after the hitlag that starts on row `i`, if the replay records hitlag.
+fn hitlag_end(post: &Post, i: usize) -> Option<usize> {
+    let hitlag = post.hitlag.as_ref()?;
+    let end = (i..hitlag.len())
+        .find(|&j| hitlag.value(j) <= 0.0)
+        .unwrap_or(hitlag.len());
+    Some(end)
+}
+
+/// 8-way direction the stick is held in on row `i`, if it's past the smash DI threshold.
+fn stick_direction(pre: &Pre, i: usize) -> Option<&'static str> {
+    let (x, y) = (pre.joystick.x.value(i), pre.joystick.y.value(i));
+    if x.hypot(y) < SDI_THRESHOLD {
+        return None;
+    }
+    let angle = y.atan2(x).to_degrees().rem_euclid(360.0);
+    Some(DIRECTIONS[((angle + 22.5) / 45.0) as usize % 8])
+}
+
+/// Smash DI inputs made during the hitlag on rows `start..end`.
+///
+/// Every frame on which the stick enters a new direction past the threshold (including from
+/// neutral) counts as one input.
+fn sdi_inputs(pre: &Pre, start: usize, end: usize) -> Vec<&'static str> {
+    (start..end)
+        .filter_map(|i| {
+            let direction = stick_direction(pre, i)?;
+            let held = i > 0 && stick_direction(pre, i - 1) == Some(direction);
+            (!held).then_some(direction)
+        })
+        .collect()
+}
+
+/// Whether the knockback applied on row `end` (the first frame after hitlag) is a meteor smash.
+fn is_meteor(post: &Post, end: usize) -> bool {
+    let Some(velocities) = &post.velocities else {
+        return false;
+    };
+    if end >= velocities.knockback_x.len() {
+        return false;
+    }
+    let airborne = post.airborne.as_ref().is_none_or(|a| a.value(end) != 0);
+    let (x, y) = (velocities.knockback_x.value(end), velocities.knockback_y.value(end));
+    let angle = y.atan2(x).to_degrees().rem_euclid(360.0);
+    airborne && METEOR_ANGLES.contains(&angle)
+}
+
+/// Frames after hitlag at which a meteored victim jumped or used a special out of knockback.
+fn meteor_cancel(post: &Post, end: usize) -> Option<u32> {
+    let j = (end..post.state.len()).find(|&j| !DAMAGE_STATES.contains(&post.state.value(j)))?;
+    let state = post.state.value(j);
+    (AERIAL_JUMP_STATES.contains(&state) || state >= SPECIAL_STATES_START)
+        .then_some((j - end) as u32)
+}
+
+/// Pair up hits where both players hit each other within the first hit's hitlag window.
+fn mark_trades(frames: &Frame, hits: &mut [Hit]) {
+    for a in 0..hits.len() {
+        if hits[a].trade {
+            continue;
+        }
+        let Some(attacker) = hits[a].attacker else {
+            continue;
+        };
+        let window = hits[a].hitlag.unwrap_or(0.0).max(0.0) as i32;
+        let partner = (a + 1..hits.len())
+            .take_while(|&b| hits[b].frame - hits[a].frame <= window)
+            .find(|&b| hits[b].victim == attacker && hits[b].attacker == Some(hits[a].victim));
+
+        if let Some(b) = partner {
+            let winner = trade_winner(frames, &hits[a], &hits[b]);
+            for i in [a, b] {
+                hits[i].trade = true;
+                hits[i].trade_winner = winner;
+            }
+        }
+    }
+}
+
+/// Decide who won a trade between `a` and `b`, where each victim is the other's attacker.
+///
+/// A player who loses a stock shortly after the trade loses it outright; otherwise the player who
+/// dealt more damage wins.
+fn trade_winner(frames: &Frame, a: &Hit, b: &Hit) -> Option<Port> {
+    let a_lost_stock = lost_stock(frames, a.victim, a.index);
+    let b_lost_stock = lost_stock(frames, b.victim, b.index);
+    match (a_lost_stock, b_lost_stock) {
+        (true, false) => Some(b.victim),
+        (false, true) => Some(a.victim),
+        // `a.damage` was dealt by `b.victim`, and vice versa
+        _ if a.damage > b.damage => Some(b.victim),
+        _ if b.damage > a.damage => Some(a.victim),
+        _ => None,
+    }
+}
+
+fn lost_stock(frames: &Frame, port: Port, from: usize) -> bool {
+    let Some(data) = frames.ports.iter().find(|p| p.port == port) else {
+        return false;
+    };
+    let stocks = &data.leader.post.stocks;
+    let until = (from + TRADE_STOCK_WINDOW).min(stocks.len() - 1);
+    stocks.value(until) < stocks.value(from)
+}
@@ -0,0 +1,178 @@
+//! Resolving the players of a catalog to stable identities
+//!
+//! Players change their display names freely and sometimes their connect codes too, which splits a
+//! player's history across several keys and breaks studies following players over time. Resolution
+//! clusters the codes and names of a catalog that belong to the same player and gives every player
+//! in the cluster the same `player_id`:
+//!
+//! - Players with the same connect code are the same player, whatever their display names.
+//! - An alias file (a JSON object mapping a player ID to the connect codes and display names they
+//!   have played under, e.g. `{"mang0": ["MANG#0", "C9 mang0"]}`) joins whatever it lists. Aliases
+//!   containing `#` are connect codes, the rest display names.
+//!
+//! Display names alone never join players, since unrelated players share them. A cluster's ID is
+//! the one named in the alias file, else its connect code (the alphabetically first, if several),
+//! else its display name.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::catalog::{Entry, EntryPlayer};
+
+/// Node of the identity graph: a player ID, connect code, or display name.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Node {
+    Id(String),
+    Code(String),
+    Name(String),
+}
+
+impl Node {
+    fn alias(alias: &str) -> Self {
+        match alias.contains('#') {
+            true => Node::Code(alias.to_string()),
+            false => Node::Name(alias.to_string()),
+        }
+    }
+
+    /// The node a player's games are recorded under, if they have a code or name.
+    fn player(player: &EntryPlayer) -> Option<Self> {
+        match (&player.code, &player.name) {
+            (Some(code), _) => Some(Node::Code(code.clone())),
+            (None, Some(name)) => Some(Node::Name(name.clone())),
+            (None, None) => None,
+        }
+    }
+
+    fn label(&self) -> &str {
+        match self {
+            Node::Id(s) | Node::Code(s) | Node::Name(s) => s,
+        }
+    }
+}
+
+/// Union-find over the nodes of the identity graph.
+#[derive(Default)]
+struct Clusters {
+    index: HashMap<Node, usize>,
+    parent: Vec<usize>,
+}
+
+impl Clusters {
+    fn add(&mut self, node: Node) -> usize {
+        let next = self.parent.len();
+        let i = *self.index.entry(node).or_insert(next);
+        if i == next {
+            self.parent.push(next);
+        }
+        i
+    }
+
+    fn root(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    fn join(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.root(a), self.root(b));
+        self.parent[a.max(b)] = a.min(b);
+    }
+}
+
+pub fn read_aliases(path: &Path) -> io::Result<BTreeMap<String, Vec<String>>> {
+    let file = io::BufReader::new(fs::File::open(path)?);
+    Ok(serde_json::from_reader(file)?)
+}
+
+/// Set the `player_id` of every player in the catalog from the codes and names they share and
+/// `aliases`, returning the number of distinct player IDs. Players without a code or name have
+/// theirs cleared.
+pub fn resolve(entries: &mut [Entry], aliases: &BTreeMap<String, Vec<String>>) -> usize {
+    let mut clusters = Clusters::default();
+    for (id, names) in aliases {
+        let id = clusters.add(Node::Id(id.clone()));
+        for alias in names {
+            let alias = clusters.add(Node::alias(alias));
+            clusters.join(id, alias);
+        }
+    }
+    for player in entries.iter().flat_map(|e| &e.players) {
+        if let Some(node) = Node::player(player) {
+            clusters.add(node);
+        }
+    }
+
+    // The ID of a cluster is its best-ranked node: IDs before codes before names.
+    let mut ids: HashMap<usize, Node> = HashMap::new();
+    let nodes: Vec<(Node, usize)> = clusters.index.iter().map(|(n, &i)| (n.clone(), i)).collect();
+    for (node, i) in nodes {
+        let root = clusters.root(i);
+        let best = ids.entry(root).or_insert_with(|| node.clone());
+        if node < *best {
+            *best = node;
+        }
+    }
+
+    let mut resolved = BTreeSet::new();
+    for player in entries.iter_mut().flat_map(|e| e.players.iter_mut()) {
+        player.player_id = Node::player(player).map(|node| {
+            let root = clusters.root(clusters.index[&node]);
+            ids[&root].label().to_string()
+        });
+        resolved.extend(player.player_id.clone());
+    }
+    resolved.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::tests::entry;
+
+    #[test]
+    fn clusters_join_transitively() {
+        let mut clusters = Clusters::default();
+        let a = clusters.add(Node::Code("A#1".to_string()));
+        let b = clusters.add(Node::Code("B#2".to_string()));
+        let c = clusters.add(Node::Name("c".to_string()));
+        assert_eq!(clusters.add(Node::Code("A#1".to_string())), a);
+        assert_ne!(clusters.root(a), clusters.root(b));
+        clusters.join(b, c);
+        clusters.join(c, a);
+        assert_eq!(clusters.root(b), clusters.root(a));
+        assert_eq!(clusters.root(c), a);
+    }
+
+    #[test]
+    fn resolves_codes_aliases_and_names() {
+        let mut entries = vec![
+            entry("1.slp", &["MANG#0", "ARMY#1"]),
+            entry("2.slp", &["MANG#1", "ZAIN#2"]),
+            entry("3.slp", &["A#1"]),
+        ];
+        // Players without a code go by their display name, which only joins through the alias file.
+        entries[2].players[0].code = None;
+        entries[2].players[0].name = Some("C9 mang0".to_string());
+        let mut unlisted = entry("4.slp", &["B#1"]);
+        unlisted.players[0].code = None;
+        unlisted.players[0].name = Some("Mango".to_string());
+        entries.push(unlisted);
+        let aliases = BTreeMap::from([(
+            "mang0".to_string(),
+            vec!["MANG#0".to_string(), "MANG#1".to_string(), "C9 mang0".to_string()],
+        )]);
+
+        assert_eq!(resolve(&mut entries, &aliases), 4);
+        let ids: Vec<_> = entries
+            .iter()
+            .flat_map(|e| &e.players)
+            .map(|p| p.player_id.as_deref().unwrap())
+            .collect();
+        assert_eq!(ids, ["mang0", "ARMY#1", "mang0", "ZAIN#2", "mang0", "Mango"]);
+    }
+}
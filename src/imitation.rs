@@ -0,0 +1,56 @@
+//! Supervised sequences for imitation learning
+//!
+//! An imitation export pairs every frame's features with the controller inputs a chosen player
+//! made `latency` frames later, which is what a bot reacting with that latency would have to
+//! predict. Frames without a target (the last `latency` of the game) are left out, so every row is
+//! a complete training example.
+
+use std::fs;
+
+use arrow2::array::{Array, StructArray};
+use arrow2::chunk::Chunk;
+use arrow2::datatypes::{Field, Schema};
+use arrow2::error::{Error, Result};
+use arrow2::io::ipc::write::FileWriter;
+use peppi::game::Port;
+
+use crate::{export, ipc};
+
+/// Pre-frame fields used as targets, relative to the port's `leader.pre` block.
+const TARGETS: [&str; 6] = ["joystick.x", "joystick.y", "cstick.x", "cstick.y", "triggers", "buttons"];
+
+/// Write `frames` as an Arrow IPC file of feature columns, followed by `target.*` columns holding
+/// `port`'s inputs `latency` frames later. Returns the number of rows written.
+pub fn write(
+    file: &mut fs::File,
+    frames: &StructArray,
+    port: Port,
+    latency: usize,
+    policy: export::FollowerPolicy,
+) -> Result<usize> {
+    let rows = frames.len().saturating_sub(latency);
+    let columns = export::flatten(frames, policy);
+    let prefix = format!("ports.{port:?}.leader.pre.");
+
+    let mut fields = Vec::new();
+    let mut arrays: Vec<Box<dyn Array>> = Vec::new();
+    for (name, array) in &columns {
+        fields.push(Field::new(name.clone(), array.data_type().clone(), true));
+        arrays.push(array.sliced(0, rows));
+    }
+    for target in TARGETS {
+        let name = format!("{prefix}{target}");
+        let (_, array) = columns
+            .iter()
+            .find(|(n, _)| *n == name)
+            .ok_or_else(|| Error::InvalidArgumentError(format!("no column {name}")))?;
+        fields.push(Field::new(format!("target.{target}"), array.data_type().clone(), true));
+        arrays.push(array.sliced(latency.min(frames.len()), rows));
+    }
+
+    let schema = Schema::from(fields);
+    let mut writer = FileWriter::try_new(file, schema, None, ipc::write_options())?;
+    writer.write(&Chunk::new(arrays), None)?;
+    writer.finish()?;
+    Ok(rows)
+}
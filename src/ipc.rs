@@ -0,0 +1,53 @@
+//! Options for the Arrow IPC files written
+//!
+//! Frames of a long game take hundreds of megabytes uncompressed, which archives of many games pay
+//! for on disk. Arrow IPC files can compress each buffer with LZ4 or ZSTD instead, at the cost of
+//! decompressing them on read, so they can no longer be memory-mapped without copies. Compression
+//! is off by default to keep reads zero-copy.
+
+use std::str::FromStr;
+
+use arrow2::io::ipc::write::{self, WriteOptions};
+use serde::Serialize;
+
+use crate::config;
+
+/// Compression of the buffers of Arrow IPC files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    #[default]
+    None,
+    /// LZ4 frames: fast, with a moderate ratio
+    Lz4,
+    /// Zstandard: slower, with a better ratio
+    Zstd,
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" | "none" => Ok(Compression::None),
+            "lz4" => Ok(Compression::Lz4),
+            "zstd" => Ok(Compression::Zstd),
+            _ => Err(format!("unknown compression: {s}")),
+        }
+    }
+}
+
+/// Options for writing Arrow IPC files with the configured compression.
+pub fn write_options() -> WriteOptions {
+    options(config::get().compression)
+}
+
+/// Options for writing Arrow IPC files with the given compression.
+pub fn options(compression: Compression) -> WriteOptions {
+    let compression = match compression {
+        Compression::None => None,
+        Compression::Lz4 => Some(write::Compression::LZ4),
+        Compression::Zstd => Some(write::Compression::ZSTD),
+    };
+    WriteOptions { compression }
+}
@@ -0,0 +1,41 @@
+//! Canonical JSON output
+//!
+//! Everything this library hands out as JSON, whether returned to Julia or written to a file, goes
+//! through here so that the same value always gives the same bytes: object keys sorted at every
+//! level (struct fields and replay metadata alike), no insignificant whitespace unless
+//! pretty-printed, and numbers in the shortest form that reads back to the same value. Rust's
+//! number formatting doesn't depend on the system locale, so neither does the output. Outputs can
+//! then be diffed, cached, and compared against golden files.
+//!
+//! Values are serialized, read back, and sorted. Floats survive the trip exactly, since `serde_json`
+//! is built with `float_roundtrip`, and an `f32` printed in its shortest form reads back as the
+//! `f64` that prints the same way.
+//!
+//! Content hashes ([content::hash](crate::content::hash)) serialize their options directly, so
+//! existing artifact names stay valid.
+
+use std::io::{self, Write};
+
+use serde::Serialize;
+use serde_json::Value;
+
+fn canonical<T: Serialize + ?Sized>(value: &T) -> serde_json::Result<Value> {
+    let mut value: Value = serde_json::from_str(&serde_json::to_string(value)?)?;
+    value.sort_all_objects();
+    Ok(value)
+}
+
+/// Serialize `value` as canonical JSON (empty if it can't be serialized).
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> String {
+    canonical(value).map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Write `value` to `w` as canonical JSON, pretty-printed or not.
+pub fn to_writer<W: Write, T: Serialize + ?Sized>(w: W, value: &T, pretty: bool) -> io::Result<()> {
+    let value = canonical(value)?;
+    match pretty {
+        true => serde_json::to_writer_pretty(w, &value)?,
+        false => serde_json::to_writer(w, &value)?,
+    }
+    Ok(())
+}
@@ -15,6 +15,7 @@
 
 use jlrs::{
     data::managed::{
+        array::TypedVector,
         ccall_ref::CCallRefRet,
         string::{JuliaString, StringRet},
         value::typed::TypedValue,
@@ -22,27 +23,128 @@ use jlrs::{
     prelude::*,
     weak_handle_unchecked,
 };
-use arrow2::array::{Array};
+use arrow2::array::{Array, StructArray};
 use arrow2::io::ipc::write::{FileWriter, WriteOptions};
-use arrow2::datatypes::{Schema, Field};
+use arrow2::datatypes::{Metadata, Schema, Field};
 use arrow2::chunk::Chunk;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
 use std::{fs, io};
 
 use peppi::frame::PortOccupancy;
-use peppi::game::{Start, ICE_CLIMBERS};
+use peppi::frame::immutable::Frame;
+use peppi::game::{End, GeckoCodes, Port, Quirks, Start, ICE_CLIMBERS};
 use peppi::game::immutable::Game as SlippiGame;
 use peppi::io::peppi::de::Opts as PeppiReadOpts;
+use peppi::io::slippi::Version;
 use peppi::io::slippi::de::Opts as SlippiReadOpts;
 
+use end::EndInfo;
+use player::Player;
+use start::StartInfo;
+
+mod annotations;
+mod atomic;
+mod batch;
+mod catalog;
+mod cdata;
+mod cleanup;
+mod clock;
+mod config;
+mod content;
+mod coverage;
+mod cues;
+mod dataset;
+mod describe;
+mod dictionary;
+mod end;
+mod errors;
+mod excitement;
+mod export;
+mod features;
+mod follower;
+mod grabs;
+mod hits;
+mod ids;
+mod identity;
+mod imitation;
+mod ipc;
+mod items;
+mod json;
+mod limits;
+mod live;
+mod lock;
+mod metadata;
+mod metrics;
+mod nulls;
+mod outcome;
+mod playback;
+mod player;
+mod port_frames;
+mod privacy;
+mod profiles;
+mod prometheus;
+mod pyslippi;
+mod queries;
+mod raw;
+mod sample;
+mod sandbox;
+mod schema;
+mod selftest;
+mod sets;
+mod shm;
+mod skill;
+mod snapshot;
+mod stages;
+mod stalls;
+mod start;
+mod stats;
+mod store;
+mod stream;
+mod tags;
+mod timeline;
+mod transcode;
+mod trim;
+mod units;
+mod warnings;
+mod win_probability;
+mod windows;
+
+/// Frame index of the first frame of every game.
+const FIRST_FRAME: i32 = -123;
+
 /// Game data structure exposed to Julia
 #[derive(OpaqueType)]
 #[jlrs(key = "Game")]
 pub struct Game {
     pub start: String,
+    parsed_start: Start, // The same start block, for the typed getters
     pub end: Option<String>,
     pub metadata:Option<String>,
+    parsed_metadata: Option<serde_json::Map<String, serde_json::Value>>, // The same metadata, for lookups
     pub hash: Option<String>,
+    pub console_nick: Option<String>,
+    pub played_on: Option<String>,
     pub frames_arrow_path: String, // Path to Arrow IPC file for memory-mapping
+    frame_offsets: Vec<raw::FrameOffset>, // Byte range of each frame in the source .slp file
+    frames: StructArray, // Frames kept in memory for the native analyses
+    version: Version,
+    stage: u16,
+    warnings: Vec<warnings::Warning>, // Fields requested but too new for the replay's version
+    managed: AtomicBool, // Whether the Arrow file is deleted once no open game uses it
+    players: Vec<catalog::EntryPlayer>,
+    source_hash: String, // SHA-256 of the source .slp file, keying its annotations
+    gecko_codes: Option<GeckoCodes>, // Kept to write the game back out as a .slp file
+    quirks: Option<Quirks>,
+}
+
+impl Drop for Game {
+    fn drop(&mut self) {
+        self.close();
+    }
 }
 
 impl Game {
@@ -52,6 +154,46 @@ impl Game {
         JuliaString::new(handle, &self.start).leak()
     }
 
+    /// Get the start data as a typed [StartInfo], without going through JSON
+    pub fn get_start_info(&self) -> CCallRefRet<StartInfo> {
+        let handle = unsafe { weak_handle_unchecked!() };
+        CCallRefRet::new(TypedValue::new(handle, StartInfo::new(self.parsed_start.clone())).leak())
+    }
+
+    /// Number of players in the game, for `get_player`
+    pub fn num_players(&self) -> usize {
+        self.parsed_start.players.len()
+    }
+
+    /// Get each player's netplay display name as a JSON object by port (e.g. `{"P1": "mang0", "P2":
+    /// null}`), from the start block (Slippi 3.9+) or else the metadata; null for offline players
+    pub fn get_display_names(&self) -> StringRet {
+        self.player_names(|p| p.name.as_deref())
+    }
+
+    /// Get each player's netplay connect code as a JSON object by port (e.g. `{"P1": "MANG#0"}`),
+    /// from the start block (Slippi 3.9+) or else the metadata; null for offline players
+    pub fn get_connect_codes(&self) -> StringRet {
+        self.player_names(|p| p.code.as_deref())
+    }
+
+    fn player_names(&self, name: impl Fn(&catalog::EntryPlayer) -> Option<&str>) -> StringRet {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let names: BTreeMap<String, Option<&str>> =
+            self.players.iter().map(|p| (format!("{:?}", p.port), name(p))).collect();
+        JuliaString::new(handle, json::to_string(&names)).leak()
+    }
+
+    /// Get the `index`th player (from 1, in port order) as a typed [Player]
+    pub fn get_player(&self, index: usize) -> JlrsResult<CCallRefRet<Player>> {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let player = index
+            .checked_sub(1)
+            .and_then(|i| self.parsed_start.players.get(i))
+            .ok_or_else(|| invalid_input(format!("no player {index} of {}", self.num_players())))?;
+        Ok(CCallRefRet::new(TypedValue::new(handle, Player::new(player.clone())).leak()))
+    }
+
     /// Get the end data as a Julia String via StringRet (empty if missing)
     pub fn get_end(&self) -> StringRet {
         let handle = unsafe { weak_handle_unchecked!() };
@@ -59,6 +201,15 @@ impl Game {
         JuliaString::new(handle, s).leak()
     }
 
+    /// Get the end data as a typed [EndInfo]. Games cut off before they ended have none, and
+    /// throw; check `get_end` first for those.
+    pub fn get_end_info(&self) -> JlrsResult<CCallRefRet<EndInfo>> {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let end = self.end.as_deref().ok_or_else(|| invalid_input("game has no end data"))?;
+        let end = serde_json::from_str::<End>(end).map_err(julia_error)?;
+        Ok(CCallRefRet::new(TypedValue::new(handle, EndInfo::new(end)).leak()))
+    }
+
     /// Get the metadata as a Julia String via StringRet (empty if missing)
     pub fn get_metadata(&self) -> StringRet {
         let handle = unsafe { weak_handle_unchecked!() };
@@ -73,203 +224,2148 @@ impl Game {
         JuliaString::new(handle, s).leak()
     }
 
+    /// Get the hex SHA-256 of the source replay, which names the game in a catalog (see `tag_game`)
+    pub fn get_source_hash(&self) -> StringRet {
+        let handle = unsafe { weak_handle_unchecked!() };
+        JuliaString::new(handle, &self.source_hash).leak()
+    }
+
+    /// Get the nickname of the console that recorded the game (empty if missing)
+    pub fn get_console_nick(&self) -> StringRet {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let s = self.console_nick.as_deref().unwrap_or("");
+        JuliaString::new(handle, s).leak()
+    }
+
+    /// Get the platform the game was played on: "dolphin", "nintendont" for consoles, or "network" for
+    /// early netplay (empty if missing)
+    pub fn get_played_on(&self) -> StringRet {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let s = self.played_on.as_deref().unwrap_or("");
+        JuliaString::new(handle, s).leak()
+    }
+
+    /// Get when the game started, as an ISO-8601 string (e.g. "2018-06-22T07:52:59Z"), from the
+    /// metadata (empty if missing)
+    pub fn get_start_time(&self) -> StringRet {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let s = self.metadata_value("startAt").and_then(|v| v.as_str()).unwrap_or("");
+        JuliaString::new(handle, s).leak()
+    }
+
+    /// Get the length of the game in frames, countdown included, from the metadata's `lastFrame`, or
+    /// the number of frames read when the metadata doesn't record it
+    pub fn get_duration_frames(&self) -> i64 {
+        match self.metadata_value("lastFrame").and_then(|v| v.as_i64()) {
+            Some(last_frame) => last_frame - FIRST_FRAME as i64 + 1,
+            None => self.frames.len() as i64,
+        }
+    }
+
+    /// Get the internal ID of the character the player on `port` (from 1) played the most frames as,
+    /// from the metadata, or -1 if not recorded
+    pub fn get_metadata_character(&self, port: u8) -> i16 {
+        let characters = self.metadata_characters(port);
+        let most = characters.into_iter().max_by_key(|&(_, frames)| frames);
+        most.map_or(-1, |(character, _)| character as i16)
+    }
+
+    /// Get the number of frames the player on `port` (from 1) played as the character with internal ID
+    /// `character`, from the metadata (0 if not recorded)
+    pub fn get_metadata_character_frames(&self, port: u8, character: u8) -> u64 {
+        let characters = self.metadata_characters(port);
+        characters.into_iter().find(|&(c, _)| c == character).map_or(0, |(_, frames)| frames)
+    }
+
+    /// A top-level value of the metadata
+    fn metadata_value(&self, key: &str) -> Option<&serde_json::Value> {
+        self.parsed_metadata.as_ref()?.get(key)
+    }
+
+    /// Frames played as each character (by internal ID) by the player on `port` (from 1), as recorded
+    /// in the metadata's `players` object, which is keyed by port from 0
+    fn metadata_characters(&self, port: u8) -> Vec<(u8, u64)> {
+        let players = self.metadata_value("players");
+        let key = port.checked_sub(1).map(|p| p.to_string()).unwrap_or_default();
+        let characters = players.and_then(|p| p.get(&key)).and_then(|p| p.get("characters"));
+        let characters = characters.and_then(|c| c.as_object()).into_iter().flatten();
+        characters
+            .filter_map(|(character, frames)| Some((character.parse().ok()?, frames.as_u64()?)))
+            .collect()
+    }
+
     /// Get the Arrow IPC file path as a Julia String
     pub fn get_frames_arrow_path(&self) -> StringRet {
         let handle = unsafe { weak_handle_unchecked!() };
         JuliaString::new(handle, &self.frames_arrow_path).leak()
     }
+
+    /// Get the byte offset and length of each frame's events in the source .slp file as a JSON
+    /// array (empty for games read from .slpp)
+    pub fn get_frame_offsets(&self) -> StringRet {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let s = json::to_string(&self.frame_offsets);
+        JuliaString::new(handle, s).leak()
+    }
+
+    /// Get the hit table as a JSON array of hits (see [hits::Hit])
+    pub fn get_hits(&self) -> StringRet {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let hits = hits::detect(&self.frames());
+        let s = json::to_string(&hits);
+        JuliaString::new(handle, s).leak()
+    }
+
+    /// Get the warnings recorded when the game was read, as a JSON array of objects with the
+    /// `field` (a frame column, or `hits.<column>`) that came out null because it needs a newer
+    /// Slippi than recorded the replay, its `required_version`, and the `replay_version`
+    pub fn get_warnings(&self) -> StringRet {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let s = json::to_string(&self.warnings);
+        JuliaString::new(handle, s).leak()
+    }
+
+    /// Get a short human-readable summary of the game: stage, duration, and result, then each
+    /// player's character, name, final stocks and percent, and hits landed
+    pub fn describe(&self) -> JlrsResult<StringRet> {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let frames = self.frames();
+        let hits = hits::detect(&frames);
+        let outcome = self.outcome()?;
+        let s = describe::describe(&self.players, self.stage, &frames, &hits, &outcome);
+        Ok(JuliaString::new(handle, s).leak())
+    }
+
+    /// Get the game as JSON laid out like py-slippi's `Game` (see [pyslippi]), with its frames
+    /// when `include_frames` is nonzero, for checking results against a py-slippi pipeline
+    pub fn to_py_slippi_json(&self, include_frames: i8) -> StringRet {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let parse = |s: &str| serde_json::from_str::<serde_json::Value>(s).unwrap_or_default();
+        let start = parse(&self.start);
+        let end = self.end.as_deref().map(parse);
+        let metadata = self.metadata.as_deref().map(parse);
+        let frames = (include_frames != 0).then(|| self.frames());
+        let game = pyslippi::game(&start, end.as_ref(), metadata.as_ref(), self.version, &self.players, frames.as_ref());
+        JuliaString::new(handle, json::to_string(&game)).leak()
+    }
+
+    /// Get the stretches of at least `min_seconds` in which a player didn't move, as a JSON array
+    /// of objects with the player's `port`, the `start` and `end` frame, length in `frames`, and
+    /// `kind` ("frozen" when their inputs didn't change either, otherwise "idle")
+    pub fn get_stalls(&self, min_seconds: f64) -> StringRet {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let stalls = stalls::detect(&self.frames(), (min_seconds * stalls::FPS) as usize);
+        JuliaString::new(handle, json::to_string(&stalls)).leak()
+    }
+
+    /// Get each player's actions and openings, in total and per minute, as JSON. A player's stalls
+    /// of at least `stall_seconds` (see `get_stalls`) are left out of their minutes and listed
+    /// under `excluded`; nothing is left out when 0.
+    pub fn get_stats(&self, stall_seconds: f64) -> StringRet {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let frames = self.frames();
+        let hits = hits::detect(&frames);
+        let stats = stats::compute(&frames, &hits, (stall_seconds.max(0.0) * stalls::FPS) as usize);
+        JuliaString::new(handle, json::to_string(&stats)).leak()
+    }
+
+    /// Get the result of the game as JSON: the `result` ("ko", "timeout", "lras", "tie", or
+    /// "incomplete"), the `winner` and `loser` (singles only), the `lras_initiator`, and every
+    /// player's final `stocks`, `percent`, and `placement`
+    pub fn game_result(&self) -> JlrsResult<StringRet> {
+        let handle = unsafe { weak_handle_unchecked!() };
+        Ok(JuliaString::new(handle, json::to_string(&self.outcome()?)).leak())
+    }
+
+    /// The result of the game, decided from its end data and frames
+    fn outcome(&self) -> JlrsResult<outcome::Outcome> {
+        let end = self.end.as_deref().map(serde_json::from_str::<End>).transpose();
+        Ok(outcome::decide(end.map_err(julia_error)?.as_ref(), &self.frames()))
+    }
+
+    /// Get the coverage of the game's frame columns as JSON: for every column a replay of the latest
+    /// Slippi version would have, the version that `introduced` it, whether this replay `recorded`
+    /// it, and the share of frames on which it's `non_null`
+    pub fn field_coverage(&self) -> StringRet {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let coverage = coverage::report(&self.frames, self.version);
+        JuliaString::new(handle, json::to_string(&coverage)).leak()
+    }
+
+    /// Get the game's audio cues as a JSON array of objects with the cue's `kind` ("hit", "death", or
+    /// "shield_break"), a hit's `strength` ("light", "medium", or "heavy"), its `frame` and `time`
+    /// in seconds from the start of the replay, the `port` it happened to, and an `intensity` from
+    /// 0 to 1, in order
+    pub fn get_audio_cues(&self) -> StringRet {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let cues = cues::detect(&self.frames());
+        JuliaString::new(handle, json::to_string(&cues)).leak()
+    }
+
+    /// Get an excitement score timeline as JSON: the game's mean `score`, its `peak` and
+    /// `peak_frame`, and `segments` of `segment_seconds` seconds (the whole game when 0) with their
+    /// `score`, `damage`, `stocks_lost`, and `close_calls`, for ranking highlights
+    pub fn get_excitement(&self, segment_seconds: f64) -> StringRet {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let segment_frames = (segment_seconds.max(0.0) * stalls::FPS) as usize;
+        let excitement = excitement::score(&self.frames(), self.stage, segment_frames);
+        JuliaString::new(handle, json::to_string(&excitement)).leak()
+    }
+
+    /// Path of the file holding this game's annotations, next to its Arrow file
+    fn annotations_path(&self) -> PathBuf {
+        let arrow_path = Path::new(&self.frames_arrow_path);
+        annotations::path_for(arrow_path.parent().unwrap_or(Path::new("")), &self.source_hash)
+    }
+
+    /// Annotate frames `start_frame` through `end_frame` with a label and an optional note (empty
+    /// for none), returning how many annotations the game now has (throws if the frames are out of
+    /// order or out of range)
+    pub fn add_annotation(&self, start_frame: i64, end_frame: i64, label: JuliaString, note: JuliaString) -> JlrsResult<usize> {
+        let label = unsafe { label.as_str_unchecked() };
+        let note = unsafe { note.as_str_unchecked() };
+        let frame = |f: i64| i32::try_from(f).map_err(|_| invalid_input(format!("no such frame: {f}")));
+        let annotation = annotations::Annotation {
+            start: frame(start_frame)?,
+            end: frame(end_frame)?,
+            label: label.to_string(),
+            note: (!note.is_empty()).then(|| note.to_string()),
+        };
+        annotations::add(&self.annotations_path(), &self.source_hash, annotation).map_err(julia_error)
+    }
+
+    /// Get the game's annotations as a JSON array (see [annotations::Annotation]), throwing if its
+    /// annotation file can't be read
+    pub fn get_annotations(&self) -> JlrsResult<StringRet> {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let annotations = annotations::read(&self.annotations_path()).map_err(julia_error)?;
+        Ok(JuliaString::new(handle, json::to_string(&annotations)).leak())
+    }
+
+    /// Remove the game's annotations with the given label (all of them if empty), returning how
+    /// many were removed
+    pub fn remove_annotations(&self, label: JuliaString) -> JlrsResult<usize> {
+        let label = unsafe { label.as_str_unchecked() };
+        let label = (!label.is_empty()).then_some(label);
+        annotations::remove(&self.annotations_path(), &self.source_hash, label).map_err(julia_error)
+    }
+
+    /// Get the start and metadata as a JSON object of `start` and `metadata`, with names, connect
+    /// codes, and the console nickname pseudonymized using `salt` (or blanked if it's empty), as
+    /// `anonymize_slippi` does for files
+    pub fn anonymize(&self, salt: JuliaString) -> JlrsResult<StringRet> {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let salt = unsafe { salt.as_str_unchecked() };
+        let anonymizer = privacy::Anonymizer::new(salt);
+        let mut start: serde_json::Value = serde_json::from_str(&self.start).map_err(julia_error)?;
+        let mut metadata = self.parsed_metadata.clone().map(serde_json::Value::Object);
+        anonymizer.start(&mut start);
+        if let Some(metadata) = &mut metadata {
+            anonymizer.metadata(metadata);
+        }
+        let s = json::to_string(&serde_json::json!({ "start": start, "metadata": metadata }));
+        Ok(JuliaString::new(handle, s).leak())
+    }
+
+    /// Get the grab table as a JSON array of grabs (see [grabs::Grab])
+    pub fn get_grabs(&self) -> StringRet {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let grabs = grabs::detect(&self.frames());
+        let s = json::to_string(&grabs);
+        JuliaString::new(handle, s).leak()
+    }
+
+    /// Write the frames and hit table to their own group in an HDF5 file, creating it if needed
+    /// (needs the `hdf5` feature)
+    pub fn write_hdf5(&self, path: JuliaString, group: JuliaString) -> JlrsResult<()> {
+        #[cfg(not(feature = "hdf5"))]
+        {
+            let _ = (path, group);
+            Err(disabled("hdf5"))
+        }
+        #[cfg(feature = "hdf5")]
+        {
+            let path = unsafe { path.as_str_unchecked() };
+            let group = unsafe { group.as_str_unchecked() };
+            let hits = hits::detect(&self.frames());
+            export::hdf5::write(path, group, &self.export_frames()?, &hits)
+                .map_err(|e| julia_error(io::Error::other(e.to_string())))?;
+            self.write_dictionary(&dictionary::path_for(Path::new(path)))
+        }
+    }
+
+    /// Append the frames and hit table to a DuckDB database file, tagging rows with `game`; throws
+    /// if its `frames` table has other columns (needs the `duckdb` feature)
+    pub fn write_duckdb(&self, path: JuliaString, game: JuliaString) -> JlrsResult<()> {
+        #[cfg(not(feature = "duckdb"))]
+        {
+            let _ = (path, game);
+            Err(disabled("duckdb"))
+        }
+        #[cfg(feature = "duckdb")]
+        {
+            let path = unsafe { path.as_str_unchecked() };
+            let game = unsafe { game.as_str_unchecked() };
+            let hits = hits::detect(&self.frames());
+            export::duckdb::append(path, game, &self.export_frames()?, &hits)
+                .map_err(|e| julia_error(io::Error::other(e.to_string())))?;
+            self.write_dictionary(&dictionary::path_for(Path::new(path)))
+        }
+    }
+
+    /// Write the frames and hit table as Postgres `COPY` CSV files plus a `schema.sql` to `dir`
+    pub fn write_postgres_copy(&self, dir: JuliaString, game: JuliaString) -> JlrsResult<()> {
+        let dir = unsafe { dir.as_str_unchecked() };
+        let game = unsafe { game.as_str_unchecked() };
+        let hits = hits::detect(&self.frames());
+        export::postgres::write_copy_files(Path::new(dir), game, &self.export_frames()?, &hits).map_err(julia_error)?;
+        self.write_dictionary(&Path::new(dir).join("dictionary.json"))
+    }
+
+    /// Bulk-load the frames and hit table into the Postgres database at `url`, tagging rows with
+    /// `game`; throws if its `frames` table has other columns (needs the `postgres` feature)
+    pub fn copy_postgres(&self, url: JuliaString, game: JuliaString) -> JlrsResult<()> {
+        #[cfg(not(feature = "postgres"))]
+        {
+            let _ = (url, game);
+            Err(disabled("postgres"))
+        }
+        #[cfg(feature = "postgres")]
+        {
+            let url = unsafe { url.as_str_unchecked() };
+            let game = unsafe { game.as_str_unchecked() };
+            let hits = hits::detect(&self.frames());
+            export::postgres::copy(url, game, &self.export_frames()?, &hits)
+                .map_err(|e| julia_error(io::Error::other(e.to_string())))
+        }
+    }
+
+    /// Write the frames to a Parquet file, with pages compressed with `compression` ("none",
+    /// "snappy", "gzip", "lz4", or "zstd") in row groups of `row_group_size` rows (one row group
+    /// when 0)
+    pub fn write_frames_parquet(&self, path: JuliaString, compression: JuliaString, row_group_size: usize) -> JlrsResult<()> {
+        let path = unsafe { path.as_str_unchecked() };
+        let compression = unsafe { compression.as_str_unchecked() };
+        let compression = compression.parse().map_err(invalid_input)?;
+        let frames = self.export_frames()?;
+        atomic::write(Path::new(path), |file| {
+            export::parquet::write(file, &frames, compression, row_group_size, export_metadata())
+        })
+        .map_err(julia_error)?;
+        self.write_dictionary(&dictionary::path_for(Path::new(path)))
+    }
+
+    /// The game as peppi's [SlippiGame], from its start, end, metadata, and frames as parsed (none
+    /// if they were skipped)
+    fn slippi_game(&self) -> JlrsResult<SlippiGame> {
+        let end = self.end.as_deref().map(serde_json::from_str::<End>).transpose();
+        Ok(SlippiGame {
+            start: self.parsed_start.clone(),
+            end: end.map_err(julia_error)?,
+            frames: self.frames(),
+            metadata: self.parsed_metadata.clone(),
+            gecko_codes: self.gecko_codes.clone(),
+            hash: self.hash.clone(),
+            quirks: self.quirks.clone(),
+        })
+    }
+
+    /// Write the game back out as a `.slp` file with peppi's writer. Events peppi doesn't parse,
+    /// like messages, aren't kept.
+    pub fn write_slippi(&self, path: JuliaString) -> JlrsResult<()> {
+        let path = unsafe { path.as_str_unchecked() };
+        let game = self.slippi_game()?;
+        atomic::write(Path::new(path), |file| {
+            let mut w = io::BufWriter::new(file);
+            peppi::io::slippi::write(&mut w, &game).map_err(io::Error::other)?;
+            w.flush()
+        })
+        .map_err(julia_error)
+    }
+
+    /// Write the game as a `.slpp` file, peppi's own Arrow-based format, which reads much faster
+    /// than `.slp` (see `read_slpp`)
+    pub fn write_slpp(&self, path: JuliaString) -> JlrsResult<()> {
+        let path = unsafe { path.as_str_unchecked() };
+        let game = self.slippi_game()?;
+        atomic::write(Path::new(path), |file| {
+            let mut w = io::BufWriter::new(file);
+            peppi::io::peppi::write(&mut w, game, Default::default()).map_err(io::Error::other)?;
+            w.flush()
+        })
+        .map_err(julia_error)
+    }
+
+    /// Write the frames Arrow IPC file into a new named POSIX shared-memory segment (e.g.
+    /// "/peppi_frames"), returning its size in bytes. Other processes on the same machine can map
+    /// it without touching the filesystem; it stays until `unlink_shm` is called.
+    pub fn write_frames_shm(&self, name: JuliaString) -> JlrsResult<usize> {
+        let name = unsafe { name.as_str_unchecked() };
+        let mut bytes = Vec::new();
+        write_frames_arrow(&mut bytes, &self.export_frames()?, ipc::write_options(), export_metadata())
+            .map_err(julia_error)?;
+        shm::create(name, &bytes).map_err(julia_error)?;
+        Ok(bytes.len())
+    }
+
+    /// Release the game's Arrow file, deleting it if no other open game uses it and Arrow files are
+    /// deleted (see `set_delete_arrow_files`). Happens anyway when the game is garbage collected;
+    /// the game's in-memory data stays usable, but not its Arrow file.
+    pub fn close(&self) {
+        if self.managed.swap(false, Ordering::SeqCst) {
+            cleanup::release(Path::new(&self.frames_arrow_path));
+        }
+    }
+
+    /// Export the frames through the Arrow C Data Interface into an `ArrowArray` and an
+    /// `ArrowSchema` struct allocated by the caller (of `arrow_c_array_size()` and
+    /// `arrow_c_schema_size()` bytes) at the addresses `array` and `schema`, without copying them.
+    /// The caller owns both structs afterwards and must call their `release` callbacks.
+    pub fn export_frames_c(&self, array: usize, schema: usize) -> JlrsResult<()> {
+        unsafe { cdata::export(self.export_frames()?, array, schema) }.map_err(julia_error)
+    }
+
+    /// Get a snapshot of frame index `frame` as JSON: every player's position, facing direction,
+    /// percent, stocks, and action state (throws if the game has no such frame)
+    pub fn state_at(&self, frame: i64) -> JlrsResult<StringRet> {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let snapshot = i32::try_from(frame).ok().and_then(|f| snapshot::at(&self.frames(), f));
+        let snapshot = snapshot.ok_or_else(|| invalid_input(format!("no frame {frame}")))?;
+        let s = json::to_string(&snapshot);
+        Ok(JuliaString::new(handle, s).leak())
+    }
+
+    /// Get a snapshot of the frame shown `seconds` into a recording of the game (counted from its
+    /// first frame, before the countdown), as [Game::state_at] does
+    pub fn state_at_seconds(&self, seconds: f64) -> JlrsResult<StringRet> {
+        if !seconds.is_finite() {
+            return Err(invalid_input(format!("invalid time {seconds}")));
+        }
+        let frame = snapshot::frame_at(seconds);
+        self.state_at(frame as i64)
+    }
+
+    /// Get the events within `radius` frames of frame index `frame` as a JSON array of objects with
+    /// the event's `kind`, `frame`, `port`, and `offset` from `frame`, in order. `types` is a JSON
+    /// array of the kinds to include ("hit", "grab", "death", "tech"), or "" for all of them.
+    pub fn events_near(&self, frame: i64, radius: usize, types: JuliaString) -> JlrsResult<StringRet> {
+        let types = unsafe { types.as_str_unchecked() };
+        let types: Option<Vec<String>> = match types {
+            "" => None,
+            t => Some(serde_json::from_str(t).map_err(|e| invalid_input(format!("invalid event types: {e}")))?),
+        };
+        let frame = i32::try_from(frame).map_err(|_| invalid_input(format!("invalid frame {frame}")))?;
+        let radius = u32::try_from(radius).unwrap_or(u32::MAX);
+        let events = timeline::near(&self.frames(), frame, radius, types.as_deref()).map_err(invalid_input)?;
+
+        let handle = unsafe { weak_handle_unchecked!() };
+        let s = json::to_string(&events);
+        Ok(JuliaString::new(handle, s).leak())
+    }
+
+    /// Sample `n` frames, returning their frame indices (as stored in the replay) as a JSON array
+    /// in ascending order. The same seed always selects the same frames.
+    pub fn sample_frames(&self, n: usize, seed: u64) -> StringRet {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let frames = self.frames();
+        let ids: Vec<i32> = sample::indices(frames.id.len(), n, seed)
+            .into_iter()
+            .map(|i| frames.id.value(i))
+            .collect();
+        let s = json::to_string(&ids);
+        JuliaString::new(handle, s).leak()
+    }
+
+    /// Write the data dictionary of a flattened frames and hits export to `path`, if enabled
+    fn write_dictionary(&self, path: &Path) -> JlrsResult<()> {
+        let config = config::get();
+        if config.emit_dictionary {
+            let columns = export::flatten(&self.export_frames()?, config.follower_policy);
+            dictionary::write(path, &columns, true).map_err(julia_error)?;
+        }
+        Ok(())
+    }
+
+    /// Write an Arrow IPC file with one row per `event` ("hit", "grab", "death", or "tech"), holding the event's
+    /// frame and port and a window of every frame column from `before` frames before it to `after`
+    /// frames after it. Returns the number of windows written.
+    pub fn write_windows(&self, path: JuliaString, event: JuliaString, before: usize, after: usize) -> JlrsResult<usize> {
+        let path = unsafe { path.as_str_unchecked() };
+        let event = unsafe { event.as_str_unchecked() };
+        let anchors = windows::anchors(&self.frames(), event).map_err(invalid_input)?;
+        let frames = self.export_frames()?;
+        let policy = config::get().follower_policy;
+        atomic::write(Path::new(path), |file| {
+            windows::write(file, &frames, &anchors, before, after, policy)
+        })
+        .map_err(julia_error)?;
+        Ok(anchors.len())
+    }
+
+    /// Write an Arrow IPC file pairing every frame's columns with the controller inputs of `port`
+    /// (1-4) `latency` frames later, as `target.*` columns. Returns the number of rows written.
+    pub fn write_imitation(&self, path: JuliaString, port: u8, latency: usize) -> JlrsResult<usize> {
+        let path = unsafe { path.as_str_unchecked() };
+        let port = port_arg(port)?;
+        let frames = self.export_frames()?;
+        let policy = config::get().follower_policy;
+        let mut rows = 0;
+        atomic::write(Path::new(path), |file| {
+            rows = imitation::write(file, &frames, port, latency, policy)?;
+            Ok::<_, arrow2::error::Error>(())
+        })
+        .map_err(julia_error)?;
+        Ok(rows)
+    }
+
+    /// Write an Arrow IPC file of the frame index and the columns of the player on `port` (1-4) alone,
+    /// flattened and named without the port (e.g. `post.percent`; see [port_frames]). Returns the
+    /// number of columns written.
+    pub fn write_port_frames(&self, port: u8, path: JuliaString) -> JlrsResult<usize> {
+        let path = unsafe { path.as_str_unchecked() };
+        let port = port_arg(port)?;
+        let frames = self.export_frames()?;
+        let policy = config::get().follower_policy;
+        let mut columns = 0;
+        atomic::write(Path::new(path), |file| {
+            columns = port_frames::write(file, &frames, port, policy, export_metadata())?;
+            Ok::<_, arrow2::error::Error>(())
+        })
+        .map_err(julia_error)?;
+        Ok(columns)
+    }
+
+    /// Write an Arrow IPC file of the Ice Climbers follower (Nana) on `port` (1-4), one row per frame
+    /// of the game aligned with the leader's: the `frame` index, whether she's `alive`, and her
+    /// `pre.*` and `post.*` columns, null wherever she isn't whatever the null policy (see
+    /// [follower]). Returns the number of frames she's alive on.
+    pub fn write_follower_frames(&self, port: u8, path: JuliaString) -> JlrsResult<usize> {
+        let path = unsafe { path.as_str_unchecked() };
+        let port = port_arg(port)?;
+        // Exported frames already have the null policy applied, leaving only her dead rows null.
+        let (table, alive) =
+            follower::table(&self.export_frames()?, &self.frames, port).map_err(julia_error)?;
+        atomic::write(Path::new(path), |file| follower::write(file, &table, export_metadata()))
+            .map_err(julia_error)?;
+        Ok(alive)
+    }
+
+    /// Write the game's items as an Arrow IPC table with one row per item per frame: the `frame`
+    /// index, `type`, `state`, `position.*`, `velocity.*`, `owner`, spawn `id`, and the rest of the
+    /// Item Update fields, in the configured units (see [items]). Returns the number of rows
+    /// written. Throws for replays older than Slippi 3.0.0, which have no item data.
+    pub fn write_items(&self, path: JuliaString) -> JlrsResult<usize> {
+        let path = unsafe { path.as_str_unchecked() };
+        let config = config::get();
+        let items = items::table(&self.frames).map_err(julia_error)?;
+        let items = units::apply_items(&items, self.stage, config.units).map_err(invalid_input)?;
+        let items = nulls::apply(&items, config.null_policy);
+        let mut rows = 0;
+        atomic::write(Path::new(path), |file| {
+            rows = items::write(file, &items, export_metadata())?;
+            Ok::<_, arrow2::error::Error>(())
+        })
+        .map_err(julia_error)?;
+        Ok(rows)
+    }
+
+    /// Frames converted to the configured units, plus any enabled derived features, for exports
+    /// (throws if the configured units or columns can't be applied to the game)
+    fn export_frames(&self) -> JlrsResult<StructArray> {
+        export_frames(&self.frames, self.version, self.stage)
+            .map(|(frames, _)| frames)
+            .map_err(invalid_input)
+    }
+
+    /// Rebuild peppi's columnar frame data from the in-memory struct array.
+    fn frames(&self) -> Frame {
+        Frame::from_struct_array(self.frames.clone(), self.version)
+    }
+}
+
+/// Read a `.slpp` file (peppi's own format, see `write_slpp`), frames included
+pub fn read_slpp(path: JuliaString) -> JlrsResult<CCallRefRet<Game>> {
+    read_peppi(path, 0)
 }
 
-pub fn read_slippi(path: JuliaString, skip_frames:i8) -> CCallRefRet<Game> {
+/// Read a `.slp` replay. Failures are thrown as exceptions, whose message starts with the kind of
+/// failure: "file not found", "I/O error", "truncated replay", "unsupported version", "invalid
+/// replay", or "limit exceeded" (see [set_limits]).
+pub fn read_slippi(path: JuliaString, skip_frames:i8) -> JlrsResult<CCallRefRet<Game>> {
     // Open the file and parse the Slippi replay into an immutable Game.
     // JuliaString::as_str returns a Result; avoid `?` by using unchecked.
     let path_str = unsafe { path.as_str_unchecked() };
-    let file = fs::File::open(path_str).expect("Failed to open file");
+    let started = Instant::now();
+    // Read the whole file up front so frame offsets can be found in the same bytes peppi parses.
+    let config = config::get();
+    let data = limits::read(Path::new(path_str), &config.limits).map_err(julia_error)?;
+    parse_slippi(data, skip_frames, started, &config.limits, None).map(to_julia).map_err(julia_error)
+}
+
+/// Read a `.slp` replay as [read_slippi] does, writing its frames to `output_path` rather than the
+/// output directory. When `output_path` is a directory the Arrow file is named as usual, and
+/// reused if already there; otherwise it's the Arrow file itself, which is always rewritten.
+pub fn read_slippi_to(path: JuliaString, skip_frames: i8, output_path: JuliaString) -> JlrsResult<CCallRefRet<Game>> {
+    let path_str = unsafe { path.as_str_unchecked() };
+    let output_path = unsafe { output_path.as_str_unchecked() };
+    let started = Instant::now();
+    let config = config::get();
+    let data = limits::read(Path::new(path_str), &config.limits).map_err(julia_error)?;
+    parse_slippi(data, skip_frames, started, &config.limits, Some(Path::new(output_path))).map(to_julia).map_err(julia_error)
+}
+
+/// Read a `.slp` replay from its bytes (e.g. a download, or a file in an archive or database),
+/// throwing exceptions as [read_slippi] does.
+pub fn read_slippi_bytes(data: TypedVector<'_, '_, u8>, skip_frames: i8) -> JlrsResult<CCallRefRet<Game>> {
+    let started = Instant::now();
+    // Copy the bytes, since Julia may free or change the array once this returns.
+    let data = unsafe { data.bits_data() }.as_slice().to_vec();
+    let config = config::get();
+    parse_slippi(data, skip_frames, started, &config.limits, None).map(to_julia).map_err(julia_error)
+}
+
+/// Parse a `.slp` replay's bytes, which started being read at `started`, within `limits`.
+fn parse_slippi(
+    data: Vec<u8>,
+    skip_frames: i8,
+    started: Instant,
+    limits: &limits::Limits,
+    output: Option<&Path>,
+) -> Result<Game, errors::ReadError> {
+    limits::check(&data, limits)?;
+    if let Some(sandbox) = config::get().sandbox {
+        sandbox
+            .parse(&data, skip_frames != 0, limits)?
+            .map_err(|e| errors::ReadError::parse(&data, e))?;
+    }
 
-    let mut reader = io::BufReader::new(file);
     // Use default parse options; `parse_opts` is accepted but not yet decoded.
     let opts = SlippiReadOpts{
 		skip_frames: skip_frames != 0,
 		..Default::default()
 	};
-    let slippi_game: SlippiGame = peppi::io::slippi::read(&mut reader, Some(&opts))
-        .expect("Failed to read Slippi file");
+    let (data, parsed) = limits::with_timeout(limits, move || {
+        let parsed = peppi::io::slippi::read(&mut io::Cursor::new(&data), Some(&opts));
+        (data, parsed)
+    })?;
+    let slippi_game: SlippiGame = parsed.map_err(|e| errors::ReadError::parse(&data, e))?;
+    let frame_offsets = raw::frame_offsets(&data).map_err(|e| errors::ReadError::parse(&data, e))?;
 
-    // Map fields from SlippiGame similar to the PyO3 example.
-    let start_json = serde_json::to_string(&slippi_game.start).unwrap_or_default();
-    let end_json = slippi_game
-        .end
-        .as_ref()
-        .and_then(|m| serde_json::to_string(m).ok());
-    let metadata_json = slippi_game
-        .metadata
-        .as_ref()
-        .and_then(|m| serde_json::to_string(m).ok());
+    export_game(slippi_game, frame_offsets, &data, skip_frames != 0, started, output)
+}
 
-    // Convert frames to Arrow IPC bytes
-    let frames_struct_array = slippi_game.frames.into_struct_array(
-        slippi_game.start.slippi.version,
-        &port_occupancy(&slippi_game.start),
-    );
+/// Read every `.slp` replay in `dir` (and its subdirectories, if `recursive`) whose file name
+/// matches `pattern` (where `*` matches anything and `?` any one character; "" for `*.slp`), as
+/// [read_slippi] does, on as many threads as configured for batch work. Returns the ID of the batch
+/// holding the games, to take them from with `batch_game`. A file that can't be read is thrown as
+/// an exception prefixed with its path.
+pub fn read_slippi_dir(dir: JuliaString, recursive: i8, pattern: JuliaString) -> JlrsResult<u64> {
+    let results = read_dir_games(dir, recursive, pattern)?;
+    // Games read before a failure are dropped with the rest, releasing their Arrow files.
+    let games = results
+        .into_iter()
+        .map(|(path, result)| result.map_err(|e| format!("{}: {e}", path.display())))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| julia_error(io::Error::other(e)))?;
+    Ok(batch::insert(games, Vec::new()))
+}
 
-    // Write to Arrow IPC file for memory-mapping
-    let schema = Schema::from(vec![Field {
-        name: "frame".to_string(),
-        data_type: frames_struct_array.data_type().clone(),
-        is_nullable: false,
-        metadata: Default::default(),
-    }]);
-
-    let chunk = Chunk::new(vec![Box::new(frames_struct_array) as Box<dyn Array>]);
-    
-    // Create a temporary Arrow file - using a deterministic path based on hash or temp dir
-    let arrow_path = std::env::temp_dir()
-        .join(format!("slippi_frames_{}.arrow", 
-            slippi_game.hash.as_deref().unwrap_or("unknown")));
-    
-    let arrow_file = fs::File::create(&arrow_path)
-        .expect("Failed to create Arrow file");
-    
-    let mut writer = FileWriter::try_new(
-        arrow_file,
-        schema,
-        None,
-        WriteOptions { compression: None },
-    ).expect("Failed to create Arrow writer");
-    
-    writer.write(&chunk, None).expect("Failed to write Arrow chunk");
-    writer.finish().expect("Failed to finish Arrow writer");
-
-    let arrow_path_str = arrow_path.to_str()
-        .expect("Path contains invalid UTF-8")
-        .to_string();
+/// Read a directory of replays as [read_slippi_dir] does, but skip the files that can't be read
+/// rather than throwing. The batch holds the games of the others, and a table of the skipped
+/// files (see `batch_skipped`).
+pub fn read_slippi_dir_tolerant(dir: JuliaString, recursive: i8, pattern: JuliaString) -> JlrsResult<u64> {
+    let results = read_dir_games(dir, recursive, pattern)?;
+    let mut games = Vec::new();
+    let mut skipped = Vec::new();
+    for (path, result) in results {
+        match result {
+            Ok(game) => games.push(game),
+            Err(e) => skipped.push(errors::FileError::read(&path, &e)),
+        }
+    }
+    Ok(batch::insert(games, skipped))
+}
 
-	
-    // Leak the exported Game to Julia through jlrs.
+/// Read the replays of a directory for [read_slippi_dir], returning each file's game or error.
+fn read_dir_games(
+    dir: JuliaString,
+    recursive: i8,
+    pattern: JuliaString,
+) -> JlrsResult<Vec<(PathBuf, Result<Game, errors::ReadError>)>> {
+    let dir = unsafe { dir.as_str_unchecked() };
+    let pattern = match unsafe { pattern.as_str_unchecked() } {
+        "" => "*.slp",
+        p => p,
+    };
+    let config = config::get();
+    let limits = config.limits;
+    let paths = batch::paths(Path::new(dir), recursive != 0, pattern).map_err(julia_error)?;
+    let results = batch::map(&paths, config.threads, |path| {
+        let started = Instant::now();
+        limits::read(path, &limits).and_then(|data| parse_slippi(data, 0, started, &limits, None))
+    });
+    Ok(paths.into_iter().zip(results).collect())
+}
+
+/// Number of games in a batch read by `read_slippi_dir` (throws for unknown batches)
+pub fn batch_len(id: u64) -> JlrsResult<usize> {
+    batch::len(id).ok_or_else(|| invalid_input(format!("unknown batch {id}")))
+}
+
+/// Take game `i` (1-based) of a batch read by `read_slippi_dir`. Each game can be taken once.
+pub fn batch_game(id: u64, i: usize) -> JlrsResult<CCallRefRet<Game>> {
+    let game = i
+        .checked_sub(1)
+        .and_then(|i| batch::take(id, i))
+        .ok_or_else(|| julia_error(io::Error::other(format!("no game {i} to take from batch {id}"))))?;
+    Ok(to_julia(game))
+}
+
+/// Files skipped by `read_slippi_dir_tolerant` as a JSON array of rows with the file's `path`, the
+/// `class` of error (`io`, `parse`, `limit`, or `unsupported`), and its `message` (throws for
+/// unknown batches)
+pub fn batch_skipped(id: u64) -> JlrsResult<StringRet> {
+    let errors = batch::errors(id).ok_or_else(|| invalid_input(format!("unknown batch {id}")))?;
     let handle = unsafe { weak_handle_unchecked!() };
-    CCallRefRet::new(TypedValue::new(
-		handle, 
-		Game {
-			start: start_json,
-			end: end_json,
-			metadata: metadata_json,
-			hash: slippi_game.hash,
-			frames_arrow_path: arrow_path_str,
-    	}
-	).leak())
-}
-
-pub fn read_peppi(path: JuliaString, skip_frames:i8) -> CCallRefRet<Game> {
+    Ok(JuliaString::new(handle, json::to_string(&errors)).leak())
+}
+
+/// Free a batch read by `read_slippi_dir`, dropping any games not taken from it
+pub fn batch_free(id: u64) {
+    batch::free(id);
+}
+
+/// Upgrade an Arrow IPC or Parquet frames file written by an older version of this library, in
+/// place, to schema version `target_version` (0 for the current one). Returns the version the file
+/// had; files already at the target are left alone.
+pub fn migrate_artifact(path: JuliaString, target_version: u32) -> JlrsResult<u32> {
+    let path = Path::new(unsafe { path.as_str_unchecked() });
+    let target = match target_version {
+        0 => schema::VERSION,
+        v => v,
+    };
+    schema::migrate_file(path, target).map_err(julia_error)
+}
+
+/// Read a `.slpp` (Peppi format) replay, throwing exceptions as [read_slippi] does.
+pub fn read_peppi(path: JuliaString, skip_frames:i8) -> JlrsResult<CCallRefRet<Game>> {
     // Open the file and parse the Slippi replay into an immutable Game.
     // JuliaString::as_str returns a Result; avoid `?` by using unchecked.
     let path_str = unsafe { path.as_str_unchecked() };
-    let file = fs::File::open(path_str).expect("Failed to open file");
+    let started = Instant::now();
+    // Read the whole file up front, since the Arrow file is named by a hash of its contents.
+    let limits = config::get().limits;
+    let data = limits::read(Path::new(path_str), &limits).map_err(julia_error)?;
 
-    let mut reader = io::BufReader::new(file);
     // Use default parse options; `parse_opts` is accepted but not yet decoded.
     let opts = PeppiReadOpts{
 		skip_frames: skip_frames != 0,
 		..Default::default()
 	};
-    let slippi_game: SlippiGame = peppi::io::peppi::read(&mut reader, Some(&opts))
-        .expect("Failed to read Slippi file");
+    let (data, parsed) = limits::with_timeout(&limits, move || {
+        let parsed = peppi::io::peppi::read(&mut io::Cursor::new(&data), Some(&opts));
+        (data, parsed)
+    })
+    .map_err(julia_error)?;
+    // The .slpp container isn't a `.slp` file, so its failures can't be classified further.
+    let slippi_game: SlippiGame =
+        parsed.map_err(|e| julia_error(errors::ReadError::Invalid(e.to_string())))?;
+
+    // .slpp files don't keep the original event stream, so there are no frame offsets to record.
+    export_game(slippi_game, Vec::new(), &data, skip_frames != 0, started, None)
+        .map(to_julia)
+        .map_err(julia_error)
+}
+
+/// Wrap an error so it's thrown to Julia as an exception.
+fn julia_error(e: impl std::error::Error + Send + Sync + 'static) -> Box<JlrsError> {
+    Box::new(JlrsError::other(e))
+}
 
+/// An exception for an invalid argument, thrown to Julia with `message`.
+fn invalid_input(message: impl std::fmt::Display) -> Box<JlrsError> {
+    julia_error(io::Error::new(io::ErrorKind::InvalidInput, message.to_string()))
+}
+
+/// The exception thrown by functions needing a cargo `feature` the library was built without.
+#[cfg(not(all(feature = "hdf5", feature = "duckdb", feature = "postgres")))]
+fn disabled(feature: &str) -> Box<JlrsError> {
+    let message = format!("peppi-jlrs was built without the {feature} feature");
+    julia_error(io::Error::new(io::ErrorKind::Unsupported, message))
+}
+
+/// The port numbered `port` (1-4), or an exception for any other number.
+fn port_arg(port: u8) -> JlrsResult<Port> {
+    port.checked_sub(1)
+        .and_then(|p| Port::try_from(p).ok())
+        .ok_or_else(|| invalid_input(format!("invalid port: {port}")))
+}
+
+/// Convert a parsed replay into the [Game] exposed to Julia, writing its frames to an Arrow IPC
+/// file along the way. `source` is the file the replay was parsed from, starting at `started`,
+/// with `skip_frames`.
+/// `output` overrides where the Arrow file goes, as in [read_slippi_to].
+fn export_game(
+    slippi_game: SlippiGame,
+    frame_offsets: Vec<raw::FrameOffset>,
+    source: &[u8],
+    skip_frames: bool,
+    started: Instant,
+    output: Option<&Path>,
+) -> Result<Game, errors::ReadError> {
     // Map fields from SlippiGame similar to the PyO3 example.
-    let start_json = serde_json::to_string(&slippi_game.start).unwrap_or_default();
+    let start_json = json::to_string(&slippi_game.start);
     let end_json = slippi_game
         .end
         .as_ref()
-        .and_then(|m| serde_json::to_string(m).ok());
+        .map(json::to_string);
     let metadata_json = slippi_game
         .metadata
         .as_ref()
-        .and_then(|m| serde_json::to_string(m).ok());
+        .map(json::to_string);
+    let metadata_str = |key: &str| {
+        slippi_game
+            .metadata
+            .as_ref()
+            .and_then(|m| m.get(key))
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+    };
+    let players = catalog::players(&slippi_game);
+    let console_nick = metadata_str("consoleNick");
+    let played_on = metadata_str("playedOn");
 
     // Convert frames to Arrow IPC bytes
+    let version = slippi_game.start.slippi.version;
     let frames_struct_array = slippi_game.frames.into_struct_array(
-        slippi_game.start.slippi.version,
+        version,
         &port_occupancy(&slippi_game.start),
     );
 
-    // Write to Arrow IPC file for memory-mapping
-    let schema = Schema::from(vec![Field {
-        name: "frame".to_string(),
-        data_type: frames_struct_array.data_type().clone(),
-        is_nullable: false,
-        metadata: Default::default(),
-    }]);
-
-    let chunk = Chunk::new(vec![Box::new(frames_struct_array) as Box<dyn Array>]);
-    
-    // Create a temporary Arrow file - using a deterministic path based on hash or temp dir
-    let arrow_path = std::env::temp_dir()
-        .join(format!("slippi_frames_{}.arrow", 
-            slippi_game.hash.as_deref().unwrap_or("unknown")));
-    
-    let arrow_file = fs::File::create(&arrow_path)
-        .expect("Failed to create Arrow file");
-    
-    let mut writer = FileWriter::try_new(
-        arrow_file,
-        schema,
-        None,
-        WriteOptions { compression: None },
-    ).expect("Failed to create Arrow writer");
-    
-    writer.write(&chunk, None).expect("Failed to write Arrow chunk");
-    writer.finish().expect("Failed to finish Arrow writer");
-
-    let arrow_path_str = arrow_path.to_str()
-        .expect("Path contains invalid UTF-8")
+    let stage = slippi_game.start.stage;
+    let (export_frames, mut warnings) = export_frames(&frames_struct_array, version, stage)
+        .map_err(|e| errors::ReadError::Io(io::Error::new(io::ErrorKind::InvalidInput, e)))?;
+    warnings.extend(warnings::hits(version));
+
+    // Create the Arrow file in the output directory - using a deterministic path based on the
+    // contents of the source file and the export options - unless told where to put it
+    let config = config::get();
+    let mut options = content::frame_options(
+        config.units,
+        config.stage_features,
+        config.relative_features,
+        config.win_probability.as_ref(),
+        config.null_policy,
+        config.columns.as_deref(),
+    );
+    // Compression changes the file but not the frames, so it's only named when used, leaving the
+    // names of uncompressed files as they were.
+    if config.compression != ipc::Compression::None {
+        options["compression"] = serde_json::to_value(config.compression).unwrap_or_default();
+    }
+    // Likewise how the replay was read: a file of skipped frames never stands in for a full one.
+    if skip_frames {
+        options["skip_frames"] = serde_json::json!(true);
+    }
+    let arrow_name = format!("slippi_frames_{}.arrow", content::hash(source, &options));
+    let (arrow_path, reusable) = match output {
+        None => (config.output_dir.join(arrow_name), true),
+        Some(dir) if dir.is_dir() => (dir.join(arrow_name), true),
+        // A file named by the caller could hold anything, so it's never reused.
+        Some(file) => (file.to_path_buf(), false),
+    };
+    // The game hands the path to Julia as a string, so one that isn't UTF-8 is refused before
+    // anything is written.
+    let arrow_path_str = arrow_path
+        .to_str()
+        .ok_or_else(|| {
+            let msg = format!("output path isn't valid UTF-8: {}", arrow_path.display());
+            errors::ReadError::Io(io::Error::new(io::ErrorKind::InvalidInput, msg))
+        })?
         .to_string();
 
-	
-    // Leak the exported Game to Julia through jlrs.
+    // Hold the lock while converting, so processes sharing the output directory don't convert the
+    // same replay at once. A replay already converted by another process (or from another copy of
+    // the same file) is reused as is.
+    let _lock = lock::FileLock::exclusive(&arrow_path).map_err(errors::ReadError::Io)?;
+    let cached = reusable && arrow_path.exists();
+    metrics::record_cache(cached);
+    if !cached {
+        atomic::write(&arrow_path, |arrow_file| {
+            write_frames_arrow(arrow_file, &export_frames, ipc::write_options(), export_metadata())
+        })
+            .map_err(|e| errors::ReadError::Io(io::Error::other(e)))?;
+    }
+    metrics::record_game(source.len(), frames_struct_array.len(), started.elapsed());
+    if config.emit_dictionary {
+        let columns = export::leaves(&export_frames);
+        dictionary::write(&dictionary::path_for(&arrow_path), &columns, false).map_err(errors::ReadError::Io)?;
+    }
+
+    // Files in the output directory are only there for the games using them. They're counted
+    // while the lock is held, so they can't be deleted in between.
+    let managed = output.is_none();
+    if managed {
+        cleanup::acquire(&arrow_path);
+    }
+    Ok(Game {
+		start: start_json,
+		parsed_start: slippi_game.start,
+		end: end_json,
+		metadata: metadata_json,
+		parsed_metadata: slippi_game.metadata,
+		hash: slippi_game.hash,
+		console_nick,
+		played_on,
+		frames_arrow_path: arrow_path_str,
+		frame_offsets,
+		frames: frames_struct_array,
+		version,
+		stage,
+		warnings,
+		managed: AtomicBool::new(managed),
+		players,
+		source_hash: content::source_hash(source),
+		gecko_codes: slippi_game.gecko_codes,
+		quirks: slippi_game.quirks,
+	})
+}
+
+/// Leak a [Game] to Julia through jlrs.
+fn to_julia(game: Game) -> CCallRefRet<Game> {
     let handle = unsafe { weak_handle_unchecked!() };
-    CCallRefRet::new(TypedValue::new(
-		handle, 
-		Game {
-			start: start_json,
-			end: end_json,
-			metadata: metadata_json,
-			hash: slippi_game.hash,
-			frames_arrow_path: arrow_path_str,
-    	}
-	).leak())
+    CCallRefRet::new(TypedValue::new(handle, game).leak())
 }
 
-fn port_occupancy(start: &Start) -> Vec<PortOccupancy> {
-    start
-        .players
-        .iter()
-        .map(|p| PortOccupancy {
-            port: p.port,
-            follower: p.character == ICE_CLIMBERS,
-        })
-        .collect()
+/// Build a catalog of every `.slp` file below `dir` and write it to `out` as JSON, returning the
+/// number of games cataloged. Rebuilding a catalog keeps what was added to it since (tags,
+/// corrected start times, and players' IDs, ratings, and inferred placements) for the games still
+/// there, wherever they've moved.
+pub fn build_catalog(dir: JuliaString, out: JuliaString) -> JlrsResult<usize> {
+    let dir = unsafe { dir.as_str_unchecked() };
+    let out = Path::new(unsafe { out.as_str_unchecked() });
+    let (mut entries, failed) = catalog::build(Path::new(dir)).map_err(julia_error)?;
+    let _lock = lock::FileLock::exclusive(out).map_err(julia_error)?;
+    let previous = match catalog::read(out) {
+        Ok(previous) => previous,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(julia_error(e)),
+    };
+    catalog::carry_over(&mut entries, &previous);
+    catalog::write(&entries, out).map_err(julia_error)?;
+    errors::write(out, &failed).map_err(julia_error)?;
+    Ok(entries.len())
 }
 
+/// Count games and per-player activity per day, week, or month (`period`) of a catalog, as JSON.
+/// Throws for any other period.
+pub fn catalog_buckets(path: JuliaString, period: JuliaString) -> JlrsResult<StringRet> {
+    let path = unsafe { path.as_str_unchecked() };
+    let period: catalog::Period = unsafe { period.as_str_unchecked() }.parse().map_err(invalid_input)?;
+    let entries = catalog::read(Path::new(path)).map_err(julia_error)?;
+    let buckets = catalog::buckets(&entries, period);
 
-julia_module! {
-    become peppi_jlrs_init;
-		
-	/// read_slippi_path(path::String)
-	///
-    /// Read a Slippi replay file from the given path and return a SlippiGame object.
-    struct Game;
+    let handle = unsafe { weak_handle_unchecked!() };
+    let s = json::to_string(&buckets);
+    Ok(JuliaString::new(handle, s).leak())
+}
 
-    fn read_peppi(path: JuliaString, skip_frames: i8) -> CCallRefRet<Game> as read_peppi;
-    fn read_slippi(path: JuliaString, skip_frames: i8) -> CCallRefRet<Game> as read_slippi;
+/// Sample `n` replays below `dir` matching `filter` (a JSON object with optional `characters`,
+/// `stages`, and `players` arrays; empty for no filter), returning their paths as a JSON array.
+/// The same seed always selects the same replays from the same directory.
+pub fn sample_games(dir: JuliaString, n: usize, seed: u64, filter: JuliaString) -> JlrsResult<StringRet> {
+    let dir = unsafe { dir.as_str_unchecked() };
+    let filter = unsafe { filter.as_str_unchecked() };
+    let filter: catalog::Filter = match filter {
+        "" => Default::default(),
+        f => serde_json::from_str(f).map_err(|e| invalid_input(format!("invalid filter: {e}")))?,
+    };
+    let entries: Vec<_> = catalog::build(Path::new(dir))
+        .map_err(julia_error)?
+        .0
+        .into_iter()
+        .filter(|e| filter.matches(e))
+        .collect();
+    let paths: Vec<&str> = sample::indices(entries.len(), n, seed)
+        .into_iter()
+        .map(|i| entries[i].path.as_str())
+        .collect();
 
-    // Expose getters to Julia
-    #[untracked_self]
-    in Game fn get_start(&self) -> jlrs::data::managed::string::StringRet as get_start;
-    #[untracked_self]
-    in Game fn get_end(&self) -> jlrs::data::managed::string::StringRet as get_end;
-    #[untracked_self]
-    in Game fn get_metadata(&self) -> jlrs::data::managed::string::StringRet as get_metadata;
-    #[untracked_self]
-    in Game fn get_hash(&self) -> jlrs::data::managed::string::StringRet as get_hash;
+    let handle = unsafe { weak_handle_unchecked!() };
+    let s = json::to_string(&paths);
+    Ok(JuliaString::new(handle, s).leak())
+}
+
+/// Sample up to `per_class` games per character, stage, or matchup (`by`) from a catalog, writing
+/// the selection as a JSON manifest to `out` and returning the number of games selected.
+pub fn sample_balanced(catalog_path: JuliaString, by: JuliaString, per_class: usize, seed: u64, out: JuliaString) -> JlrsResult<usize> {
+    let catalog_path = unsafe { catalog_path.as_str_unchecked() };
+    let by = unsafe { by.as_str_unchecked() };
+    let out = unsafe { out.as_str_unchecked() };
+    let entries = catalog::read(Path::new(catalog_path)).map_err(julia_error)?;
+    let manifest = sample::balanced(&entries, by, per_class, seed).map_err(invalid_input)?;
+    atomic::write_json(Path::new(out), &manifest, true).map_err(julia_error)?;
+    Ok(manifest.classes.values().map(Vec::len).sum())
+}
+
+/// Estimate the clock offsets between the setups (console nicknames) of a catalog from players'
+/// consecutive games across setups and `hints` (a JSON array of `[earlier, later]` replay path
+/// pairs known from bracket order; empty for none), anchored to true time by `references` (a JSON
+/// array of `[path, start time]` pairs of replays whose true ISO-8601 start time is known; empty
+/// for none). Returns them as a JSON object with the `reference` setup (when there are no
+/// references), whether the offsets are `anchored`, and each setup's `offset` in seconds. If
+/// `apply`, every game on a setup whose offset was measured gets a `corrected_start_time` in the
+/// catalog.
+pub fn correct_clock_drift(catalog_path: JuliaString, hints: JuliaString, references: JuliaString, apply: i8) -> JlrsResult<StringRet> {
+    let catalog_path = Path::new(unsafe { catalog_path.as_str_unchecked() });
+    let pairs = |name: &str, s: &str| -> JlrsResult<Vec<(String, String)>> {
+        match s {
+            "" => Ok(Vec::new()),
+            s => serde_json::from_str(s).map_err(|e| invalid_input(format!("invalid {name}: {e}"))),
+        }
+    };
+    let hints = pairs("hints", unsafe { hints.as_str_unchecked() })?;
+    let references = pairs("references", unsafe { references.as_str_unchecked() })?;
+    if let Some((path, time)) = references.iter().find(|(_, time)| clock::parse(time).is_none()) {
+        return Err(invalid_input(format!("invalid start time of {path}: {time}")));
+    }
+
+    let _lock = lock::FileLock::exclusive(catalog_path).map_err(julia_error)?;
+    let mut entries = catalog::read(catalog_path).map_err(julia_error)?;
+    let drift = clock::estimate(&entries, &hints, &references);
+    if apply != 0 {
+        clock::apply(&mut entries, &drift);
+        catalog::write(&entries, catalog_path).map_err(julia_error)?;
+    }
+
+    let handle = unsafe { weak_handle_unchecked!() };
+    Ok(JuliaString::new(handle, json::to_string(&drift)).leak())
+}
+
+/// Rank the `top` most exciting segments of `segment_seconds` seconds (whole games when 0) across
+/// the games of a catalog, on `threads` threads (as configured when 0), returning them best first as
+/// JSON `highlights` (each with the game's `path`, the segment's `start` and `end` frames, and the
+/// scores of `get_excitement`) along with the files that `failed`
+pub fn rank_highlights(catalog_path: JuliaString, segment_seconds: f64, top: usize, threads: usize) -> JlrsResult<StringRet> {
+    let catalog_path = unsafe { catalog_path.as_str_unchecked() };
+    let entries = catalog::read(Path::new(catalog_path)).map_err(julia_error)?;
+    let segment_frames = (segment_seconds.max(0.0) * stalls::FPS) as usize;
+    let ranking = excitement::rank(&entries, segment_frames, top, threads);
+
+    let handle = unsafe { weak_handle_unchecked!() };
+    Ok(JuliaString::new(handle, json::to_string(&ranking)).leak())
+}
+
+/// Infer the sets played in a catalog from consecutive games between the same players on the same
+/// setup, at most `max_gap_seconds` apart (5 minutes when 0), returning them as a JSON array of
+/// sets with their `players`, `games`, likely `best_of`, and inferred `round`.
+pub fn infer_sets(catalog_path: JuliaString, max_gap_seconds: f64) -> JlrsResult<StringRet> {
+    let catalog_path = unsafe { catalog_path.as_str_unchecked() };
+    let entries = catalog::read(Path::new(catalog_path)).map_err(julia_error)?;
+    let max_gap = if max_gap_seconds > 0.0 { max_gap_seconds } else { sets::DEFAULT_MAX_GAP };
+    let sets = sets::infer(&entries, max_gap);
+
+    let handle = unsafe { weak_handle_unchecked!() };
+    Ok(JuliaString::new(handle, json::to_string(&sets)).leak())
+}
+
+/// Resolve the players of a catalog to canonical player IDs, clustering players by connect code and
+/// by an alias file (a JSON object mapping player IDs to the codes and names they've played under;
+/// empty for none). Sets each player's `player_id`, returning the number of distinct player IDs.
+pub fn resolve_identities(catalog_path: JuliaString, aliases_path: JuliaString) -> JlrsResult<usize> {
+    let catalog_path = Path::new(unsafe { catalog_path.as_str_unchecked() });
+    let aliases_path = unsafe { aliases_path.as_str_unchecked() };
+    let aliases = match aliases_path {
+        "" => Default::default(),
+        path => identity::read_aliases(Path::new(path)).map_err(julia_error)?,
+    };
+
+    let _lock = lock::FileLock::exclusive(catalog_path).map_err(julia_error)?;
+    let mut entries = catalog::read(catalog_path).map_err(julia_error)?;
+    let resolved = identity::resolve(&mut entries, &aliases);
+    catalog::write(&entries, catalog_path).map_err(julia_error)?;
+    Ok(resolved)
+}
+
+/// Give every player of a catalog a placement, inferring those replays older than Slippi 3.13
+/// don't record from stocks, percent, and who quit out (see `game_result`), on `threads` threads
+/// (as configured when 0). Each player's `placement_recorded` says which it is. Returns JSON with
+/// the number of games `normalized` and the files that `failed`.
+pub fn normalize_placements(catalog_path: JuliaString, threads: usize) -> JlrsResult<StringRet> {
+    let catalog_path = Path::new(unsafe { catalog_path.as_str_unchecked() });
+    let _lock = lock::FileLock::exclusive(catalog_path).map_err(julia_error)?;
+    let mut entries = catalog::read(catalog_path).map_err(julia_error)?;
+    let normalized = outcome::normalize(&mut entries, threads);
+    catalog::write(&entries, catalog_path).map_err(julia_error)?;
+
+    let handle = unsafe { weak_handle_unchecked!() };
+    Ok(JuliaString::new(handle, json::to_string(&normalized)).leak())
+}
+
+/// Split the games of a catalog into named splits in proportion to `fractions` (a JSON object such
+/// as `{"train": 0.8, "val": 0.1, "test": 0.1}`), by "game" or by "player" so no player appears in
+/// more than one split. Writes the splits as a JSON manifest to `out`, returning the number of
+/// games assigned. Throws for invalid fractions or an unknown `by`.
+pub fn split_dataset(catalog_path: JuliaString, fractions: JuliaString, by: JuliaString, seed: u64, out: JuliaString) -> JlrsResult<usize> {
+    let catalog_path = unsafe { catalog_path.as_str_unchecked() };
+    let fractions = unsafe { fractions.as_str_unchecked() };
+    let by = unsafe { by.as_str_unchecked() };
+    let out = unsafe { out.as_str_unchecked() };
+    let fractions: BTreeMap<String, f64> =
+        serde_json::from_str(fractions).map_err(|e| invalid_input(format!("invalid fractions: {e}")))?;
+    let entries = catalog::read(Path::new(catalog_path)).map_err(julia_error)?;
+    let manifest = sample::split(&entries, &fractions, by, seed).map_err(invalid_input)?;
+    atomic::write_json(Path::new(out), &manifest, true).map_err(julia_error)?;
+    Ok(manifest.splits.values().map(Vec::len).sum())
+}
+
+/// Annotate the players of a catalog with ratings and skill brackets from a ratings file (a JSON
+/// object mapping connect codes to ratings), returning the number of players annotated.
+pub fn annotate_brackets(catalog_path: JuliaString, ratings_path: JuliaString) -> JlrsResult<usize> {
+    let catalog_path = Path::new(unsafe { catalog_path.as_str_unchecked() });
+    let ratings_path = unsafe { ratings_path.as_str_unchecked() };
+    let ratings = skill::read_ratings(Path::new(ratings_path)).map_err(julia_error)?;
+
+    let _lock = lock::FileLock::exclusive(catalog_path).map_err(julia_error)?;
+    let mut entries = catalog::read(catalog_path).map_err(julia_error)?;
+    let annotated = skill::annotate(&mut entries, &ratings);
+    catalog::write(&entries, catalog_path).map_err(julia_error)?;
+    Ok(annotated)
+}
+
+/// Tag a game of a catalog, named by the SHA-256 of its file (see `get_source_hash`) or its path,
+/// with `tag` (e.g. "money-match"). Returns the number of entries newly tagged, which is more than
+/// one when the catalog holds copies of the replay. Throws for an empty tag.
+pub fn tag_game(catalog_path: JuliaString, game: JuliaString, tag: JuliaString) -> JlrsResult<usize> {
+    let catalog_path = Path::new(unsafe { catalog_path.as_str_unchecked() });
+    let game = unsafe { game.as_str_unchecked() };
+    let tag = unsafe { tag.as_str_unchecked() };
+    if tag.is_empty() {
+        return Err(invalid_input("empty tag"));
+    }
+
+    let _lock = lock::FileLock::exclusive(catalog_path).map_err(julia_error)?;
+    let mut entries = catalog::read(catalog_path).map_err(julia_error)?;
+    let added = tags::add(&mut entries, game, tag);
+    catalog::write(&entries, catalog_path).map_err(julia_error)?;
+    Ok(added)
+}
+
+/// Remove `tag` from a game of a catalog, named as for `tag_game` (every tag if it's empty),
+/// returning the number of tags removed.
+pub fn untag_game(catalog_path: JuliaString, game: JuliaString, tag: JuliaString) -> JlrsResult<usize> {
+    let catalog_path = Path::new(unsafe { catalog_path.as_str_unchecked() });
+    let game = unsafe { game.as_str_unchecked() };
+    let tag = unsafe { tag.as_str_unchecked() };
+    let tag = (!tag.is_empty()).then_some(tag);
+
+    let _lock = lock::FileLock::exclusive(catalog_path).map_err(julia_error)?;
+    let mut entries = catalog::read(catalog_path).map_err(julia_error)?;
+    let removed = tags::remove(&mut entries, game, tag);
+    catalog::write(&entries, catalog_path).map_err(julia_error)?;
+    Ok(removed)
+}
+
+/// Get the entries of a catalog tagged with `tag` as a JSON array. Catalog filters (as in feature
+/// sets) can also select on tags, with a `tags` array.
+pub fn games_with_tag(catalog_path: JuliaString, tag: JuliaString) -> JlrsResult<StringRet> {
+    let catalog_path = unsafe { catalog_path.as_str_unchecked() };
+    let tag = unsafe { tag.as_str_unchecked() };
+    let entries = catalog::read(Path::new(catalog_path)).map_err(julia_error)?;
+    let tagged = tags::tagged(&entries, tag);
+
+    let handle = unsafe { weak_handle_unchecked!() };
+    Ok(JuliaString::new(handle, json::to_string(&tagged)).leak())
+}
+
+/// Get every tag used in a catalog as a JSON object mapping tags to their number of games
+pub fn list_tags(catalog_path: JuliaString) -> JlrsResult<StringRet> {
+    let catalog_path = unsafe { catalog_path.as_str_unchecked() };
+    let entries = catalog::read(Path::new(catalog_path)).map_err(julia_error)?;
+    let counts = tags::counts(&entries);
+
+    let handle = unsafe { weak_handle_unchecked!() };
+    Ok(JuliaString::new(handle, json::to_string(&counts)).leak())
+}
+
+/// Save a named query on a catalog: a filter (a JSON object with optional `characters`, `stages`,
+/// `players`, `brackets`, and `tags` arrays, and `since` and `until` start times, ISO-8601 or a
+/// date alone), kept next to the catalog for every tool using it. Returns whether a query of that
+/// name was replaced. Throws for an invalid filter, without saving it.
+pub fn save_query(catalog_path: JuliaString, name: JuliaString, filter: JuliaString) -> JlrsResult<bool> {
+    let catalog_path = unsafe { catalog_path.as_str_unchecked() };
+    let name = unsafe { name.as_str_unchecked() };
+    let filter = unsafe { filter.as_str_unchecked() };
+    let filter: catalog::Filter =
+        serde_json::from_str(filter).map_err(|e| invalid_input(format!("invalid filter: {e}")))?;
+    let path = queries::path_for(Path::new(catalog_path));
+    queries::save(&path, name, filter).map_err(julia_error)
+}
+
+/// Delete a saved query of a catalog, returning whether there was one
+pub fn delete_query(catalog_path: JuliaString, name: JuliaString) -> JlrsResult<bool> {
+    let catalog_path = unsafe { catalog_path.as_str_unchecked() };
+    let name = unsafe { name.as_str_unchecked() };
+    let path = queries::path_for(Path::new(catalog_path));
+    queries::delete(&path, name).map_err(julia_error)
+}
+
+/// Get the saved queries of a catalog as a JSON object mapping their names to their filters
+pub fn list_queries(catalog_path: JuliaString) -> JlrsResult<StringRet> {
+    let catalog_path = unsafe { catalog_path.as_str_unchecked() };
+    let path = queries::path_for(Path::new(catalog_path));
+    let queries = queries::read(&path).map_err(julia_error)?;
+
+    let handle = unsafe { weak_handle_unchecked!() };
+    Ok(JuliaString::new(handle, json::to_string(&queries)).leak())
+}
+
+/// Run a saved query of a catalog, getting the entries it matches now as a JSON array of rows, one
+/// per game (throws if there's no such query)
+pub fn run_query(catalog_path: JuliaString, name: JuliaString) -> JlrsResult<StringRet> {
+    let catalog_path = Path::new(unsafe { catalog_path.as_str_unchecked() });
+    let name = unsafe { name.as_str_unchecked() };
+    let entries = catalog::read(catalog_path).map_err(julia_error)?;
+    let matched = queries::run(&queries::path_for(catalog_path), name, &entries)
+        .map_err(julia_error)?
+        .ok_or_else(|| invalid_input(format!("no such query: {name}")))?;
+
+    let handle = unsafe { weak_handle_unchecked!() };
+    Ok(JuliaString::new(handle, json::to_string(&matched)).leak())
+}
+
+/// Materialize a feature set (a JSON object with a `name` and which artifacts to compute: `frames`,
+/// with `units`, `stage_features`, and `relative_features` options, `hits`, and `grabs`) for every
+/// game of a catalog matching its optional `filter` under `store`, returning the number of games
+/// computed. Artifacts already computed with the same options and code versions are reused.
+pub fn materialize_features(catalog_path: JuliaString, feature_set: JuliaString, store: JuliaString) -> JlrsResult<usize> {
+    let catalog_path = unsafe { catalog_path.as_str_unchecked() };
+    let feature_set = feature_set_arg(feature_set)?;
+    let store = unsafe { store.as_str_unchecked() };
+    let entries = catalog::read(Path::new(catalog_path)).map_err(julia_error)?;
+    store::materialize(Path::new(store), &feature_set, &entries).map_err(julia_error)
+}
+
+/// Report what `materialize_features` would do without storing anything, as JSON: how many games
+/// match the filter and still need computing, and the estimated output size and time, extrapolated
+/// from computing up to `sample` of them.
+pub fn dry_run_features(catalog_path: JuliaString, feature_set: JuliaString, store: JuliaString, sample: usize) -> JlrsResult<StringRet> {
+    let catalog_path = unsafe { catalog_path.as_str_unchecked() };
+    let feature_set = feature_set_arg(feature_set)?;
+    let store = unsafe { store.as_str_unchecked() };
+    let entries = catalog::read(Path::new(catalog_path)).map_err(julia_error)?;
+    let estimate = store::estimate(Path::new(store), &feature_set, &entries, sample).map_err(julia_error)?;
+
+    let handle = unsafe { weak_handle_unchecked!() };
+    let s = json::to_string(&estimate);
+    Ok(JuliaString::new(handle, s).leak())
+}
+
+/// Remove every stored artifact of a feature set under `store`, whatever definition it was computed
+/// with, returning the number of files removed.
+pub fn invalidate_features(feature_set: JuliaString, store: JuliaString) -> JlrsResult<usize> {
+    let feature_set = feature_set_arg(feature_set)?;
+    let store = unsafe { store.as_str_unchecked() };
+    store::invalidate(Path::new(store), &feature_set).map_err(julia_error)
+}
+
+/// The feature set defined by the JSON `feature_set`, or an exception when it isn't one.
+fn feature_set_arg(feature_set: JuliaString) -> JlrsResult<store::FeatureSet> {
+    let feature_set = unsafe { feature_set.as_str_unchecked() };
+    serde_json::from_str(feature_set).map_err(|e| invalid_input(format!("invalid feature set: {e}")))
+}
+
+/// Get the files the last batch job writing to `output` (a catalog, or a feature set's directory
+/// `<store>/<name>`) failed on, as a JSON array of objects with a `path`, error `class`, `message`,
+/// and byte `offset` of malformed event data.
+pub fn batch_errors(output: JuliaString) -> JlrsResult<StringRet> {
+    let output = unsafe { output.as_str_unchecked() };
+    let failed = errors::read(Path::new(output)).map_err(julia_error)?;
+
+    let handle = unsafe { weak_handle_unchecked!() };
+    let s = json::to_string(&failed);
+    Ok(JuliaString::new(handle, s).leak())
+}
+
+/// Convert every `.slp` replay below `src` into `format` ("slpp", "arrow", or "parquet") at the
+/// same relative path below `dst`, compressed with `compression` ("" for none), on `threads` threads
+/// (as many as configured when 0). Replays already converted into `dst` with the same options are
+/// skipped. Returns a JSON object with the number of files `converted` and `skipped`, and the rows
+/// of the files that `failed` (as in errors files).
+pub fn transcode_dir(src: JuliaString, dst: JuliaString, format: JuliaString, compression: JuliaString, threads: usize) -> JlrsResult<StringRet> {
+    let src = unsafe { src.as_str_unchecked() };
+    let dst = unsafe { dst.as_str_unchecked() };
+    let format = unsafe { format.as_str_unchecked() };
+    let compression = unsafe { compression.as_str_unchecked() };
+    let format: transcode::Format = format
+        .parse()
+        .map_err(|e: String| julia_error(io::Error::new(io::ErrorKind::InvalidInput, e)))?;
+    let summary = transcode::dir(Path::new(src), Path::new(dst), format, compression, threads).map_err(julia_error)?;
+    let handle = unsafe { weak_handle_unchecked!() };
+    Ok(JuliaString::new(handle, json::to_string(&summary)).leak())
+}
+
+/// Write a Dolphin playback queue for `clips` (a JSON array of objects with a `path` and optional
+/// `start_frame`/`end_frame`) to `out`, returning the number of queued clips.
+pub fn make_playback_queue(clips: JuliaString, out: JuliaString) -> usize {
+    let clips = unsafe { clips.as_str_unchecked() };
+    let out = unsafe { out.as_str_unchecked() };
+    let clips: Vec<playback::Clip> = serde_json::from_str(clips).expect("Invalid clip list");
+    playback::CommFile::queue(&clips)
+        .write(Path::new(out))
+        .expect("Failed to write playback queue");
+    clips.len()
+}
+
+/// Write a Dolphin playback queue for `clips` to `comm_out`, and a recording plan with an output
+/// name, expected duration, and overlay text per clip to `plan_out`. Clips may additionally set
+/// `name` and `overlay_text`. Returns the number of planned clips.
+pub fn make_recording_plan(clips: JuliaString, comm_out: JuliaString, plan_out: JuliaString) -> usize {
+    let clips = unsafe { clips.as_str_unchecked() };
+    let comm_out = unsafe { comm_out.as_str_unchecked() };
+    let plan_out = unsafe { plan_out.as_str_unchecked() };
+    let clips: Vec<playback::Clip> = serde_json::from_str(clips).expect("Invalid clip list");
+    playback::CommFile::queue(&clips)
+        .write(Path::new(comm_out))
+        .expect("Failed to write playback queue");
+    playback::RecordingPlan::new(&clips, Path::new(comm_out))
+        .write(Path::new(plan_out))
+        .expect("Failed to write recording plan");
+    clips.len()
+}
+
+/// Copy the replay at `input` to `output` with message and emote events stripped, returning the
+/// number of events removed.
+pub fn redact_slippi(input: JuliaString, output: JuliaString) -> JlrsResult<usize> {
+    let input = unsafe { input.as_str_unchecked() };
+    let output = unsafe { output.as_str_unchecked() };
+    let data = fs::read(input).map_err(julia_error)?;
+    let (redacted, removed) = privacy::redact_messages(&data).map_err(julia_error)?;
+    atomic::write_bytes(Path::new(output), &redacted).map_err(julia_error)?;
+    Ok(removed)
+}
+
+/// Write a copy of the replay at `input` to `output` with its players' display names, connect codes,
+/// Slippi UIDs, and name tags, and its console nickname, pseudonymized using `salt` (kept secret),
+/// or blanked if it's empty. Returns the number of fields changed.
+pub fn anonymize_slippi(input: JuliaString, output: JuliaString, salt: JuliaString) -> JlrsResult<usize> {
+    let input = unsafe { input.as_str_unchecked() };
+    let output = unsafe { output.as_str_unchecked() };
+    let salt = unsafe { salt.as_str_unchecked() };
+    let data = fs::read(input).map_err(julia_error)?;
+    let anonymizer = privacy::Anonymizer::new(salt);
+    let (anonymized, changed) = privacy::anonymize(&data, &anonymizer).map_err(julia_error)?;
+    atomic::write_bytes(Path::new(output), &anonymized).map_err(julia_error)?;
+    Ok(changed)
+}
+
+/// Write a copy of the replay at `input` to `output` with its metadata replaced by `metadata` (a
+/// JSON object), or edited by it when `patch` is set: keys are merged in, and null values remove
+/// them (e.g. `{"startAt": "2023-04-01T18:30:00Z", "tournament": {"round": "WF"}}`). The events are
+/// copied byte for byte, unlike `write_slippi`, which writes what peppi parsed. That's why it goes
+/// from file to file rather than taking a read game: a game doesn't keep the bytes it was parsed
+/// from.
+pub fn with_metadata_slippi(input: JuliaString, output: JuliaString, metadata: JuliaString, patch: i8) -> JlrsResult<()> {
+    let input = unsafe { input.as_str_unchecked() };
+    let output = unsafe { output.as_str_unchecked() };
+    let metadata = unsafe { metadata.as_str_unchecked() };
+    let metadata: serde_json::Value =
+        serde_json::from_str(metadata).map_err(|e| invalid_input(format!("invalid metadata: {e}")))?;
+    let data = fs::read(input).map_err(julia_error)?;
+    let rewritten = metadata::rewrite(&data, &metadata, patch != 0).map_err(julia_error)?;
+    atomic::write_bytes(Path::new(output), &rewritten).map_err(julia_error)
+}
+
+/// Write a copy of the replay at `input` to `output` keeping only frames `first_frame` through
+/// `last_frame` (by frame index, from -123), with its metadata's `lastFrame` and `startAt` updated
+/// to match. Returns the number of frames kept.
+pub fn trim_slippi(input: JuliaString, output: JuliaString, first_frame: i64, last_frame: i64) -> JlrsResult<usize> {
+    let input = unsafe { input.as_str_unchecked() };
+    let output = unsafe { output.as_str_unchecked() };
+    if first_frame > last_frame {
+        return Err(invalid_input(format!("last frame {last_frame} is before first frame {first_frame}")));
+    }
+    let data = fs::read(input).map_err(julia_error)?;
+    let clamp = |f: i64| f.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+    let (trimmed, kept) = trim::trim(&data, clamp(first_frame), clamp(last_frame)).map_err(julia_error)?;
+    atomic::write_bytes(Path::new(output), &trimmed).map_err(julia_error)?;
+    Ok(kept)
+}
+
+/// Write a manifest of every file in the dataset directory `dir`, returning the number of files.
+/// When `secret_key` (hex-encoded ed25519) isn't empty, the manifest is signed as well.
+pub fn write_dataset_manifest(dir: JuliaString, secret_key: JuliaString) -> JlrsResult<usize> {
+    let dir = unsafe { dir.as_str_unchecked() };
+    let secret_key = match unsafe { secret_key.as_str_unchecked() } {
+        "" => None,
+        key => Some(dataset::signing_key(key).map_err(invalid_input)?),
+    };
+    dataset::write_manifest(Path::new(dir), secret_key.as_ref()).map_err(julia_error)
+}
+
+/// Check the dataset in `dir` against its signed manifest and the hex-encoded ed25519 `public_key`.
+pub fn verify_dataset(dir: JuliaString, public_key: JuliaString) -> JlrsResult<bool> {
+    let dir = unsafe { dir.as_str_unchecked() };
+    let public_key = unsafe { public_key.as_str_unchecked() };
+    let public_key = dataset::verifying_key(public_key).map_err(invalid_input)?;
+    dataset::verify(Path::new(dir), &public_key).map_err(julia_error)
+}
+
+/// List the raw events of the replay at `path` as JSON, for debugging malformed files. At most
+/// `limit` events are listed (all when 0), keeping only the comma-separated event `types` given
+/// (e.g. "game_start,game_end"; all when empty).
+pub fn dump_events(path: JuliaString, limit: usize, types: JuliaString) -> StringRet {
+    let path = unsafe { path.as_str_unchecked() };
+    let types = unsafe { types.as_str_unchecked() };
+    let types: Vec<&str> = types.split(',').map(str::trim).filter(|t| !t.is_empty()).collect();
+    let data = fs::read(path).expect("Failed to open file");
+    let events = raw::dump(&data, limit, &types).expect("Failed to read Slippi file");
+
+    let handle = unsafe { weak_handle_unchecked!() };
+    let s = json::to_string(&events);
+    JuliaString::new(handle, s).leak()
+}
+
+/// Describe the outer UBJSON object of the replay at `path` as JSON, for debugging files whose
+/// wrapper a third-party recorder mangled: whether it starts with the `raw` element's header, the
+/// `raw` element's `declared_len` and whether it fits in the file, whether a `metadata` element
+/// follows (and its `metadata_len`, if it decodes), whether the object is `closed`, and how many
+/// bytes are `trailing` after it
+pub fn inspect_wrapper(path: JuliaString) -> JlrsResult<StringRet> {
+    let path = unsafe { path.as_str_unchecked() };
+    let data = fs::read(path).map_err(julia_error)?;
+    let wrapper = raw::wrapper(&data);
+
+    let handle = unsafe { weak_handle_unchecked!() };
+    Ok(JuliaString::new(handle, json::to_string(&wrapper)).leak())
+}
+
+/// Name of the character with external ID `id`, e.g. "Fox", or "Unknown(id)" outside the vanilla
+/// characters
+pub fn character_name(id: u8) -> StringRet {
+    let handle = unsafe { weak_handle_unchecked!() };
+    JuliaString::new(handle, ids::Character::from_id(id).to_string()).leak()
+}
+
+/// Name of the stage with ID `id`, e.g. "Battlefield", or "Unknown(id)" outside the vanilla stages
+pub fn stage_name(id: u16) -> StringRet {
+    let handle = unsafe { weak_handle_unchecked!() };
+    JuliaString::new(handle, ids::Stage::from_id(id).to_string()).leak()
+}
+
+/// Name of action `state` of the character with external ID `character`, e.g. "DamageFlyHi" (see
+/// [ids::action_state_name])
+pub fn action_state_name(character: u8, state: u16) -> StringRet {
+    let handle = unsafe { weak_handle_unchecked!() };
+    JuliaString::new(handle, ids::action_state_name(character, state)).leak()
+}
+
+/// Open the replay at `path` to pull its events from one batch at a time, without reading the rest
+/// of the file (see [stream]). Only the comma-separated event `types` given are returned (e.g.
+/// "game_start,post_frame"); when empty, Game Start, Pre-Frame, Post-Frame, Item Update, and Game
+/// End. Returns the ID of the stream.
+pub fn open_event_stream(path: JuliaString, types: JuliaString) -> JlrsResult<u64> {
+    let path = unsafe { path.as_str_unchecked() };
+    let types = unsafe { types.as_str_unchecked() };
+    let types: Vec<&str> = types.split(',').map(str::trim).filter(|t| !t.is_empty()).collect();
+    stream::open(Path::new(path), &types).map_err(julia_error)
+}
+
+/// Pull up to `max` events (all that are left when 0) from an event stream, as a JSON array of
+/// objects with the event's `type` and fields. An empty array means the stream is exhausted.
+pub fn next_events(id: u64, max: usize) -> JlrsResult<StringRet> {
+    let events = stream::next(id, max)
+        .ok_or_else(|| julia_error(io::Error::other(format!("no event stream {id}"))))?
+        .map_err(julia_error)?;
+    let handle = unsafe { weak_handle_unchecked!() };
+    Ok(JuliaString::new(handle, json::to_string(&events)).leak())
+}
+
+/// Close an event stream, e.g. once a filter has seen what it needs
+pub fn close_event_stream(id: u64) {
+    stream::close(id);
+}
+
+/// Set the directory Arrow files are written to (overrides `PEPPI_JL_OUTPUT_DIR`)
+pub fn set_output_dir(dir: JuliaString) {
+    let dir = unsafe { dir.as_str_unchecked() };
+    config::update(|c| c.output_dir = dir.into());
+}
+
+/// Delete Arrow files in the output directory once the games backed by them are closed or garbage
+/// collected, rather than keeping them (overrides `PEPPI_JL_DELETE_ARROW`)
+pub fn set_delete_arrow_files(delete: i8) {
+    config::update(|c| c.delete_arrow_files = delete != 0);
+}
+
+/// Set the compression of the Arrow IPC files written from now on: "none" (the default, and the
+/// only one whose files can be memory-mapped without copies), "lz4", or "zstd"
+pub fn set_compression(compression: JuliaString) {
+    let compression = unsafe { compression.as_str_unchecked() };
+    let compression = compression.parse().expect("Invalid compression");
+    config::update(|c| c.compression = compression);
+}
+
+/// Set the directory cached artifacts are kept in, or disable caching when empty (overrides
+/// `PEPPI_JL_CACHE`)
+pub fn set_cache_dir(dir: JuliaString) {
+    let dir = unsafe { dir.as_str_unchecked() };
+    config::update(|c| c.cache_dir = (!dir.is_empty()).then(|| dir.into()));
+}
+
+/// Set the number of threads used for batch work (overrides `PEPPI_JL_THREADS`)
+pub fn set_threads(threads: usize) {
+    config::update(|c| c.threads = threads.max(1));
+}
+
+/// Set how Ice Climbers follower columns are laid out in flattened exports: "separate" (a follower
+/// block per port, null-filled for other characters), "merged" (each follower column next to its
+/// leader column), or "dropped"
+pub fn set_follower_policy(policy: JuliaString) {
+    let policy = unsafe { policy.as_str_unchecked() };
+    let policy = policy.parse().expect("Invalid follower policy");
+    config::update(|c| c.follower_policy = policy);
+}
+
+/// Set the units exported frames are converted to: positions in "game" units or "stage" (scaled
+/// so the blast zones lie at -1 and 1; legal stages only), and facing as a "sign" (-1/1) or "bool"
+/// (true when facing right)
+pub fn set_units(positions: JuliaString, facing: JuliaString) {
+    let positions = unsafe { positions.as_str_unchecked() };
+    let facing = unsafe { facing.as_str_unchecked() };
+    let units = units::Units {
+        positions: positions.parse().expect("Invalid position units"),
+        facing: facing.parse().expect("Invalid facing units"),
+    };
+    config::update(|c| c.units = units);
+}
+
+/// Add stage-relative features (distance to the nearer ledge, height above the surface below,
+/// and distance to each blast zone) to every subsequent export, as a `features` column
+pub fn set_stage_features(enabled: i8) {
+    config::update(|c| c.stage_features = enabled != 0);
+}
+
+/// Add relative features for every pair of players (offset, separation, whether each faces the
+/// other, and percent and stock differences) to every subsequent export, as a `features` column
+pub fn set_relative_features(enabled: i8) {
+    config::update(|c| c.relative_features = enabled != 0);
+}
+
+/// Add each player's win probability on every frame to every subsequent export, as a
+/// `win_probability` column, scored by the logistic model with coefficients from the JSON file at
+/// `coefficients` (see the [win_probability] module for its shape), or the built-in ones when ""
+pub fn set_win_probability(enabled: i8, coefficients: JuliaString) {
+    let coefficients = unsafe { coefficients.as_str_unchecked() };
+    let model = match (enabled != 0, coefficients) {
+        (false, _) => None,
+        (true, "") => Some(win_probability::Model::default()),
+        (true, path) => {
+            let model = win_probability::Model::read(Path::new(path));
+            Some(model.expect("Failed to read win probability coefficients"))
+        }
+    };
+    config::update(|c| c.win_probability = model);
+}
+
+/// Set how nulls in exported frame columns are written: "null" (left as nulls), "sentinel" (NaN,
+/// -1, or false), or "forward_fill" (the last value before them, else the sentinel). Files written
+/// with a policy other than "null" record it in their schema metadata
+pub fn set_null_policy(policy: JuliaString) {
+    let policy = unsafe { policy.as_str_unchecked() };
+    let policy = policy.parse().expect("Invalid null policy");
+    config::update(|c| c.null_policy = policy);
+}
+
+/// Keep only the exported frame columns matching the patterns in a JSON array (e.g.
+/// `["id", "ports.*.leader.post.position"]`, where `*` matches any one path segment and a struct's
+/// path keeps everything below it), or "" to keep every column
+pub fn set_columns(columns: JuliaString) {
+    let columns = match unsafe { columns.as_str_unchecked() } {
+        "" => None,
+        c => Some(serde_json::from_str(c).expect("Invalid column list")),
+    };
+    config::update(|c| c.columns = columns);
+}
+
+/// Set every export option a named profile covers (units, derived features, and columns):
+/// "full", "stats_minimal", "ml_inputs", or "positions_only"
+pub fn set_profile(name: JuliaString) {
+    let name = unsafe { name.as_str_unchecked() };
+    let profile = profiles::get(name).expect("Unknown profile");
+    config::update(|c| {
+        c.units = profile.units;
+        c.stage_features = profile.stage_features;
+        c.relative_features = profile.relative_features;
+        c.columns = profile.columns.map(|cs| cs.iter().map(|c| c.to_string()).collect());
+    });
+}
+
+/// Get the built-in export profiles and the options each sets, as JSON
+pub fn list_profiles() -> StringRet {
+    let handle = unsafe { weak_handle_unchecked!() };
+    let s = json::to_string(profiles::PROFILES);
+    JuliaString::new(handle, s).leak()
+}
+
+/// Run the self-test, checking the bytes this build produces on generated inputs (stream
+/// splitting, UBJSON, content hashes, unit conversion, and Arrow IPC) against expected values.
+/// Returns a JSON array with each check's `name`, whether it `passed`, and what differed if not
+pub fn self_test() -> StringRet {
+    let checks = selftest::run();
+
+    let handle = unsafe { weak_handle_unchecked!() };
+    let s = json::to_string(&checks);
+    JuliaString::new(handle, s).leak()
+}
+
+/// Get the self-test's public test vectors as JSON: each one's `name`, `description`, and hex
+/// `input` and `expected` output, for checking other implementations against
+pub fn test_vectors() -> StringRet {
+    let handle = unsafe { weak_handle_unchecked!() };
+    let s = json::to_string(&selftest::vectors());
+    JuliaString::new(handle, s).leak()
+}
+
+/// Set how many times batch jobs retry reading a file that failed before recording it as failed
+pub fn set_retries(retries: usize) {
+    config::update(|c| c.retries = retries);
+}
+
+/// Set the directory batch jobs copy files that still fail into, or disable quarantining when empty
+pub fn set_quarantine_dir(dir: JuliaString) {
+    let dir = unsafe { dir.as_str_unchecked() };
+    config::update(|c| c.quarantine_dir = (!dir.is_empty()).then(|| dir.into()));
+}
+
+/// Set how many events live sessions started from now on queue for Julia, and what they do when
+/// the queue is full: "block" (pause the producer), "drop_oldest", or "drop_newest"
+pub fn set_live_queue(capacity: usize, overflow: JuliaString) {
+    let overflow = unsafe { overflow.as_str_unchecked() };
+    let overflow = overflow.parse().expect("Invalid overflow policy");
+    config::update(|c| {
+        c.live_capacity = capacity.max(1);
+        c.live_overflow = overflow;
+    });
+}
+
+/// Write a JSON data dictionary (column name, type, unit, source event, and the Slippi version
+/// that introduced it) next to every subsequent export
+pub fn set_emit_dictionary(emit: i8) {
+    config::update(|c| c.emit_dictionary = emit != 0);
+}
+
+/// Get conversion throughput metrics accumulated over the session as JSON: games, bytes, and frames
+/// converted, cache hits and misses, time spent converting, and the rates derived from them
+pub fn get_metrics() -> StringRet {
+    let handle = unsafe { weak_handle_unchecked!() };
+    let s = json::to_string(&metrics::get());
+    JuliaString::new(handle, s).leak()
+}
+
+/// Serve the session's metrics in the Prometheus text format at `http://<addr>/metrics` from a
+/// background thread (e.g. "0.0.0.0:9898"), returning the address bound. Only one endpoint runs per
+/// session; later calls return its address.
+pub fn serve_metrics(addr: JuliaString) -> StringRet {
+    let addr = unsafe { addr.as_str_unchecked() };
+    let bound = prometheus::serve(addr).expect("Failed to start metrics endpoint");
+
+    let handle = unsafe { weak_handle_unchecked!() };
+    JuliaString::new(handle, bound).leak()
+}
+
+/// Reset the conversion throughput metrics
+pub fn reset_metrics() {
+    metrics::reset();
+}
+
+/// Limit the replays read from now on, for servers parsing untrusted uploads: files over
+/// `max_bytes` or with more than `max_events` events are refused before parsing, and parsing that
+/// takes longer than `timeout_ms` is abandoned. 0 leaves a limit off
+pub fn set_limits(max_bytes: u64, max_events: usize, timeout_ms: u64) {
+    let limits = limits::Limits {
+        max_bytes,
+        max_events,
+        timeout_ms,
+    };
+    config::update(|c| c.limits = limits);
+}
+
+/// Parse every `.slp` replay read from now on in a `peppi-jl-worker` subprocess at `worker` first,
+/// with its address space capped at `max_memory` bytes (0 for no cap; Unix only) and killed at the
+/// parse time limit, so a pathological file can't crash or exhaust the Julia process. "" turns the
+/// sandbox off
+pub fn set_sandbox(worker: JuliaString, max_memory: u64) {
+    let worker = unsafe { worker.as_str_unchecked() };
+    let sandbox = (!worker.is_empty()).then(|| sandbox::Sandbox {
+        worker: worker.into(),
+        max_memory,
+    });
+    config::update(|c| c.sandbox = sandbox);
+}
+
+/// Get the current configuration as JSON
+pub fn get_config() -> StringRet {
+    let handle = unsafe { weak_handle_unchecked!() };
+    let s = json::to_string(&config::get());
+    JuliaString::new(handle, s).leak()
+}
+
+/// Convert frames to the configured units and append any enabled derived features. Selected
+/// columns the replay is too old to have come out null, with a warning for each.
+fn export_frames(
+    frames: &StructArray,
+    version: Version,
+    stage: u16,
+) -> Result<(StructArray, Vec<warnings::Warning>), String> {
+    let config = config::get();
+    let mut exported = units::apply(frames, stage, config.units)?;
+    if config.stage_features || config.relative_features || config.win_probability.is_some() {
+        let raw = Frame::from_struct_array(frames.clone(), version);
+        if config.stage_features || config.relative_features {
+            exported = features::append(&exported, &raw, stage, config.stage_features, config.relative_features)?;
+        }
+        if let Some(model) = &config.win_probability {
+            exported = win_probability::append(&exported, &raw, stage, model)?;
+        }
+    }
+    let (exported, warnings) = match &config.columns {
+        Some(columns) => {
+            let (filled, warnings) = warnings::fill(&exported, version, columns);
+            (profiles::select(&filled, columns)?, warnings)
+        }
+        None => (exported, Vec::new()),
+    };
+    Ok((nulls::apply(&exported, config.null_policy), warnings))
+}
+
+/// Schema metadata for files of exported frames: the schema version, and the null policy they
+/// were written with.
+fn export_metadata() -> Metadata {
+    let mut metadata = schema::metadata();
+    nulls::stamp(&mut metadata, config::get().null_policy);
+    metadata
+}
+
+/// Write frames as an Arrow IPC file with a single `frame` struct column, for memory-mapping, with
+/// `metadata` as its schema's metadata.
+fn write_frames_arrow<W: Write>(
+    w: W,
+    frames: &StructArray,
+    options: WriteOptions,
+    metadata: Metadata,
+) -> arrow2::error::Result<()> {
+    let schema = Schema::from(vec![Field {
+        name: "frame".to_string(),
+        data_type: frames.data_type().clone(),
+        is_nullable: false,
+        metadata: Default::default(),
+    }])
+    .with_metadata(metadata);
+
+    let chunk = Chunk::new(vec![Box::new(frames.clone()) as Box<dyn Array>]);
+    let mut writer = FileWriter::try_new(w, schema, None, options)?;
+    writer.write(&chunk, None)?;
+    writer.finish()
+}
+
+/// Set the directory live sessions started from now on record every game they receive to, as
+/// `.slp` files (in a subdirectory per source, for multiplexed sessions), or "" to not record
+pub fn set_live_recording(dir: JuliaString) {
+    let dir = unsafe { dir.as_str_unchecked() };
+    config::update(|c| c.live_record_dir = (!dir.is_empty()).then(|| dir.into()));
+}
+
+/// Follow the replay at `path` as it's recorded (waiting for it to be created if needed), queueing
+/// its events until Game End. Returns the ID of the live session to poll.
+pub fn live_follow_file(path: JuliaString) -> u64 {
+    let path = PathBuf::from(unsafe { path.as_str_unchecked() });
+    live::start(move |sink| live::file::follow(path, sink)).expect("Failed to start live session")
+}
+
+/// Read raw event streams written to the named pipe (FIFO) at `path`, queueing their events until
+/// the session is stopped. Unix only. Returns the ID of the live session to poll.
+pub fn live_read_pipe(path: JuliaString) -> u64 {
+    let path = PathBuf::from(unsafe { path.as_str_unchecked() });
+    live::start(move |sink| live::pipe::fifo(&path, sink)).expect("Failed to start live session")
+}
+
+/// Listen on a Unix domain socket at `path` for clients writing raw event streams, queueing their
+/// events until the session is stopped. Unix only. Returns the ID of the live session to poll.
+pub fn live_listen_socket(path: JuliaString) -> u64 {
+    let path = PathBuf::from(unsafe { path.as_str_unchecked() });
+    live::start(move |sink| live::pipe::socket(&path, sink)).expect("Failed to start live session")
+}
+
+/// Connect to a Slippi console (Wii) at `addr` (`host`, or `host:port`; the port is
+/// 51441 by default), queueing its events from the start of the next game. Dropped connections are
+/// re-established with exponential backoff until the session is stopped. Returns the ID of the
+/// live session to poll.
+pub fn live_connect_console(addr: JuliaString) -> u64 {
+    let addr = unsafe { addr.as_str_unchecked() }.to_string();
+    live::start(move |sink| live::console::connect(&addr, sink)).expect("Failed to start live session")
+}
+
+/// Spectate Slippi Dolphin at `addr` (`host`, or `host:port`; the port is 51441 by default) over
+/// its ENet spectator protocol, queueing its events from the start of the next game. Reconnects as
+/// [live_connect_console] does. Returns the ID of the live session to poll.
+pub fn live_spectate_dolphin(addr: JuliaString) -> u64 {
+    let addr = unsafe { addr.as_str_unchecked() }.to_string();
+    live::start(move |sink| live::dolphin::spectate(&addr, sink)).expect("Failed to start live session")
+}
+
+/// Connect to several consoles at once, given as a JSON object mapping source IDs to addresses
+/// (e.g. `{"setup1": "192.168.1.10", "setup2": "192.168.1.11:51441"}`). Their events share one
+/// live session, each tagged with the `source` it came from. Returns the ID of the session.
+pub fn live_connect_consoles(sources: JuliaString) -> u64 {
+    let sources = unsafe { sources.as_str_unchecked() };
+    let sources: BTreeMap<String, String> = serde_json::from_str(sources).expect("Invalid console sources");
+    let producers = sources
+        .into_iter()
+        .map(|(source, addr)| {
+            let produce: live::Producer = Box::new(move |sink| live::console::connect(&addr, sink));
+            (Some(source), produce)
+        })
+        .collect();
+    live::start_sources(producers).expect("Failed to start live session")
+}
+
+/// Take up to `max` queued events (all of them when 0) of a live session, as a JSON array of
+/// objects with the event's `command`, `name`, `frame`, and hex `payload` (and `source`, for
+/// multiplexed sessions). Never waits for events.
+pub fn live_poll(id: u64, max: usize) -> StringRet {
+    let events = live::poll(id, max).expect("Unknown live session");
+
+    let handle = unsafe { weak_handle_unchecked!() };
+    let s = json::to_string(&events);
+    JuliaString::new(handle, s).leak()
+}
+
+/// Get the status of a live session as JSON: events queued and dropped, whether (and why) it
+/// finished, why recording games stopped (if it did), and for console sessions the `connection`:
+/// its state, console nickname, reconnects, and bytes and games missed while disconnected.
+/// Multiplexed sessions report these per source, under `sources`
+pub fn live_status(id: u64) -> StringRet {
+    let status = live::status(id).expect("Unknown live session");
+
+    let handle = unsafe { weak_handle_unchecked!() };
+    let s = json::to_string(&status);
+    JuliaString::new(handle, s).leak()
+}
+
+/// Stop a live session, discarding any events still queued
+pub fn live_stop(id: u64) {
+    live::stop(id).expect("Unknown live session");
+}
+
+/// Size in bytes of the `ArrowArray` struct to allocate for `export_frames_c`
+pub fn arrow_c_array_size() -> usize {
+    cdata::ARRAY_SIZE
+}
+
+/// Size in bytes of the `ArrowSchema` struct to allocate for `export_frames_c`
+pub fn arrow_c_schema_size() -> usize {
+    cdata::SCHEMA_SIZE
+}
+
+/// Remove a shared-memory segment created by `write_frames_shm`
+pub fn unlink_shm(name: JuliaString) {
+    let name = unsafe { name.as_str_unchecked() };
+    shm::unlink(name).expect("Failed to unlink shared memory");
+}
+
+fn port_occupancy(start: &Start) -> Vec<PortOccupancy> {
+    start
+        .players
+        .iter()
+        .map(|p| PortOccupancy {
+            port: p.port,
+            follower: p.character == ICE_CLIMBERS,
+        })
+        .collect()
+}
+
+
+julia_module! {
+    become peppi_jlrs_init;
+		
+	/// read_slippi_path(path::String)
+	///
+    /// Read a Slippi replay file from the given path and return a SlippiGame object.
+    struct Game;
+
+    struct StartInfo;
+    #[untracked_self]
+    in StartInfo fn stage(&self) -> u16 as stage;
+    #[untracked_self]
+    in StartInfo fn is_teams(&self) -> bool as is_teams;
+    #[untracked_self]
+    in StartInfo fn timer(&self) -> u32 as timer;
+    #[untracked_self]
+    in StartInfo fn random_seed(&self) -> u32 as random_seed;
+    #[untracked_self]
+    in StartInfo fn damage_ratio(&self) -> f32 as damage_ratio;
+    #[untracked_self]
+    in StartInfo fn item_spawn_frequency(&self) -> i8 as item_spawn_frequency;
+    #[untracked_self]
+    in StartInfo fn self_destruct_score(&self) -> i8 as self_destruct_score;
+    #[untracked_self]
+    in StartInfo fn is_raining_bombs(&self) -> bool as is_raining_bombs;
+    #[untracked_self]
+    in StartInfo fn is_pal(&self) -> bool as is_pal;
+    #[untracked_self]
+    in StartInfo fn is_frozen_ps(&self) -> bool as is_frozen_ps;
+    #[untracked_self]
+    in StartInfo fn version_major(&self) -> u8 as version_major;
+    #[untracked_self]
+    in StartInfo fn version_minor(&self) -> u8 as version_minor;
+    #[untracked_self]
+    in StartInfo fn version_revision(&self) -> u8 as version_revision;
+    #[untracked_self]
+    in StartInfo fn num_players(&self) -> usize as num_players;
+    #[untracked_self]
+    in StartInfo fn match_id(&self) -> jlrs::data::managed::string::StringRet as match_id;
+    #[untracked_self]
+    in StartInfo fn match_mode(&self) -> jlrs::data::managed::string::StringRet as match_mode;
+    #[untracked_self]
+    in StartInfo fn game_number(&self) -> u32 as game_number;
+    #[untracked_self]
+    in StartInfo fn tiebreak_number(&self) -> u32 as tiebreak_number;
+
+    struct EndInfo;
+    #[untracked_self]
+    in EndInfo fn method(&self) -> u8 as method;
+    #[untracked_self]
+    in EndInfo fn method_name(&self) -> jlrs::data::managed::string::StringRet as method_name;
+    #[untracked_self]
+    in EndInfo fn lras_initiator(&self) -> i8 as lras_initiator;
+    #[untracked_self]
+    in EndInfo fn has_placements(&self) -> bool as has_placements;
+    #[untracked_self]
+    in EndInfo fn placement(&self, port: u8) -> i8 as placement;
+    #[untracked_self]
+    in EndInfo fn winner(&self) -> u8 as winner;
+
+    struct Player;
+    #[untracked_self]
+    in Player fn port(&self) -> u8 as port;
+    #[untracked_self]
+    in Player fn character(&self) -> u8 as character;
+    #[untracked_self]
+    in Player fn costume(&self) -> u8 as costume;
+    #[untracked_self]
+    in Player fn player_type(&self) -> u8 as player_type;
+    #[untracked_self]
+    in Player fn is_cpu(&self) -> bool as is_cpu;
+    #[untracked_self]
+    in Player fn team(&self) -> i8 as team;
+    #[untracked_self]
+    in Player fn stocks(&self) -> u8 as stocks;
+    #[untracked_self]
+    in Player fn cpu_level(&self) -> u8 as cpu_level;
+
+    fn read_peppi(path: JuliaString, skip_frames: i8) -> JlrsResult<CCallRefRet<Game>> as read_peppi;
+    fn read_slpp(path: JuliaString) -> JlrsResult<CCallRefRet<Game>> as read_slpp;
+    fn read_slippi(path: JuliaString, skip_frames: i8) -> JlrsResult<CCallRefRet<Game>> as read_slippi;
+    fn read_slippi_to(path: JuliaString, skip_frames: i8, output_path: JuliaString) -> JlrsResult<CCallRefRet<Game>> as read_slippi_to;
+    fn read_slippi_bytes(data: TypedVector<'_, '_, u8>, skip_frames: i8) -> JlrsResult<CCallRefRet<Game>> as read_slippi_bytes;
+    fn read_slippi_dir(dir: JuliaString, recursive: i8, pattern: JuliaString) -> JlrsResult<u64> as read_slippi_dir;
+    fn read_slippi_dir_tolerant(dir: JuliaString, recursive: i8, pattern: JuliaString) -> JlrsResult<u64> as read_slippi_dir_tolerant;
+    fn batch_len(id: u64) -> JlrsResult<usize> as batch_len;
+    fn batch_game(id: u64, i: usize) -> JlrsResult<CCallRefRet<Game>> as batch_game;
+    fn batch_skipped(id: u64) -> JlrsResult<jlrs::data::managed::string::StringRet> as batch_skipped;
+    fn batch_free(id: u64) as batch_free;
+    fn migrate_artifact(path: JuliaString, target_version: u32) -> JlrsResult<u32> as migrate_artifact;
+    fn build_catalog(dir: JuliaString, out: JuliaString) -> JlrsResult<usize> as build_catalog;
+    fn make_playback_queue(clips: JuliaString, out: JuliaString) -> usize as make_playback_queue;
+    fn catalog_buckets(path: JuliaString, period: JuliaString) -> JlrsResult<jlrs::data::managed::string::StringRet> as catalog_buckets;
+    fn transcode_dir(src: JuliaString, dst: JuliaString, format: JuliaString, compression: JuliaString, threads: usize) -> JlrsResult<jlrs::data::managed::string::StringRet> as transcode_dir;
+    fn redact_slippi(input: JuliaString, output: JuliaString) -> JlrsResult<usize> as redact_slippi;
+    fn anonymize_slippi(input: JuliaString, output: JuliaString, salt: JuliaString) -> JlrsResult<usize> as anonymize_slippi;
+    fn trim_slippi(input: JuliaString, output: JuliaString, first_frame: i64, last_frame: i64) -> JlrsResult<usize> as trim_slippi;
+    fn with_metadata_slippi(input: JuliaString, output: JuliaString, metadata: JuliaString, patch: i8) -> JlrsResult<()> as with_metadata_slippi;
+    fn make_recording_plan(clips: JuliaString, comm_out: JuliaString, plan_out: JuliaString) -> usize as make_recording_plan;
+    fn write_dataset_manifest(dir: JuliaString, secret_key: JuliaString) -> JlrsResult<usize> as write_dataset_manifest;
+    fn verify_dataset(dir: JuliaString, public_key: JuliaString) -> JlrsResult<bool> as verify_dataset;
+    fn inspect_wrapper(path: JuliaString) -> JlrsResult<jlrs::data::managed::string::StringRet> as inspect_wrapper;
+    fn character_name(id: u8) -> jlrs::data::managed::string::StringRet as character_name;
+    fn stage_name(id: u16) -> jlrs::data::managed::string::StringRet as stage_name;
+    fn action_state_name(character: u8, state: u16) -> jlrs::data::managed::string::StringRet as action_state_name;
+    fn dump_events(path: JuliaString, limit: usize, types: JuliaString) -> jlrs::data::managed::string::StringRet as dump_events;
+    fn open_event_stream(path: JuliaString, types: JuliaString) -> JlrsResult<u64> as open_event_stream;
+    fn next_events(id: u64, max: usize) -> JlrsResult<jlrs::data::managed::string::StringRet> as next_events;
+    fn close_event_stream(id: u64) as close_event_stream;
+    fn set_output_dir(dir: JuliaString) as set_output_dir;
+    fn set_delete_arrow_files(delete: i8) as set_delete_arrow_files;
+    fn set_compression(compression: JuliaString) as set_compression;
+    fn set_cache_dir(dir: JuliaString) as set_cache_dir;
+    fn set_threads(threads: usize) as set_threads;
+    fn set_follower_policy(policy: JuliaString) as set_follower_policy;
+    fn set_emit_dictionary(emit: i8) as set_emit_dictionary;
+    fn set_units(positions: JuliaString, facing: JuliaString) as set_units;
+    fn set_stage_features(enabled: i8) as set_stage_features;
+    fn set_relative_features(enabled: i8) as set_relative_features;
+    fn set_win_probability(enabled: i8, coefficients: JuliaString) as set_win_probability;
+    fn set_null_policy(policy: JuliaString) as set_null_policy;
+    fn set_columns(columns: JuliaString) as set_columns;
+    fn set_profile(name: JuliaString) as set_profile;
+    fn list_profiles() -> jlrs::data::managed::string::StringRet as list_profiles;
+    fn set_retries(retries: usize) as set_retries;
+    fn set_quarantine_dir(dir: JuliaString) as set_quarantine_dir;
+    fn set_limits(max_bytes: u64, max_events: usize, timeout_ms: u64) as set_limits;
+    fn set_sandbox(worker: JuliaString, max_memory: u64) as set_sandbox;
+    fn get_config() -> jlrs::data::managed::string::StringRet as get_config;
+    fn self_test() -> jlrs::data::managed::string::StringRet as self_test;
+    fn test_vectors() -> jlrs::data::managed::string::StringRet as test_vectors;
+    fn get_metrics() -> jlrs::data::managed::string::StringRet as get_metrics;
+    fn reset_metrics() as reset_metrics;
+    fn serve_metrics(addr: JuliaString) -> jlrs::data::managed::string::StringRet as serve_metrics;
+    fn unlink_shm(name: JuliaString) as unlink_shm;
+    fn arrow_c_array_size() -> usize as arrow_c_array_size;
+    fn arrow_c_schema_size() -> usize as arrow_c_schema_size;
+    fn set_live_queue(capacity: usize, overflow: JuliaString) as set_live_queue;
+    fn set_live_recording(dir: JuliaString) as set_live_recording;
+    fn live_follow_file(path: JuliaString) -> u64 as live_follow_file;
+    fn live_read_pipe(path: JuliaString) -> u64 as live_read_pipe;
+    fn live_listen_socket(path: JuliaString) -> u64 as live_listen_socket;
+    fn live_connect_console(addr: JuliaString) -> u64 as live_connect_console;
+    fn live_connect_consoles(sources: JuliaString) -> u64 as live_connect_consoles;
+    fn live_spectate_dolphin(addr: JuliaString) -> u64 as live_spectate_dolphin;
+    fn live_poll(id: u64, max: usize) -> jlrs::data::managed::string::StringRet as live_poll;
+    fn live_status(id: u64) -> jlrs::data::managed::string::StringRet as live_status;
+    fn live_stop(id: u64) as live_stop;
+    fn sample_games(dir: JuliaString, n: usize, seed: u64, filter: JuliaString) -> JlrsResult<jlrs::data::managed::string::StringRet> as sample_games;
+    fn annotate_brackets(catalog_path: JuliaString, ratings_path: JuliaString) -> JlrsResult<usize> as annotate_brackets;
+    fn tag_game(catalog_path: JuliaString, game: JuliaString, tag: JuliaString) -> JlrsResult<usize> as tag_game;
+    fn untag_game(catalog_path: JuliaString, game: JuliaString, tag: JuliaString) -> JlrsResult<usize> as untag_game;
+    fn games_with_tag(catalog_path: JuliaString, tag: JuliaString) -> JlrsResult<jlrs::data::managed::string::StringRet> as games_with_tag;
+    fn list_tags(catalog_path: JuliaString) -> JlrsResult<jlrs::data::managed::string::StringRet> as list_tags;
+    fn save_query(catalog_path: JuliaString, name: JuliaString, filter: JuliaString) -> JlrsResult<bool> as save_query;
+    fn delete_query(catalog_path: JuliaString, name: JuliaString) -> JlrsResult<bool> as delete_query;
+    fn list_queries(catalog_path: JuliaString) -> JlrsResult<jlrs::data::managed::string::StringRet> as list_queries;
+    fn run_query(catalog_path: JuliaString, name: JuliaString) -> JlrsResult<jlrs::data::managed::string::StringRet> as run_query;
+    fn sample_balanced(catalog_path: JuliaString, by: JuliaString, per_class: usize, seed: u64, out: JuliaString) -> JlrsResult<usize> as sample_balanced;
+    fn correct_clock_drift(catalog_path: JuliaString, hints: JuliaString, references: JuliaString, apply: i8) -> JlrsResult<jlrs::data::managed::string::StringRet> as correct_clock_drift;
+    fn rank_highlights(catalog_path: JuliaString, segment_seconds: f64, top: usize, threads: usize) -> JlrsResult<jlrs::data::managed::string::StringRet> as rank_highlights;
+    fn normalize_placements(catalog_path: JuliaString, threads: usize) -> JlrsResult<jlrs::data::managed::string::StringRet> as normalize_placements;
+    fn infer_sets(catalog_path: JuliaString, max_gap_seconds: f64) -> JlrsResult<jlrs::data::managed::string::StringRet> as infer_sets;
+    fn resolve_identities(catalog_path: JuliaString, aliases_path: JuliaString) -> JlrsResult<usize> as resolve_identities;
+    fn split_dataset(catalog_path: JuliaString, fractions: JuliaString, by: JuliaString, seed: u64, out: JuliaString) -> JlrsResult<usize> as split_dataset;
+    fn materialize_features(catalog_path: JuliaString, feature_set: JuliaString, store: JuliaString) -> JlrsResult<usize> as materialize_features;
+    fn invalidate_features(feature_set: JuliaString, store: JuliaString) -> JlrsResult<usize> as invalidate_features;
+    fn dry_run_features(catalog_path: JuliaString, feature_set: JuliaString, store: JuliaString, sample: usize) -> JlrsResult<jlrs::data::managed::string::StringRet> as dry_run_features;
+    fn batch_errors(output: JuliaString) -> JlrsResult<jlrs::data::managed::string::StringRet> as batch_errors;
+
+    // Expose getters to Julia
+    #[untracked_self]
+    in Game fn get_start(&self) -> jlrs::data::managed::string::StringRet as get_start;
+    #[untracked_self]
+    in Game fn get_start_info(&self) -> CCallRefRet<StartInfo> as get_start_info;
+    #[untracked_self]
+    in Game fn get_end_info(&self) -> JlrsResult<CCallRefRet<EndInfo>> as get_end_info;
+    #[untracked_self]
+    in Game fn num_players(&self) -> usize as num_players;
+    #[untracked_self]
+    in Game fn get_player(&self, index: usize) -> JlrsResult<CCallRefRet<Player>> as get_player;
+    #[untracked_self]
+    in Game fn get_display_names(&self) -> jlrs::data::managed::string::StringRet as get_display_names;
+    #[untracked_self]
+    in Game fn get_connect_codes(&self) -> jlrs::data::managed::string::StringRet as get_connect_codes;
+    #[untracked_self]
+    in Game fn get_end(&self) -> jlrs::data::managed::string::StringRet as get_end;
+    #[untracked_self]
+    in Game fn get_metadata(&self) -> jlrs::data::managed::string::StringRet as get_metadata;
+    #[untracked_self]
+    in Game fn get_hash(&self) -> jlrs::data::managed::string::StringRet as get_hash;
+    #[untracked_self]
+    in Game fn get_source_hash(&self) -> jlrs::data::managed::string::StringRet as get_source_hash;
+    #[untracked_self]
+    in Game fn get_console_nick(&self) -> jlrs::data::managed::string::StringRet as get_console_nick;
+    #[untracked_self]
+    in Game fn get_played_on(&self) -> jlrs::data::managed::string::StringRet as get_played_on;
+    // Under the names used alongside the other typed metadata getters
+    #[untracked_self]
+    in Game fn get_played_on(&self) -> jlrs::data::managed::string::StringRet as get_platform;
+    #[untracked_self]
+    in Game fn get_console_nick(&self) -> jlrs::data::managed::string::StringRet as get_console_name;
+    #[untracked_self]
+    in Game fn get_start_time(&self) -> jlrs::data::managed::string::StringRet as get_start_time;
+    #[untracked_self]
+    in Game fn get_duration_frames(&self) -> i64 as get_duration_frames;
+    #[untracked_self]
+    in Game fn get_metadata_character(&self, port: u8) -> i16 as get_metadata_character;
+    #[untracked_self]
+    in Game fn get_metadata_character_frames(&self, port: u8, character: u8) -> u64 as get_metadata_character_frames;
     #[untracked_self]
     in Game fn get_frames_arrow_path(&self) -> jlrs::data::managed::string::StringRet as get_frames_arrow_path;
+    #[untracked_self]
+    in Game fn get_frame_offsets(&self) -> jlrs::data::managed::string::StringRet as get_frame_offsets;
+    #[untracked_self]
+    in Game fn get_hits(&self) -> jlrs::data::managed::string::StringRet as get_hits;
+    #[untracked_self]
+    in Game fn get_warnings(&self) -> jlrs::data::managed::string::StringRet as get_warnings;
+    #[untracked_self]
+    in Game fn describe(&self) -> JlrsResult<jlrs::data::managed::string::StringRet> as describe;
+    #[untracked_self]
+    in Game fn to_py_slippi_json(&self, include_frames: i8) -> jlrs::data::managed::string::StringRet as to_py_slippi_json;
+    #[untracked_self]
+    in Game fn anonymize(&self, salt: JuliaString) -> JlrsResult<jlrs::data::managed::string::StringRet> as anonymize;
+    #[untracked_self]
+    in Game fn get_grabs(&self) -> jlrs::data::managed::string::StringRet as get_grabs;
+    #[untracked_self]
+    in Game fn get_stalls(&self, min_seconds: f64) -> jlrs::data::managed::string::StringRet as get_stalls;
+    #[untracked_self]
+    in Game fn get_stats(&self, stall_seconds: f64) -> jlrs::data::managed::string::StringRet as get_stats;
+    #[untracked_self]
+    in Game fn game_result(&self) -> JlrsResult<jlrs::data::managed::string::StringRet> as game_result;
+    #[untracked_self]
+    in Game fn field_coverage(&self) -> jlrs::data::managed::string::StringRet as field_coverage;
+    #[untracked_self]
+    in Game fn get_audio_cues(&self) -> jlrs::data::managed::string::StringRet as get_audio_cues;
+    #[untracked_self]
+    in Game fn get_excitement(&self, segment_seconds: f64) -> jlrs::data::managed::string::StringRet as get_excitement;
+    #[untracked_self]
+    in Game fn add_annotation(&self, start_frame: i64, end_frame: i64, label: JuliaString, note: JuliaString) -> JlrsResult<usize> as add_annotation;
+    #[untracked_self]
+    in Game fn get_annotations(&self) -> JlrsResult<jlrs::data::managed::string::StringRet> as get_annotations;
+    #[untracked_self]
+    in Game fn remove_annotations(&self, label: JuliaString) -> JlrsResult<usize> as remove_annotations;
+    #[untracked_self]
+    in Game fn write_hdf5(&self, path: JuliaString, group: JuliaString) -> JlrsResult<()> as write_hdf5;
+    #[untracked_self]
+    in Game fn write_duckdb(&self, path: JuliaString, game: JuliaString) -> JlrsResult<()> as write_duckdb;
+    #[untracked_self]
+    in Game fn write_postgres_copy(&self, dir: JuliaString, game: JuliaString) -> JlrsResult<()> as write_postgres_copy;
+    #[untracked_self]
+    in Game fn copy_postgres(&self, url: JuliaString, game: JuliaString) -> JlrsResult<()> as copy_postgres;
+    #[untracked_self]
+    in Game fn write_frames_parquet(&self, path: JuliaString, compression: JuliaString, row_group_size: usize) -> JlrsResult<()> as write_frames_parquet;
+    #[untracked_self]
+    in Game fn write_slippi(&self, path: JuliaString) -> JlrsResult<()> as write_slippi;
+    #[untracked_self]
+    in Game fn write_slpp(&self, path: JuliaString) -> JlrsResult<()> as write_slpp;
+    #[untracked_self]
+    in Game fn write_frames_shm(&self, name: JuliaString) -> JlrsResult<usize> as write_frames_shm;
+    #[untracked_self]
+    in Game fn close(&self) as close;
+    #[untracked_self]
+    in Game fn export_frames_c(&self, array: usize, schema: usize) -> JlrsResult<()> as export_frames_c;
+    #[untracked_self]
+    in Game fn state_at(&self, frame: i64) -> JlrsResult<jlrs::data::managed::string::StringRet> as state_at;
+    #[untracked_self]
+    in Game fn state_at_seconds(&self, seconds: f64) -> JlrsResult<jlrs::data::managed::string::StringRet> as state_at_seconds;
+    #[untracked_self]
+    in Game fn events_near(&self, frame: i64, radius: usize, types: JuliaString) -> JlrsResult<jlrs::data::managed::string::StringRet> as events_near;
+    #[untracked_self]
+    in Game fn sample_frames(&self, n: usize, seed: u64) -> jlrs::data::managed::string::StringRet as sample_frames;
+    #[untracked_self]
+    in Game fn write_windows(&self, path: JuliaString, event: JuliaString, before: usize, after: usize) -> JlrsResult<usize> as write_windows;
+    #[untracked_self]
+    in Game fn write_imitation(&self, path: JuliaString, port: u8, latency: usize) -> JlrsResult<usize> as write_imitation;
+    #[untracked_self]
+    in Game fn write_port_frames(&self, port: u8, path: JuliaString) -> JlrsResult<usize> as write_port_frames;
+    #[untracked_self]
+    in Game fn write_follower_frames(&self, port: u8, path: JuliaString) -> JlrsResult<usize> as write_follower_frames;
+    #[untracked_self]
+    in Game fn write_items(&self, path: JuliaString) -> JlrsResult<usize> as write_items;
 }
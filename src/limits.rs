@@ -0,0 +1,199 @@
+//! Limits on untrusted replays
+//!
+//! A server parsing replays uploaded by strangers can't trust a file's declared sizes: a garbage or
+//! malicious `.slp` can claim an enormous event stream, or hold millions of tiny events, each of
+//! which the parser allocates for. Before a replay reaches peppi, its size is checked (before
+//! reading it, for files) and its event stream walked without decoding anything, rejecting files
+//! over the configured limits or whose container contradicts itself.
+//!
+//! Parsing can also be given a deadline. Rust can't stop a thread, and the parser has no way to be
+//! interrupted, so a parse that misses its deadline keeps running in the background until it
+//! finishes, but the caller gets an error right away. With the other limits in place, that work is
+//! bounded, and so is the number of such parses: while [MAX_ABANDONED] are still running, parses
+//! with a deadline are refused, so a stream of pathological files can't pile up threads.
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::errors::ReadError;
+use crate::raw;
+
+/// Limits applied when reading replays; 0 means unlimited.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Limits {
+    /// Largest file accepted, in bytes
+    pub max_bytes: u64,
+    /// Most events accepted in a replay's event stream
+    pub max_events: usize,
+    /// Longest a parse may take, in milliseconds
+    pub timeout_ms: u64,
+}
+
+/// Most parses that missed their deadline left running at once.
+pub const MAX_ABANDONED: usize = 4;
+
+/// Parses that missed their deadline and are still running.
+static ABANDONED: AtomicUsize = AtomicUsize::new(0);
+
+/// Where a parse run with a deadline is at, shared by it and the caller waiting on it.
+#[derive(Default)]
+struct Progress {
+    finished: bool,
+    abandoned: bool,
+}
+
+fn exceeded(msg: String) -> ReadError {
+    ReadError::Limit(msg)
+}
+
+/// Read the file at `path`, refusing it without reading it all if it's over the size limit.
+pub fn read(path: &Path, limits: &Limits) -> Result<Vec<u8>, ReadError> {
+    let file = fs::File::open(path).map_err(|e| ReadError::io(path, e))?;
+    if limits.max_bytes == 0 {
+        let mut data = Vec::new();
+        io::BufReader::new(file).read_to_end(&mut data).map_err(|e| ReadError::io(path, e))?;
+        return Ok(data);
+    }
+    let len = file.metadata().map_err(|e| ReadError::io(path, e))?.len();
+    check_size(len, limits)?;
+    // The file may grow after the check, so never read past the limit either way.
+    let mut data = Vec::with_capacity(len as usize);
+    file.take(limits.max_bytes + 1)
+        .read_to_end(&mut data)
+        .map_err(|e| ReadError::io(path, e))?;
+    check_size(data.len() as u64, limits)?;
+    Ok(data)
+}
+
+fn check_size(len: u64, limits: &Limits) -> Result<(), ReadError> {
+    match limits.max_bytes {
+        0 => Ok(()),
+        max if len > max => Err(exceeded(format!("file is {len} bytes, over the limit of {max}"))),
+        _ => Ok(()),
+    }
+}
+
+/// Check a `.slp` file's contents against the limits before parsing it.
+///
+/// Files whose container can't be read are let through, for the parser to report what's wrong.
+pub fn check(data: &[u8], limits: &Limits) -> Result<(), ReadError> {
+    check_size(data.len() as u64, limits)?;
+    if limits.max_events == 0 {
+        return Ok(());
+    }
+    let Ok(container) = raw::parse(data) else {
+        return Ok(());
+    };
+    match raw::count_events(container.raw, limits.max_events) {
+        Some(count) if count > limits.max_events => Err(exceeded(format!(
+            "more than {} events in the event stream",
+            limits.max_events
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Run `parse`, giving up on it once the timeout passes. Refuses to start while [MAX_ABANDONED]
+/// parses given up on are still running.
+pub fn with_timeout<T: Send + 'static>(
+    limits: &Limits,
+    parse: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, ReadError> {
+    counted_with_timeout(limits, &ABANDONED, parse)
+}
+
+/// [with_timeout], counting parses given up on in `abandoned_count`.
+fn counted_with_timeout<T: Send + 'static>(
+    limits: &Limits,
+    abandoned_count: &'static AtomicUsize,
+    parse: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, ReadError> {
+    if limits.timeout_ms == 0 {
+        return Ok(parse());
+    }
+    let abandoned = abandoned_count.load(Ordering::SeqCst);
+    if abandoned >= MAX_ABANDONED {
+        return Err(exceeded(format!("{abandoned} parses that timed out are still running")));
+    }
+    let (sender, receiver) = mpsc::sync_channel(1);
+    let progress = Arc::new(Mutex::new(Progress::default()));
+    let parse_progress = Arc::clone(&progress);
+    thread::Builder::new()
+        .name("peppi-parse".to_string())
+        .spawn(move || {
+            let parsed = parse();
+            let mut progress = parse_progress.lock().unwrap_or_else(|e| e.into_inner());
+            progress.finished = true;
+            if progress.abandoned {
+                abandoned_count.fetch_sub(1, Ordering::SeqCst);
+            }
+            // The receiver is gone if we gave up waiting, in which case the result is dropped.
+            let _ = sender.send(parsed);
+        })
+        .map_err(ReadError::Io)?;
+    match receiver.recv_timeout(Duration::from_millis(limits.timeout_ms)) {
+        Ok(parsed) => Ok(parsed),
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            let mut progress = progress.lock().unwrap_or_else(|e| e.into_inner());
+            if progress.finished {
+                // It finished just as we stopped waiting, so the result is on its way.
+                drop(progress);
+                return receiver
+                    .recv()
+                    .map_err(|_| ReadError::Invalid("parser stopped without a result".to_string()));
+            }
+            progress.abandoned = true;
+            abandoned_count.fetch_add(1, Ordering::SeqCst);
+            Err(exceeded(format!("parsing took longer than {} ms", limits.timeout_ms)))
+        }
+        Err(mpsc::RecvTimeoutError::Disconnected) => {
+            Err(ReadError::Invalid("parser stopped without a result".to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_left_running_are_capped() {
+        static ABANDONED: AtomicUsize = AtomicUsize::new(0);
+        let limits = Limits {
+            timeout_ms: 1,
+            ..Limits::default()
+        };
+        // Each parse blocks until its sender is dropped, so it can only miss its deadline.
+        let mut releases = Vec::new();
+        for _ in 0..MAX_ABANDONED {
+            let (release, released) = mpsc::channel::<()>();
+            releases.push(release);
+            let blocked = move || {
+                let _ = released.recv();
+            };
+            let result = counted_with_timeout(&limits, &ABANDONED, blocked);
+            assert!(matches!(result, Err(ReadError::Limit(_))));
+        }
+        assert_eq!(ABANDONED.load(Ordering::SeqCst), MAX_ABANDONED);
+        // Refused without starting, however quick it would be.
+        let result = counted_with_timeout(&limits, &ABANDONED, || ());
+        assert!(matches!(result, Err(ReadError::Limit(_))));
+
+        drop(releases);
+        while ABANDONED.load(Ordering::SeqCst) > 0 {
+            thread::yield_now();
+        }
+        let limits = Limits {
+            timeout_ms: 60_000,
+            ..Limits::default()
+        };
+        assert_eq!(counted_with_timeout(&limits, &ABANDONED, || 1).unwrap(), 1);
+    }
+}
@@ -0,0 +1,222 @@
+//! Live client for the Slippi console protocol (Wii)
+//!
+//! Consoles serve their event stream over TCP as messages of a big-endian length followed by a
+//! UBJSON object. Every replay message carries its position in the console's stream, and the
+//! client sends the position it got to when it connects, so a dropped connection picks up where it
+//! left off.
+//!
+//! Tournament networks drop connections constantly, so any error (including the console going
+//! quiet for longer than [TIMEOUT], since it sends keep-alives when idle) just reconnects, with
+//! exponential backoff. If the console no longer has the part of the stream that was missed, the
+//! gap is counted in the session's [Connection] and the client skips to the next game, since a
+//! game with missing events can't be split reliably.
+//!
+//! [Connection]: super::Connection
+
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use super::ubjson::{self, Encode};
+use super::Sink;
+use crate::raw;
+
+pub const DEFAULT_PORT: u16 = 51441;
+
+/// How long the console may stay silent before the connection is considered dead.
+pub const TIMEOUT: Duration = Duration::from_secs(8);
+
+/// Largest message accepted. Replay messages carry a few kilobytes of events at most, so anything
+/// near this is a broken or hostile peer rather than one to allocate for.
+const MAX_MESSAGE: usize = 8 << 20;
+
+pub(super) const MIN_BACKOFF: Duration = Duration::from_millis(500);
+pub(super) const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Message types (anything else, like keep-alives, is ignored)
+const HANDSHAKE: i64 = 1;
+const REPLAY: i64 = 2;
+
+/// Progress through the console's stream, kept across connections.
+#[derive(Default)]
+struct Stream {
+    /// Position of the next expected message, once one has been received
+    cursor: Option<[u8; 8]>,
+    /// Token the console gave us, so it recognizes us when reconnecting
+    token: [u8; 4],
+    connected: bool,
+    splitter: raw::Splitter,
+    /// Between a game's Event Payloads and its Game End
+    in_game: bool,
+    /// Discarding data until the next game starts
+    skipping: bool,
+}
+
+/// Queue the events the console at `addr` (`host` or `host:port`) streams, reconnecting whenever
+/// the connection drops, until the session is stopped.
+pub fn connect(addr: &str, sink: &Sink) -> io::Result<()> {
+    let addr = match addr.rsplit_once(':') {
+        Some((_, port)) if port.parse::<u16>().is_ok() => addr.to_string(),
+        _ => format!("{addr}:{DEFAULT_PORT}"),
+    };
+    // Joining mid-game, we can't split the stream until the next game starts.
+    let mut stream = Stream {
+        skipping: true,
+        ..Default::default()
+    };
+    let mut backoff = MIN_BACKOFF;
+    while !sink.queue.is_closed() {
+        sink.update_connection(|c| c.state = "connecting");
+        let Err(e) = run(&addr, &mut stream, sink, &mut backoff) else {
+            break;
+        };
+        sink.update_connection(|c| {
+            c.state = "reconnecting";
+            c.last_error = Some(e.to_string());
+        });
+        wait(sink, backoff);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+    sink.update_connection(|c| c.state = "closed");
+    Ok(())
+}
+
+/// Sleep for `duration`, waking early if the session is stopped.
+pub(super) fn wait(sink: &Sink, duration: Duration) {
+    let step = Duration::from_millis(50);
+    let mut waited = Duration::ZERO;
+    while waited < duration && !sink.queue.is_closed() {
+        thread::sleep(step);
+        waited += step;
+    }
+}
+
+/// Connect once and queue events until the connection fails (an error) or the session is stopped.
+fn run(addr: &str, stream: &mut Stream, sink: &Sink, backoff: &mut Duration) -> io::Result<()> {
+    let target = addr
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no address for {addr}")))?;
+    let mut socket = TcpStream::connect_timeout(&target, TIMEOUT)?;
+    socket.set_read_timeout(Some(TIMEOUT))?;
+    socket.set_nodelay(true)?;
+
+    let cursor = stream.cursor.unwrap_or_default();
+    let handshake = Encode::Object(vec![
+        ("type", Encode::Int(HANDSHAKE)),
+        (
+            "payload",
+            Encode::Object(vec![
+                ("cursor", Encode::Bytes(&cursor)),
+                ("clientToken", Encode::Bytes(&stream.token)),
+                ("isRealtime", Encode::Bool(false)),
+            ]),
+        ),
+    ]);
+    send(&mut socket, &handshake)?;
+
+    while !sink.queue.is_closed() {
+        let message = receive(&mut socket)?;
+        let payload = &message["payload"];
+        match message["type"].as_i64() {
+            Some(HANDSHAKE) => {
+                let token = ubjson::bytes(&payload["clientToken"]).and_then(|t| t.try_into().ok());
+                if let Some(token) = token {
+                    stream.token = token;
+                }
+                let reconnected = stream.connected;
+                stream.connected = true;
+                *backoff = MIN_BACKOFF;
+                sink.update_connection(|c| {
+                    c.state = "connected";
+                    c.nick = payload["nick"].as_str().map(str::to_string);
+                    c.reconnects += reconnected as u32;
+                });
+            }
+            Some(REPLAY) => {
+                if !replay(payload, stream, sink)? {
+                    return Ok(());
+                }
+            }
+            // Keep-alives only need to arrive, to show the connection is still up.
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Queue the events of a replay message, returning `false` once the session is stopped.
+fn replay(payload: &Value, stream: &mut Stream, sink: &Sink) -> io::Result<bool> {
+    let position = |key: &str| -> Option<[u8; 8]> { ubjson::bytes(&payload[key])?.try_into().ok() };
+    let (Some(pos), Some(next)) = (position("pos"), position("nextPos")) else {
+        return Err(invalid("replay message without positions"));
+    };
+    if let Some(cursor) = stream.cursor.filter(|&c| c != pos) {
+        let missed = u64::from_be_bytes(pos).saturating_sub(u64::from_be_bytes(cursor));
+        let in_game = stream.in_game;
+        sink.update_connection(|c| {
+            c.missed_bytes += missed;
+            c.missed_games += in_game as u32;
+        });
+        stream.splitter.reset();
+        stream.in_game = false;
+        stream.skipping = true;
+    }
+    stream.cursor = Some(next);
+
+    let data = ubjson::bytes(&payload["data"]).unwrap_or_default();
+    if stream.skipping {
+        if data.first() != Some(&raw::EVENT_PAYLOADS) {
+            return Ok(true);
+        }
+        stream.skipping = false;
+    }
+    for event in stream.splitter.feed(&data)? {
+        match event[0] {
+            raw::EVENT_PAYLOADS => stream.in_game = true,
+            raw::GAME_END => stream.in_game = false,
+            _ => {}
+        }
+        if !sink.push(&event) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn send(socket: &mut TcpStream, message: &Encode) -> io::Result<()> {
+    let mut body = Vec::new();
+    ubjson::encode(message, &mut body);
+    socket.write_all(&(body.len() as u32).to_be_bytes())?;
+    socket.write_all(&body)
+}
+
+fn receive(socket: &mut TcpStream) -> io::Result<Value> {
+    let mut len = [0; 4];
+    socket.read_exact(&mut len).map_err(silent)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_MESSAGE {
+        return Err(invalid(&format!("{len}-byte message from the console")));
+    }
+    let mut body = vec![0; len];
+    socket.read_exact(&mut body).map_err(silent)?;
+    ubjson::decode(&body)
+}
+
+/// Explain a read timing out, which is how a dead connection shows up.
+fn silent(e: io::Error) -> io::Error {
+    match e.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("no message from the console for {}s", TIMEOUT.as_secs()),
+        ),
+        _ => e,
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
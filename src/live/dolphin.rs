@@ -0,0 +1,229 @@
+//! Live client for Slippi Dolphin's spectator protocol
+//!
+//! Dolphin (netplay, or a console mirrored through the Launcher) serves its event stream to
+//! spectators over ENet rather than the Wii's TCP protocol. Messages are JSON: the client sends a
+//! `connect_request` with the cursor it got to, Dolphin answers with a `connect_reply` carrying its
+//! nickname and current cursor, then sends `game_event` messages whose base64 `payload` holds the
+//! next chunk of the event stream, each with its `cursor` and the `next_cursor` to expect.
+//!
+//! As with consoles, a dropped connection (or ENet giving up on an unresponsive peer) reconnects
+//! with exponential backoff and resumes from the last cursor, and a gap Dolphin can no longer fill
+//! is counted in the session's [Connection], skipping to the next game.
+//!
+//! [Connection]: super::Connection
+
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::thread;
+use std::time::Duration;
+
+use rusty_enet::{Event, Host, HostSettings, Packet};
+use serde_json::{Value, json};
+
+use super::Sink;
+use super::console::{self, DEFAULT_PORT};
+use crate::raw;
+
+/// Channels Dolphin's spectator server opens.
+const CHANNELS: usize = 3;
+/// Connection data Slippi's spectator clients send.
+const CONNECT_DATA: u32 = 1337;
+/// How long to sleep between servicing the ENet host when nothing arrives.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+/// Cursor asking Dolphin to start at what it's sending now.
+const LIVE_CURSOR: u64 = i64::MAX as u64;
+
+/// Progress through Dolphin's stream, kept across connections.
+struct Stream {
+    cursor: u64,
+    connected: bool,
+    splitter: raw::Splitter,
+    /// Between a game's Event Payloads and its Game End
+    in_game: bool,
+    /// Discarding data until the next game starts
+    skipping: bool,
+}
+
+/// Queue the events Dolphin at `addr` (`host` or `host:port`) streams to spectators, reconnecting
+/// whenever the connection drops, until the session is stopped.
+pub fn spectate(addr: &str, sink: &Sink) -> io::Result<()> {
+    let addr = match addr.rsplit_once(':') {
+        Some((_, port)) if port.parse::<u16>().is_ok() => addr.to_string(),
+        _ => format!("{addr}:{DEFAULT_PORT}"),
+    };
+    // Joining mid-game, we can't split the stream until the next game starts.
+    let mut stream = Stream {
+        cursor: LIVE_CURSOR,
+        connected: false,
+        splitter: raw::Splitter::new(),
+        in_game: false,
+        skipping: true,
+    };
+    let mut backoff = console::MIN_BACKOFF;
+    while !sink.queue.is_closed() {
+        sink.update_connection(|c| c.state = "connecting");
+        let Err(e) = run(&addr, &mut stream, sink, &mut backoff) else {
+            break;
+        };
+        sink.update_connection(|c| {
+            c.state = "reconnecting";
+            c.last_error = Some(e.to_string());
+        });
+        console::wait(sink, backoff);
+        backoff = (backoff * 2).min(console::MAX_BACKOFF);
+    }
+    sink.update_connection(|c| c.state = "closed");
+    Ok(())
+}
+
+/// Connect once and queue events until the connection fails (an error) or the session is stopped.
+fn run(addr: &str, stream: &mut Stream, sink: &Sink, backoff: &mut Duration) -> io::Result<()> {
+    let target = addr
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no address for {addr}")))?;
+    let local = match target {
+        SocketAddr::V4(_) => SocketAddr::from(([0, 0, 0, 0], 0)),
+        SocketAddr::V6(_) => SocketAddr::from(([0u16; 8], 0)),
+    };
+    let settings = HostSettings {
+        peer_limit: 1,
+        channel_limit: CHANNELS,
+        ..Default::default()
+    };
+    let mut host = Host::new(UdpSocket::bind(local)?, settings)
+        .map_err(|e| io::Error::other(format!("failed to create ENet host: {e:?}")))?;
+    host.connect(target, CHANNELS, CONNECT_DATA)
+        .map_err(|_| io::Error::other("no ENet peer available"))?;
+
+    // Dolphin only sends while there's something to send, so a dead connection is left to ENet's
+    // own pings to notice, as a disconnect.
+    while !sink.queue.is_closed() {
+        let Some(event) = host.service()? else {
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        };
+        match event {
+            Event::Connect { peer, .. } => {
+                let request = json!({ "type": "connect_request", "cursor": stream.cursor });
+                peer.send(0, &Packet::reliable(request.to_string().as_bytes()))
+                    .map_err(|_| io::Error::other("failed to send the connect request"))?;
+            }
+            Event::Disconnect { .. } => {
+                return Err(io::Error::new(io::ErrorKind::ConnectionReset, "disconnected from Dolphin"));
+            }
+            Event::Receive { packet, .. } => {
+                let message: Value = serde_json::from_slice(packet.data())?;
+                if !receive(&message, stream, sink, backoff)? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Handle one message, returning `false` once the session is stopped.
+fn receive(message: &Value, stream: &mut Stream, sink: &Sink, backoff: &mut Duration) -> io::Result<bool> {
+    match message["type"].as_str() {
+        Some("connect_reply") => {
+            let reconnected = stream.connected;
+            stream.connected = true;
+            *backoff = console::MIN_BACKOFF;
+            // Dolphin answers with where it'll start; anything between ours and that is gone.
+            if let Some(cursor) = message["cursor"].as_u64() {
+                skip_to(cursor, stream, sink);
+            }
+            sink.update_connection(|c| {
+                c.state = "connected";
+                c.nick = message["nick"].as_str().map(str::to_string);
+                c.reconnects += reconnected as u32;
+            });
+            Ok(true)
+        }
+        Some("game_event") => {
+            let (Some(cursor), Some(next)) = (message["cursor"].as_u64(), message["next_cursor"].as_u64()) else {
+                return Err(invalid("game event without cursors"));
+            };
+            skip_to(cursor, stream, sink);
+            stream.cursor = next;
+            let data = message["payload"].as_str().and_then(base64).ok_or_else(|| invalid("game event with invalid payload"))?;
+            events(&data, stream, sink)
+        }
+        // `start_game` and `end_game` only repeat what the event stream says.
+        _ => Ok(true),
+    }
+}
+
+/// Move the stream to `cursor`, counting a gap if it isn't where the stream was.
+fn skip_to(cursor: u64, stream: &mut Stream, sink: &Sink) {
+    if stream.cursor == LIVE_CURSOR {
+        stream.cursor = cursor;
+        return;
+    }
+    if cursor == stream.cursor {
+        return;
+    }
+    // Dolphin's cursors count messages rather than bytes, so only the games cut short are counted.
+    let in_game = stream.in_game;
+    sink.update_connection(|c| c.missed_games += in_game as u32);
+    stream.cursor = cursor;
+    stream.splitter.reset();
+    stream.in_game = false;
+    stream.skipping = true;
+}
+
+/// Queue the events of a chunk of the stream, returning `false` once the session is stopped.
+fn events(data: &[u8], stream: &mut Stream, sink: &Sink) -> io::Result<bool> {
+    if stream.skipping {
+        if data.first() != Some(&raw::EVENT_PAYLOADS) {
+            return Ok(true);
+        }
+        stream.skipping = false;
+    }
+    for event in stream.splitter.feed(data)? {
+        match event[0] {
+            raw::EVENT_PAYLOADS => stream.in_game = true,
+            raw::GAME_END => stream.in_game = false,
+            _ => {}
+        }
+        if !sink.push(&event) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Decode standard base64 (with or without padding).
+fn base64(s: &str) -> Option<Vec<u8>> {
+    let digit = |c: u8| -> Option<u32> {
+        Some(match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        } as u32)
+    };
+    let s = s.trim_end_matches('=').as_bytes();
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    for chunk in s.chunks(4) {
+        let mut bits = 0;
+        for (i, &c) in chunk.iter().enumerate() {
+            bits |= digit(c)? << (18 - 6 * i);
+        }
+        let bytes = bits.to_be_bytes();
+        match chunk.len() {
+            4 => out.extend_from_slice(&bytes[1..4]),
+            3 => out.extend_from_slice(&bytes[1..3]),
+            2 => out.push(bytes[1]),
+            _ => return None,
+        }
+    }
+    Some(out)
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
@@ -0,0 +1,59 @@
+//! Following a replay while it's being recorded
+//!
+//! Slippi writes the event stream of the game in progress to its `.slp` file as it's played,
+//! filling in the `raw` element's length and the metadata only once the game ends. Following the
+//! file picks up new events as they're written, until Game End.
+
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use super::Sink;
+use crate::raw;
+
+/// How long to wait for the file to grow before checking again.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Queue the events of the replay at `path` as they're written, until Game End or until the session
+/// is stopped.
+pub fn follow(path: PathBuf, sink: &Sink) -> io::Result<()> {
+    let queue = &sink.queue;
+    let mut file = loop {
+        match fs::File::open(&path) {
+            Ok(file) => break file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !queue.is_closed() => {
+                thread::sleep(POLL_INTERVAL)
+            }
+            Err(e) => return Err(e),
+        }
+    };
+    // Wait for the header, then start reading from the first event.
+    while file.metadata()?.len() < raw::RAW_START as u64 {
+        if queue.is_closed() {
+            return Ok(());
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    file.seek(SeekFrom::Start(raw::RAW_START as u64))?;
+
+    let mut splitter = raw::Splitter::new();
+    let mut chunk = vec![0; 64 * 1024];
+    loop {
+        let n = file.read(&mut chunk)?;
+        if n == 0 {
+            if queue.is_closed() {
+                return Ok(());
+            }
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        }
+        for event in splitter.feed(&chunk[..n])? {
+            let game_end = event[0] == raw::GAME_END;
+            if !sink.push(&event) || game_end {
+                return Ok(());
+            }
+        }
+    }
+}
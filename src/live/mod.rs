@@ -0,0 +1,271 @@
+//! Live ingestion of Slippi event streams
+//!
+//! A live session runs a producer on a background thread, which splits the incoming event stream
+//! into events and queues them for Julia to poll. The queue is bounded, so a slow consumer can't
+//! make memory grow without limit: when it's full the producer either waits or drops events,
+//! according to the configured [Overflow] policy, and dropped events are counted.
+//!
+//! Polling never blocks, since a Julia task waiting inside a foreign call would stall its thread.
+//!
+//! Sessions connected to a console (or spectating Dolphin) also report the state of their
+//! [Connection], which is re-established automatically when it drops.
+//!
+//! When a recording directory is configured, sessions also write every game they receive to a
+//! `.slp` file there, so the host ingesting the stream doubles as a backup recorder.
+//!
+//! A session can also multiplex several sources (e.g. every setup at an event) into one queue, with
+//! each event tagged by the ID of the source it came from.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::{io, thread};
+
+use serde::Serialize;
+
+use crate::{config, raw};
+
+pub mod console;
+pub mod dolphin;
+pub mod file;
+pub mod pipe;
+pub mod queue;
+pub mod record;
+pub mod ubjson;
+
+pub use queue::{Overflow, Queue};
+use record::Recorder;
+
+/// A single event of a live stream.
+#[derive(Debug, Clone, Serialize)]
+pub struct LiveEvent {
+    /// ID of the source the event came from, in multiplexed sessions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    pub command: u8,
+    pub name: &'static str,
+    /// Frame number, for frame events
+    pub frame: Option<i32>,
+    /// Hex of the event's bytes after the command byte
+    pub payload: String,
+}
+
+impl LiveEvent {
+    pub fn new(event: &[u8], source: Option<String>) -> Self {
+        LiveEvent {
+            source,
+            command: event[0],
+            name: raw::event_name(event[0]),
+            frame: raw::event_frame(event),
+            payload: event[1..].iter().map(|b| format!("{b:02x}")).collect(),
+        }
+    }
+}
+
+/// State of a session's connection to a console.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Connection {
+    /// `connecting`, `connected`, `reconnecting` (waiting out a backoff), or `closed`
+    pub state: &'static str,
+    /// Nickname the console reported in its handshake
+    pub nick: Option<String>,
+    /// Times the connection was re-established after dropping
+    pub reconnects: u32,
+    /// Bytes of the stream the console no longer had when reconnecting
+    pub missed_bytes: u64,
+    /// Games left incomplete by such gaps
+    pub missed_games: u32,
+    /// Why the connection last dropped
+    pub last_error: Option<String>,
+}
+
+/// What a session's producer writes to: the session's queue, tagged with the producer's source.
+pub struct Sink {
+    pub queue: Arc<Queue<LiveEvent>>,
+    source: Option<String>,
+    connection: Mutex<Option<Connection>>,
+    error: Mutex<Option<String>>,
+    finished: AtomicBool,
+    recorder: Mutex<Option<Recorder>>,
+    recording_error: Mutex<Option<String>>,
+}
+
+impl Sink {
+    /// Queue an event (starting with its command byte), returning `false` once the session is
+    /// stopped.
+    pub fn push(&self, event: &[u8]) -> bool {
+        self.record(|recorder, nick| recorder.record(event, nick));
+        self.queue.push(LiveEvent::new(event, self.source.clone()))
+    }
+
+    /// Run `f` on the recorder, if recording. Recording stops at the first error, which is reported
+    /// in the session's status, while events keep being queued.
+    fn record(&self, f: impl FnOnce(&mut Recorder, Option<&str>) -> io::Result<()>) {
+        let mut recorder = self.recorder.lock().unwrap();
+        let Some(r) = recorder.as_mut() else {
+            return;
+        };
+        let nick = self.connection.lock().unwrap().as_ref().and_then(|c| c.nick.clone());
+        if let Err(e) = f(r, nick.as_deref()) {
+            *recorder = None;
+            *self.recording_error.lock().unwrap() = Some(e.to_string());
+        }
+    }
+
+    /// Update the connection state, for producers with a connection.
+    pub fn update_connection(&self, f: impl FnOnce(&mut Connection)) {
+        f(self.connection.lock().unwrap().get_or_insert_with(Default::default))
+    }
+}
+
+/// A session's producer, run on its own thread.
+pub type Producer = Box<dyn FnOnce(&Sink) -> io::Result<()> + Send>;
+
+/// State of one source of a multiplexed session.
+#[derive(Debug, Serialize)]
+pub struct SourceStatus {
+    pub finished: bool,
+    pub error: Option<String>,
+    pub connection: Option<Connection>,
+    pub recording_error: Option<String>,
+}
+
+/// State of a session, as reported to Julia.
+#[derive(Debug, Serialize)]
+pub struct Status {
+    /// Events waiting to be polled
+    pub queued: usize,
+    /// Events dropped because the queue was full
+    pub dropped: u64,
+    /// Whether every producer has stopped (events may still be queued)
+    pub finished: bool,
+    /// Why the producer stopped early, if it did
+    pub error: Option<String>,
+    /// Connection state, for sessions connected to a console
+    pub connection: Option<Connection>,
+    /// Why recording games to `.slp` files stopped, if it did
+    pub recording_error: Option<String>,
+    /// State of each source, by ID, for multiplexed sessions
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub sources: BTreeMap<String, SourceStatus>,
+}
+
+struct Session {
+    queue: Arc<Queue<LiveEvent>>,
+    sinks: Vec<Arc<Sink>>,
+}
+
+fn sessions() -> &'static Mutex<HashMap<u64, Session>> {
+    static SESSIONS: OnceLock<Mutex<HashMap<u64, Session>>> = OnceLock::new();
+    SESSIONS.get_or_init(Default::default)
+}
+
+/// Start a session whose producer runs `produce` with the session's sink, returning its ID.
+///
+/// The producer should return once [Sink::push] returns `false` (or [Queue::is_closed] is true),
+/// which happens when the session is stopped. The queue is closed when it returns.
+pub fn start<F>(produce: F) -> io::Result<u64>
+where
+    F: FnOnce(&Sink) -> io::Result<()> + Send + 'static,
+{
+    start_sources(vec![(None, Box::new(produce))])
+}
+
+/// Start a session with a producer per source, each tagging its events with the source's ID. The
+/// queue is closed once every producer has returned.
+pub fn start_sources(producers: Vec<(Option<String>, Producer)>) -> io::Result<u64> {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    let config = config::get();
+    let queue = Arc::new(Queue::new(config.live_capacity, config.live_overflow));
+    let running = Arc::new(AtomicUsize::new(producers.len()));
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let mut sinks = Vec::new();
+    for (i, (source, produce)) in producers.into_iter().enumerate() {
+        let sink = Arc::new(Sink {
+            queue: queue.clone(),
+            connection: Mutex::new(None),
+            error: Mutex::new(None),
+            finished: AtomicBool::new(false),
+            recorder: Mutex::new(config.live_record_dir.as_ref().map(|dir| match &source {
+                Some(source) => Recorder::new(&dir.join(source)),
+                None => Recorder::new(dir),
+            })),
+            recording_error: Mutex::new(None),
+            source,
+        });
+        let (producer_sink, running) = (sink.clone(), running.clone());
+        let spawned = thread::Builder::new()
+            .name(format!("peppi-live-{id}-{i}"))
+            .spawn(move || {
+                if let Err(e) = produce(&producer_sink) {
+                    *producer_sink.error.lock().unwrap() = Some(e.to_string());
+                }
+                producer_sink.record(|recorder, nick| recorder.finish(nick));
+                producer_sink.finished.store(true, Ordering::Relaxed);
+                if running.fetch_sub(1, Ordering::AcqRel) == 1 {
+                    producer_sink.queue.close();
+                }
+            });
+        if let Err(e) = spawned {
+            // Stop the producers already started.
+            queue.close();
+            return Err(e);
+        }
+        sinks.push(sink);
+    }
+    if sinks.is_empty() {
+        queue.close();
+    }
+    sessions().lock().unwrap().insert(id, Session { queue, sinks });
+    Ok(id)
+}
+
+/// Take up to `max` queued events (all of them when 0) of session `id`.
+pub fn poll(id: u64, max: usize) -> Option<Vec<LiveEvent>> {
+    let queue = sessions().lock().unwrap().get(&id)?.queue.clone();
+    Some(queue.pop(max))
+}
+
+pub fn status(id: u64) -> Option<Status> {
+    let sessions = sessions().lock().unwrap();
+    let session = sessions.get(&id)?;
+    let mut status = Status {
+        queued: session.queue.len(),
+        dropped: session.queue.dropped(),
+        finished: session.queue.is_closed(),
+        error: None,
+        connection: None,
+        recording_error: None,
+        sources: BTreeMap::new(),
+    };
+    for sink in &session.sinks {
+        let error = sink.error.lock().unwrap().clone();
+        let connection = sink.connection.lock().unwrap().clone();
+        let recording_error = sink.recording_error.lock().unwrap().clone();
+        match &sink.source {
+            None => {
+                status.error = error;
+                status.connection = connection;
+                status.recording_error = recording_error;
+            }
+            Some(source) => {
+                let source_status = SourceStatus {
+                    finished: sink.finished.load(Ordering::Relaxed),
+                    error,
+                    connection,
+                    recording_error,
+                };
+                status.sources.insert(source.clone(), source_status);
+            }
+        }
+    }
+    Some(status)
+}
+
+/// Stop session `id` and discard it, along with any events still queued.
+pub fn stop(id: u64) -> Option<()> {
+    let session = sessions().lock().unwrap().remove(&id)?;
+    session.queue.close();
+    Some(())
+}
@@ -0,0 +1,108 @@
+//! Ingesting event streams from named pipes and Unix domain sockets
+//!
+//! Custom recorders and emulator forks can write the raw event stream (exactly the bytes of a
+//! replay's `raw` element, one game after another) to a pipe or socket instead of a file. Both are
+//! read without blocking, so stopping the session takes effect even while no writer is connected.
+
+use std::io;
+use std::path::Path;
+
+#[cfg(unix)]
+use std::{fs, io::Read, os::unix::fs::FileTypeExt, os::unix::fs::OpenOptionsExt};
+#[cfg(unix)]
+use std::{os::unix::net::UnixListener, thread, time::Duration};
+
+use super::Sink;
+#[cfg(unix)]
+use crate::raw;
+
+/// How long to wait for data (or a writer) before checking again.
+#[cfg(unix)]
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Queue the events written to `reader` until it ends, returning `false` once the session is
+/// stopped.
+#[cfg(unix)]
+fn read(mut reader: impl Read, sink: &Sink, until_eof: bool) -> io::Result<bool> {
+    let mut splitter = raw::Splitter::new();
+    let mut chunk = vec![0; 64 * 1024];
+    loop {
+        if sink.queue.is_closed() {
+            return Ok(false);
+        }
+        let n = match reader.read(&mut chunk) {
+            Ok(0) if until_eof => return Ok(true),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => 0,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if n == 0 {
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        }
+        for event in splitter.feed(&chunk[..n])? {
+            if !sink.push(&event) {
+                return Ok(false);
+            }
+        }
+    }
+}
+
+/// Queue the events written to the named pipe (FIFO) at `path`, by any number of writers in turn,
+/// until the session is stopped.
+#[cfg(unix)]
+pub fn fifo(path: &Path, sink: &Sink) -> io::Result<()> {
+    if !fs::metadata(path)?.file_type().is_fifo() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a named pipe"));
+    }
+    // Opening for reading doesn't wait for a writer when non-blocking, and reads find no data
+    // (rather than the end of the stream) between writers, so the splitter carries over.
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)?;
+    read(file, sink, false)?;
+    Ok(())
+}
+
+/// Listen on a Unix domain socket at `path`, queueing the events written by each client in turn,
+/// until the session is stopped. A stale socket left at `path` is replaced.
+#[cfg(unix)]
+pub fn socket(path: &Path, sink: &Sink) -> io::Result<()> {
+    if fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_socket()) {
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    listener.set_nonblocking(true)?;
+    let result = (|| {
+        while !sink.queue.is_closed() {
+            let stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(POLL_INTERVAL);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            // Each client sends whole games, so a new one starts with a fresh splitter.
+            stream.set_nonblocking(true)?;
+            if !read(stream, sink, true)? {
+                break;
+            }
+        }
+        Ok(())
+    })();
+    let _ = fs::remove_file(path);
+    result
+}
+
+#[cfg(not(unix))]
+pub fn fifo(_path: &Path, _sink: &Sink) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "named pipes require a Unix platform"))
+}
+
+#[cfg(not(unix))]
+pub fn socket(_path: &Path, _sink: &Sink) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "Unix domain sockets require a Unix platform"))
+}
@@ -0,0 +1,111 @@
+//! Bounded queue between a live producer and the Julia consumer
+
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::sync::{Condvar, Mutex};
+
+use serde::Serialize;
+
+/// What a producer does when the queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Overflow {
+    /// Wait for the consumer to make room, pausing the producer
+    Block,
+    /// Make room by dropping the oldest queued item
+    DropOldest,
+    /// Drop the new item
+    DropNewest,
+}
+
+impl FromStr for Overflow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "block" => Ok(Overflow::Block),
+            "drop_oldest" => Ok(Overflow::DropOldest),
+            "drop_newest" => Ok(Overflow::DropNewest),
+            _ => Err(format!("unknown overflow policy: {s}")),
+        }
+    }
+}
+
+struct State<T> {
+    items: VecDeque<T>,
+    dropped: u64,
+    closed: bool,
+}
+
+pub struct Queue<T> {
+    state: Mutex<State<T>>,
+    not_full: Condvar,
+    capacity: usize,
+    overflow: Overflow,
+}
+
+impl<T> Queue<T> {
+    pub fn new(capacity: usize, overflow: Overflow) -> Self {
+        Queue {
+            state: Mutex::new(State {
+                items: VecDeque::new(),
+                dropped: 0,
+                closed: false,
+            }),
+            not_full: Condvar::new(),
+            capacity: capacity.max(1),
+            overflow,
+        }
+    }
+
+    /// Queue an item according to the overflow policy, returning `false` once the queue is closed.
+    pub fn push(&self, item: T) -> bool {
+        let mut state = self.state.lock().unwrap();
+        while self.overflow == Overflow::Block && state.items.len() >= self.capacity && !state.closed {
+            state = self.not_full.wait(state).unwrap();
+        }
+        if state.closed {
+            return false;
+        }
+        if state.items.len() >= self.capacity {
+            state.dropped += 1;
+            match self.overflow {
+                Overflow::DropNewest => return true,
+                _ => {
+                    state.items.pop_front();
+                }
+            }
+        }
+        state.items.push_back(item);
+        true
+    }
+
+    /// Take up to `max` queued items (all of them when 0) without waiting.
+    pub fn pop(&self, max: usize) -> Vec<T> {
+        let mut state = self.state.lock().unwrap();
+        let n = if max == 0 { state.items.len() } else { max.min(state.items.len()) };
+        let items = state.items.drain(..n).collect();
+        self.not_full.notify_all();
+        items
+    }
+
+    /// Stop accepting items, waking a producer blocked on a full queue. Queued items can still be
+    /// taken.
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.not_full.notify_all();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().closed
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().items.len()
+    }
+
+    /// Number of items dropped because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.state.lock().unwrap().dropped
+    }
+}
@@ -0,0 +1,97 @@
+//! Recording live games to `.slp` files
+//!
+//! Games are written the way Slippi records them: the container header with a `raw` length of 0
+//! goes out first, events are appended as they arrive, and at Game End the length is filled in and
+//! the metadata written. A game cut short (by a gap in the stream, or the session stopping) is
+//! closed the same way, so every recording is a valid replay, and one interrupted mid-write can
+//! still be read as an unfinished recording.
+
+use std::fs;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::ubjson::{self, Encode};
+use crate::raw;
+
+struct Recording {
+    file: io::BufWriter<fs::File>,
+    /// Bytes of the `raw` element written so far
+    len: u64,
+    last_frame: Option<i32>,
+}
+
+pub struct Recorder {
+    dir: PathBuf,
+    games: usize,
+    recording: Option<Recording>,
+}
+
+impl Recorder {
+    pub fn new(dir: &Path) -> Self {
+        Recorder {
+            dir: dir.to_path_buf(),
+            games: 0,
+            recording: None,
+        }
+    }
+
+    /// Write an event (starting with its command byte) to the current game's file, starting a new
+    /// one at Event Payloads. Events before the first Event Payloads are ignored.
+    pub fn record(&mut self, event: &[u8], nick: Option<&str>) -> io::Result<()> {
+        if event[0] == raw::EVENT_PAYLOADS {
+            self.finish(nick)?;
+            self.recording = Some(self.create()?);
+        }
+        let Some(recording) = &mut self.recording else {
+            return Ok(());
+        };
+        recording.file.write_all(event)?;
+        recording.len += event.len() as u64;
+        recording.last_frame = raw::event_frame(event).or(recording.last_frame);
+        if event[0] == raw::GAME_END {
+            self.finish(nick)?;
+        }
+        Ok(())
+    }
+
+    fn create(&mut self) -> io::Result<Recording> {
+        fs::create_dir_all(&self.dir)?;
+        let started = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        self.games += 1;
+        let path = self.dir.join(format!("Game_{}_{}.slp", started.as_secs(), self.games));
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        file.write_all(raw::RAW_HEADER)?;
+        file.write_all(&0u32.to_be_bytes())?;
+        Ok(Recording {
+            file,
+            len: 0,
+            last_frame: None,
+        })
+    }
+
+    /// Close the current game's file, if any, filling in the `raw` length and the metadata.
+    pub fn finish(&mut self, nick: Option<&str>) -> io::Result<()> {
+        let Some(mut recording) = self.recording.take() else {
+            return Ok(());
+        };
+        let len = u32::try_from(recording.len)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "`raw` element too large"))?;
+        let mut metadata = Vec::new();
+        if let Some(frame) = recording.last_frame {
+            metadata.push(("lastFrame", Encode::Int(frame as i64)));
+        }
+        if let Some(nick) = nick {
+            metadata.push(("consoleNick", Encode::Str(nick)));
+        }
+        let mut tail = b"U\x08metadata".to_vec();
+        ubjson::encode(&Encode::Object(metadata), &mut tail);
+        tail.push(b'}');
+
+        recording.file.write_all(&tail)?;
+        let mut file = recording.file.into_inner().map_err(|e| e.into_error())?;
+        file.seek(SeekFrom::Start(raw::RAW_HEADER.len() as u64))?;
+        file.write_all(&len.to_be_bytes())?;
+        file.sync_all()
+    }
+}
@@ -0,0 +1,229 @@
+//! Just enough UBJSON for the Slippi console protocol
+//!
+//! Console messages (and the metadata of recorded replays) are small objects of integers, strings,
+//! booleans, and byte arrays. Byte arrays are written as strongly typed `uint8` arrays, and read
+//! back as arrays of numbers.
+
+use std::io;
+
+use serde_json::{Map, Value};
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("UBJSON: {msg}"))
+}
+
+/// A value to encode.
+pub enum Encode<'a> {
+    Int(i64),
+    Bool(bool),
+    Str(&'a str),
+    Bytes(&'a [u8]),
+    Object(Vec<(&'a str, Encode<'a>)>),
+}
+
+pub fn encode(value: &Encode, out: &mut Vec<u8>) {
+    match value {
+        Encode::Int(n) => {
+            out.push(b'L');
+            out.extend_from_slice(&n.to_be_bytes());
+        }
+        Encode::Bool(b) => out.push(if *b { b'T' } else { b'F' }),
+        Encode::Str(s) => {
+            out.push(b'S');
+            encode_len(s.len(), out);
+            out.extend_from_slice(s.as_bytes());
+        }
+        Encode::Bytes(bytes) => {
+            out.extend_from_slice(b"[$U#l");
+            out.extend_from_slice(&(bytes.len() as i32).to_be_bytes());
+            out.extend_from_slice(bytes);
+        }
+        Encode::Object(fields) => {
+            out.push(b'{');
+            for (key, value) in fields {
+                encode_len(key.len(), out);
+                out.extend_from_slice(key.as_bytes());
+                encode(value, out);
+            }
+            out.push(b'}');
+        }
+    }
+}
+
+/// Lengths are written as `uint8` when they fit, as Slippi does.
+fn encode_len(len: usize, out: &mut Vec<u8>) {
+    match u8::try_from(len) {
+        Ok(len) => out.extend_from_slice(&[b'U', len]),
+        Err(_) => {
+            out.push(b'l');
+            out.extend_from_slice(&(len as i32).to_be_bytes());
+        }
+    }
+}
+
+pub fn decode(data: &[u8]) -> io::Result<Value> {
+    decode_prefix(data).map(|(value, _)| value)
+}
+
+/// Decode the value at the start of `data`, returning it and the number of bytes it takes up.
+pub fn decode_prefix(data: &[u8]) -> io::Result<(Value, usize)> {
+    let mut reader = Reader { data, pos: 0 };
+    let marker = reader.byte()?;
+    let value = reader.value(marker)?;
+    Ok((value, reader.pos))
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> io::Result<&[u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + n)
+            .ok_or_else(|| invalid("truncated"))?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn int(&mut self, marker: u8) -> io::Result<i64> {
+        Ok(match marker {
+            b'i' => self.byte()? as i8 as i64,
+            b'U' => self.byte()? as i64,
+            b'I' => i16::from_be_bytes(self.take(2)?.try_into().unwrap()) as i64,
+            b'l' => i32::from_be_bytes(self.take(4)?.try_into().unwrap()) as i64,
+            b'L' => i64::from_be_bytes(self.take(8)?.try_into().unwrap()),
+            _ => return Err(invalid("expected an integer")),
+        })
+    }
+
+    fn len(&mut self) -> io::Result<usize> {
+        let marker = self.byte()?;
+        usize::try_from(self.int(marker)?).map_err(|_| invalid("negative length"))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.len()?;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| invalid("invalid UTF-8"))
+    }
+
+    fn value(&mut self, marker: u8) -> io::Result<Value> {
+        Ok(match marker {
+            b'Z' => Value::Null,
+            b'T' => Value::Bool(true),
+            b'F' => Value::Bool(false),
+            b'i' | b'U' | b'I' | b'l' | b'L' => Value::from(self.int(marker)?),
+            b'd' => Value::from(f32::from_be_bytes(self.take(4)?.try_into().unwrap()) as f64),
+            b'D' => Value::from(f64::from_be_bytes(self.take(8)?.try_into().unwrap())),
+            b'S' => Value::String(self.string()?),
+            b'[' => {
+                let elements = self.container(b']', |_| Ok(None))?;
+                Value::Array(elements.into_iter().map(|(_, v)| v).collect())
+            }
+            b'{' => {
+                let elements = self.container(b'}', |r| r.string().map(Some))?;
+                let fields = elements.into_iter().map(|(k, v)| (k.unwrap_or_default(), v));
+                Value::Object(fields.collect::<Map<_, _>>())
+            }
+            _ => return Err(invalid("unsupported marker")),
+        })
+    }
+
+    /// Read the elements of an array or object (with `key` reading each element's key), in either
+    /// the plain form ended by `end` or the optimized form with a type and/or count.
+    fn container(
+        &mut self,
+        end: u8,
+        key: impl Fn(&mut Self) -> io::Result<Option<String>>,
+    ) -> io::Result<Vec<(Option<String>, Value)>> {
+        let mut elem_type = None;
+        let mut count = None;
+        if self.data.get(self.pos) == Some(&b'$') {
+            self.pos += 1;
+            elem_type = Some(self.byte()?);
+        }
+        if self.data.get(self.pos) == Some(&b'#') {
+            self.pos += 1;
+            count = Some(self.len()?);
+        }
+
+        let mut elements = Vec::new();
+        loop {
+            match count {
+                Some(n) if elements.len() == n => break,
+                None if self.data.get(self.pos) == Some(&end) => {
+                    self.pos += 1;
+                    break;
+                }
+                _ => {}
+            }
+            let k = key(self)?;
+            let marker = match elem_type {
+                Some(t) => t,
+                None => self.byte()?,
+            };
+            elements.push((k, self.value(marker)?));
+        }
+        Ok(elements)
+    }
+}
+
+/// Bytes of an array of numbers, as decoded from a `uint8` array.
+pub fn bytes(value: &Value) -> Option<Vec<u8>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|v| v.as_u64().and_then(|n| u8::try_from(n).ok()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let long = "x".repeat(300);
+        let message = Encode::Object(vec![
+            ("type", Encode::Int(-2)),
+            ("ok", Encode::Bool(false)),
+            ("nick", Encode::Str(&long)),
+            ("payload", Encode::Object(vec![("pos", Encode::Bytes(&[0, 1, 255]))])),
+        ]);
+        let mut encoded = Vec::new();
+        encode(&message, &mut encoded);
+        // Lengths past a byte are written as `int32`.
+        assert!(encoded.windows(5).any(|w| w == [b'S', b'l', 0, 0, 1]));
+        let decoded = decode(&encoded).unwrap();
+        let payload = json!({ "pos": [0, 1, 255] });
+        let expected = json!({ "type": -2, "ok": false, "nick": long, "payload": payload });
+        assert_eq!(decoded, expected);
+        assert_eq!(bytes(&decoded["payload"]["pos"]), Some(vec![0, 1, 255]));
+        assert_eq!(bytes(&json!([1, 256])), None);
+    }
+
+    #[test]
+    fn decodes_every_container_form() {
+        assert_eq!(decode(b"[$U#U\x03\x01\x02\x03").unwrap(), json!([1, 2, 3]));
+        assert_eq!(decode(b"[#U\x02i\xffZ").unwrap(), json!([-1, null]));
+        assert_eq!(decode(b"[I\x01\x00SU\x01a]").unwrap(), json!([256, "a"]));
+        assert_eq!(decode(b"{$T#U\x01U\x01a").unwrap(), json!({ "a": true }));
+        assert_eq!(decode(b"{U\x01d[]}").unwrap(), json!({ "d": [] }));
+    }
+
+    #[test]
+    fn reports_where_a_value_ends() {
+        assert_eq!(decode_prefix(b"SU\x02hi}trailing").unwrap(), (json!("hi"), 5));
+        assert!(decode(b"[$U#U\x05\x01").is_err());
+        assert!(decode(b"SU\x02\xff\xfe").is_err());
+        assert!(decode(b"H").is_err());
+    }
+}
@@ -0,0 +1,57 @@
+//! Advisory file locks coordinating processes that share a cache or catalog
+//!
+//! Atomic writes keep readers from seeing partial files, but two Julia workers can still both decide
+//! to convert the same replay, or both read-modify-write the same catalog and lose one update. Such
+//! work is done while holding an exclusive lock on a `.lock` file next to the artifact.
+
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use fs2::FileExt;
+
+/// An exclusive lock, released when dropped.
+pub struct FileLock {
+    file: fs::File,
+}
+
+/// Lock file guarding `path`.
+fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    path.with_file_name(name)
+}
+
+impl FileLock {
+    /// Block until the lock guarding `path` is held by this process.
+    pub fn exclusive(path: &Path) -> io::Result<Self> {
+        // The lock file is never removed, since another process may be waiting on it.
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_path(path))?;
+        file.lock_exclusive()?;
+        Ok(FileLock { file })
+    }
+
+    /// Take the lock guarding `path` if no one holds it, without waiting.
+    pub fn try_exclusive(path: &Path) -> io::Result<Option<Self>> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_path(path))?;
+        match file.try_lock_exclusive() {
+            Ok(()) => Ok(Some(FileLock { file })),
+            Err(e) if e.kind() == fs2::lock_contended_error().kind() => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.file);
+    }
+}
@@ -0,0 +1,131 @@
+//! Rewriting the metadata of replays
+//!
+//! Curating an archive means fixing what recorders got wrong (a console clock that was off, a
+//! missing console nickname) and adding what they couldn't know (tournament, round, bracket tags),
+//! without touching the games themselves. The metadata element sits after the event stream in a
+//! `.slp` file, so it can be replaced while the `raw` element is copied over byte for byte.
+//!
+//! Edits are JSON merge patches (RFC 7386): objects are merged key by key, `null` removes a key,
+//! and anything else replaces the value. Metadata is UBJSON holding only objects, integers,
+//! strings, and booleans, so values outside of those (floats, arrays) can't be written.
+
+use std::io;
+
+use serde_json::Value;
+
+use crate::live::ubjson;
+use crate::raw;
+
+/// UBJSON encoding of metadata, which only holds objects, integers, and strings.
+fn encodable(value: &Value) -> Option<ubjson::Encode<'_>> {
+    Some(match value {
+        Value::Bool(b) => ubjson::Encode::Bool(*b),
+        Value::Number(n) => ubjson::Encode::Int(n.as_i64()?),
+        Value::String(s) => ubjson::Encode::Str(s),
+        Value::Object(fields) => ubjson::Encode::Object(
+            fields
+                .iter()
+                .map(|(k, v)| Some((k.as_str(), encodable(v)?)))
+                .collect::<Option<_>>()?,
+        ),
+        Value::Null | Value::Array(_) => return None,
+    })
+}
+
+/// The metadata in the tail of a `.slp` file (see [raw::Container]), if it has any.
+pub fn decode(tail: &[u8]) -> io::Result<Option<Value>> {
+    tail.strip_prefix(raw::METADATA_KEY).map(ubjson::decode).transpose()
+}
+
+/// Tail of a `.slp` file holding `metadata`: its element and the closing brace of the outer
+/// object.
+pub fn tail(metadata: &Value) -> io::Result<Vec<u8>> {
+    let metadata = encodable(metadata)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "unexpected value in metadata"))?;
+    let mut tail = raw::METADATA_KEY.to_vec();
+    ubjson::encode(&metadata, &mut tail);
+    tail.push(b'}');
+    Ok(tail)
+}
+
+/// Apply the merge `patch` to `target`.
+pub fn merge(target: &mut Value, patch: &Value) {
+    let Value::Object(fields) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    let target = target.as_object_mut().expect("target is an object");
+    for (key, value) in fields {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge(target.entry(key.as_str()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// Rewrite a `.slp` file with its metadata replaced by `metadata`, or with `metadata` merged into
+/// it when `patch` is set (into an empty object for files without any), returning the new file's
+/// bytes. The event stream is kept exactly as it was. Fails unless `metadata` is a JSON object,
+/// as Slippi's metadata always is.
+pub fn rewrite(data: &[u8], metadata: &Value, patch: bool) -> io::Result<Vec<u8>> {
+    if !metadata.is_object() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "metadata must be a JSON object"));
+    }
+    let container = raw::parse(data)?;
+    let metadata = if patch {
+        let mut merged = decode(container.tail)?.unwrap_or_else(|| Value::Object(Default::default()));
+        merge(&mut merged, metadata);
+        merged
+    } else {
+        metadata.clone()
+    };
+
+    let mut out = Vec::with_capacity(data.len());
+    raw::write(&mut out, container.raw, &tail(&metadata)?)?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn merge_patches_objects_key_by_key() {
+        let mut metadata = json!({
+            "startAt": "x",
+            "players": {"0": {"names": {"netplay": "a"}}},
+            "playedOn": "dolphin",
+        });
+        let patch = json!({
+            "startAt": "y",
+            "players": {"0": {"names": {"code": "A#1"}}},
+            "playedOn": null,
+        });
+        merge(&mut metadata, &patch);
+        let merged = json!({
+            "startAt": "y",
+            "players": {"0": {"names": {"netplay": "a", "code": "A#1"}}},
+        });
+        assert_eq!(metadata, merged);
+    }
+
+    #[test]
+    fn tail_round_trips_and_rejects_floats() {
+        let metadata = json!({"lastFrame": 1234, "consoleNick": "Setup 1", "ranked": true});
+        assert_eq!(decode(&tail(&metadata).unwrap()).unwrap(), Some(metadata));
+        assert!(tail(&json!({"lastFrame": 1.5})).is_err());
+    }
+
+    #[test]
+    fn rewrite_rejects_non_object_metadata() {
+        for metadata in [json!(null), json!([1]), json!("startAt")] {
+            let e = rewrite(&[], &metadata, false).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! Conversion throughput metrics
+//!
+//! Counters accumulate over the whole session (since the library was loaded, or since the last
+//! reset) across every conversion, whether from `read_slippi`/`read_peppi` or from batch jobs.
+//! Rates are computed over the time spent converting rather than wall-clock time, so they measure
+//! this library's throughput regardless of what the caller does between conversions.
+//!
+//! Long-running services can also expose the counters to Prometheus, see
+//! [prometheus](crate::prometheus).
+
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// Upper bounds (in seconds) of the conversion latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 9] = [0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Counters {
+    games: u64,
+    bytes: u64,
+    frames: u64,
+    cache_hits: u64,
+    cache_misses: u64,
+    errors: u64,
+    busy: Duration,
+    /// Conversions per latency bucket, not cumulative, with a last bucket for slower ones
+    latencies: [u64; LATENCY_BUCKETS.len() + 1],
+}
+
+static COUNTERS: Mutex<Counters> = Mutex::new(Counters {
+    games: 0,
+    bytes: 0,
+    frames: 0,
+    cache_hits: 0,
+    cache_misses: 0,
+    errors: 0,
+    busy: Duration::ZERO,
+    latencies: [0; LATENCY_BUCKETS.len() + 1],
+});
+
+/// A snapshot of the counters, with the rates derived from them.
+#[derive(Debug, Serialize)]
+pub struct Metrics {
+    pub games: u64,
+    pub bytes: u64,
+    pub frames: u64,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    /// Files batch jobs failed on
+    pub errors: u64,
+    /// Seconds spent converting
+    pub seconds: f64,
+    pub games_per_sec: f64,
+    pub mb_per_sec: f64,
+    pub frames_per_sec: f64,
+    /// Fraction of cache lookups that found an existing artifact
+    pub cache_hit_rate: Option<f64>,
+}
+
+/// Record a converted game of `bytes` bytes and `frames` frames, which took `elapsed`.
+pub fn record_game(bytes: usize, frames: usize, elapsed: Duration) {
+    let mut c = COUNTERS.lock().unwrap();
+    c.games += 1;
+    c.bytes += bytes as u64;
+    c.frames += frames as u64;
+    c.busy += elapsed;
+    let bucket = LATENCY_BUCKETS
+        .iter()
+        .position(|&le| elapsed.as_secs_f64() <= le)
+        .unwrap_or(LATENCY_BUCKETS.len());
+    c.latencies[bucket] += 1;
+}
+
+/// Record a file a batch job failed on.
+pub fn record_error() {
+    COUNTERS.lock().unwrap().errors += 1;
+}
+
+/// Record a cache lookup, and whether it found an existing artifact.
+pub fn record_cache(hit: bool) {
+    let mut c = COUNTERS.lock().unwrap();
+    match hit {
+        true => c.cache_hits += 1,
+        false => c.cache_misses += 1,
+    }
+}
+
+pub fn get() -> Metrics {
+    let c = *COUNTERS.lock().unwrap();
+    let seconds = c.busy.as_secs_f64();
+    let rate = |n: f64| if seconds > 0.0 { n / seconds } else { 0.0 };
+    let lookups = c.cache_hits + c.cache_misses;
+    Metrics {
+        games: c.games,
+        bytes: c.bytes,
+        frames: c.frames,
+        cache_hits: c.cache_hits,
+        cache_misses: c.cache_misses,
+        errors: c.errors,
+        seconds,
+        games_per_sec: rate(c.games as f64),
+        mb_per_sec: rate(c.bytes as f64 / 1e6),
+        frames_per_sec: rate(c.frames as f64),
+        cache_hit_rate: (lookups > 0).then(|| c.cache_hits as f64 / lookups as f64),
+    }
+}
+
+/// The counters in the Prometheus text exposition format.
+pub fn prometheus() -> String {
+    let c = *COUNTERS.lock().unwrap();
+    let mut out = String::new();
+    let mut counter = |name: &str, help: &str, value: u64| {
+        let _ = writeln!(out, "# HELP peppi_{name} {help}");
+        let _ = writeln!(out, "# TYPE peppi_{name} counter");
+        let _ = writeln!(out, "peppi_{name} {value}");
+    };
+    counter("games_total", "Games converted.", c.games);
+    counter("bytes_total", "Bytes of replays converted.", c.bytes);
+    counter("frames_total", "Frames converted.", c.frames);
+    counter("cache_hits_total", "Cache lookups that found an existing artifact.", c.cache_hits);
+    counter("cache_misses_total", "Cache lookups that found no artifact.", c.cache_misses);
+    counter("errors_total", "Files batch jobs failed on.", c.errors);
+
+    let _ = writeln!(out, "# HELP peppi_conversion_seconds Time taken to convert a game.");
+    let _ = writeln!(out, "# TYPE peppi_conversion_seconds histogram");
+    let mut cumulative = 0;
+    for (le, count) in LATENCY_BUCKETS.iter().zip(c.latencies) {
+        cumulative += count;
+        let _ = writeln!(out, "peppi_conversion_seconds_bucket{{le=\"{le}\"}} {cumulative}");
+    }
+    let _ = writeln!(out, "peppi_conversion_seconds_bucket{{le=\"+Inf\"}} {}", c.games);
+    let _ = writeln!(out, "peppi_conversion_seconds_sum {}", c.busy.as_secs_f64());
+    let _ = writeln!(out, "peppi_conversion_seconds_count {}", c.games);
+    out
+}
+
+pub fn reset() {
+    *COUNTERS.lock().unwrap() = Counters::default();
+}
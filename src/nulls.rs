@@ -0,0 +1,187 @@
+//! Handling of nulls in exported frames
+//!
+//! Frame columns can be null: fields a replay is too old to have come out as columns of nulls when
+//! selected (see [warnings](crate::warnings)), and some fields are only set on some frames.
+//! DataFrames handle nulls natively, but tensors have no place for them. Exports can choose how
+//! null values of numeric and boolean columns are written:
+//!
+//! - `null`: left as nulls (the default)
+//! - `sentinel`: replaced with NaN for floats, -1 for signed integers, the largest value (-1's bit
+//!   pattern) for unsigned integers, and false for booleans
+//! - `forward_fill`: replaced with the last value before them in the column, or the sentinel when
+//!   there's none, as for fields missing altogether
+//!
+//! A struct that's null on a frame (a port's follower block where there's no follower, say) counts
+//! as null in every column below it, so under a policy other than `null` no nulls are left at any
+//! level.
+//!
+//! Files written with a policy other than `null` record it under [METADATA_KEY] in their schema's
+//! metadata, so readers can tell sentinels from real values.
+
+use std::str::FromStr;
+
+use arrow2::array::{Array, BooleanArray, PrimitiveArray, StructArray};
+use arrow2::bitmap::Bitmap;
+use arrow2::datatypes::{DataType, Metadata};
+use serde::{Deserialize, Serialize};
+
+/// Key of the null policy in a file's schema metadata.
+pub const METADATA_KEY: &str = "peppi_jl.null_policy";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NullPolicy {
+    #[default]
+    Null,
+    Sentinel,
+    ForwardFill,
+}
+
+impl FromStr for NullPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "null" => Ok(NullPolicy::Null),
+            "sentinel" => Ok(NullPolicy::Sentinel),
+            "forward_fill" => Ok(NullPolicy::ForwardFill),
+            _ => Err(format!("unknown null policy: {s}")),
+        }
+    }
+}
+
+impl NullPolicy {
+    fn name(self) -> &'static str {
+        match self {
+            NullPolicy::Null => "null",
+            NullPolicy::Sentinel => "sentinel",
+            NullPolicy::ForwardFill => "forward_fill",
+        }
+    }
+}
+
+/// Record `policy` in a file's schema `metadata`, unless it's `null`.
+pub fn stamp(metadata: &mut Metadata, policy: NullPolicy) {
+    if policy != NullPolicy::Null {
+        metadata.insert(METADATA_KEY.to_string(), policy.name().to_string());
+    }
+}
+
+/// Replace the nulls of every numeric and boolean column below `frames` according to `policy`.
+pub fn apply(frames: &StructArray, policy: NullPolicy) -> StructArray {
+    if policy == NullPolicy::Null {
+        return frames.clone();
+    }
+    let forward = policy == NullPolicy::ForwardFill;
+    let validity = frames.validity();
+    let values = frames.values().iter().map(|v| column(v.as_ref(), validity, forward)).collect();
+    StructArray::new(frames.data_type().clone(), values, None)
+}
+
+/// Validity of an array below a struct with validity `parent`: valid where both are.
+fn within(parent: Option<&Bitmap>, validity: Option<&Bitmap>) -> Option<Bitmap> {
+    match (parent, validity) {
+        (Some(parent), Some(validity)) => Some(parent & validity),
+        (Some(v), None) | (None, Some(v)) => Some(v.clone()),
+        (None, None) => None,
+    }
+}
+
+fn column(array: &dyn Array, parent: Option<&Bitmap>, forward: bool) -> Box<dyn Array> {
+    let validity = within(parent, array.validity());
+    if let Some(inner) = array.as_any().downcast_ref::<StructArray>() {
+        let validity = validity.as_ref();
+        let values = inner.values().iter().map(|v| column(v.as_ref(), validity, forward)).collect();
+        return StructArray::new(inner.data_type().clone(), values, None).boxed();
+    }
+    let array = array.with_validity(validity);
+    if array.null_count() == 0 {
+        return array;
+    }
+
+    macro_rules! primitive {
+        ($t:ty, $sentinel:expr) => {{
+            let array = array.as_any().downcast_ref::<PrimitiveArray<$t>>().expect("data type matches array");
+            let values = fill(array.iter().map(|v| v.copied()), $sentinel, forward);
+            PrimitiveArray::new(array.data_type().clone(), values.into(), None).boxed()
+        }};
+    }
+
+    match array.data_type() {
+        DataType::Int8 => primitive!(i8, -1),
+        DataType::Int16 => primitive!(i16, -1),
+        DataType::Int32 => primitive!(i32, -1),
+        DataType::Int64 => primitive!(i64, -1),
+        DataType::UInt8 => primitive!(u8, u8::MAX),
+        DataType::UInt16 => primitive!(u16, u16::MAX),
+        DataType::UInt32 => primitive!(u32, u32::MAX),
+        DataType::UInt64 => primitive!(u64, u64::MAX),
+        DataType::Float32 => primitive!(f32, f32::NAN),
+        DataType::Float64 => primitive!(f64, f64::NAN),
+        DataType::Boolean => {
+            let array = array.as_any().downcast_ref::<BooleanArray>().expect("data type matches array");
+            let values = fill(array.iter(), false, forward);
+            BooleanArray::from_slice(values).boxed()
+        }
+        // Item lists and other nested types are left as they are.
+        _ => array.to_boxed(),
+    }
+}
+
+/// Values with nulls replaced by the last value before them (when `forward`) or `sentinel`.
+fn fill<T: Copy>(values: impl Iterator<Item = Option<T>>, sentinel: T, forward: bool) -> Vec<T> {
+    let mut last = sentinel;
+    values
+        .map(|v| match v {
+            Some(v) => {
+                last = v;
+                v
+            }
+            None if forward => last,
+            None => sentinel,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow2::datatypes::Field;
+
+    /// A struct of a `leader` and a `follower` struct, each with one `percent` column, over three
+    /// frames. The follower is missing on the second, and her percent wasn't recorded on the third.
+    fn frames() -> StructArray {
+        let percent = |values: Vec<Option<f32>>| PrimitiveArray::<f32>::from(values).boxed();
+        let block = |percent: Box<dyn Array>, validity: Option<Bitmap>| {
+            let fields = vec![Field::new("percent", DataType::Float32, true)];
+            StructArray::new(DataType::Struct(fields), vec![percent], validity).boxed()
+        };
+        let leader = block(percent(vec![Some(1.0), Some(2.0), Some(3.0)]), None);
+        let follower = block(
+            percent(vec![Some(10.0), Some(20.0), None]),
+            Some(Bitmap::from([true, false, true])),
+        );
+        let fields = vec![
+            Field::new("leader", leader.data_type().clone(), true),
+            Field::new("follower", follower.data_type().clone(), true),
+        ];
+        StructArray::new(DataType::Struct(fields), vec![leader, follower], None)
+    }
+
+    fn follower_percent(frames: &StructArray) -> Vec<Option<f32>> {
+        let follower = frames.values()[1].as_any().downcast_ref::<StructArray>().unwrap();
+        assert_eq!(follower.validity(), None);
+        let percent = follower.values()[0].as_any().downcast_ref::<PrimitiveArray<f32>>().unwrap();
+        percent.iter().map(|v| v.copied()).collect()
+    }
+
+    #[test]
+    fn null_structs_count_as_null_below() {
+        let frames = frames();
+        let sentinel = follower_percent(&apply(&frames, NullPolicy::Sentinel));
+        assert_eq!(sentinel[0], Some(10.0));
+        assert!(sentinel[1].unwrap().is_nan() && sentinel[2].unwrap().is_nan());
+        let filled = follower_percent(&apply(&frames, NullPolicy::ForwardFill));
+        assert_eq!(filled, [Some(10.0), Some(10.0), Some(10.0)]);
+    }
+}
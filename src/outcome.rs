@@ -0,0 +1,247 @@
+//! Who won a game, and how
+//!
+//! Deciding the result of a game looks simple and has edge cases everyone gets wrong. The Game End
+//! block says how the game ended, but only replays from Slippi 3.13.0 record placements, a quit out
+//! (L+R+A+Start) is recorded as a no contest, and a timeout is decided on stocks and then percent.
+//! The result combines the end block with the players' stocks and percent on the last frame:
+//!
+//! - `ko`: the game ended with players out of stocks. The winner is the one placed first, or
+//!   without placements, the one left with the most stocks.
+//! - `timeout`: time ran out. The winner has the most stocks, and then the lowest percent.
+//! - `lras`: a player quit out. The quitter loses, and in singles the other player wins.
+//! - `tie`: time ran out with the leaders level on stocks and percent.
+//! - `incomplete`: the replay has no end, or an unresolved one (a disconnect or crash).
+//!
+//! Winners and losers are only named in singles; every player's standing is listed regardless.
+//!
+//! Placements are taken as recorded when they are, and otherwise inferred the same way for every
+//! version, so historical results line up with recent ones: a player who quit out places last,
+//! and the rest place by stocks and then percent, with level players sharing a placement. Where
+//! only some players' placements are recorded, the rest are ranked among themselves into the
+//! placements left over, so no two players claim the same one unless they're level. Each standing
+//! says which it is.
+
+use std::cmp::Ordering;
+use std::path::Path;
+
+use peppi::frame::immutable::Frame;
+use peppi::game::{End, Port};
+use serde::Serialize;
+
+use crate::catalog::Entry;
+use crate::errors::{self, FileError};
+use crate::{batch, config};
+
+/// End methods, as recorded in the Game End block.
+const TIME: u8 = 1;
+const GAME: u8 = 2;
+const RESOLVED: u8 = 3;
+const NO_CONTEST: u8 = 7;
+
+#[derive(Debug, Serialize)]
+pub struct Standing {
+    pub port: Port,
+    /// Stocks and percent on the last frame
+    pub stocks: u8,
+    pub percent: f32,
+    /// From 1, as recorded (Slippi 3.13+) or else inferred
+    pub placement: usize,
+    /// Whether the placement was recorded rather than inferred
+    pub placement_recorded: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Outcome {
+    /// `ko`, `timeout`, `lras`, `tie`, or `incomplete`
+    pub result: &'static str,
+    pub winner: Option<Port>,
+    pub loser: Option<Port>,
+    /// Player who quit out, for `lras`
+    pub lras_initiator: Option<Port>,
+    pub players: Vec<Standing>,
+}
+
+/// Better standing first: not having quit out, then more stocks, then lower percent.
+fn compare(a: &Standing, b: &Standing, quitter: Option<Port>) -> Ordering {
+    let quit = |s: &Standing| Some(s.port) == quitter;
+    quit(a)
+        .cmp(&quit(b))
+        .then(b.stocks.cmp(&a.stocks))
+        .then(a.percent.total_cmp(&b.percent))
+}
+
+/// Place `players`: those in `recorded` (by port, from 1) as recorded, and the rest ranked among
+/// themselves into the placements left over, level players sharing one.
+fn place(players: &mut [Standing], recorded: &[(Port, usize)], quitter: Option<Port>) {
+    for standing in players.iter_mut() {
+        if let Some(&(_, placement)) = recorded.iter().find(|(port, _)| *port == standing.port) {
+            standing.placement = placement;
+            standing.placement_recorded = true;
+        }
+    }
+    let taken: Vec<usize> = players.iter().filter(|p| p.placement_recorded).map(|p| p.placement).collect();
+    let mut free = (1..).filter(|n| !taken.contains(n));
+    let mut ranked: Vec<usize> = (0..players.len()).filter(|&i| !players[i].placement_recorded).collect();
+    ranked.sort_by(|&a, &b| compare(&players[a], &players[b], quitter));
+    let mut previous: Option<usize> = None;
+    for i in ranked {
+        // Level players still use up a placement each, as in `1, 1, 3`.
+        let next = free.next().unwrap_or_default();
+        let tied = previous.is_some_and(|p| compare(&players[p], &players[i], quitter) == Ordering::Equal);
+        players[i].placement = match previous {
+            Some(p) if tied => players[p].placement,
+            _ => next,
+        };
+        previous = Some(i);
+    }
+}
+
+/// Decide the outcome of a game from its end block (if any) and frames.
+pub fn decide(end: Option<&End>, frames: &Frame) -> Outcome {
+    let last = frames.id.len().checked_sub(1);
+    let mut players: Vec<Standing> = frames
+        .ports
+        .iter()
+        .map(|p| Standing {
+            port: p.port,
+            stocks: last.map_or(0, |i| p.leader.post.stocks.value(i)),
+            percent: last.map_or(0.0, |i| p.leader.post.percent.value(i)),
+            placement: 0,
+            placement_recorded: false,
+        })
+        .collect();
+
+    let lras_initiator = end.and_then(|e| e.lras_initiator.flatten());
+    let recorded: Vec<(Port, usize)> = end
+        .and_then(|e| e.players.as_ref())
+        .map(|ps| ps.iter().map(|p| (p.port, p.placement as usize + 1)).collect())
+        .unwrap_or_default();
+    place(&mut players, &recorded, lras_initiator);
+
+    let method = end.map(|e| e.method as u8);
+    let result = match method {
+        _ if lras_initiator.is_some() => "lras",
+        Some(NO_CONTEST) => "lras",
+        Some(TIME) if players.iter().filter(|p| p.placement == 1).count() > 1 => "tie",
+        Some(TIME) => "timeout",
+        Some(GAME | RESOLVED) => "ko",
+        _ => "incomplete",
+    };
+
+    let singles = players.len() == 2;
+    let (winner, loser) = match result {
+        "lras" if singles => match lras_initiator {
+            Some(quitter) => (players.iter().map(|p| p.port).find(|&p| p != quitter), Some(quitter)),
+            None => (None, None),
+        },
+        "ko" | "timeout" if singles => {
+            let first: Vec<Port> = players.iter().filter(|p| p.placement == 1).map(|p| p.port).collect();
+            match first.as_slice() {
+                &[winner] => (Some(winner), players.iter().map(|p| p.port).find(|&p| p != winner)),
+                _ => (None, None),
+            }
+        }
+        _ => (None, None),
+    };
+
+    Outcome {
+        result,
+        winner,
+        loser,
+        lras_initiator,
+        players,
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Normalized {
+    /// Games whose players were all given a placement
+    pub normalized: usize,
+    pub failed: Vec<FileError>,
+}
+
+/// Fill in the placements of the players of every catalog entry missing some, inferring them from
+/// the replay's frames where they weren't recorded, on `threads` threads (as many as configured
+/// for batch work when 0).
+pub fn normalize(entries: &mut [Entry], threads: usize) -> Normalized {
+    let threads = if threads == 0 { config::get().threads } else { threads };
+    let pending: Vec<usize> = (0..entries.len())
+        .filter(|&i| entries[i].players.iter().any(|p| p.placement.is_none()))
+        .collect();
+    let decided = batch::map(&pending, threads, |&i| -> Result<Outcome, FileError> {
+        let (_, game) = errors::read_replay(Path::new(&entries[i].path), None, false)?;
+        Ok(decide(game.end.as_ref(), &game.frames))
+    });
+
+    let mut normalized = Normalized {
+        normalized: 0,
+        failed: Vec::new(),
+    };
+    for (i, outcome) in pending.into_iter().zip(decided) {
+        let outcome = match outcome {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                normalized.failed.push(e);
+                continue;
+            }
+        };
+        for player in &mut entries[i].players {
+            if let Some(standing) = outcome.players.iter().find(|s| s.port == player.port) {
+                player.placement = Some(standing.placement);
+                player.placement_recorded = standing.placement_recorded;
+            }
+        }
+        normalized.normalized += entries[i].players.iter().all(|p| p.placement.is_some()) as usize;
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn standing(port: Port, stocks: u8, percent: f32) -> Standing {
+        Standing {
+            port,
+            stocks,
+            percent,
+            placement: 0,
+            placement_recorded: false,
+        }
+    }
+
+    fn placements(players: &[Standing]) -> Vec<(usize, bool)> {
+        players.iter().map(|p| (p.placement, p.placement_recorded)).collect()
+    }
+
+    #[test]
+    fn inferred_by_stocks_then_percent_with_quitters_last() {
+        let mut players = [
+            standing(Port::P1, 2, 80.0),
+            standing(Port::P2, 2, 40.0),
+            standing(Port::P3, 3, 120.0),
+            standing(Port::P4, 4, 0.0),
+        ];
+        place(&mut players, &[], Some(Port::P4));
+        assert_eq!(placements(&players), [(3, false), (2, false), (1, false), (4, false)]);
+    }
+
+    #[test]
+    fn level_players_share_a_placement() {
+        let mut players = [standing(Port::P1, 1, 50.0), standing(Port::P2, 1, 50.0), standing(Port::P3, 0, 0.0)];
+        place(&mut players, &[], None);
+        assert_eq!(placements(&players), [(1, false), (1, false), (3, false)]);
+    }
+
+    #[test]
+    fn partially_recorded_placements_rank_the_rest_into_those_left() {
+        // P2 is recorded 1st though P3 would be inferred ahead of it, leaving 2nd and 3rd to the rest.
+        let mut players = [standing(Port::P1, 1, 90.0), standing(Port::P2, 1, 10.0), standing(Port::P3, 2, 0.0)];
+        place(&mut players, &[(Port::P2, 1)], None);
+        assert_eq!(placements(&players), [(3, false), (1, true), (2, false)]);
+
+        let mut players = [standing(Port::P1, 4, 0.0), standing(Port::P2, 0, 0.0), standing(Port::P3, 1, 0.0)];
+        place(&mut players, &[(Port::P3, 1)], None);
+        assert_eq!(placements(&players), [(2, false), (3, false), (1, true)]);
+    }
+}
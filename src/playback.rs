@@ -0,0 +1,142 @@
+//! Slippi Dolphin playback queues
+//!
+//! Dolphin plays replays listed in a JSON "comm" file passed with `-i`. In queue mode it plays each
+//! entry in turn, optionally limited to a frame range, which is all highlight reels need.
+//!
+//! A recording plan pairs a queue with what a script driving Dolphin and FFmpeg needs to know about
+//! each clip: the file to record it to, how long it should run, and the text to overlay on it.
+
+use std::path::Path;
+use std::{fs, io};
+
+use peppi::io::slippi::de::Opts as SlippiReadOpts;
+use serde::{Deserialize, Serialize};
+
+use crate::stalls::FPS;
+use crate::{FIRST_FRAME, atomic};
+
+/// A replay, or part of one, to play back.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Clip {
+    pub path: String,
+    /// First frame to play (Dolphin starts from the beginning when missing)
+    pub start_frame: Option<i32>,
+    /// Last frame to play (Dolphin plays to the end when missing)
+    pub end_frame: Option<i32>,
+    /// Base name of the recording (defaults to `clip_NNNN`)
+    pub name: Option<String>,
+    /// Text to overlay on the recording
+    pub overlay_text: Option<String>,
+}
+
+/// Contents of a Dolphin comm file in queue mode.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommFile {
+    mode: &'static str,
+    replay: &'static str,
+    is_real_time_mode: bool,
+    output_overlay_files: bool,
+    queue: Vec<QueueEntry>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct QueueEntry {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_frame: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_frame: Option<i32>,
+}
+
+impl CommFile {
+    pub fn queue(clips: &[Clip]) -> Self {
+        CommFile {
+            mode: "queue",
+            replay: "",
+            is_real_time_mode: false,
+            output_overlay_files: true,
+            queue: clips
+                .iter()
+                .map(|c| QueueEntry {
+                    path: c.path.clone(),
+                    start_frame: c.start_frame,
+                    end_frame: c.end_frame,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        atomic::write_json(path, self, true)
+    }
+}
+
+/// Everything needed to record a queue of clips, one output file per clip.
+#[derive(Debug, Serialize)]
+pub struct RecordingPlan {
+    /// Path of the comm file to pass to Dolphin
+    pub comm_file: String,
+    pub clips: Vec<PlannedClip>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlannedClip {
+    pub name: String,
+    pub path: String,
+    /// Output file name for the recording
+    pub output: String,
+    pub start_frame: i32,
+    /// Last frame, read from the replay's metadata when the clip doesn't set one
+    pub end_frame: Option<i32>,
+    pub expected_frames: Option<u32>,
+    pub expected_seconds: Option<f64>,
+    pub overlay_text: String,
+}
+
+impl RecordingPlan {
+    pub fn new(clips: &[Clip], comm_file: &Path) -> Self {
+        let clips = clips
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                let name = c.name.clone().unwrap_or_else(|| format!("clip_{i:04}"));
+                let start_frame = c.start_frame.unwrap_or(FIRST_FRAME);
+                let end_frame = c.end_frame.or_else(|| last_frame(Path::new(&c.path)));
+                let expected_frames = end_frame.map(|end| (end - start_frame + 1).max(0) as u32);
+                PlannedClip {
+                    output: format!("{name}.mp4"),
+                    name,
+                    path: c.path.clone(),
+                    start_frame,
+                    end_frame,
+                    expected_frames,
+                    expected_seconds: expected_frames.map(|f| f as f64 / FPS),
+                    overlay_text: c.overlay_text.clone().unwrap_or_default(),
+                }
+            })
+            .collect();
+
+        RecordingPlan {
+            comm_file: comm_file.to_string_lossy().into_owned(),
+            clips,
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        atomic::write_json(path, self, true)
+    }
+}
+
+/// `lastFrame` from a replay's metadata, if the replay can be read and records one.
+fn last_frame(path: &Path) -> Option<i32> {
+    let opts = SlippiReadOpts {
+        skip_frames: true,
+        ..Default::default()
+    };
+    let mut reader = io::BufReader::new(fs::File::open(path).ok()?);
+    let game = peppi::io::slippi::read(&mut reader, Some(&opts)).ok()?;
+    let last_frame = game.metadata?.get("lastFrame")?.as_i64()?;
+    i32::try_from(last_frame).ok()
+}
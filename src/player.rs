@@ -0,0 +1,58 @@
+//! Typed access to the players of a game
+//!
+//! Nearly every analysis starts from who played what on which port, which otherwise means digging
+//! through the start block's JSON. A [Player] holds one entry of the start block's player list as
+//! an opaque Julia object with one getter per field.
+
+use jlrs::prelude::*;
+use peppi::game::Player as SlippiPlayer;
+
+/// A player of a game, as set up before it started.
+#[derive(OpaqueType)]
+pub struct Player {
+    player: SlippiPlayer,
+}
+
+impl Player {
+    pub fn new(player: SlippiPlayer) -> Self {
+        Player { player }
+    }
+
+    /// Port number, from 1
+    pub fn port(&self) -> u8 {
+        self.player.port as u8 + 1
+    }
+
+    /// External character ID
+    pub fn character(&self) -> u8 {
+        self.player.character
+    }
+
+    pub fn costume(&self) -> u8 {
+        self.player.costume
+    }
+
+    /// Player type: 0 for a human, 1 for a CPU, 2 for a demo
+    pub fn player_type(&self) -> u8 {
+        self.player.r#type as u8
+    }
+
+    pub fn is_cpu(&self) -> bool {
+        self.player.r#type as u8 == 1
+    }
+
+    /// Team color (0 red, 1 blue, 2 green), or -1 outside of teams
+    pub fn team(&self) -> i8 {
+        self.player.team.as_ref().map_or(-1, |t| t.color as i8)
+    }
+
+    /// Starting stocks
+    pub fn stocks(&self) -> u8 {
+        self.player.stocks
+    }
+
+    /// CPU level, or 0 for humans (and CPUs in replays older than Slippi 1.0.0)
+    pub fn cpu_level(&self) -> u8 {
+        self.player.cpu_level.unwrap_or(0)
+    }
+}
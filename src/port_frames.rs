@@ -0,0 +1,72 @@
+//! Frames of a single player
+//!
+//! Single-player analyses (an imitation model for one character, one player's habits) only need
+//! one port's columns, and the nested `ports` struct makes every consumer dig the same few levels
+//! down for them. A port export holds the frame index and that port's columns, flattened with the
+//! port taken out of their names:
+//!
+//! - `pre.*` and `post.*`: the leader's frame data (e.g. `post.percent`)
+//! - `follower.pre.*` and `follower.post.*`: the follower's, as the [FollowerPolicy] lays them out
+//! - `features.*` and `win_probability`: the port's derived features, when enabled
+//!
+//! Files from different ports and games have the same columns whichever character was played:
+//! under `Separate` and `Merged`, ports without a follower get all-null `follower.*` columns, and
+//! under `Dropped` no port has any, Ice Climbers included. Replays recorded by different Slippi
+//! versions can still differ in the fields they have, unless the exported columns are selected.
+
+use std::fs;
+
+use arrow2::array::{Array, StructArray};
+use arrow2::chunk::Chunk;
+use arrow2::datatypes::{Field, Metadata, Schema};
+use arrow2::error::{Error, Result};
+use arrow2::io::ipc::write::FileWriter;
+use peppi::game::Port;
+
+use crate::export::{self, FollowerPolicy};
+use crate::ipc;
+
+/// Name of the column `name` of an export of every port in a port's own export, if it has one.
+fn port_column(name: &str, port: &str) -> Option<String> {
+    if name == "id" {
+        return Some(name.to_string());
+    }
+    if let Some(field) = name.strip_prefix(&format!("ports.{port}.")) {
+        return Some(field.strip_prefix("leader.").unwrap_or(field).to_string());
+    }
+    if let Some(field) = name.strip_prefix(&format!("features.{port}.")) {
+        return Some(format!("features.{field}"));
+    }
+    (name == format!("win_probability.{port}")).then(|| "win_probability".to_string())
+}
+
+/// Write `port`'s columns of `frames` as an Arrow IPC file with the given schema `metadata`.
+/// Returns the number of columns written.
+pub fn write(
+    file: &mut fs::File,
+    frames: &StructArray,
+    port: Port,
+    policy: FollowerPolicy,
+    metadata: Metadata,
+) -> Result<usize> {
+    let port = format!("{port:?}");
+    let mut fields = Vec::new();
+    let mut arrays: Vec<Box<dyn Array>> = Vec::new();
+    for (name, array) in export::flatten(frames, policy) {
+        if let Some(name) = port_column(&name, &port) {
+            fields.push(Field::new(name, array.data_type().clone(), true));
+            arrays.push(array);
+        }
+    }
+    // The frame index is always there, so finding nothing else means nobody played on the port.
+    if fields.len() <= 1 {
+        return Err(Error::InvalidArgumentError(format!("no player on port {port}")));
+    }
+
+    let columns = fields.len();
+    let schema = Schema::from(fields).with_metadata(metadata);
+    let mut writer = FileWriter::try_new(file, schema, None, ipc::write_options())?;
+    writer.write(&Chunk::new(arrays), None)?;
+    writer.finish()?;
+    Ok(columns)
+}
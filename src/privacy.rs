@@ -0,0 +1,218 @@
+//! Privacy passes applied when rewriting replays for sharing
+
+use std::io;
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::dataset::to_hex;
+use crate::{metadata, raw};
+
+/// Rewrite a `.slp` file with message and emote data stripped, returning the new file's bytes and
+/// the number of events removed.
+///
+/// Anything that isn't a game event described by the Slippi spec is treated as message data, so
+/// whatever newer versions of Slippi embed never leaves the machine by accident. Message Splitter
+/// events are kept, since Gecko code lists are sent through them.
+pub fn redact_messages(data: &[u8]) -> io::Result<(Vec<u8>, usize)> {
+    let container = raw::parse(data)?;
+    let events = raw::events(container.raw)?;
+
+    let mut stream = Vec::with_capacity(container.raw.len());
+    let mut removed = 0;
+    for event in &events {
+        if raw::KNOWN_EVENTS.contains(&event.command) {
+            stream.extend_from_slice(&container.raw[event.offset..event.offset + event.size]);
+        } else {
+            removed += 1;
+        }
+    }
+
+    let mut out = Vec::with_capacity(data.len());
+    raw::write(&mut out, &stream, container.tail)?;
+    Ok((out, removed))
+}
+
+/// Offsets within Game Start (counting its command byte) of the per-port identifying fields, with
+/// their sizes: in-game name tags, netplay display names, connect codes, and Slippi UIDs.
+const NAME_TAGS: (usize, usize) = (0x161, 0x10);
+const DISPLAY_NAMES: (usize, usize) = (0x1A5, 0x1F);
+const CONNECT_CODES: (usize, usize) = (0x221, 0xA);
+const UIDS: (usize, usize) = (0x249, 0x1D);
+
+/// Shift-JIS encoding of the `#` in connect codes (a full-width number sign).
+const SJIS_HASH: [u8; 2] = [0x81, 0x94];
+
+/// Replaces the identifying strings of a replay: blanks them, or with a salt, swaps them for
+/// pseudonyms derived from it.
+///
+/// Pseudonyms are keyed on connect codes, so a player keeps the same pseudonymous code and name
+/// across every replay anonymized with the same salt and studies can still follow them. Codes are
+/// few enough to enumerate, so the salt must be kept secret for the pseudonyms to stay anonymous.
+pub struct Anonymizer {
+    salt: Option<String>,
+}
+
+impl Anonymizer {
+    /// An anonymizer using `salt`, or blanking everything if it's empty.
+    pub fn new(salt: &str) -> Self {
+        Anonymizer {
+            salt: (!salt.is_empty()).then(|| salt.to_string()),
+        }
+    }
+
+    fn digest(&self, kind: &str, value: &str) -> Option<[u8; 32]> {
+        let salt = self.salt.as_ref()?;
+        let mut hasher = Sha256::new();
+        for part in [salt.as_str(), kind, value] {
+            hasher.update((part.len() as u64).to_be_bytes());
+            hasher.update(part.as_bytes());
+        }
+        Some(hasher.finalize().into())
+    }
+
+    /// Letters and number of the pseudonym of the player with connect code `code`.
+    fn pseudonym(&self, code: &str) -> Option<(String, u16)> {
+        if code.is_empty() {
+            return None;
+        }
+        let d = self.digest("player", code)?;
+        let letters = d[..4].iter().map(|b| (b'A' + b % 26) as char).collect();
+        Some((letters, u16::from_be_bytes([d[4], d[5]]) % 1000))
+    }
+
+    /// Pseudonymous connect code (`ABCD#123`) for `code`. Codes are blanked without a salt, and
+    /// names as well for players without a code.
+    pub fn code(&self, code: &str) -> String {
+        self.pseudonym(code).map_or_else(String::new, |(letters, n)| format!("{letters}#{n}"))
+    }
+
+    /// Pseudonymous display name for the player with connect code `code`.
+    pub fn name(&self, code: &str) -> String {
+        self.pseudonym(code).map_or_else(String::new, |(letters, n)| format!("Player {letters}{n}"))
+    }
+
+    /// Pseudonymous console nickname for `nick`.
+    pub fn console(&self, nick: &str) -> String {
+        self.digest("console", nick).map_or_else(String::new, |d| format!("Console {}", to_hex(&d[..3])))
+    }
+
+    /// Anonymize the metadata of a replay (as JSON), returning the number of fields changed.
+    pub fn metadata(&self, metadata: &mut Value) -> usize {
+        let mut changed = 0;
+        if let Some(nick) = metadata.get_mut("consoleNick") {
+            *nick = Value::from(self.console(nick.as_str().unwrap_or_default()));
+            changed += 1;
+        }
+        let players = metadata.get_mut("players").and_then(Value::as_object_mut);
+        for names in players.into_iter().flat_map(|p| p.values_mut()).filter_map(|p| p.get_mut("names")) {
+            let code = names["code"].as_str().unwrap_or_default().to_string();
+            for (key, value) in [("netplay", self.name(&code)), ("code", self.code(&code))] {
+                if let Some(field) = names.get_mut(key) {
+                    *field = Value::from(value);
+                    changed += 1;
+                }
+            }
+        }
+        changed
+    }
+
+    /// Anonymize the start of a game (as JSON, as peppi writes it), returning the number of fields
+    /// changed.
+    pub fn start(&self, start: &mut Value) -> usize {
+        let mut changed = 0;
+        let players = start.get_mut("players").and_then(Value::as_array_mut);
+        for player in players.into_iter().flatten() {
+            if let Some(tag) = player.get_mut("name_tag").filter(|t| !t.is_null()) {
+                *tag = Value::from("");
+                changed += 1;
+            }
+            let Some(netplay) = player.get_mut("netplay").filter(|n| n.is_object()) else {
+                continue;
+            };
+            let code = netplay["code"].as_str().unwrap_or_default().to_string();
+            netplay["name"] = Value::from(self.name(&code));
+            netplay["code"] = Value::from(self.code(&code));
+            if let Some(suid) = netplay.get_mut("suid").filter(|s| !s.is_null()) {
+                *suid = Value::from("");
+            }
+            changed += 1;
+        }
+        changed
+    }
+
+    /// Anonymize a Game Start event in place, returning the number of ports changed.
+    fn game_start(&self, event: &mut [u8]) -> usize {
+        let mut changed = 0;
+        for port in 0..4 {
+            let field = |(offset, size): (usize, usize)| offset + size * port..offset + size * (port + 1);
+            let code = event.get(field(CONNECT_CODES)).map(decode_code).unwrap_or_default();
+            let mut touched = false;
+            for (range, value) in [
+                (field(NAME_TAGS), Vec::new()),
+                (field(DISPLAY_NAMES), self.name(&code).into_bytes()),
+                (field(CONNECT_CODES), encode_code(&self.code(&code))),
+                (field(UIDS), Vec::new()),
+            ] {
+                let Some(bytes) = event.get_mut(range) else {
+                    continue;
+                };
+                touched |= bytes.iter().any(|&b| b != 0);
+                // Strings are null-terminated within their field, so the last byte always stays 0.
+                let n = value.len().min(bytes.len() - 1);
+                bytes.fill(0);
+                bytes[..n].copy_from_slice(&value[..n]);
+            }
+            changed += touched as usize;
+        }
+        changed
+    }
+}
+
+/// A connect code from its Shift-JIS bytes (letters, digits, and a full-width `#`).
+fn decode_code(bytes: &[u8]) -> String {
+    let mut code = String::new();
+    let mut i = 0;
+    while i < bytes.len() && bytes[i] != 0 {
+        if bytes[i..].starts_with(&SJIS_HASH) {
+            code.push('#');
+            i += 2;
+        } else {
+            code.push(bytes[i] as char);
+            i += 1;
+        }
+    }
+    code
+}
+
+fn encode_code(code: &str) -> Vec<u8> {
+    code.bytes()
+        .flat_map(|b| match b {
+            b'#' => SJIS_HASH.to_vec(),
+            b => vec![b],
+        })
+        .collect()
+}
+
+/// Rewrite a `.slp` file with the display names, connect codes, Slippi UIDs, and name tags of its
+/// players, and the nickname of the console that recorded it, blanked or pseudonymized, returning
+/// the new file's bytes and the number of fields changed.
+pub fn anonymize(data: &[u8], anonymizer: &Anonymizer) -> io::Result<(Vec<u8>, usize)> {
+    let container = raw::parse(data)?;
+    let events = raw::events(container.raw)?;
+    let mut stream = container.raw.to_vec();
+    let mut changed = 0;
+    if let Some(start) = events.iter().find(|e| e.command == raw::GAME_START) {
+        changed += anonymizer.game_start(&mut stream[start.offset..start.offset + start.size]);
+    }
+
+    let mut tail = container.tail.to_vec();
+    if let Some(mut metadata) = metadata::decode(container.tail)? {
+        changed += anonymizer.metadata(&mut metadata);
+        tail = metadata::tail(&metadata)?;
+    }
+
+    let mut out = Vec::with_capacity(data.len());
+    raw::write(&mut out, &stream, &tail)?;
+    Ok((out, changed))
+}
@@ -0,0 +1,133 @@
+//! Export profiles: standard shapes of exported frames, selectable by name
+//!
+//! Composing the export options (units, derived features, and which columns to keep) by hand is
+//! tedious when most users want one of a few standard shapes. A [Profile] bundles them, and setting
+//! one from Julia sets every option it covers.
+//!
+//! Columns are selected by patterns over dotted leaf paths (as named by
+//! [export::flatten](crate::export::flatten)), where `*` matches any one segment and a pattern
+//! naming a struct keeps everything below it: `ports.*.leader.post.position` keeps the `x` and `y`
+//! of every port's leader.
+
+use arrow2::array::{Array, StructArray};
+use arrow2::datatypes::{DataType, Field};
+use serde::Serialize;
+
+use crate::units::{FacingUnits, PositionUnits, Units};
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Profile {
+    pub name: &'static str,
+    /// Column patterns to keep, or `None` for every column
+    pub columns: Option<&'static [&'static str]>,
+    pub units: Units,
+    pub stage_features: bool,
+    pub relative_features: bool,
+}
+
+pub const PROFILES: &[Profile] = &[
+    // Everything, as recorded, with every derived feature.
+    Profile {
+        name: "full",
+        columns: None,
+        units: Units {
+            positions: PositionUnits::Game,
+            facing: FacingUnits::Sign,
+        },
+        stage_features: true,
+        relative_features: true,
+    },
+    // What per-game stats (damage, stocks, kills, movement) are computed from.
+    Profile {
+        name: "stats_minimal",
+        columns: Some(&[
+            "id",
+            "ports.*.leader.post.percent",
+            "ports.*.leader.post.stocks",
+            "ports.*.leader.post.state",
+            "ports.*.leader.post.position",
+            "ports.*.leader.post.direction",
+            "ports.*.leader.post.last_attack_landed",
+        ]),
+        units: Units {
+            positions: PositionUnits::Game,
+            facing: FacingUnits::Sign,
+        },
+        stage_features: false,
+        relative_features: false,
+    },
+    // Controller inputs and the state a model would predict them from, in stage-independent units.
+    Profile {
+        name: "ml_inputs",
+        columns: Some(&[
+            "id",
+            "ports.*.leader.pre.joystick",
+            "ports.*.leader.pre.cstick",
+            "ports.*.leader.pre.triggers",
+            "ports.*.leader.pre.buttons",
+            "ports.*.leader.post.position",
+            "ports.*.leader.post.direction",
+            "ports.*.leader.post.percent",
+            "ports.*.leader.post.state",
+            "features",
+        ]),
+        units: Units {
+            positions: PositionUnits::Stage,
+            facing: FacingUnits::Bool,
+        },
+        stage_features: true,
+        relative_features: true,
+    },
+    // Just where everyone is, e.g. for heatmaps and movement analysis.
+    Profile {
+        name: "positions_only",
+        columns: Some(&["id", "ports.*.leader.post.position"]),
+        units: Units {
+            positions: PositionUnits::Game,
+            facing: FacingUnits::Sign,
+        },
+        stage_features: false,
+        relative_features: false,
+    },
+];
+
+pub fn get(name: &str) -> Option<&'static Profile> {
+    PROFILES.iter().find(|p| p.name == name)
+}
+
+/// Whether the leaf or struct at `path` is kept by `pattern`: fully (`Some(true)`), in part, for
+/// structs with matching leaves below them (`Some(false)`), or not at all (`None`).
+pub fn matches(pattern: &str, path: &[&str]) -> Option<bool> {
+    let pattern: Vec<&str> = pattern.split('.').collect();
+    let n = pattern.len().min(path.len());
+    let prefix_matches = pattern[..n].iter().zip(&path[..n]).all(|(p, s)| *p == "*" || p == s);
+    prefix_matches.then_some(pattern.len() <= path.len())
+}
+
+/// Keep only the columns of `frames` matching any of `patterns`, dropping structs left empty.
+///
+/// Fails when no column matches, since a struct array needs at least one field.
+pub fn select(frames: &StructArray, patterns: &[String]) -> Result<StructArray, String> {
+    select_in(frames, &mut Vec::new(), patterns).ok_or_else(|| "no columns match the selection".to_string())
+}
+
+fn select_in<'a>(array: &'a StructArray, path: &mut Vec<&'a str>, patterns: &[String]) -> Option<StructArray> {
+    let mut fields = Vec::new();
+    let mut values = Vec::new();
+    for (field, column) in array.fields().iter().zip(array.values()) {
+        path.push(&field.name);
+        let matched = patterns.iter().filter_map(|p| matches(p, path)).max();
+        let kept = match (matched, column.as_any().downcast_ref::<StructArray>()) {
+            (Some(true), _) => Some(column.clone()),
+            (Some(false), Some(inner)) => select_in(inner, path, patterns).map(|s| s.boxed()),
+            _ => None,
+        };
+        path.pop();
+        if let Some(column) = kept {
+            fields.push(Field::new(field.name.clone(), column.data_type().clone(), field.is_nullable));
+            values.push(column);
+        }
+    }
+    (!fields.is_empty())
+        .then(|| StructArray::new(DataType::Struct(fields), values, array.validity().cloned()))
+}
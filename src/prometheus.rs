@@ -0,0 +1,66 @@
+//! Prometheus `/metrics` endpoint
+//!
+//! Services built on this library (watch-folder ingestion, live relays) run for days, and
+//! tournament infrastructure monitors them with Prometheus. The endpoint is a minimal HTTP server
+//! on a background thread that answers `GET /metrics` with the session's [metrics] and anything
+//! else with a 404; it needs no HTTP dependency for that.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::Duration;
+
+use crate::metrics;
+
+/// How long a client may take to send its request or read the response. Connections are answered
+/// one at a time, so a client that stalls would otherwise hold up every scrape after it.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest request line read; anything past it is left unread.
+const MAX_REQUEST_LINE: u64 = 8 << 10;
+
+/// Address the endpoint is listening on, once started.
+static LISTENING: OnceLock<String> = OnceLock::new();
+
+/// Start serving metrics on `addr` (e.g. `0.0.0.0:9898`), returning the address actually bound.
+///
+/// The endpoint can only be started once per session; later calls return the existing address.
+pub fn serve(addr: &str) -> io::Result<String> {
+    if let Some(existing) = LISTENING.get() {
+        return Ok(existing.clone());
+    }
+    let listener = TcpListener::bind(addr)?;
+    let bound = listener.local_addr()?.to_string();
+    if LISTENING.set(bound.clone()).is_err() {
+        // Another thread started the endpoint first; drop this listener.
+        return Ok(LISTENING.get().cloned().unwrap_or_default());
+    }
+    thread::Builder::new()
+        .name("peppi-metrics".to_string())
+        .spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // A misbehaving client only affects its own connection.
+                let _ = respond(stream);
+            }
+        })?;
+    Ok(bound)
+}
+
+fn respond(mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut request_line = String::new();
+    BufReader::new((&stream).take(MAX_REQUEST_LINE)).read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", metrics::prometheus()),
+        _ => ("404 Not Found", String::new()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
@@ -0,0 +1,178 @@
+//! JSON laid out like py-slippi's objects
+//!
+//! Teams moving analysis code from py-slippi still have pipelines (and expected outputs) built on
+//! its object model. A game rendered here has the attributes of py-slippi's `Game` at the same
+//! paths, so old and new results can be compared field by field before switching to the Arrow
+//! tables:
+//!
+//! - `start`: `is_teams`, `players` (4 slots, `null` when empty), `random_seed`, `slippi.version`,
+//!   `stage`, `is_pal`, `is_frozen_ps`
+//! - `end`: `method`, `lras_initiator`
+//! - `metadata`: `date`, `duration`, `platform`, `players` (4 slots), `console_name`
+//! - `frames`: one per frame index from -123, each with `index`, `ports` (4 slots of `leader` and
+//!   `follower`, each with `pre` and `post`), `items`, `start`, and `end`
+//!
+//! py-slippi's enums are `IntEnum`s, which Python's `json` writes as their values; they're written
+//! as IDs here too. Items aren't included, so `items` is always empty. As in py-slippi, a frame
+//! index stored more than once by rollback keeps its last (finalized) copy.
+
+use std::collections::BTreeMap;
+
+use peppi::frame::immutable::{Data, Frame};
+use peppi::io::slippi::Version;
+use serde_json::{Value, json};
+
+use crate::FIRST_FRAME;
+use crate::catalog::EntryPlayer;
+
+/// Number of ports py-slippi always has slots for.
+const PORTS: usize = 4;
+
+fn position(x: f32, y: f32) -> Value {
+    json!({ "x": x, "y": y })
+}
+
+/// The `pre` and `post` of a character on row `i`.
+fn pre_post(character: &Data, i: usize) -> Value {
+    let (pre, post) = (&character.pre, &character.post);
+    json!({
+        "pre": {
+            "state": pre.state.value(i),
+            "position": position(pre.position.x.value(i), pre.position.y.value(i)),
+            "direction": pre.direction.value(i),
+            "joystick": position(pre.joystick.x.value(i), pre.joystick.y.value(i)),
+            "cstick": position(pre.cstick.x.value(i), pre.cstick.y.value(i)),
+            "triggers": {
+                "logical": pre.triggers.value(i),
+                "physical": {
+                    "l": pre.triggers_physical.l.value(i),
+                    "r": pre.triggers_physical.r.value(i),
+                },
+            },
+            "buttons": {
+                "logical": pre.buttons.value(i),
+                "physical": pre.buttons_physical.value(i),
+            },
+            "random_seed": pre.random_seed.value(i),
+            "raw_analog_x": pre.raw_analog_x.as_ref().map(|a| a.value(i)),
+            "damage": pre.percent.as_ref().map(|a| a.value(i)),
+        },
+        "post": {
+            "character": post.character.value(i),
+            "state": post.state.value(i),
+            "position": position(post.position.x.value(i), post.position.y.value(i)),
+            "direction": post.direction.value(i),
+            "damage": post.percent.value(i),
+            "shield": post.shield.value(i),
+            "stocks": post.stocks.value(i),
+            "last_attack_landed": post.last_attack_landed.value(i),
+            "last_hit_by": post.last_hit_by.value(i),
+            "combo_count": post.combo_count.value(i),
+            "state_age": post.state_age.as_ref().map(|a| a.value(i)),
+            "flags": post.state_flags.as_ref().map(|f| {
+                // py-slippi reads the five flag bytes as one big-endian bitfield.
+                [&f.0, &f.1, &f.2, &f.3, &f.4].iter().fold(0u64, |acc, b| (acc << 8) | b.value(i) as u64)
+            }),
+            "hit_stun": post.misc_as.as_ref().map(|a| a.value(i)),
+            "airborne": post.airborne.as_ref().map(|a| a.value(i) != 0),
+            "ground": post.ground.as_ref().map(|a| a.value(i)),
+            "jumps": post.jumps.as_ref().map(|a| a.value(i)),
+            "l_cancel": post.l_cancel.as_ref().map(|a| a.value(i)),
+        },
+    })
+}
+
+fn frame_rows(frames: &Frame) -> Vec<Value> {
+    // Rollback can store a frame index more than once; the last one stored is what was played.
+    let mut rows = BTreeMap::new();
+    for i in 0..frames.id.len() {
+        rows.insert(frames.id.value(i), i);
+    }
+    rows.into_iter()
+        .filter(|(index, _)| *index >= FIRST_FRAME)
+        .map(|(index, i)| {
+            let mut ports = vec![Value::Null; PORTS];
+            for port in &frames.ports {
+                ports[port.port as usize] = json!({
+                    "leader": pre_post(&port.leader, i),
+                    "follower": port.follower.as_ref().map(|f| pre_post(f, i)),
+                });
+            }
+            json!({
+                "index": index,
+                "ports": ports,
+                "items": [],
+                "start": frames.start.as_ref().map(|s| json!({ "random_seed": s.random_seed.value(i) })),
+                "end": frames.end.as_ref().map(|_| json!({})),
+            })
+        })
+        .collect()
+}
+
+fn start_block(start: &Value, version: Version, players: &[EntryPlayer]) -> Value {
+    let mut slots = vec![Value::Null; PORTS];
+    // The start block lists its players in the same order as `players`.
+    let start_players = start["players"].as_array().map_or(&[][..], Vec::as_slice);
+    for (player, p) in players.iter().zip(start_players) {
+        slots[player.port as usize] = json!({
+            "character": p["character"],
+            "type": p["type"],
+            "stocks": p["stocks"],
+            "costume": p["costume"],
+            "team": p["team"].get("color"),
+            "ucf": p["ucf"],
+            "tag": p["name_tag"],
+        });
+    }
+    json!({
+        "is_teams": start["is_teams"],
+        "players": slots,
+        "random_seed": start["random_seed"],
+        "slippi": { "version": { "major": version.0, "minor": version.1, "revision": version.2 } },
+        "stage": start["stage"],
+        "is_pal": start["is_pal"],
+        "is_frozen_ps": start["is_frozen_ps"],
+    })
+}
+
+fn metadata_block(metadata: &Value) -> Value {
+    let mut slots = vec![Value::Null; PORTS];
+    if let Some(players) = metadata["players"].as_object() {
+        for (port, player) in players {
+            let Some(slot) = port.parse::<usize>().ok().and_then(|p| slots.get_mut(p)) else {
+                continue;
+            };
+            let names = &player["names"];
+            *slot = json!({
+                "characters": player["characters"],
+                "netplay": names.get("code").map(|code| json!({ "code": code, "name": names["netplay"] })),
+            });
+        }
+    }
+    json!({
+        "date": metadata["startAt"],
+        // Frames from the first (-123) through the last, inclusive
+        "duration": metadata["lastFrame"].as_i64().map(|last| last - FIRST_FRAME as i64 + 1),
+        "platform": metadata["playedOn"],
+        "players": slots,
+        "console_name": metadata["consoleNick"],
+    })
+}
+
+/// A game in py-slippi's layout, from its start, end, and metadata as JSON. Frames are only
+/// included when given, since they're most of the output.
+pub fn game(
+    start: &Value,
+    end: Option<&Value>,
+    metadata: Option<&Value>,
+    version: Version,
+    players: &[EntryPlayer],
+    frames: Option<&Frame>,
+) -> Value {
+    json!({
+        "start": start_block(start, version, players),
+        "end": end.map(|e| json!({ "method": e["method"], "lras_initiator": e["lras_initiator"] })),
+        "metadata": metadata.map(metadata_block),
+        "frames": frames.map(frame_rows),
+    })
+}
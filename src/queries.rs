@@ -0,0 +1,105 @@
+//! Saved catalog queries
+//!
+//! Browsing a replay archive means coming back to the same selections ("my Fox dittos this
+//! month", "everything tagged money-match on Battlefield"). Queries are saved by name as catalog
+//! [Filter]s in a file next to the catalog, so any tool using the catalog can re-run them by name
+//! and gets the same games back.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use crate::catalog::{Entry, Filter};
+use crate::{atomic, lock};
+
+/// Path of the saved queries of the catalog at `catalog`.
+pub fn path_for(catalog: &Path) -> PathBuf {
+    let mut name = catalog.file_name().unwrap_or_default().to_os_string();
+    name.push(".queries.json");
+    catalog.with_file_name(name)
+}
+
+/// Queries saved in the file at `path`, by name (none if there's no file).
+pub fn read(path: &Path) -> io::Result<BTreeMap<String, Filter>> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(serde_json::from_slice(&data)?)
+}
+
+/// Read, change, and rewrite the queries at `path` while holding its lock, so processes saving
+/// queries at the same time don't lose each other's changes.
+fn update<T>(path: &Path, f: impl FnOnce(&mut BTreeMap<String, Filter>) -> T) -> io::Result<T> {
+    let _lock = lock::FileLock::exclusive(path)?;
+    let mut queries = read(path)?;
+    let result = f(&mut queries);
+    atomic::write_json(path, &queries, true)?;
+    Ok(result)
+}
+
+/// Save `filter` as the query `name` at `path`, replacing any query of that name. Returns whether
+/// one was replaced.
+pub fn save(path: &Path, name: &str, filter: Filter) -> io::Result<bool> {
+    update(path, |queries| queries.insert(name.to_string(), filter).is_some())
+}
+
+/// Delete the query `name` at `path`, returning whether there was one.
+pub fn delete(path: &Path, name: &str) -> io::Result<bool> {
+    update(path, |queries| queries.remove(name).is_some())
+}
+
+/// The entries matching the query `name` at `path`, or `None` if there's no such query.
+pub fn run<'a>(path: &Path, name: &str, entries: &'a [Entry]) -> io::Result<Option<Vec<&'a Entry>>> {
+    let queries = read(path)?;
+    Ok(queries.get(name).map(|filter| entries.iter().filter(|e| filter.matches(e)).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::tests::entry;
+
+    fn filter(json: &str) -> serde_json::Result<Filter> {
+        serde_json::from_str(json)
+    }
+
+    #[test]
+    fn saved_queries_run_by_name() {
+        let dir = std::env::temp_dir().join(format!("peppi-jl-queries-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = path_for(&dir.join("catalog.json"));
+        assert!(path.ends_with("catalog.json.queries.json"));
+
+        let mut april = entry("april.slp", &["A#1", "B#2"]);
+        april.start_time = Some("2023-04-01T18:00:00Z".to_string());
+        let mut may = entry("may.slp", &["A#1", "C#3"]);
+        may.start_time = Some("2023-05-02T18:00:00Z".to_string());
+        let entries = [april, may];
+
+        let april_only = filter(r#"{"since": "2023-04-01", "until": "2023-05-01"}"#).unwrap();
+        assert!(!save(&path, "april", april_only).unwrap());
+        assert!(!save(&path, "c", filter(r#"{"players": ["C#3"]}"#).unwrap()).unwrap());
+        let paths = |name: &str| {
+            let matched = run(&path, name, &entries).unwrap()?;
+            Some(matched.iter().map(|e| e.path.clone()).collect::<Vec<_>>())
+        };
+        assert_eq!(paths("april"), Some(vec!["april.slp".to_string()]));
+        assert_eq!(paths("c"), Some(vec!["may.slp".to_string()]));
+        assert_eq!(paths("missing"), None);
+
+        assert!(delete(&path, "april").unwrap());
+        assert!(!delete(&path, "april").unwrap());
+        assert_eq!(read(&path).unwrap().keys().collect::<Vec<_>>(), ["c"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_times_are_rejected_when_read() {
+        assert!(filter(r#"{"since": "yesterday"}"#).is_err());
+        assert!(filter(r#"{"until": "2023-04-01T25"}"#).is_err());
+        let filter = filter(r#"{"since": "2023-04-01T18:00:00+02:00"}"#).unwrap();
+        assert_eq!(serde_json::to_value(&filter).unwrap()["since"], "2023-04-01T18:00:00+02:00");
+    }
+}
@@ -0,0 +1,507 @@
+//! Raw access to the `.slp` container and its event stream
+//!
+//! A `.slp` file is a UBJSON object whose `raw` element holds the replay's event stream, followed
+//! by an optional `metadata` element. The stream starts with an Event Payloads event that gives the
+//! size of every other event type, which is all that's needed to walk it without decoding events.
+//! This lets replays be rewritten with events dropped while every other byte stays untouched, and
+//! lets the bytes of individual frames be located for later extraction. Malformed replays can be
+//! inspected event by event with [dump].
+//!
+//! Event streams arriving over time (from a replay still being recorded, or over a connection) are
+//! split into events with a [Splitter] as their bytes come in.
+
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::live::ubjson;
+
+/// Bytes before the length of the `raw` element: `{`, `U\x03raw`, `[$U#l`.
+pub const RAW_HEADER: &[u8] = b"{U\x03raw[$U#l";
+/// Offset of the start of the `raw` element's contents, after its header and length.
+pub const RAW_START: usize = RAW_HEADER.len() + 4;
+/// Key of the `metadata` element, which starts the tail of a finished recording.
+pub const METADATA_KEY: &[u8] = b"U\x08metadata";
+
+pub const EVENT_PAYLOADS: u8 = 0x35;
+pub const GAME_START: u8 = 0x36;
+pub const PRE_FRAME: u8 = 0x37;
+pub const POST_FRAME: u8 = 0x38;
+pub const GAME_END: u8 = 0x39;
+pub const FRAME_START: u8 = 0x3A;
+pub const ITEM_UPDATE: u8 = 0x3B;
+pub const FRAME_BOOKEND: u8 = 0x3C;
+pub const GECKO_LIST: u8 = 0x3D;
+pub const MESSAGE_SPLITTER: u8 = 0x10;
+pub const FOD_PLATFORM: u8 = 0x3F;
+pub const WHISPY: u8 = 0x40;
+pub const STADIUM_TRANSFORMATION: u8 = 0x41;
+
+/// Event types described by the Slippi spec.
+pub const KNOWN_EVENTS: [u8; 13] = [
+    EVENT_PAYLOADS,
+    GAME_START,
+    PRE_FRAME,
+    POST_FRAME,
+    GAME_END,
+    FRAME_START,
+    ITEM_UPDATE,
+    FRAME_BOOKEND,
+    GECKO_LIST,
+    MESSAGE_SPLITTER,
+    FOD_PLATFORM,
+    WHISPY,
+    STADIUM_TRANSFORMATION,
+];
+
+/// A `.slp` file split into its event stream and everything after it.
+pub struct Container<'a> {
+    /// Contents of the `raw` element
+    pub raw: &'a [u8],
+    /// The `metadata` element (if any) and the closing brace of the outer object
+    pub tail: &'a [u8],
+    /// Length of the `raw` element as written in the file (0 for an unfinished recording)
+    pub declared_len: u32,
+}
+
+/// The outer UBJSON object of a `.slp` file as found, for diagnosing files whose wrapper a
+/// recorder mangled.
+#[derive(Debug, Serialize)]
+pub struct Wrapper {
+    pub file_len: usize,
+    /// Whether the file starts with the `raw` element's header
+    pub raw_header: bool,
+    /// Length of the `raw` element as written (0 for an unfinished recording)
+    pub declared_len: Option<u32>,
+    /// Bytes of the file after the `raw` element's length
+    pub available_len: usize,
+    /// Whether the `raw` element fits in the file
+    pub raw_fits: bool,
+    /// Whether a `metadata` element follows the `raw` element
+    pub metadata: bool,
+    /// Size of the `metadata` element's value, if it decodes
+    pub metadata_len: Option<usize>,
+    /// Whether the outer object is closed after its last element
+    pub closed: bool,
+    /// Bytes after the outer object (0 in a well-formed file)
+    pub trailing: usize,
+}
+
+/// A single event within the `raw` element.
+#[derive(Debug, Clone, Copy)]
+pub struct Event {
+    pub command: u8,
+    /// Offset of the command byte from the start of the `raw` element
+    pub offset: usize,
+    /// Size of the event including its command byte
+    pub size: usize,
+}
+
+/// Bytes of each event shown in a [dump] preview.
+const PREVIEW_LEN: usize = 16;
+
+/// A single event as listed by [dump].
+#[derive(Debug, Serialize)]
+pub struct EventDump {
+    /// Offset of the command byte from the start of the file
+    pub offset: usize,
+    pub command: u8,
+    pub name: &'static str,
+    pub size: usize,
+    /// Frame number, for frame events
+    pub frame: Option<i32>,
+    /// Hex of the first bytes after the command byte
+    pub preview: String,
+}
+
+/// Byte range of all the events belonging to one frame.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct FrameOffset {
+    pub frame: i32,
+    /// Offset of the frame's first event from the start of the file
+    pub offset: usize,
+    /// Length in bytes, up to the end of the frame's last event
+    pub len: usize,
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Split a `.slp` file into its parts.
+pub fn parse(data: &[u8]) -> io::Result<Container<'_>> {
+    if !data.starts_with(RAW_HEADER) {
+        return Err(invalid("missing `raw` element"));
+    }
+    let len_end = RAW_START;
+    let len_bytes = data
+        .get(RAW_HEADER.len()..len_end)
+        .ok_or_else(|| invalid("truncated `raw` element length"))?;
+    let declared_len = u32::from_be_bytes(len_bytes.try_into().unwrap());
+    // Replays still being recorded have a length of 0 and no tail.
+    let raw_end = match declared_len {
+        0 => data.len(),
+        n => len_end + n as usize,
+    };
+    let raw = data
+        .get(len_end..raw_end)
+        .ok_or_else(|| invalid("`raw` element is longer than the file"))?;
+    Ok(Container {
+        raw,
+        tail: &data[raw_end..],
+        declared_len,
+    })
+}
+
+/// Inspect the outer object of a `.slp` file without reading its events.
+pub fn wrapper(data: &[u8]) -> Wrapper {
+    let raw_header = data.starts_with(RAW_HEADER);
+    let declared_len = data
+        .get(RAW_HEADER.len()..RAW_START)
+        .filter(|_| raw_header)
+        .map(|b| u32::from_be_bytes(b.try_into().unwrap()));
+    let available_len = data.len().saturating_sub(RAW_START);
+    let raw_fits = declared_len.is_some_and(|n| n as usize <= available_len);
+
+    let tail = match declared_len {
+        Some(n) if raw_fits && n > 0 => &data[RAW_START + n as usize..],
+        _ => &[],
+    };
+    let value = tail.strip_prefix(METADATA_KEY);
+    let metadata_len = value.and_then(|v| ubjson::decode_prefix(v).ok()).map(|(_, len)| len);
+    // What's left after the metadata element, or right after the `raw` element without one
+    let rest = match (value, metadata_len) {
+        (Some(v), Some(len)) => &v[len..],
+        (Some(_), None) => &[],
+        (None, _) => tail,
+    };
+    let closed = rest.first() == Some(&b'}');
+    Wrapper {
+        file_len: data.len(),
+        raw_header,
+        declared_len,
+        available_len,
+        raw_fits,
+        metadata: value.is_some(),
+        metadata_len,
+        closed,
+        trailing: rest.len() - closed as usize,
+    }
+}
+
+/// Walk the events of a `raw` element, using the sizes given by its Event Payloads event.
+///
+/// A trailing partial event (as in an unfinished recording) ends the walk without an error.
+pub fn events(raw: &[u8]) -> io::Result<Vec<Event>> {
+    match walk(raw)? {
+        (events, None) => Ok(events),
+        (_, Some(offset)) => Err(invalid(format!("unknown event {:#04x} at {offset}", raw[offset]))),
+    }
+}
+
+/// Walk as many events as possible, also returning the offset of the first event whose size isn't
+/// known (which ends the walk, since nothing after it can be located).
+fn walk(raw: &[u8]) -> io::Result<(Vec<Event>, Option<usize>)> {
+    let sizes = payload_sizes(raw)?.ok_or_else(|| invalid("truncated Event Payloads"))?;
+    let mut events = Vec::new();
+    let mut offset = 0;
+    while offset < raw.len() {
+        let command = raw[offset];
+        let Some(size) = sizes[command as usize].map(|s| s + 1) else {
+            return Ok((events, Some(offset)));
+        };
+        if offset + size > raw.len() {
+            break;
+        }
+        events.push(Event {
+            command,
+            offset,
+            size,
+        });
+        offset += size;
+    }
+    Ok((events, None))
+}
+
+/// Offset from the start of the file of the first event that can't be walked (one of unknown type,
+/// or cut short), or of the `raw` element if the container itself is malformed. `None` when the
+/// whole event stream can be walked.
+pub fn error_offset(data: &[u8]) -> Option<usize> {
+    let base = RAW_START;
+    let Ok(container) = parse(data) else {
+        return Some(0);
+    };
+    let Ok((events, _)) = walk(container.raw) else {
+        return Some(base);
+    };
+    let end = events.last().map_or(0, |e| e.offset + e.size);
+    (end < container.raw.len()).then_some(base + end)
+}
+
+/// Number of events in a `raw` element, counting no further than just past `limit`, without
+/// allocating per event. `None` if the Event Payloads event is incomplete or invalid.
+///
+/// Counting stops at an event of unknown type, since nothing after it can be located.
+pub fn count_events(raw: &[u8], limit: usize) -> Option<usize> {
+    let sizes = payload_sizes(raw).ok()??;
+    let mut count = 0;
+    let mut offset = 0;
+    while offset < raw.len() && count <= limit {
+        let Some(size) = sizes[raw[offset] as usize] else {
+            break;
+        };
+        offset += size + 1;
+        count += 1;
+    }
+    Some(count)
+}
+
+/// Whether the file ends before its event stream does: within the container's header, the `raw`
+/// element, or an event.
+pub fn truncated(data: &[u8]) -> bool {
+    if data.len() < RAW_START {
+        return RAW_HEADER.starts_with(&data[..data.len().min(RAW_HEADER.len())]);
+    }
+    let Ok(container) = parse(data) else {
+        // With the header intact, the only other failure is a `raw` element longer than the file.
+        return data.starts_with(RAW_HEADER);
+    };
+    match payload_sizes(container.raw) {
+        Ok(None) => true,
+        Ok(Some(_)) => match walk(container.raw) {
+            Ok((events, None)) => events.last().map_or(0, |e| e.offset + e.size) < container.raw.len(),
+            _ => false,
+        },
+        Err(_) => false,
+    }
+}
+
+/// Slippi version (major, minor, build) that recorded a `.slp` file, from its Game Start event.
+pub fn game_version(data: &[u8]) -> Option<[u8; 3]> {
+    let raw = parse(data).ok()?.raw;
+    let sizes = payload_sizes(raw).ok()??;
+    let start = sizes[EVENT_PAYLOADS as usize]? + 1;
+    if *raw.get(start)? != GAME_START {
+        return None;
+    }
+    raw.get(start + 1..start + 4)?.try_into().ok()
+}
+
+/// Payload size of every event type, from the Event Payloads event at the start of `raw`, or `None`
+/// if it's incomplete.
+fn payload_sizes(raw: &[u8]) -> io::Result<Option<[Option<usize>; 256]>> {
+    match raw.first() {
+        None => return Ok(None),
+        Some(&EVENT_PAYLOADS) => {}
+        Some(_) => return Err(invalid("stream doesn't start with Event Payloads")),
+    }
+    let Some(payloads_size) = raw.get(1).map(|&s| s as usize) else {
+        return Ok(None);
+    };
+    let Some(payloads) = raw.get(2..1 + payloads_size) else {
+        return Ok(None);
+    };
+
+    let mut sizes = [None; 256];
+    sizes[EVENT_PAYLOADS as usize] = Some(payloads_size);
+    for entry in payloads.chunks_exact(3) {
+        sizes[entry[0] as usize] = Some(u16::from_be_bytes([entry[1], entry[2]]) as usize);
+    }
+    Ok(Some(sizes))
+}
+
+/// Splits an event stream arriving in arbitrary chunks into whole events.
+///
+/// The stream may hold several games back to back, each starting with its own Event Payloads.
+#[derive(Default)]
+pub struct Splitter {
+    /// Bytes received but not yet returned as part of an event
+    pending: Vec<u8>,
+    sizes: Option<[Option<usize>; 256]>,
+}
+
+impl Splitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take in the next chunk of the stream, returning every event it completes (each including
+    /// its command byte). Fails on an event type the Event Payloads event gave no size for.
+    pub fn feed(&mut self, chunk: &[u8]) -> io::Result<Vec<Vec<u8>>> {
+        self.pending.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        let mut offset = 0;
+        loop {
+            if self.sizes.is_none() {
+                self.pending.drain(..offset);
+                offset = 0;
+                self.sizes = payload_sizes(&self.pending)?;
+            }
+            let Some(sizes) = &self.sizes else {
+                break;
+            };
+            let Some(&command) = self.pending.get(offset) else {
+                break;
+            };
+            let size = sizes[command as usize]
+                .map(|s| s + 1)
+                .ok_or_else(|| invalid(format!("unknown event {command:#04x}")))?;
+            let Some(event) = self.pending.get(offset..offset + size) else {
+                break;
+            };
+            events.push(event.to_vec());
+            offset += size;
+            if command == GAME_END {
+                self.sizes = None;
+            }
+        }
+        self.pending.drain(..offset);
+        Ok(events)
+    }
+
+    /// Discard any partial event and wait for the next game's Event Payloads, after a gap in the
+    /// stream.
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.sizes = None;
+    }
+}
+
+/// Write a `.slp` file from a `raw` element's events and the tail of an existing file.
+pub fn write<W: Write>(mut w: W, raw: &[u8], tail: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(raw.len()).map_err(|_| invalid("`raw` element too large"))?;
+    w.write_all(RAW_HEADER)?;
+    w.write_all(&len.to_be_bytes())?;
+    w.write_all(raw)?;
+    // An unfinished recording has no tail, so close the outer object ourselves.
+    match tail {
+        [] => w.write_all(b"}"),
+        _ => w.write_all(tail),
+    }
+}
+
+/// Frame number of a frame event, which every one of them stores right after its command byte.
+fn frame_number(raw: &[u8], event: &Event) -> Option<i32> {
+    event_frame(raw.get(event.offset..event.offset + event.size)?)
+}
+
+/// Frame number of a frame event given as its bytes (starting with the command byte).
+pub fn event_frame(event: &[u8]) -> Option<i32> {
+    match *event.first()? {
+        FRAME_START | PRE_FRAME | POST_FRAME | ITEM_UPDATE | FRAME_BOOKEND => {
+            let bytes = event.get(1..5)?;
+            Some(i32::from_be_bytes(bytes.try_into().unwrap()))
+        }
+        _ => None,
+    }
+}
+
+/// Locate the bytes of every frame in a `.slp` file.
+///
+/// A frame's range starts at its first event (Frame Start, or Pre-Frame before 2.2) and ends with
+/// its last (Frame Bookend from 3.0), so it covers any non-frame events recorded in between.
+pub fn frame_offsets(data: &[u8]) -> io::Result<Vec<FrameOffset>> {
+    let container = parse(data)?;
+    let base = RAW_START;
+    let mut offsets: Vec<FrameOffset> = Vec::new();
+    for event in events(container.raw)? {
+        let Some(frame) = frame_number(container.raw, &event) else {
+            continue;
+        };
+        let end = base + event.offset + event.size;
+        match offsets.last_mut() {
+            Some(last) if last.frame == frame => last.len = end - last.offset,
+            _ => offsets.push(FrameOffset {
+                frame,
+                offset: base + event.offset,
+                len: event.size,
+            }),
+        }
+    }
+    Ok(offsets)
+}
+
+/// Name of an event type, as used by [dump] and accepted by its filter.
+pub fn event_name(command: u8) -> &'static str {
+    match command {
+        EVENT_PAYLOADS => "event_payloads",
+        GAME_START => "game_start",
+        PRE_FRAME => "pre_frame",
+        POST_FRAME => "post_frame",
+        GAME_END => "game_end",
+        FRAME_START => "frame_start",
+        ITEM_UPDATE => "item_update",
+        FRAME_BOOKEND => "frame_bookend",
+        GECKO_LIST => "gecko_list",
+        MESSAGE_SPLITTER => "message_splitter",
+        FOD_PLATFORM => "fod_platform",
+        WHISPY => "whispy",
+        STADIUM_TRANSFORMATION => "stadium_transformation",
+        _ => "unknown",
+    }
+}
+
+/// List up to `limit` events (all of them when 0) of a `.slp` file, keeping only those whose
+/// [event_name] is in `types` (all of them when empty).
+///
+/// An event with no size in the Event Payloads table is listed as `unknown`, covering the rest of
+/// the stream, since the walk can't continue past it.
+pub fn dump(data: &[u8], limit: usize, types: &[&str]) -> io::Result<Vec<EventDump>> {
+    let container = parse(data)?;
+    let raw = container.raw;
+    let base = RAW_START;
+    let (mut events, unknown) = walk(raw)?;
+    if let Some(offset) = unknown {
+        events.push(Event {
+            command: raw[offset],
+            offset,
+            size: raw.len() - offset,
+        });
+    }
+
+    let events = events
+        .into_iter()
+        .filter(|e| types.is_empty() || types.contains(&event_name(e.command)))
+        .take(if limit == 0 { usize::MAX } else { limit })
+        .map(|e| EventDump {
+            offset: base + e.offset,
+            command: e.command,
+            name: event_name(e.command),
+            size: e.size,
+            frame: frame_number(raw, &e),
+            preview: raw[e.offset + 1..e.offset + e.size]
+                .iter()
+                .take(PREVIEW_LEN)
+                .map(|b| format!("{b:02x}"))
+                .collect::<Vec<_>>()
+                .join(" "),
+        })
+        .collect();
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(tail: &[u8]) -> Vec<u8> {
+        let mut data = RAW_HEADER.to_vec();
+        data.extend(2u32.to_be_bytes());
+        data.extend([0, 0]);
+        data.extend(tail);
+        data
+    }
+
+    #[test]
+    fn wrapper_counts_bytes_after_the_object() {
+        let closed = wrapper(&file(b"U\x08metadata{}}xy"));
+        assert!(closed.raw_fits && closed.metadata && closed.closed);
+        assert_eq!((closed.metadata_len, closed.trailing), (Some(2), 2));
+
+        // Without the closing brace, every byte after the metadata is trailing.
+        let unclosed = wrapper(&file(b"U\x08metadata{}xy"));
+        assert!(!unclosed.closed);
+        assert_eq!(unclosed.trailing, 2);
+        assert_eq!(wrapper(&file(b"")).trailing, 0);
+    }
+}
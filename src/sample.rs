@@ -0,0 +1,336 @@
+//! Seeded, reproducible sampling of games and frames
+//!
+//! Sampling uses its own small PRNG (SplitMix64) rather than an external crate, so a given seed
+//! selects the same games and frames on every platform and with every version of this library.
+//!
+//! Balanced sampling draws up to a fixed number of games per class (character, stage, matchup, or
+//! skill bracket) from a catalog, so datasets aren't dominated by the most popular picks.
+//!
+//! Splitting assigns the games of a catalog to train/validation/test (or any other named) splits.
+//! Splitting by game lets a player's games land in every split, so a model can score well on the
+//! test split by recognizing players it was trained on. Splitting by player keeps each player (by
+//! connect code, else display name) in one split instead. Since a game can only go to one split,
+//! players who played each other have to share it, so players are grouped into components of
+//! everyone connected by games played, and whole components are assigned. No game is left out,
+//! but a tightly knit community goes to one split together, so the splits can miss their fractions
+//! by up to the size of the largest component.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use crate::catalog::Entry;
+use crate::skill;
+
+/// SplitMix64 generator.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform integer in `0..n` (Lemire's multiply-shift, unbiased enough for sampling).
+    pub fn below(&mut self, n: usize) -> usize {
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+}
+
+/// Pick `n` distinct indices from `0..len` (all of them if `n >= len`), in ascending order.
+pub fn indices(len: usize, n: usize, seed: u64) -> Vec<usize> {
+    let mut rng = Rng::new(seed);
+    let mut all: Vec<usize> = (0..len).collect();
+    let n = n.min(len);
+    // Partial Fisher-Yates: the first `n` slots end up a uniform sample.
+    for i in 0..n {
+        let j = i + rng.below(len - i);
+        all.swap(i, j);
+    }
+    all.truncate(n);
+    all.sort_unstable();
+    all
+}
+
+/// Games selected by [balanced], by class.
+#[derive(Debug, Serialize)]
+pub struct BalancedManifest {
+    pub by: String,
+    pub seed: u64,
+    pub per_class: usize,
+    pub classes: BTreeMap<String, Vec<SelectedGame>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SelectedGame {
+    pub path: String,
+    /// Per-game seed for reproducible augmentation (see [Entry::seed])
+    pub seed: u64,
+}
+
+/// Classes a catalog entry belongs to when balancing `by` "character", "stage", "matchup", or
+/// "bracket" (the lowest skill bracket among the players, for annotated catalogs).
+fn classes(entry: &Entry, by: &str) -> Result<Vec<String>, String> {
+    Ok(match by {
+        "character" => {
+            let mut names: Vec<_> = entry.players.iter().map(|p| p.character_name.to_string()).collect();
+            names.sort();
+            names.dedup();
+            names
+        }
+        "stage" => vec![entry.stage_name.to_string()],
+        "matchup" => {
+            let mut names: Vec<_> = entry.players.iter().map(|p| p.character_name.to_string()).collect();
+            names.sort();
+            vec![names.join(" vs ")]
+        }
+        "bracket" => skill::game_bracket(entry).map(str::to_string).into_iter().collect(),
+        _ => return Err(format!("can't balance by {by}")),
+    })
+}
+
+/// Select up to `per_class` games for every class, never selecting a game twice.
+///
+/// Classes are filled in order of name, each from the games not yet taken by an earlier class, so
+/// a game with two characters counts towards only one of them.
+pub fn balanced(entries: &[Entry], by: &str, per_class: usize, seed: u64) -> Result<BalancedManifest, String> {
+    let mut members: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (i, entry) in entries.iter().enumerate() {
+        for class in classes(entry, by)? {
+            members.entry(class).or_default().push(i);
+        }
+    }
+
+    let mut rng = Rng::new(seed);
+    let mut taken = vec![false; entries.len()];
+    let mut selected = BTreeMap::new();
+    for (class, mut candidates) in members {
+        candidates.retain(|&i| !taken[i]);
+        let n = per_class.min(candidates.len());
+        for i in 0..n {
+            let j = i + rng.below(candidates.len() - i);
+            candidates.swap(i, j);
+        }
+        candidates.truncate(n);
+        candidates.sort_unstable();
+        for &i in &candidates {
+            taken[i] = true;
+        }
+        let games = candidates
+            .iter()
+            .map(|&i| SelectedGame {
+                path: entries[i].path.clone(),
+                seed: entries[i].seed,
+            })
+            .collect();
+        selected.insert(class, games);
+    }
+
+    Ok(BalancedManifest {
+        by: by.to_string(),
+        seed,
+        per_class,
+        classes: selected,
+    })
+}
+
+/// Games assigned to splits by [split].
+#[derive(Debug, Serialize)]
+pub struct SplitManifest {
+    pub by: String,
+    pub seed: u64,
+    pub fractions: BTreeMap<String, f64>,
+    pub splits: BTreeMap<String, Vec<SelectedGame>>,
+}
+
+/// Units a catalog entry is split by when splitting `by` "game" or "player". A game without any
+/// identifiable player is its own unit.
+fn units(entry: &Entry, by: &str) -> Result<Vec<String>, String> {
+    let game = || vec![format!("game:{}", entry.path)];
+    Ok(match by {
+        "game" => game(),
+        "player" => {
+            let keys: BTreeSet<_> = entry.players.iter().filter_map(|p| p.key()).map(|k| format!("player:{k}")).collect();
+            match keys.is_empty() {
+                true => game(),
+                false => keys.into_iter().collect(),
+            }
+        }
+        _ => return Err(format!("can't split by {by}")),
+    })
+}
+
+/// Union-find over the units of a split, joining the units of each game.
+struct Components(Vec<usize>);
+
+impl Components {
+    fn root(&mut self, mut i: usize) -> usize {
+        while self.0[i] != i {
+            self.0[i] = self.0[self.0[i]];
+            i = self.0[i];
+        }
+        i
+    }
+
+    fn join(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.root(a), self.root(b));
+        self.0[a.max(b)] = a.min(b);
+    }
+}
+
+/// The component of units every entry belongs to, named by its first unit by name.
+fn components<'a>(entry_units: &'a [Vec<String>]) -> Vec<&'a str> {
+    let mut index: BTreeMap<&str, usize> = BTreeMap::new();
+    for unit in entry_units.iter().flatten() {
+        let next = index.len();
+        index.entry(unit.as_str()).or_insert(next);
+    }
+    let mut components = Components((0..index.len()).collect());
+    for units in entry_units {
+        for pair in units.windows(2) {
+            components.join(index[pair[0].as_str()], index[pair[1].as_str()]);
+        }
+    }
+    // Units are visited by name, so the first one seen for a root is its component's name.
+    let mut names: BTreeMap<usize, &str> = BTreeMap::new();
+    for (&unit, &i) in &index {
+        names.entry(components.root(i)).or_insert(unit);
+    }
+    entry_units
+        .iter()
+        .map(|units| names[&components.root(index[units[0].as_str()])])
+        .collect()
+}
+
+/// Assign every game to one of the splits named in `fractions`, in proportion to their fractions.
+///
+/// Components of units (games, or players joined by the games between them) are taken in a
+/// seeded random order, each going to the split furthest below its share of the games assigned so
+/// far, weighted by how many games the component played.
+pub fn split(entries: &[Entry], fractions: &BTreeMap<String, f64>, by: &str, seed: u64) -> Result<SplitManifest, String> {
+    if fractions.values().any(|&f| !f.is_finite() || f < 0.0) || fractions.values().sum::<f64>() <= 0.0 {
+        return Err("fractions must be non-negative numbers with a positive sum".to_string());
+    }
+    let entry_units = entries.iter().map(|e| units(e, by)).collect::<Result<Vec<_>, _>>()?;
+    let entry_components = components(&entry_units);
+    let mut weights: BTreeMap<&str, usize> = BTreeMap::new();
+    for &component in &entry_components {
+        *weights.entry(component).or_default() += 1;
+    }
+
+    let mut order: Vec<(&str, usize)> = weights.into_iter().collect();
+    let mut rng = Rng::new(seed);
+    for i in 0..order.len() {
+        let j = i + rng.below(order.len() - i);
+        order.swap(i, j);
+    }
+    let total: f64 = fractions.values().sum();
+    let mut assigned: BTreeMap<&str, f64> = fractions.keys().map(|name| (name.as_str(), 0.0)).collect();
+    let mut split_of: BTreeMap<&str, &str> = BTreeMap::new();
+    for (component, weight) in order {
+        let placed: f64 = assigned.values().sum();
+        // Ties go to the first split by name, so a seed always gives the same assignment.
+        let (name, _) = fractions
+            .iter()
+            .map(|(name, f)| (name.as_str(), f / total * (placed + weight as f64) - assigned[name.as_str()]))
+            .fold(None, |best: Option<(&str, f64)>, (name, deficit)| match best {
+                Some((_, d)) if d >= deficit => best,
+                _ => Some((name, deficit)),
+            })
+            .unwrap();
+        *assigned.get_mut(name).unwrap() += weight as f64;
+        split_of.insert(component, name);
+    }
+
+    let mut splits: BTreeMap<String, Vec<SelectedGame>> = fractions.keys().map(|name| (name.clone(), Vec::new())).collect();
+    for (entry, component) in entries.iter().zip(entry_components) {
+        splits.get_mut(split_of[component]).unwrap().push(SelectedGame {
+            path: entry.path.clone(),
+            seed: entry.seed,
+        });
+    }
+
+    Ok(SplitManifest {
+        by: by.to_string(),
+        seed,
+        fractions: fractions.clone(),
+        splits,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::tests::entry;
+
+    fn fractions(pairs: &[(&str, f64)]) -> BTreeMap<String, f64> {
+        pairs.iter().map(|&(name, f)| (name.to_string(), f)).collect()
+    }
+
+    fn split_of<'a>(manifest: &'a SplitManifest, path: &str) -> &'a str {
+        manifest
+            .splits
+            .iter()
+            .find(|(_, games)| games.iter().any(|g| g.path == path))
+            .map(|(name, _)| name.as_str())
+            .unwrap()
+    }
+
+    #[test]
+    fn indices_are_distinct_and_sorted() {
+        let picked = indices(100, 10, 7);
+        assert_eq!(picked.len(), 10);
+        assert!(picked.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(picked, indices(100, 10, 7));
+        assert_eq!(indices(3, 10, 7), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn split_by_player_keeps_components_whole() {
+        // A#1 and B#2 are joined through C#3; D#4 and E#5 only play each other.
+        let entries = vec![
+            entry("1.slp", &["A#1", "C#3"]),
+            entry("2.slp", &["C#3", "B#2"]),
+            entry("3.slp", &["D#4", "E#5"]),
+            entry("4.slp", &["E#5", "D#4"]),
+            entry("5.slp", &["B#2", "A#1"]),
+        ];
+        let fractions = fractions(&[("train", 0.5), ("test", 0.5)]);
+        for seed in 0..20 {
+            let manifest = split(&entries, &fractions, "player", seed).unwrap();
+            assert_eq!(manifest.splits.values().map(Vec::len).sum::<usize>(), entries.len());
+            let first = split_of(&manifest, "1.slp");
+            assert_eq!(split_of(&manifest, "2.slp"), first);
+            assert_eq!(split_of(&manifest, "5.slp"), first);
+            assert_eq!(split_of(&manifest, "3.slp"), split_of(&manifest, "4.slp"));
+        }
+    }
+
+    #[test]
+    fn split_by_game_is_seeded() {
+        let entries: Vec<_> = (0..50).map(|i| entry(&format!("{i}.slp"), &["A#1", "B#2"])).collect();
+        let fractions = fractions(&[("train", 0.8), ("test", 0.2)]);
+        let a = split(&entries, &fractions, "game", 1).unwrap();
+        let b = split(&entries, &fractions, "game", 1).unwrap();
+        assert_eq!(a.splits["train"].len(), 40);
+        assert_eq!(a.splits["test"].len(), 10);
+        let paths = |m: &SplitManifest| m.splits["test"].iter().map(|g| g.path.clone()).collect::<Vec<_>>();
+        assert_eq!(paths(&a), paths(&b));
+    }
+
+    #[test]
+    fn split_rejects_bad_fractions_and_units() {
+        let entries = vec![entry("1.slp", &["A#1", "B#2"])];
+        assert!(split(&entries, &fractions(&[("train", -1.0)]), "game", 0).is_err());
+        assert!(split(&entries, &fractions(&[("train", 0.0)]), "game", 0).is_err());
+        assert!(split(&entries, &fractions(&[("train", f64::NAN)]), "game", 0).is_err());
+        assert!(split(&entries, &fractions(&[("train", 1.0)]), "stage", 0).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+//! Sandboxed parsing in a worker subprocess
+//!
+//! A web service embedding the library can't have one pathological upload crash or exhaust the
+//! memory of the Julia process serving everyone. In sandbox mode every replay is first parsed by a
+//! worker process (the `peppi-jl-worker` binary), with its address space capped and killed at the
+//! parse deadline, and only replays the worker parsed without incident are parsed in-process.
+//! Parsing is deterministic, so the second parse sees exactly what the worker did.
+//!
+//! The protocol is described in the worker's source: a JSON header line and the replay's bytes
+//! in, a JSON result line out.
+
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::errors::ReadError;
+use crate::limits::Limits;
+
+/// How often to check whether the worker has exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Sandbox {
+    /// Path of the worker executable
+    pub worker: PathBuf,
+    /// Cap on the worker's address space, in bytes (0 for none; Unix only)
+    pub max_memory: u64,
+}
+
+fn crashed(msg: String) -> ReadError {
+    ReadError::Invalid(format!("parser worker crashed: {msg}"))
+}
+
+impl Sandbox {
+    /// Parse `data` in a worker, returning the parser's error message if it rejected the replay.
+    pub fn parse(
+        &self,
+        data: &[u8],
+        skip_frames: bool,
+        limits: &Limits,
+    ) -> Result<Result<(), String>, ReadError> {
+        let mut command = Command::new(&self.worker);
+        command.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::null());
+        #[cfg(unix)]
+        if self.max_memory > 0 {
+            use std::os::unix::process::CommandExt;
+            let max_memory = self.max_memory as libc::rlim_t;
+            // Runs in the child between fork and exec, so it may only make async-signal-safe calls.
+            unsafe {
+                command.pre_exec(move || {
+                    let limit = libc::rlimit {
+                        rlim_cur: max_memory,
+                        rlim_max: max_memory,
+                    };
+                    match libc::setrlimit(libc::RLIMIT_AS, &limit) {
+                        0 => Ok(()),
+                        _ => Err(io::Error::last_os_error()),
+                    }
+                });
+            }
+        }
+        let started = Instant::now();
+        let deadline = (limits.timeout_ms > 0).then(|| Duration::from_millis(limits.timeout_ms));
+        let mut child = command.spawn().map_err(ReadError::Io)?;
+
+        // The replay is written from another thread, since a worker that stops reading would
+        // otherwise block us past the deadline. Killing the worker closes the pipe, ending the
+        // write. A worker that dies early closes it too; its exit status tells us why.
+        let stdin = child.stdin.take();
+        let status = thread::scope(|scope| {
+            if let Some(mut stdin) = stdin {
+                let header = json!({ "len": data.len(), "skip_frames": skip_frames });
+                scope.spawn(move || {
+                    let _ = writeln!(stdin, "{header}").and_then(|()| stdin.write_all(data));
+                });
+            }
+            loop {
+                if let Some(status) = child.try_wait().map_err(ReadError::Io)? {
+                    return Ok(status);
+                }
+                if deadline.is_some_and(|d| started.elapsed() > d) {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(ReadError::Limit(format!(
+                        "parsing took longer than {} ms",
+                        limits.timeout_ms
+                    )));
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+        })?;
+
+        let mut output = String::new();
+        if let Some(mut stdout) = child.stdout.take() {
+            stdout.read_to_string(&mut output).map_err(ReadError::Io)?;
+        }
+        let response: serde_json::Value = match serde_json::from_str(output.trim()) {
+            Ok(response) if status.success() => response,
+            _ => return Err(crashed(status.to_string())),
+        };
+        match response["ok"].as_bool() {
+            Some(true) => Ok(Ok(())),
+            _ => Ok(Err(response["error"].as_str().unwrap_or("unknown error").to_string())),
+        }
+    }
+}
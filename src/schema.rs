@@ -0,0 +1,113 @@
+//! Versions of the exported frame schema, and migrating files between them
+//!
+//! Converted libraries can hold thousands of Arrow and Parquet files, which are slow to regenerate
+//! from the replays (if the replays are still around at all). Every file is stamped with the
+//! version of the schema it was written with, under the [VERSION_KEY] of its schema's metadata, and
+//! when the schema changes (columns renamed, or new ones added as nulls for old files), a migration
+//! from the previous version is added here. Old files can then be upgraded in place.
+//!
+//! Files without a stamp predate it and are version 1.
+
+use std::path::Path;
+use std::{fs, io};
+
+use arrow2::array::{Array, StructArray, new_empty_array};
+use arrow2::compute::concatenate::concatenate;
+use arrow2::datatypes::Metadata;
+use arrow2::error::{Error, Result};
+use arrow2::io::ipc::read::{FileReader, read_file_metadata};
+
+use crate::{atomic, export, ipc, lock};
+
+/// Version of the schema files are written with.
+pub const VERSION: u32 = 2;
+/// Key of the schema version in a file's schema metadata.
+pub const VERSION_KEY: &str = "peppi_jl.schema_version";
+
+/// Upgrades the frames of a file to each version from the one before it, starting with version 2.
+const MIGRATIONS: [fn(StructArray) -> StructArray; 1] = [
+    // Version 2 only started stamping files with their version.
+    |frames| frames,
+];
+
+/// Schema metadata stamping a file with the current version.
+pub fn metadata() -> Metadata {
+    Metadata::from([(VERSION_KEY.to_string(), VERSION.to_string())])
+}
+
+/// Version a file with schema metadata `metadata` was written with.
+pub fn version(metadata: &Metadata) -> u32 {
+    metadata.get(VERSION_KEY).and_then(|v| v.parse().ok()).unwrap_or(1)
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// Upgrade frames written with version `from` to the current version.
+pub fn migrate(mut frames: StructArray, from: u32) -> StructArray {
+    for migration in MIGRATIONS.iter().skip(from.saturating_sub(1) as usize) {
+        frames = migration(frames);
+    }
+    frames
+}
+
+/// Upgrade the Arrow IPC or Parquet (by its `.parquet` extension) frames file at `path` in place
+/// to version `target`, returning the version it had. Only the current version can be targeted.
+pub fn migrate_file(path: &Path, target: u32) -> io::Result<u32> {
+    if target != VERSION {
+        return Err(invalid(format!("files can only be migrated to the current schema version ({VERSION})")));
+    }
+    let _lock = lock::FileLock::exclusive(path)?;
+    let mut file = fs::File::open(path)?;
+    let parquet = path.extension().is_some_and(|e| e == "parquet");
+    let (frames, metadata, parquet_options) = match parquet {
+        true => {
+            let (frames, metadata, compression, row_group_size) = export::parquet::read(&mut file).map_err(io::Error::other)?;
+            (frames, metadata, Some((compression, row_group_size)))
+        }
+        false => {
+            let (frames, metadata) = read_arrow(&mut file).map_err(io::Error::other)?;
+            (frames, metadata, None)
+        }
+    };
+    let from = version(&metadata);
+    if from > VERSION {
+        return Err(invalid(format!("file has schema version {from}, newer than this library's ({VERSION})")));
+    }
+    if from == VERSION {
+        return Ok(from);
+    }
+
+    let frames = migrate(frames, from);
+    // Anything else the file's metadata records, like its null policy, still holds.
+    let mut metadata = metadata;
+    metadata.extend(self::metadata());
+    atomic::write(path, |file| match parquet_options {
+        Some((compression, row_group_size)) => {
+            export::parquet::write(file, &frames, compression, row_group_size, metadata)
+        }
+        None => crate::write_frames_arrow(file, &frames, ipc::write_options(), metadata),
+    })
+    .map_err(io::Error::other)?;
+    Ok(from)
+}
+
+/// Read the frames of an Arrow IPC file with a single `frame` column, and its schema's metadata.
+fn read_arrow(file: &mut fs::File) -> Result<(StructArray, Metadata)> {
+    let metadata = read_file_metadata(file)?;
+    let schema = metadata.schema.clone();
+    let chunks = FileReader::new(file, metadata, None, None)
+        .map(|chunk| chunk.map(|c| c.into_arrays().remove(0)))
+        .collect::<Result<Vec<_>>>()?;
+    let frames = match (chunks.as_slice(), schema.fields.first()) {
+        ([], Some(field)) => new_empty_array(field.data_type.clone()),
+        ([], None) => return Err(Error::InvalidArgumentError("file has no frame column".to_string())),
+        (chunks, _) => concatenate(&chunks.iter().map(|c| c.as_ref()).collect::<Vec<_>>())?,
+    };
+    let frames = frames
+        .as_any()
+        .downcast_ref::<StructArray>()
+        .ok_or_else(|| Error::InvalidArgumentError("frame column isn't a struct".to_string()))?;
+    Ok((frames.clone(), schema.metadata))
+}
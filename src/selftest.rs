@@ -0,0 +1,262 @@
+//! Self-test against public test vectors
+//!
+//! Builds of the library for a new platform can differ in ways that only show up in the bytes they
+//! produce: float handling, endianness, or Arrow buffer layout. The self-test runs the code that
+//! produces bytes (stream splitting, UBJSON, content hashing, unit conversion, and Arrow IPC) on
+//! fixed inputs and checks the outputs exactly, so users can validate a build without any replays.
+//!
+//! Inputs are generated rather than shipped as files, except for one small golden replay compiled
+//! into the library (`tests/data/golden.slp`), which is parsed and exported end to end. The
+//! byte-level [vectors] are published so other implementations can check themselves against the
+//! same data.
+
+use std::io::Cursor;
+
+use arrow2::array::{Array, BooleanArray, PrimitiveArray, StructArray};
+use arrow2::io::ipc::read::{FileReader, read_file_metadata};
+use arrow2::io::ipc::write::WriteOptions;
+use peppi::game::Port;
+use peppi::io::slippi::Version;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::dataset::{from_hex, to_hex};
+use crate::live::ubjson::{self, Encode};
+use crate::units::{FacingUnits, PositionUnits, Units};
+use crate::{content, features, json, raw, schema, stages, units};
+
+/// An input and the exact output expected from it, as hex.
+#[derive(Debug, Serialize)]
+pub struct Vector {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub input: String,
+    pub expected: String,
+}
+
+/// Outcome of one check of the self-test.
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub passed: bool,
+    /// What differed, for failed checks
+    pub detail: Option<String>,
+}
+
+/// A made-up game's event stream: Event Payloads (with sizes made up for the test), Game Start, two
+/// Post-Frames (frames -123 and -122), and Game End. [stream] holds two of them back to back.
+const STREAM_GAME: &str = "350a360002380005390001\
+                           36aabb\
+                           38ffffff8501\
+                           38ffffff8602\
+                           3903";
+
+/// A Slippi 0.1.0 replay built to the replay spec: Fox (port 1) against Marth (port 2) on
+/// Battlefield for a single frame, with a `startAt` and `lastFrame` in its metadata.
+const GOLDEN: &[u8] = include_bytes!("../tests/data/golden.slp");
+
+fn stream() -> Vec<u8> {
+    from_hex(&STREAM_GAME.repeat(2)).expect("valid hex")
+}
+
+/// Event boundaries and frame numbers of each event of [stream], in order.
+fn stream_events() -> Vec<(usize, Option<i32>)> {
+    let game = [(11, None), (3, None), (6, Some(-123)), (6, Some(-122)), (2, None)];
+    game.iter().chain(game.iter()).copied().collect()
+}
+
+fn message() -> Encode<'static> {
+    Encode::Object(vec![
+        ("type", Encode::Int(1)),
+        ("ok", Encode::Bool(true)),
+        ("nick", Encode::Str("Wii")),
+        ("pos", Encode::Bytes(&[1, 2])),
+    ])
+}
+
+pub fn vectors() -> Vec<Vector> {
+    vec![
+        Vector {
+            name: "content_hash",
+            description: "SHA-256 of the SHA-256 of the input followed by the options JSON \
+                          {\"units\":\"game\"}",
+            input: to_hex(b"peppi"),
+            expected: "5e1d2480c09912d932c636266c94eaf70503c017f8949910c3eb4b97f1b1c808".to_string(),
+        },
+        Vector {
+            name: "ubjson_message",
+            description: "Console message {type: 1, ok: true, nick: \"Wii\", pos: [1, 2]} as UBJSON, \
+                          with pos a uint8 array",
+            input: json!({ "type": 1, "ok": true, "nick": "Wii", "pos": [1, 2] }).to_string(),
+            expected: "7b5504747970654c000000000000000155026f6b5455046e69636b5355035769695503706f73\
+                       5b2455236c0000000201027d"
+                .to_string(),
+        },
+        Vector {
+            name: "event_stream",
+            description: "Event stream of two games; expected is the size and frame number (or \
+                          null) of each event",
+            input: to_hex(&stream()),
+            expected: json::to_string(&stream_events()),
+        },
+    ]
+}
+
+fn check(name: &'static str, result: Result<(), String>) -> Check {
+    Check {
+        name,
+        passed: result.is_ok(),
+        detail: result.err(),
+    }
+}
+
+fn expect_eq<T: PartialEq + std::fmt::Debug>(what: &str, actual: T, expected: T) -> Result<(), String> {
+    match actual == expected {
+        true => Ok(()),
+        false => Err(format!("{what}: expected {expected:?}, got {actual:?}")),
+    }
+}
+
+/// Run every check, returning all outcomes (not just failures).
+pub fn run() -> Vec<Check> {
+    let vectors = vectors();
+    let expected = |name: &str| {
+        let vector = vectors.iter().find(|v| v.name == name).expect("vector exists");
+        vector.expected.clone()
+    };
+    vec![
+        check("content_hash", {
+            let hash = content::hash(b"peppi", &json!({ "units": "game" }));
+            expect_eq("hash", hash, expected("content_hash"))
+        }),
+        check("ubjson_message", ubjson_message(&expected("ubjson_message"))),
+        check("event_stream", event_stream()),
+        check("stage_units", stage_units()),
+        check("arrow_round_trip", arrow_round_trip()),
+        check("golden_replay", golden_replay()),
+    ]
+}
+
+fn ubjson_message(expected: &str) -> Result<(), String> {
+    let mut encoded = Vec::new();
+    ubjson::encode(&message(), &mut encoded);
+    expect_eq("encoding", to_hex(&encoded), expected.to_string())?;
+    let decoded = ubjson::decode(&encoded).map_err(|e| e.to_string())?;
+    let expected = json!({ "type": 1, "ok": true, "nick": "Wii", "pos": [1, 2] });
+    expect_eq("decoding", decoded, expected)
+}
+
+/// Split the stream in chunks of every size, which must always give the same events.
+fn event_stream() -> Result<(), String> {
+    let stream = stream();
+    for chunk_size in 1..=stream.len() {
+        let mut splitter = raw::Splitter::new();
+        let mut events = Vec::new();
+        for chunk in stream.chunks(chunk_size) {
+            events.extend(splitter.feed(chunk).map_err(|e| e.to_string())?);
+        }
+        expect_eq(&format!("chunks of {chunk_size}"), events.concat(), stream.clone())?;
+        let found: Vec<_> = events.iter().map(|e| (e.len(), raw::event_frame(e))).collect();
+        expect_eq(&format!("chunks of {chunk_size}"), found, stream_events())?;
+    }
+    Ok(())
+}
+
+/// Blast zones must map exactly onto -1 and 1, and facing onto booleans.
+fn stage_units() -> Result<(), String> {
+    let stage = 32;
+    let b = stages::geometry(stage).ok_or("no geometry for Final Destination")?.blast_zones;
+    let column = |values: Vec<f32>| PrimitiveArray::from_vec(values).boxed();
+    let position = features::struct_array(vec![
+        ("x".to_string(), column(vec![b.left, b.right])),
+        ("y".to_string(), column(vec![b.bottom, b.top])),
+    ])?;
+    let post = features::struct_array(vec![
+        ("position".to_string(), position.boxed()),
+        ("direction".to_string(), column(vec![-1.0, 1.0])),
+    ])?;
+    let frames = features::struct_array(vec![("post".to_string(), post.boxed())])?;
+    let units = Units {
+        positions: PositionUnits::Stage,
+        facing: FacingUnits::Bool,
+    };
+    let converted = units::apply(&frames, stage, units)?;
+
+    let leaves = crate::export::leaves(&converted);
+    let floats = |i: usize| -> Result<Vec<u32>, String> {
+        let array = leaves[i].1.as_any().downcast_ref::<PrimitiveArray<f32>>();
+        let array = array.ok_or_else(|| format!("{} isn't f32", leaves[i].0))?;
+        Ok(array.values().iter().map(|v| v.to_bits()).collect())
+    };
+    let bounds = vec![(-1.0f32).to_bits(), 1.0f32.to_bits()];
+    expect_eq("post.position.x", floats(0)?, bounds.clone())?;
+    expect_eq("post.position.y", floats(1)?, bounds)?;
+    let facing = leaves[2].1.as_any().downcast_ref::<BooleanArray>().ok_or("facing isn't boolean")?;
+    expect_eq("post.direction", facing.values_iter().collect::<Vec<_>>(), vec![false, true])
+}
+
+/// Writing frames to Arrow IPC, reading them back, and writing them again must give the same bytes,
+/// with float bit patterns (negative zero, NaN payloads, subnormals) and nulls preserved.
+fn arrow_round_trip() -> Result<(), String> {
+    let floats = [0.0, -0.0, f32::from_bits(0x7fc0_0001), f32::from_bits(1), f32::MAX, f32::MIN];
+    let ints = [Some(i32::MIN), None, Some(-1), Some(0), Some(1), Some(i32::MAX)];
+    let bools = [Some(true), Some(false), None, Some(true), None, Some(false)];
+    let frames = features::struct_array(vec![
+        ("f".to_string(), PrimitiveArray::from_slice(floats).boxed()),
+        ("i".to_string(), PrimitiveArray::from(ints).boxed()),
+        ("b".to_string(), BooleanArray::from(bools).boxed()),
+    ])?;
+    let write = |frames: &StructArray| -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+        let options = WriteOptions { compression: None };
+        crate::write_frames_arrow(&mut bytes, frames, options, schema::metadata()).map_err(|e| e.to_string())?;
+        Ok(bytes)
+    };
+    let written = write(&frames)?;
+    if !written.starts_with(b"ARROW1") || !written.ends_with(b"ARROW1") {
+        return Err("missing Arrow file magic".to_string());
+    }
+
+    let mut cursor = Cursor::new(&written);
+    let metadata = read_file_metadata(&mut cursor).map_err(|e| e.to_string())?;
+    let mut reader = FileReader::new(cursor, metadata, None, None);
+    let chunk = reader.next().ok_or("no record batch")?.map_err(|e| e.to_string())?;
+    let read = chunk.arrays()[0].as_any().downcast_ref::<StructArray>().ok_or("not a struct")?;
+
+    let bits = |frames: &StructArray| -> Option<Vec<u32>> {
+        let array = frames.values()[0].as_any().downcast_ref::<PrimitiveArray<f32>>()?;
+        Some(array.values().iter().map(|v| v.to_bits()).collect())
+    };
+    expect_eq("float bits", bits(read), bits(&frames))?;
+    expect_eq("nulls", read.values()[1].null_count() + read.values()[2].null_count(), 3)?;
+    expect_eq("rewritten bytes", write(read)?, written)
+}
+
+/// The golden replay must parse into exactly what it records, and
+/// export to the columns holding it.
+fn golden_replay() -> Result<(), String> {
+    let game = peppi::io::slippi::read(&mut Cursor::new(GOLDEN), None);
+    let game = game.map_err(|e| e.to_string())?;
+    let version = game.start.slippi.version;
+    expect_eq("version", version, Version(0, 1, 0))?;
+    expect_eq("stage", game.start.stage, 31)?;
+    let players: Vec<_> = game.start.players.iter().map(|p| (p.port, p.character)).collect();
+    expect_eq("players", players, vec![(Port::P1, 2), (Port::P2, 9)])?;
+    expect_eq("end method", game.end.as_ref().map(|e| e.method as u8), Some(2))?;
+    let metadata = game.metadata.as_ref().ok_or("no metadata")?;
+    expect_eq("startAt", metadata.get("startAt"), Some(&json!("2023-04-01T18:00:00Z")))?;
+
+    let occupancy = crate::port_occupancy(&game.start);
+    let frames = game.frames.into_struct_array(version, &occupancy);
+    let leaves = crate::export::leaves(&frames);
+    let column = |name: &str| leaves.iter().find(|(n, _)| n == name).map(|(_, a)| a.as_ref());
+    let ids = column("id").and_then(|a| a.as_any().downcast_ref::<PrimitiveArray<i32>>());
+    expect_eq("frame ids", ids.map(|a| a.values().to_vec()), Some(vec![-123]))?;
+    for (port, x) in [("P1", -38.8f32), ("P2", 38.8)] {
+        let name = format!("ports.{port}.leader.post.position.x");
+        let xs = column(&name).and_then(|a| a.as_any().downcast_ref::<PrimitiveArray<f32>>());
+        let bits = xs.map(|a| a.values().iter().map(|v| v.to_bits()).collect::<Vec<_>>());
+        expect_eq(&name, bits, Some(vec![x.to_bits()]))?;
+    }
+    Ok(())
+}
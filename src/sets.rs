@@ -0,0 +1,116 @@
+//! Inferring sets and bracket rounds from a catalog
+//!
+//! Tournament archives are a flat pile of games, while results are reported by set. Sets are
+//! inferred from who played and when: consecutive games between the same players on the same setup
+//! form a set, as long as each starts within a few minutes of the previous one ending and the set
+//! hasn't already reached five games. Start times are taken corrected for clock drift when they
+//! have been (see [clock](crate::clock)).
+//!
+//! Rounds are inferred from the order of sets: a set's round is one more than the number of sets
+//! the more experienced of its players had played before it, which matches bracket rounds in the
+//! winners side of a bracket and runs ahead of them in losers. A set's length suggests its format:
+//! up to three games is taken as best of 3 and four or five as best of 5, so a 3-0 in a best of 5
+//! is misread. The table is a starting point for a TO to correct, not a result.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::catalog::Entry;
+use crate::clock;
+
+/// Longest break between the end of a game and the start of the next in the same set, in seconds,
+/// unless told otherwise.
+pub const DEFAULT_MAX_GAP: f64 = 300.0;
+/// Most games a set can have.
+const MAX_GAMES: usize = 5;
+
+#[derive(Debug, Serialize)]
+pub struct Set {
+    /// Number of the set, from 1 in order of start
+    pub set: usize,
+    /// [Keys](crate::catalog::EntryPlayer::key) of the players, sorted
+    pub players: Vec<String>,
+    /// Console nickname of the setup
+    pub setup: Option<String>,
+    pub start_time: String,
+    pub end_time: String,
+    /// Paths of the set's games, in order
+    pub games: Vec<String>,
+    /// 3 or 5, from the number of games
+    pub best_of: u8,
+    pub round: usize,
+}
+
+/// A game placed in time, with who played it where.
+struct Game<'a> {
+    start: f64,
+    end: f64,
+    players: Vec<&'a str>,
+    setup: Option<&'a str>,
+    path: &'a str,
+}
+
+/// Group the games of a catalog into sets, allowing breaks of up to `max_gap` seconds between
+/// games. Games without a start time or identifiable players are left out.
+pub fn infer(entries: &[Entry], max_gap: f64) -> Vec<Set> {
+    let mut games: Vec<Game> = entries
+        .iter()
+        .filter_map(|e| {
+            let time = e.corrected_start_time.as_deref().or(e.start_time.as_deref())?;
+            let (start, end) = clock::span(time, e.last_frame)?;
+            let mut players: Vec<&str> = e.players.iter().filter_map(|p| p.key()).collect();
+            players.sort_unstable();
+            (!players.is_empty()).then_some(Game {
+                start,
+                end,
+                players,
+                setup: e.console_nick.as_deref(),
+                path: &e.path,
+            })
+        })
+        .collect();
+    games.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    // Sets as the games in them, and the set still open for each group of players on each setup
+    let mut grouped: Vec<Vec<&Game>> = Vec::new();
+    let mut open: HashMap<(&[&str], Option<&str>), usize> = HashMap::new();
+    for game in &games {
+        let key = (game.players.as_slice(), game.setup);
+        let continues = open.get(&key).copied().filter(|&i| {
+            let set = &grouped[i];
+            set.len() < MAX_GAMES && game.start - set[set.len() - 1].end <= max_gap
+        });
+        match continues {
+            Some(i) => grouped[i].push(game),
+            None => {
+                open.insert(key, grouped.len());
+                grouped.push(vec![game]);
+            }
+        }
+    }
+
+    let mut played: HashMap<&str, usize> = HashMap::new();
+    grouped
+        .into_iter()
+        .enumerate()
+        .map(|(i, set)| {
+            let (first, last) = (set[0], set[set.len() - 1]);
+            let before = first.players.iter().map(|p| played.get(p).copied().unwrap_or(0));
+            let round = before.max().unwrap_or(0) + 1;
+            for &p in &first.players {
+                *played.entry(p).or_default() += 1;
+            }
+            Set {
+                set: i + 1,
+                players: first.players.iter().map(|p| p.to_string()).collect(),
+                setup: first.setup.map(str::to_string),
+                start_time: clock::format(first.start),
+                end_time: clock::format(last.end),
+                games: set.iter().map(|g| g.path.to_string()).collect(),
+                best_of: if set.len() <= 3 { 3 } else { 5 },
+                round,
+            }
+        })
+        .collect()
+}
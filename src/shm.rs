@@ -0,0 +1,82 @@
+//! Named POSIX shared-memory segments
+//!
+//! Segments outlive the process that created them until they're unlinked, so a Julia worker can
+//! hand frames to others on the same machine by name alone. On Linux they're visible as files in
+//! `/dev/shm`, but live entirely in memory.
+
+use std::io;
+
+#[cfg(unix)]
+fn c_name(name: &str) -> io::Result<std::ffi::CString> {
+    if !name.starts_with('/') || name[1..].contains('/') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "shared memory names must start with, and not otherwise contain, '/'",
+        ));
+    }
+    std::ffi::CString::new(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+/// Create the segment `name` holding a copy of `bytes`, failing if it already exists.
+#[cfg(unix)]
+pub fn create(name: &str, bytes: &[u8]) -> io::Result<()> {
+    let name = c_name(name)?;
+    unsafe {
+        let fd = libc::shm_open(name.as_ptr(), libc::O_CREAT | libc::O_EXCL | libc::O_RDWR, 0o600);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let result = fill(fd, bytes);
+        libc::close(fd);
+        if result.is_err() {
+            libc::shm_unlink(name.as_ptr());
+        }
+        result
+    }
+}
+
+#[cfg(unix)]
+unsafe fn fill(fd: libc::c_int, bytes: &[u8]) -> io::Result<()> {
+    unsafe {
+        if libc::ftruncate(fd, bytes.len() as libc::off_t) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if bytes.is_empty() {
+            return Ok(());
+        }
+        let ptr = libc::mmap(
+            std::ptr::null_mut(),
+            bytes.len(),
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            fd,
+            0,
+        );
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr as *mut u8, bytes.len());
+        libc::munmap(ptr, bytes.len());
+        Ok(())
+    }
+}
+
+/// Remove the segment `name`; memory is freed once every process has unmapped it.
+#[cfg(unix)]
+pub fn unlink(name: &str) -> io::Result<()> {
+    let name = c_name(name)?;
+    match unsafe { libc::shm_unlink(name.as_ptr()) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn create(_name: &str, _bytes: &[u8]) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "shared memory requires a Unix platform"))
+}
+
+#[cfg(not(unix))]
+pub fn unlink(_name: &str) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "shared memory requires a Unix platform"))
+}
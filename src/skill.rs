@@ -0,0 +1,63 @@
+//! Skill brackets from player ratings
+//!
+//! Replays don't record ratings, so they come from a ratings file: a JSON object mapping connect
+//! codes to Slippi ranked ratings, kept up to date by whoever builds the dataset. Ratings map onto
+//! brackets using the thresholds of Slippi's ranked tiers.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::catalog::Entry;
+
+/// Lowest rating of each bracket, in ascending order.
+const BRACKETS: [(f64, &str); 6] = [
+    (f64::NEG_INFINITY, "Bronze"),
+    (1054.87, "Silver"),
+    (1435.48, "Gold"),
+    (1751.66, "Platinum"),
+    (2003.92, "Diamond"),
+    (2191.75, "Master"),
+];
+
+pub fn bracket(rating: f64) -> &'static str {
+    BRACKETS
+        .iter()
+        .rev()
+        .find(|(min, _)| rating >= *min)
+        .map_or(BRACKETS[0].1, |(_, name)| name)
+}
+
+/// Position of a bracket in ascending order of skill, for comparisons.
+pub fn rank(bracket: &str) -> Option<usize> {
+    BRACKETS.iter().position(|(_, name)| *name == bracket)
+}
+
+pub fn read_ratings(path: &Path) -> io::Result<HashMap<String, f64>> {
+    let file = io::BufReader::new(fs::File::open(path)?);
+    Ok(serde_json::from_reader(file)?)
+}
+
+/// Set the rating and bracket of every player whose connect code has a rating, returning the
+/// number of players annotated. Players without one have both cleared.
+pub fn annotate(entries: &mut [Entry], ratings: &HashMap<String, f64>) -> usize {
+    let mut annotated = 0;
+    for player in entries.iter_mut().flat_map(|e| e.players.iter_mut()) {
+        player.rating = player.code.as_ref().and_then(|c| ratings.get(c)).copied();
+        player.bracket = player.rating.map(|r| bracket(r).to_string());
+        annotated += player.rating.is_some() as usize;
+    }
+    annotated
+}
+
+/// The lowest bracket among a game's players, if all of them are rated.
+pub fn game_bracket(entry: &Entry) -> Option<&str> {
+    entry
+        .players
+        .iter()
+        .map(|p| p.bracket.as_deref())
+        .collect::<Option<Vec<_>>>()?
+        .into_iter()
+        .min_by_key(|b| rank(b))
+}
@@ -0,0 +1,70 @@
+//! Snapshots of a game at a single point in time
+//!
+//! Questions like "what was happening at 3:12" need one row of a few columns, not the whole frame
+//! table. A [Snapshot] picks out where every player was, their percent, stocks, and action state
+//! on one frame, straight from the game's in-memory frames.
+//!
+//! Times are in seconds since the first frame of the game (frame -123, before the countdown), as
+//! shown by a recording of the whole replay.
+
+use peppi::frame::immutable::Frame;
+use peppi::game::Port;
+use serde::Serialize;
+
+use crate::FIRST_FRAME;
+use crate::stalls::FPS;
+
+#[derive(Debug, Serialize)]
+pub struct Snapshot {
+    /// Frame index, as stored in the replay
+    pub frame: i32,
+    /// Seconds since the first frame
+    pub seconds: f64,
+    pub players: Vec<PlayerState>,
+}
+
+/// State of a port's leader (Nana isn't included).
+#[derive(Debug, Serialize)]
+pub struct PlayerState {
+    pub port: Port,
+    pub x: f32,
+    pub y: f32,
+    /// Facing direction (-1 left, 1 right)
+    pub direction: f32,
+    pub percent: f32,
+    pub stocks: u8,
+    /// Action state ID
+    pub state: u16,
+}
+
+/// Frame index shown `seconds` into a recording of the game.
+pub fn frame_at(seconds: f64) -> i32 {
+    FIRST_FRAME + (seconds * FPS).round() as i32
+}
+
+/// Snapshot of frame index `frame`, or `None` if the game doesn't have it.
+pub fn at(frames: &Frame, frame: i32) -> Option<Snapshot> {
+    // Rollback can store a frame index more than once; the last one stored is what was played.
+    let i = (0..frames.id.len()).rev().find(|&i| frames.id.value(i) == frame)?;
+    let players = frames
+        .ports
+        .iter()
+        .map(|port| {
+            let post = &port.leader.post;
+            PlayerState {
+                port: port.port,
+                x: post.position.x.value(i),
+                y: post.position.y.value(i),
+                direction: post.direction.value(i),
+                percent: post.percent.value(i),
+                stocks: post.stocks.value(i),
+                state: post.state.value(i),
+            }
+        })
+        .collect();
+    Some(Snapshot {
+        frame,
+        seconds: (frame - FIRST_FRAME) as f64 / FPS,
+        players,
+    })
+}
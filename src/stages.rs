@@ -0,0 +1,110 @@
+//! Stage geometry
+//!
+//! Measurements of the tournament-legal stages, in game units. Stages outside this table (and the
+//! moving parts of legal ones, like Fountain of Dreams' side platforms and Pokémon Stadium's
+//! transformations) aren't modeled, so features derived from geometry are only exact on the static
+//! parts of legal stages.
+
+/// Coordinates past which a character is KO'd.
+#[derive(Debug, Clone, Copy)]
+pub struct BlastZones {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+/// A pass-through platform, spanning `left` to `right` at height `y`.
+#[derive(Debug, Clone, Copy)]
+pub struct Platform {
+    pub left: f32,
+    pub right: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Geometry {
+    pub blast_zones: BlastZones,
+    /// X coordinate of the right ledge; the main stage spans `-ledge..=ledge` at height 0
+    pub ledge: f32,
+    pub platforms: &'static [Platform],
+}
+
+impl Geometry {
+    /// Distance from `(x, y)` to the nearer of the two ledges.
+    pub fn ledge_distance(&self, x: f32, y: f32) -> f32 {
+        (self.ledge - x.abs()).hypot(y)
+    }
+
+    /// Height of `(x, y)` above the highest surface (main stage or platform) below it, if any.
+    pub fn height_above_surface(&self, x: f32, y: f32) -> Option<f32> {
+        let ground = Platform {
+            left: -self.ledge,
+            right: self.ledge,
+            y: 0.0,
+        };
+        std::iter::once(&ground)
+            .chain(self.platforms)
+            // Characters standing on a surface are very slightly below it.
+            .filter(|p| (p.left..=p.right).contains(&x) && p.y <= y + SURFACE_TOLERANCE)
+            .map(|p| y - p.y)
+            .min_by(f32::total_cmp)
+    }
+}
+
+/// How far below a surface a character can be while still counting as above it.
+const SURFACE_TOLERANCE: f32 = 0.01;
+
+const fn platform(left: f32, right: f32, y: f32) -> Platform {
+    Platform { left, right, y }
+}
+
+/// Geometry of a stage by ID, if it's tournament-legal.
+pub fn geometry(stage: u16) -> Option<Geometry> {
+    let blast_zones = |left, right, top, bottom| BlastZones {
+        left,
+        right,
+        top,
+        bottom,
+    };
+    let (blast_zones, ledge, platforms): (_, _, &'static [Platform]) = match stage {
+        // Fountain of Dreams, whose side platforms move; these are their resting heights
+        2 => (
+            blast_zones(-198.75, 198.75, 202.5, -146.25),
+            63.35,
+            &[platform(-49.5, -21.0, 16.125), platform(21.0, 49.5, 21.375), platform(-14.25, 14.25, 42.75)],
+        ),
+        // Pokémon Stadium
+        3 => (
+            blast_zones(-230.0, 230.0, 180.0, -111.0),
+            87.75,
+            &[platform(-55.0, -25.0, 25.0), platform(25.0, 55.0, 25.0)],
+        ),
+        // Yoshi's Story
+        8 => (
+            blast_zones(-175.7, 173.6, 168.0, -91.0),
+            56.0,
+            &[platform(-59.5, -28.0, 23.45), platform(28.0, 59.5, 23.45), platform(-15.75, 15.75, 42.0)],
+        ),
+        // Dream Land N64
+        28 => (
+            blast_zones(-255.0, 255.0, 250.0, -123.0),
+            77.27,
+            &[platform(-61.39, -31.73, 30.14), platform(31.7, 63.08, 30.24), platform(-19.02, 19.02, 51.43)],
+        ),
+        // Battlefield
+        31 => (
+            blast_zones(-224.0, 224.0, 200.0, -108.8),
+            68.4,
+            &[platform(-57.6, -20.0, 27.2), platform(20.0, 57.6, 27.2), platform(-18.8, 18.8, 54.4)],
+        ),
+        // Final Destination
+        32 => (blast_zones(-246.0, 246.0, 188.0, -140.0), 85.5657, &[]),
+        _ => return None,
+    };
+    Some(Geometry {
+        blast_zones,
+        ledge,
+        platforms,
+    })
+}
@@ -0,0 +1,81 @@
+//! Stretches of a game where nothing is happening
+//!
+//! Rate statistics like APM divide by the length of the game, which overstates how long a game
+//! was played when it contains stretches of no play: a player stalling on a ledge or standing in
+//! shield, both players handwarming without engaging, or controllers left untouched. Such a stall
+//! is a run of frames on which a player doesn't move, at least a chosen length, and is detected
+//! for each player on their own: one player camping a ledge stalls while the other paces the stage.
+//! Stalls where the player's inputs don't change either (a controller unplugged, or its player
+//! walking away) are reported as `frozen`, the rest as `idle`.
+//!
+//! Only frames from frame 0 (when players can first move) are considered, since everyone is idle
+//! during the countdown.
+
+use peppi::frame::immutable::{Frame, Pre};
+use peppi::game::Port;
+use serde::Serialize;
+
+/// Melee runs at 60 frames per second.
+pub const FPS: f64 = 60.0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Stall {
+    pub port: Port,
+    /// First and last frame index (as stored in the replay) of the stall
+    pub start: i32,
+    pub end: i32,
+    pub frames: usize,
+    /// `frozen` (the player's inputs didn't change either) or `idle`
+    pub kind: &'static str,
+}
+
+fn inputs_changed(pre: &Pre, i: usize) -> bool {
+    pre.buttons_physical.value(i) != pre.buttons_physical.value(i - 1)
+        || pre.joystick.x.value(i) != pre.joystick.x.value(i - 1)
+        || pre.joystick.y.value(i) != pre.joystick.y.value(i - 1)
+        || pre.cstick.x.value(i) != pre.cstick.x.value(i - 1)
+        || pre.cstick.y.value(i) != pre.cstick.y.value(i - 1)
+        || pre.triggers.value(i) != pre.triggers.value(i - 1)
+}
+
+/// Stalls of at least `min_frames` frames of every player, in frame order (by port among stalls
+/// starting on the same frame).
+pub fn detect(frames: &Frame, min_frames: usize) -> Vec<Stall> {
+    let mut stalls = Vec::new();
+    let min_frames = min_frames.max(1);
+    for p in &frames.ports {
+        // Start of the current run of rows without movement, and whether any inputs changed in it
+        let mut run: Option<(usize, bool)> = None;
+        let close = |run: Option<(usize, bool)>, end: usize, stalls: &mut Vec<Stall>| {
+            if let Some((start, changed)) = run.filter(|(start, _)| end - start >= min_frames) {
+                stalls.push(Stall {
+                    port: p.port,
+                    start: frames.id.value(start),
+                    end: frames.id.value(end - 1),
+                    frames: end - start,
+                    kind: if changed { "idle" } else { "frozen" },
+                });
+            }
+        };
+        let post = &p.leader.post;
+        for i in 1..frames.id.len() {
+            if frames.id.value(i) <= 0 {
+                continue;
+            }
+            let still = post.position.x.value(i) == post.position.x.value(i - 1)
+                && post.position.y.value(i) == post.position.y.value(i - 1);
+            if still {
+                let changed = inputs_changed(&p.leader.pre, i);
+                run = Some(match run {
+                    Some((start, c)) => (start, c || changed),
+                    None => (i, changed),
+                });
+            } else {
+                close(run.take(), i, &mut stalls);
+            }
+        }
+        close(run, frames.id.len(), &mut stalls);
+    }
+    stalls.sort_by_key(|s| s.start);
+    stalls
+}
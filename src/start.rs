@@ -0,0 +1,131 @@
+//! Typed access to a game's Game Start block
+//!
+//! `get_start` hands Julia the start block as JSON, which has to be parsed again on every access
+//! and pulls in a JSON dependency for a handful of numbers. A [StartInfo] holds the same fields as
+//! an opaque Julia object with one getter each. Fields a replay is too old to have are returned as
+//! `false`, `0`, or an empty string.
+//!
+//! Online games (from Slippi 3.14.0) record the match they were part of, which is what sets of
+//! ranked and unranked games are reconstructed from: the match ID, shared by every game of the
+//! match, the game's number within it, and its tiebreak number. The match ID's prefix also gives
+//! the mode the match was played in.
+
+use jlrs::{
+    data::managed::string::{JuliaString, StringRet},
+    prelude::*,
+    weak_handle_unchecked,
+};
+use peppi::game::Start;
+
+/// Modes a match can be played in, by the prefix of its match ID.
+const MODES: [(&str, &str); 4] = [
+    ("mode.ranked-", "ranked"),
+    ("mode.unranked-", "unranked"),
+    ("mode.direct-", "direct"),
+    ("mode.teams-", "teams"),
+];
+
+/// Mode of the match with `match_id`: "ranked", "unranked", "direct", or "teams".
+pub fn mode(match_id: &str) -> Option<&'static str> {
+    MODES.iter().find(|(prefix, _)| match_id.starts_with(prefix)).map(|(_, mode)| *mode)
+}
+
+/// The Game Start block of a game, exposed to Julia.
+#[derive(OpaqueType)]
+pub struct StartInfo {
+    start: Start,
+}
+
+impl StartInfo {
+    pub fn new(start: Start) -> Self {
+        StartInfo { start }
+    }
+
+    /// Stage ID
+    pub fn stage(&self) -> u16 {
+        self.start.stage
+    }
+
+    pub fn is_teams(&self) -> bool {
+        self.start.is_teams
+    }
+
+    /// Starting value of the game timer, as stored
+    pub fn timer(&self) -> u32 {
+        self.start.timer
+    }
+
+    pub fn random_seed(&self) -> u32 {
+        self.start.random_seed
+    }
+
+    pub fn damage_ratio(&self) -> f32 {
+        self.start.damage_ratio
+    }
+
+    pub fn item_spawn_frequency(&self) -> i8 {
+        self.start.item_spawn_frequency
+    }
+
+    pub fn self_destruct_score(&self) -> i8 {
+        self.start.self_destruct_score
+    }
+
+    pub fn is_raining_bombs(&self) -> bool {
+        self.start.is_raining_bombs
+    }
+
+    /// Whether the game was played on PAL (from Slippi 1.5.0)
+    pub fn is_pal(&self) -> bool {
+        self.start.is_pal.unwrap_or(false)
+    }
+
+    /// Whether Pokémon Stadium was frozen (from Slippi 2.0.0)
+    pub fn is_frozen_ps(&self) -> bool {
+        self.start.is_frozen_ps.unwrap_or(false)
+    }
+
+    /// Major, minor, and revision numbers of the Slippi version that recorded the game
+    pub fn version_major(&self) -> u8 {
+        self.start.slippi.version.0
+    }
+
+    pub fn version_minor(&self) -> u8 {
+        self.start.slippi.version.1
+    }
+
+    pub fn version_revision(&self) -> u8 {
+        self.start.slippi.version.2
+    }
+
+    /// Number of players in the game
+    pub fn num_players(&self) -> usize {
+        self.start.players.len()
+    }
+
+    /// ID of the match the game was part of, e.g. `mode.ranked-2024-01-01T00:00:00.00-0` (from
+    /// Slippi 3.14.0)
+    pub fn match_id(&self) -> StringRet {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let id = self.start.r#match.as_ref().map_or("", |m| m.id.as_str());
+        JuliaString::new(handle, id).leak()
+    }
+
+    /// Mode of the match from its ID: "ranked", "unranked", "direct", or "teams" (empty for offline
+    /// games and unknown modes)
+    pub fn match_mode(&self) -> StringRet {
+        let handle = unsafe { weak_handle_unchecked!() };
+        let mode = self.start.r#match.as_ref().and_then(|m| mode(&m.id));
+        JuliaString::new(handle, mode.unwrap_or("")).leak()
+    }
+
+    /// Number of the game within its match, from 1 (from Slippi 3.14.0)
+    pub fn game_number(&self) -> u32 {
+        self.start.r#match.as_ref().map_or(0, |m| m.game)
+    }
+
+    /// Number of the tiebreak game within its match (from Slippi 3.14.0)
+    pub fn tiebreak_number(&self) -> u32 {
+        self.start.r#match.as_ref().map_or(0, |m| m.tiebreaker)
+    }
+}
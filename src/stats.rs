@@ -0,0 +1,114 @@
+//! Per-player rate statistics
+//!
+//! - Actions: inputs made, counted as in Slippi's own stats: each newly pressed button, each time
+//!   a stick moves into a new region, and each time a trigger is pressed past the point it clicks
+//! - Openings: hits that start a punish, meaning the victim hadn't been hit for a while before
+//!
+//! Both are reported per minute of game time from frame 0. When stalls are excluded, the minutes
+//! of each player's [stalls](crate::stalls) of at least the given length are left out of that
+//! player's denominator, and the stretches left out are listed with the statistics.
+
+use peppi::frame::immutable::{Frame, Pre};
+use peppi::game::Port;
+use serde::Serialize;
+
+use crate::hits::Hit;
+use crate::stalls::{self, FPS, Stall};
+
+/// Frames a victim must go without being hit for the next hit to start a new punish.
+const PUNISH_GAP: i32 = 45;
+/// Stick distance from center inside which it counts as neutral.
+const DEADZONE: f32 = 0.2875;
+/// Analog trigger value past which a press counts as an action.
+const TRIGGER_PRESS: f32 = 0.3;
+
+#[derive(Debug, Serialize)]
+pub struct PlayerStats {
+    pub port: Port,
+    /// Frames of the player's stalls, left out of their per-minute denominators
+    pub excluded_frames: usize,
+    pub actions: usize,
+    pub actions_per_minute: f64,
+    pub openings: usize,
+    pub openings_per_minute: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    /// Frames of game time (from frame 0)
+    pub frames: usize,
+    /// Stalls left out, of every player
+    pub excluded: Vec<Stall>,
+    pub players: Vec<PlayerStats>,
+}
+
+/// Region of a stick: 0 for neutral, otherwise 1 through 8 counter-clockwise from right.
+fn region(x: f32, y: f32) -> u8 {
+    if x.hypot(y) < DEADZONE {
+        return 0;
+    }
+    let angle = y.atan2(x).to_degrees().rem_euclid(360.0);
+    ((angle + 22.5) / 45.0) as u8 % 8 + 1
+}
+
+/// Whether a stick moved into a new region (other than neutral).
+fn moved(now: u8, before: u8) -> usize {
+    (now != 0 && now != before) as usize
+}
+
+/// Actions on row `i`, against row `i - 1`.
+fn actions_on(pre: &Pre, i: usize) -> usize {
+    let pressed = pre.buttons_physical.value(i) & !pre.buttons_physical.value(i - 1);
+    let joystick = |j| region(pre.joystick.x.value(j), pre.joystick.y.value(j));
+    let cstick = |j| region(pre.cstick.x.value(j), pre.cstick.y.value(j));
+    let triggers = [&pre.triggers_physical.l, &pre.triggers_physical.r]
+        .iter()
+        .filter(|t| t.value(i) >= TRIGGER_PRESS && t.value(i - 1) < TRIGGER_PRESS)
+        .count();
+    pressed.count_ones() as usize + moved(joystick(i), joystick(i - 1)) + moved(cstick(i), cstick(i - 1)) + triggers
+}
+
+/// Statistics of a game from its frames and hits, leaving out stalls of at least `stall_frames`
+/// frames (none when 0).
+pub fn compute(frames: &Frame, hits: &[Hit], stall_frames: usize) -> Stats {
+    let first = (0..frames.id.len()).find(|&i| frames.id.value(i) >= 0).unwrap_or(frames.id.len());
+    let played = frames.id.len() - first;
+    let excluded = match stall_frames {
+        0 => Vec::new(),
+        n => stalls::detect(frames, n),
+    };
+
+    let players = frames
+        .ports
+        .iter()
+        .map(|p| {
+            let excluded_frames: usize = excluded.iter().filter(|s| s.port == p.port).map(|s| s.frames).sum();
+            let minutes = played.saturating_sub(excluded_frames) as f64 / FPS / 60.0;
+            let per_minute = |n: usize| if minutes > 0.0 { n as f64 / minutes } else { 0.0 };
+            let actions: usize = (first.max(1)..frames.id.len()).map(|i| actions_on(&p.leader.pre, i)).sum();
+            // Punishes are tracked per victim; an opening is credited to whoever starts one.
+            let mut openings = 0;
+            for victim in frames.ports.iter().filter(|v| v.port != p.port) {
+                let mut last_hit: Option<i32> = None;
+                for hit in hits.iter().filter(|h| h.victim == victim.port && !h.phantom) {
+                    let new = last_hit.is_none_or(|f| hit.frame - f > PUNISH_GAP);
+                    openings += (new && hit.attacker == Some(p.port)) as usize;
+                    last_hit = Some(hit.frame);
+                }
+            }
+            PlayerStats {
+                port: p.port,
+                excluded_frames,
+                actions,
+                actions_per_minute: per_minute(actions),
+                openings,
+                openings_per_minute: per_minute(openings),
+            }
+        })
+        .collect();
+    Stats {
+        frames: played,
+        excluded,
+        players,
+    }
+}
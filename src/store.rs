@@ -0,0 +1,325 @@
+//! Feature store: incremental materialization of feature sets over a catalog
+//!
+//! A feature set declares which per-game artifacts to compute (flattened frames with unit and
+//! derived-feature options, hit and grab tables). Materializing it over a catalog writes each
+//! game's artifacts under `<store>/<name>/`, named by the game's seed (which comes from the
+//! replay's contents), and skips games whose artifacts already exist.
+//!
+//! Artifact names also carry a hash of everything that determines their contents: the options
+//! that apply to them and the version of the code computing them (e.g. [hits::VERSION]). Changing
+//! either gives new names, so only the affected artifacts are recomputed. Stale artifacts stay
+//! until the set is [invalidate]d.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use peppi::frame::immutable::Frame;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::catalog::{Entry, Filter};
+use crate::errors::{self, FileError};
+use crate::nulls::NullPolicy;
+use crate::units::{self, Units};
+use crate::{
+    atomic, content, features, grabs, hits, ipc, json, lock, metrics, profiles, sample, schema,
+};
+
+/// Suffixes of the artifacts of each game.
+const FRAMES: &str = "frames.arrow";
+const HITS: &str = "hits.json";
+const GRABS: &str = "grabs.json";
+
+/// Declaration of the artifacts to compute for every game.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeatureSet {
+    pub name: String,
+    /// Write the frames as an Arrow IPC file
+    pub frames: bool,
+    pub units: Units,
+    pub stage_features: bool,
+    pub relative_features: bool,
+    /// Patterns of the frame columns to keep (all of them when `None`), as in
+    /// [profiles::select]
+    pub columns: Option<Vec<String>>,
+    /// Write the hit table as JSON
+    pub hits: bool,
+    /// Write the grab table as JSON
+    pub grabs: bool,
+    /// Games to materialize the set for (all of them by default)
+    pub filter: Filter,
+}
+
+impl Default for FeatureSet {
+    fn default() -> Self {
+        FeatureSet {
+            name: String::new(),
+            frames: true,
+            units: Units::default(),
+            stage_features: false,
+            relative_features: false,
+            columns: None,
+            hits: false,
+            grabs: false,
+            filter: Filter::default(),
+        }
+    }
+}
+
+impl FeatureSet {
+    fn dir(&self, store: &Path) -> io::Result<PathBuf> {
+        if self.name.is_empty() || self.name.contains(['/', '\\']) || self.name.starts_with('.') {
+            return Err(invalid(format!("invalid feature set name: {:?}", self.name)));
+        }
+        Ok(store.join(&self.name))
+    }
+
+    /// Hash of everything determining the contents of artifacts of `kind`.
+    fn definition_hash(&self, kind: &str) -> String {
+        let definition = match kind {
+            FRAMES => content::frame_options(
+                self.units,
+                self.stage_features,
+                self.relative_features,
+                None,
+                NullPolicy::Null,
+                self.columns.as_deref(),
+            ),
+            HITS => serde_json::json!({ "hits_version": hits::VERSION }),
+            _ => serde_json::json!({ "grabs_version": grabs::VERSION }),
+        };
+        let digest = Sha256::digest(definition.to_string().as_bytes());
+        digest[..8].iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    fn artifact(&self, dir: &Path, seed: u64, kind: &str) -> PathBuf {
+        dir.join(format!("{seed:016x}.{}.{kind}", self.definition_hash(kind)))
+    }
+
+    /// Paths of the artifacts of the game with `seed`.
+    fn artifacts(&self, dir: &Path, seed: u64) -> Vec<PathBuf> {
+        let kinds = [(self.frames, FRAMES), (self.hits, HITS), (self.grabs, GRABS)];
+        kinds
+            .into_iter()
+            .filter(|(enabled, _)| *enabled)
+            .map(|(_, kind)| self.artifact(dir, seed, kind))
+            .collect()
+    }
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Compute the artifacts of `set` for every matching catalog entry that doesn't have them yet,
+/// returning the number of games computed. Games that fail to process are skipped, and reported in
+/// the errors file of the set's directory.
+pub fn materialize(store: &Path, set: &FeatureSet, entries: &[Entry]) -> io::Result<usize> {
+    let dir = set.dir(store)?;
+    let _lock = lock::FileLock::exclusive(&dir)?;
+    fs::create_dir_all(&dir)?;
+
+    let mut computed = 0;
+    let mut failed = Vec::new();
+    for entry in entries.iter().filter(|e| set.filter.matches(e)) {
+        let stored = is_stored(set, &dir, entry);
+        metrics::record_cache(stored);
+        if stored {
+            continue;
+        }
+        match compute(set, entry, Some(&dir))? {
+            Ok(_) => computed += 1,
+            Err(e) => {
+                metrics::record_error();
+                failed.push(e);
+            }
+        }
+    }
+    errors::write(&dir, &failed)?;
+    Ok(computed)
+}
+
+/// Entries matching the set's filter whose artifacts aren't all stored in `dir`.
+fn pending<'a>(
+    set: &'a FeatureSet,
+    dir: &'a Path,
+    entries: &'a [Entry],
+) -> impl Iterator<Item = &'a Entry> {
+    entries
+        .iter()
+        .filter(move |entry| set.filter.matches(entry) && !is_stored(set, dir, entry))
+}
+
+fn is_stored(set: &FeatureSet, dir: &Path, entry: &Entry) -> bool {
+    set.artifacts(dir, entry.seed).iter().all(|p| p.exists())
+}
+
+/// Writer only counting the bytes written to it, for measuring artifacts without storing them.
+#[derive(Default)]
+struct Counter(u64);
+
+impl Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Write an artifact with `f` to `path`, or only measure it for a dry run (no `path`), returning
+/// its size in bytes.
+fn write_artifact(
+    path: Option<PathBuf>,
+    f: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> io::Result<u64> {
+    match path {
+        Some(path) => {
+            atomic::write(&path, |file| {
+                let mut w = io::BufWriter::new(file);
+                f(&mut w)?;
+                w.flush()
+            })?;
+            Ok(fs::metadata(&path)?.len())
+        }
+        None => {
+            let mut counter = Counter::default();
+            f(&mut counter)?;
+            Ok(counter.0)
+        }
+    }
+}
+
+/// Compute the artifacts of one game and write them to `dir`, returning their size in bytes.
+/// Without a `dir` it's a dry run: the artifacts are only measured, and nothing is written or
+/// quarantined.
+///
+/// Failing to write is an error, while failing to process the game is reported in the inner result.
+fn compute(
+    set: &FeatureSet,
+    entry: &Entry,
+    dir: Option<&Path>,
+) -> io::Result<Result<u64, FileError>> {
+    let path = Path::new(&entry.path);
+    let started = Instant::now();
+    let artifact = |kind| dir.map(|dir| set.artifact(dir, entry.seed, kind));
+    let mut bytes = 0;
+    let (data, game) = match errors::read_replay(path, None, dir.is_none()) {
+        Ok(read) => read,
+        Err(e) => return Ok(Err(e)),
+    };
+    let version = game.start.slippi.version;
+    let stage = game.start.stage;
+    let frames = game
+        .frames
+        .into_struct_array(version, &crate::port_occupancy(&game.start));
+    let len = frames.len();
+
+    if set.frames {
+        // Stage-relative units need the stage's geometry, so such games can't be in this set.
+        let mut exported = match units::apply(&frames, stage, set.units) {
+            Ok(exported) => exported,
+            Err(e) => return Ok(Err(FileError::unsupported(path, e))),
+        };
+        if set.stage_features || set.relative_features {
+            let raw = Frame::from_struct_array(frames.clone(), version);
+            exported = match features::append(
+                &exported,
+                &raw,
+                stage,
+                set.stage_features,
+                set.relative_features,
+            ) {
+                Ok(exported) => exported,
+                Err(e) => return Ok(Err(FileError::unsupported(path, e))),
+            };
+        }
+        if let Some(columns) = &set.columns {
+            exported = match profiles::select(&exported, columns) {
+                Ok(exported) => exported,
+                Err(e) => return Ok(Err(FileError::unsupported(path, e))),
+            };
+        }
+        bytes += write_artifact(artifact(FRAMES), |w| {
+            crate::write_frames_arrow(w, &exported, ipc::write_options(), schema::metadata())
+                .map_err(|e| invalid(e.to_string()))
+        })?;
+    }
+    if set.hits || set.grabs {
+        let raw = Frame::from_struct_array(frames, version);
+        if set.hits {
+            let hits = hits::detect(&raw);
+            bytes += write_artifact(artifact(HITS), |w| json::to_writer(w, &hits, false))?;
+        }
+        if set.grabs {
+            let grabs = grabs::detect(&raw);
+            bytes += write_artifact(artifact(GRABS), |w| json::to_writer(w, &grabs, false))?;
+        }
+    }
+    metrics::record_game(data.len(), len, started.elapsed());
+    Ok(Ok(bytes))
+}
+
+/// What materializing a feature set would do, as reported by [estimate].
+#[derive(Debug, Serialize)]
+pub struct Estimate {
+    /// Catalog entries matching the set's filter
+    pub matched: usize,
+    /// Matching entries without stored artifacts, which would be computed
+    pub pending: usize,
+    /// Pending entries computed to make the estimate
+    pub sampled: usize,
+    /// Estimated size of the artifacts to compute, in bytes
+    pub bytes: u64,
+    /// Estimated time to compute them, in seconds
+    pub seconds: f64,
+}
+
+/// Estimate the work of materializing `set` without writing anything, by computing up to `sample`
+/// randomly chosen pending games in memory and extrapolating.
+pub fn estimate(store: &Path, set: &FeatureSet, entries: &[Entry], sample: usize) -> io::Result<Estimate> {
+    let dir = set.dir(store)?;
+    let matched = entries.iter().filter(|e| set.filter.matches(e)).count();
+    let pending: Vec<&Entry> = pending(set, &dir, entries).collect();
+
+    let started = Instant::now();
+    let mut sampled = 0;
+    let mut sampled_bytes = 0;
+    for i in sample::indices(pending.len(), sample, 0) {
+        if let Ok(bytes) = compute(set, pending[i], None)? {
+            sampled += 1;
+            sampled_bytes += bytes;
+        }
+    }
+    let elapsed = started.elapsed().as_secs_f64();
+
+    let scale = match sampled {
+        0 => 0.0,
+        n => pending.len() as f64 / n as f64,
+    };
+    Ok(Estimate {
+        matched,
+        pending: pending.len(),
+        sampled,
+        bytes: (sampled_bytes as f64 * scale) as u64,
+        seconds: elapsed * scale,
+    })
+}
+
+/// Remove every stored artifact of `set`, whatever definition it was computed with, returning the
+/// number of files removed.
+pub fn invalidate(store: &Path, set: &FeatureSet) -> io::Result<usize> {
+    let dir = set.dir(store)?;
+    let _lock = lock::FileLock::exclusive(&dir)?;
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let removed = fs::read_dir(&dir)?.count();
+    fs::remove_dir_all(&dir)?;
+    Ok(removed)
+}
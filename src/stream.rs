@@ -0,0 +1,263 @@
+//! Reading a replay one event at a time
+//!
+//! Reading a game builds its whole frame table, which is wasted on a filter that only needs the
+//! characters from Game Start, or the first few seconds of frames. An event stream reads the
+//! replay's event stream from the file as it's pulled, decoding Game Start, Pre-Frame, Post-Frame,
+//! Item Update, and Game End events into their fields (other events keep only their frame number,
+//! if any). Julia pulls events in batches and can close the stream at any point, and whatever
+//! wasn't pulled is never read.
+//!
+//! Fields are decoded at their offsets in the Slippi spec, and a field past the end of an event
+//! (because the replay predates it) is `null`. Ports are 0-based, as stored.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use serde_json::{Value, json};
+
+use crate::raw::{self, Splitter};
+
+/// Bytes read from the file at a time.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Events decoded into their fields, and read when no types are asked for.
+const DECODED: [u8; 5] = [raw::GAME_START, raw::PRE_FRAME, raw::POST_FRAME, raw::ITEM_UPDATE, raw::GAME_END];
+
+struct EventStream {
+    file: fs::File,
+    /// Bytes of the `raw` element not read yet (unbounded for an unfinished recording)
+    remaining: u64,
+    splitter: Splitter,
+    /// Commands of the events returned
+    commands: Vec<u8>,
+    pending: VecDeque<Value>,
+    /// After Game End, or the end of the `raw` element
+    finished: bool,
+}
+
+fn streams() -> &'static Mutex<HashMap<u64, EventStream>> {
+    static STREAMS: OnceLock<Mutex<HashMap<u64, EventStream>>> = OnceLock::new();
+    STREAMS.get_or_init(Default::default)
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// A field of an event, `None` past its end.
+fn field<const N: usize>(event: &[u8], offset: usize) -> Option<[u8; N]> {
+    event.get(offset..offset + N)?.try_into().ok()
+}
+
+fn u8_at(event: &[u8], offset: usize) -> Option<u8> {
+    event.get(offset).copied()
+}
+
+fn i8_at(event: &[u8], offset: usize) -> Option<i8> {
+    u8_at(event, offset).map(|b| b as i8)
+}
+
+fn bool_at(event: &[u8], offset: usize) -> Option<bool> {
+    u8_at(event, offset).map(|b| b != 0)
+}
+
+fn u16_at(event: &[u8], offset: usize) -> Option<u16> {
+    field(event, offset).map(u16::from_be_bytes)
+}
+
+fn u32_at(event: &[u8], offset: usize) -> Option<u32> {
+    field(event, offset).map(u32::from_be_bytes)
+}
+
+fn f32_at(event: &[u8], offset: usize) -> Option<f32> {
+    field(event, offset).map(f32::from_be_bytes)
+}
+
+/// Fields of an event (starting with its command byte), with its `type`.
+fn decode(event: &[u8]) -> Value {
+    let e = event;
+    let name = raw::event_name(e[0]);
+    match e[0] {
+        raw::GAME_START => {
+            let players: Vec<Value> = (0..4)
+                .map(|i| {
+                    let at = 0x65 + 0x24 * i;
+                    json!({
+                        "port": i,
+                        "character": u8_at(e, at),
+                        "type": u8_at(e, at + 0x1),
+                        "stocks": u8_at(e, at + 0x2),
+                        "costume": u8_at(e, at + 0x3),
+                        "team": u8_at(e, at + 0x9),
+                    })
+                })
+                // Type 3 is an empty port.
+                .filter(|p| p["type"] != 3)
+                .collect();
+            json!({
+                "type": name,
+                "version": field::<3>(e, 0x1),
+                "is_teams": bool_at(e, 0xD),
+                "stage": u16_at(e, 0x13),
+                "players": players,
+                "random_seed": u32_at(e, 0x13D),
+                "is_pal": bool_at(e, 0x1A1),
+                "is_frozen_ps": bool_at(e, 0x1A2),
+            })
+        }
+        raw::PRE_FRAME => json!({
+            "type": name,
+            "frame": raw::event_frame(e),
+            "port": u8_at(e, 0x5),
+            "follower": bool_at(e, 0x6),
+            "random_seed": u32_at(e, 0x7),
+            "state": u16_at(e, 0xB),
+            "x": f32_at(e, 0xD),
+            "y": f32_at(e, 0x11),
+            "direction": f32_at(e, 0x15),
+            "joystick_x": f32_at(e, 0x19),
+            "joystick_y": f32_at(e, 0x1D),
+            "cstick_x": f32_at(e, 0x21),
+            "cstick_y": f32_at(e, 0x25),
+            "trigger": f32_at(e, 0x29),
+            "buttons": u32_at(e, 0x2D),
+            "buttons_physical": u16_at(e, 0x31),
+            "trigger_physical_l": f32_at(e, 0x33),
+            "trigger_physical_r": f32_at(e, 0x37),
+            "raw_analog_x": i8_at(e, 0x3B),
+            "percent": f32_at(e, 0x3C),
+        }),
+        raw::POST_FRAME => json!({
+            "type": name,
+            "frame": raw::event_frame(e),
+            "port": u8_at(e, 0x5),
+            "follower": bool_at(e, 0x6),
+            "character": u8_at(e, 0x7),
+            "state": u16_at(e, 0x8),
+            "x": f32_at(e, 0xA),
+            "y": f32_at(e, 0xE),
+            "direction": f32_at(e, 0x12),
+            "percent": f32_at(e, 0x16),
+            "shield": f32_at(e, 0x1A),
+            "last_attack_landed": u8_at(e, 0x1E),
+            "combo_count": u8_at(e, 0x1F),
+            "last_hit_by": u8_at(e, 0x20),
+            "stocks": u8_at(e, 0x21),
+            "state_age": f32_at(e, 0x22),
+            "airborne": bool_at(e, 0x2F),
+        }),
+        raw::ITEM_UPDATE => json!({
+            "type": name,
+            "frame": raw::event_frame(e),
+            "item_type": u16_at(e, 0x5),
+            "state": u8_at(e, 0x7),
+            "direction": f32_at(e, 0x8),
+            "velocity_x": f32_at(e, 0xC),
+            "velocity_y": f32_at(e, 0x10),
+            "x": f32_at(e, 0x14),
+            "y": f32_at(e, 0x18),
+            "damage": u16_at(e, 0x1C),
+            "timer": f32_at(e, 0x1E),
+            "spawn_id": u32_at(e, 0x22),
+            "owner": i8_at(e, 0x2A),
+        }),
+        raw::GAME_END => json!({
+            "type": name,
+            "method": u8_at(e, 0x1),
+            "lras_initiator": i8_at(e, 0x2),
+            "placements": field::<4>(e, 0x3),
+        }),
+        _ => json!({ "type": name, "frame": raw::event_frame(e) }),
+    }
+}
+
+/// Open the replay at `path` to pull its events of the given [types](raw::event_name) (the decoded
+/// ones when empty) from, returning the stream's ID.
+pub fn open(path: &Path, types: &[&str]) -> io::Result<u64> {
+    let commands: Vec<u8> = match types {
+        [] => DECODED.to_vec(),
+        types => {
+            let mut commands = Vec::new();
+            for t in types {
+                let command = raw::KNOWN_EVENTS.iter().find(|&&c| raw::event_name(c) == *t);
+                commands.push(*command.ok_or_else(|| invalid(format!("unknown event type: {t}")))?);
+            }
+            commands
+        }
+    };
+    let mut file = fs::File::open(path)?;
+    let mut header = [0; raw::RAW_START];
+    file.read_exact(&mut header)?;
+    let Some(len) = header.strip_prefix(raw::RAW_HEADER) else {
+        return Err(invalid("missing `raw` element".to_string()));
+    };
+    // Replays still being recorded have a length of 0.
+    let remaining = match u32::from_be_bytes(len.try_into().unwrap()) {
+        0 => u64::MAX,
+        n => n as u64,
+    };
+
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let stream = EventStream {
+        file,
+        remaining,
+        splitter: Splitter::new(),
+        commands,
+        pending: VecDeque::new(),
+        finished: false,
+    };
+    streams().lock().unwrap().insert(id, stream);
+    Ok(id)
+}
+
+impl EventStream {
+    /// Read the next chunk of the stream, queueing the events it completes.
+    fn read_chunk(&mut self) -> io::Result<()> {
+        let mut chunk = vec![0; CHUNK_SIZE.min(self.remaining.try_into().unwrap_or(usize::MAX))];
+        let n = self.file.read(&mut chunk)?;
+        if n == 0 {
+            self.finished = true;
+            return Ok(());
+        }
+        self.remaining = self.remaining.saturating_sub(n as u64);
+        for event in self.splitter.feed(&chunk[..n])? {
+            if self.commands.contains(&event[0]) {
+                self.pending.push_back(decode(&event));
+            }
+            if event[0] == raw::GAME_END {
+                self.finished = true;
+                break;
+            }
+        }
+        if self.remaining == 0 {
+            self.finished = true;
+        }
+        Ok(())
+    }
+
+    /// Take up to `max` events (all that are left when 0).
+    fn take(&mut self, max: usize) -> io::Result<Vec<Value>> {
+        let max = if max == 0 { usize::MAX } else { max };
+        while self.pending.len() < max && !self.finished {
+            self.read_chunk()?;
+        }
+        let n = self.pending.len().min(max);
+        Ok(self.pending.drain(..n).collect())
+    }
+}
+
+/// Pull up to `max` events (all that are left when 0) from stream `id`; none once it's exhausted.
+pub fn next(id: u64, max: usize) -> Option<io::Result<Vec<Value>>> {
+    // Streams are read by one Julia task at a time, so holding the lock while reading is fine.
+    Some(streams().lock().unwrap().get_mut(&id)?.take(max))
+}
+
+/// Close stream `id`, returning whether it was open.
+pub fn close(id: u64) -> bool {
+    streams().lock().unwrap().remove(&id).is_some()
+}
@@ -0,0 +1,95 @@
+//! Curation tags on catalog entries
+//!
+//! Tags ("money-match", "bad-netcode", "tournament:genesis-9") mark games for whatever curation a
+//! dataset needs. They're kept in the catalog with everything else known about a game, so every
+//! tool reading the catalog sees the same tags, and filters can select on them.
+//!
+//! Games are named by the hex SHA-256 of their file (a game's `get_source_hash`), which catalog
+//! copies of the same replay share, or by path for entries from catalogs built before hashes were
+//! recorded.
+
+use std::collections::BTreeMap;
+
+use crate::catalog::Entry;
+
+fn matches(entry: &Entry, game: &str) -> bool {
+    entry.path == game || (!entry.hash.is_empty() && entry.hash == game)
+}
+
+/// Tag the entries of `game` with `tag`, returning the number of entries newly tagged.
+pub fn add(entries: &mut [Entry], game: &str, tag: &str) -> usize {
+    let mut added = 0;
+    for entry in entries.iter_mut().filter(|e| matches(e, game)) {
+        if let Err(i) = entry.tags.binary_search_by(|t| t.as_str().cmp(tag)) {
+            entry.tags.insert(i, tag.to_string());
+            added += 1;
+        }
+    }
+    added
+}
+
+/// Remove `tag` from the entries of `game` (every tag if none is given), returning the number of
+/// tags removed.
+pub fn remove(entries: &mut [Entry], game: &str, tag: Option<&str>) -> usize {
+    let mut removed = 0;
+    for entry in entries.iter_mut().filter(|e| matches(e, game)) {
+        let before = entry.tags.len();
+        entry.tags.retain(|t| tag.is_some_and(|tag| t != tag));
+        removed += before - entry.tags.len();
+    }
+    removed
+}
+
+/// The entries tagged with `tag`.
+pub fn tagged<'a>(entries: &'a [Entry], tag: &str) -> Vec<&'a Entry> {
+    entries.iter().filter(|e| e.tags.iter().any(|t| t == tag)).collect()
+}
+
+/// Every tag in use, with the number of entries having it.
+pub fn counts(entries: &[Entry]) -> BTreeMap<&str, usize> {
+    let mut counts = BTreeMap::new();
+    for tag in entries.iter().flat_map(|e| &e.tags) {
+        *counts.entry(tag.as_str()).or_default() += 1;
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::tests::entry;
+
+    fn entries() -> Vec<Entry> {
+        let mut copy = entry("a/1.slp", &["A#1", "B#2"]);
+        copy.hash = "ab".repeat(32);
+        let mut other = copy.clone();
+        other.path = "b/1.slp".to_string();
+        vec![copy, other, entry("2.slp", &["C#3"])]
+    }
+
+    #[test]
+    fn tags_copies_by_hash_and_games_by_path() {
+        let mut entries = entries();
+        assert_eq!(add(&mut entries, &"ab".repeat(32), "money-match"), 2);
+        assert_eq!(add(&mut entries, &"ab".repeat(32), "money-match"), 0);
+        assert_eq!(add(&mut entries, "2.slp", "bad-netcode"), 1);
+        assert_eq!(add(&mut entries, "a/1.slp", "bad-netcode"), 1);
+        // An empty hash names nothing, rather than every entry without one.
+        assert_eq!(add(&mut entries, "", "x"), 0);
+        assert_eq!(tagged(&entries, "bad-netcode").len(), 2);
+        assert_eq!(counts(&entries), BTreeMap::from([("bad-netcode", 2), ("money-match", 2)]));
+    }
+
+    #[test]
+    fn removes_one_tag_or_all() {
+        let mut entries = entries();
+        add(&mut entries, "a/1.slp", "b");
+        add(&mut entries, "a/1.slp", "a");
+        assert_eq!(entries[0].tags, ["a", "b"]);
+        assert_eq!(remove(&mut entries, "a/1.slp", Some("a")), 1);
+        assert_eq!(remove(&mut entries, "a/1.slp", Some("a")), 0);
+        add(&mut entries, "a/1.slp", "c");
+        assert_eq!(remove(&mut entries, "a/1.slp", None), 2);
+        assert!(entries[0].tags.is_empty());
+    }
+}
@@ -0,0 +1,51 @@
+//! Events near a point in a game, for timeline scrubbing
+//!
+//! A timeline UI scrubbing through a replay wants the events around the current frame, not every
+//! event of the game. Events are detected as for [window exports](crate::windows), so the same
+//! kinds are available under the same names.
+
+use peppi::frame::immutable::Frame;
+use peppi::game::Port;
+use serde::Serialize;
+
+use crate::windows;
+
+/// Every kind of event that can be searched for.
+pub const KINDS: [&str; 4] = ["hit", "grab", "death", "tech"];
+
+#[derive(Debug, Serialize)]
+pub struct Event {
+    pub kind: &'static str,
+    /// Frame index, as stored in the replay
+    pub frame: i32,
+    /// Player the event happened to
+    pub port: Port,
+    /// Frames from the searched frame to the event (negative for events before it)
+    pub offset: i32,
+}
+
+/// Events of the given kinds (all of them when `None`) within `radius` frames of `frame`, ordered
+/// by frame and then by port.
+pub fn near(frames: &Frame, frame: i32, radius: u32, kinds: Option<&[String]>) -> Result<Vec<Event>, String> {
+    let kinds: Vec<&str> = match kinds {
+        Some(kinds) => kinds.iter().map(String::as_str).collect(),
+        None => KINDS.to_vec(),
+    };
+    let mut events = Vec::new();
+    for kind in kinds {
+        let kind = *KINDS.iter().find(|k| **k == kind).ok_or_else(|| format!("unknown event: {kind}"))?;
+        for anchor in windows::anchors(frames, kind)? {
+            let offset = anchor.frame - frame;
+            if offset.unsigned_abs() <= radius {
+                events.push(Event {
+                    kind,
+                    frame: anchor.frame,
+                    port: anchor.port,
+                    offset,
+                });
+            }
+        }
+    }
+    events.sort_by_key(|e| (e.frame, e.port as u8));
+    Ok(events)
+}
@@ -0,0 +1,235 @@
+//! Converting directories of replays into archive formats
+//!
+//! Large archives are parsed once and analyzed many times, so it pays to keep them in a format that
+//! reads faster than `.slp`: peppi's own `.slpp`, or the frames alone as Arrow IPC or Parquet. A
+//! transcode walks a source directory, converting every `.slp` file below it into the same relative
+//! path below the destination, with the new format's extension.
+//!
+//! The destination keeps a `transcode.json` recording the [content hash](crate::content) each file
+//! was converted from (including the format and options), so running a transcode again only
+//! converts the replays that are new or changed since, or whose output went missing.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use peppi::game::immutable::Game as SlippiGame;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::errors::{FileError, ReadError};
+use crate::export::parquet;
+use crate::{atomic, batch, config, content, ipc, limits, lock, metrics};
+
+/// Record of the files converted into a destination, kept in it.
+pub const MANIFEST: &str = "transcode.json";
+
+/// Format replays are converted into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// peppi's own replay format
+    Slpp,
+    /// Frames as an Arrow IPC file, as `read_slippi` writes them
+    Arrow,
+    /// Frames as a Parquet file
+    Parquet,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" | "slpp" => Ok(Format::Slpp),
+            "arrow" => Ok(Format::Arrow),
+            "parquet" => Ok(Format::Parquet),
+            _ => Err(format!("unknown transcode format: {s}")),
+        }
+    }
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Slpp => "slpp",
+            Format::Arrow => "arrow",
+            Format::Parquet => "parquet",
+        }
+    }
+}
+
+/// Outcome of a transcode.
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub converted: usize,
+    /// Files already converted with the same options
+    pub skipped: usize,
+    pub failed: Vec<FileError>,
+}
+
+/// Content hashes of the converted files, by path relative to the source directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    files: BTreeMap<String, String>,
+}
+
+fn read_manifest(path: &Path) -> io::Result<Manifest> {
+    match fs::read(path) {
+        Ok(data) => Ok(serde_json::from_slice(&data)?),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Manifest::default()),
+        Err(e) => Err(e),
+    }
+}
+
+/// How converted files are written, with their compression.
+#[derive(Clone, Copy)]
+enum Writer {
+    Slpp(ipc::Compression),
+    Arrow(ipc::Compression),
+    Parquet(parquet::Compression),
+}
+
+impl Writer {
+    fn new(format: Format, compression: &str) -> Result<Self, String> {
+        Ok(match format {
+            Format::Slpp => Writer::Slpp(compression.parse()?),
+            Format::Arrow => Writer::Arrow(compression.parse()?),
+            Format::Parquet => Writer::Parquet(compression.parse()?),
+        })
+    }
+}
+
+/// What happened to one file.
+enum Outcome {
+    Converted(String),
+    Skipped,
+    Failed(FileError),
+}
+
+/// Path of a replay below `src` as recorded in the manifest, with `/` separators.
+fn key(src: &Path, path: &Path) -> String {
+    path.strip_prefix(src).unwrap_or(path).to_string_lossy().replace('\\', "/")
+}
+
+/// Convert every `.slp` file below `src` into `format` below `dst`, compressed with `compression`
+/// (as named for Arrow IPC or Parquet files; `.slpp` files take the Arrow IPC names), on `threads`
+/// threads (as many as configured for batch work when 0).
+pub fn dir(src: &Path, dst: &Path, format: Format, compression: &str, threads: usize) -> io::Result<Summary> {
+    // Parse the compression up front, so a bad name fails before any file is converted.
+    let writer = Writer::new(format, compression).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let config = config::get();
+    let mut options = match format {
+        Format::Slpp => json!({}),
+        _ => content::frame_options(
+            config.units,
+            config.stage_features,
+            config.relative_features,
+            config.win_probability.as_ref(),
+            config.null_policy,
+            config.columns.as_deref(),
+        ),
+    };
+    options["format"] = json!(format);
+    options["compression"] = json!(compression);
+
+    fs::create_dir_all(dst)?;
+    let manifest_path = dst.join(MANIFEST);
+    let previous = read_manifest(&manifest_path)?;
+    let paths = batch::paths(src, true, "*.slp")?;
+    let threads = if threads == 0 { config.threads } else { threads };
+
+    let outcomes = batch::map(&paths, threads, |path| {
+        let output = dst.join(path.strip_prefix(src).unwrap_or(path)).with_extension(format.extension());
+        let data = match limits::read(path, &config.limits).and_then(|data| {
+            limits::check(&data, &config.limits)?;
+            Ok(data)
+        }) {
+            Ok(data) => data,
+            Err(e) => return Outcome::Failed(FileError::read(path, &e)),
+        };
+        let hash = content::hash(&data, &options);
+        if previous.files.get(&key(src, path)) == Some(&hash) && output.exists() {
+            return Outcome::Skipped;
+        }
+        match convert(&data, &output, writer) {
+            Ok(()) => Outcome::Converted(hash),
+            Err(e @ (ReadError::Truncated(_) | ReadError::Invalid(_))) => Outcome::Failed(FileError::parse(path, &data, e)),
+            Err(e) => Outcome::Failed(FileError::read(path, &e)),
+        }
+    });
+
+    let mut summary = Summary {
+        converted: 0,
+        skipped: 0,
+        failed: Vec::new(),
+    };
+    let mut converted = Vec::new();
+    for (path, outcome) in paths.iter().zip(outcomes) {
+        match outcome {
+            Outcome::Converted(hash) => {
+                converted.push((key(src, path), hash));
+                summary.converted += 1;
+            }
+            Outcome::Skipped => summary.skipped += 1,
+            Outcome::Failed(e) => {
+                metrics::record_error();
+                summary.failed.push(e);
+            }
+        }
+    }
+
+    // Re-read the manifest under its lock, so concurrent transcodes into `dst` don't drop each
+    // other's records.
+    let _lock = lock::FileLock::exclusive(&manifest_path)?;
+    let mut manifest = read_manifest(&manifest_path)?;
+    manifest.files.extend(converted);
+    atomic::write_json(&manifest_path, &manifest, true)?;
+    Ok(summary)
+}
+
+/// Parse a replay and write it to `output` with `writer`.
+fn convert(data: &[u8], output: &Path, writer: Writer) -> Result<(), ReadError> {
+    let game = peppi::io::slippi::read(&mut io::Cursor::new(data), None)
+        .map_err(|e| ReadError::parse(data, e))?;
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent).map_err(ReadError::Io)?;
+    }
+    let arrow_error = |e: arrow2::error::Error| ReadError::Io(io::Error::other(e));
+    // Arrow and Parquet files hold the frames alone, exported as `read_slippi` exports them.
+    let frames = |game: SlippiGame| {
+        let version = game.start.slippi.version;
+        let frames = game.frames.into_struct_array(version, &crate::port_occupancy(&game.start));
+        crate::export_frames(&frames, version, game.start.stage)
+            .map(|(frames, _)| frames)
+            .map_err(|e| ReadError::Io(io::Error::new(io::ErrorKind::InvalidInput, e)))
+    };
+    match writer {
+        Writer::Slpp(compression) => {
+            let opts = peppi::io::peppi::ser::Opts {
+                compression: ipc::options(compression).compression,
+            };
+            atomic::write(output, |file| {
+                let mut w = io::BufWriter::new(file);
+                peppi::io::peppi::write(&mut w, game, Some(&opts)).map_err(io::Error::other)?;
+                w.flush()
+            })
+            .map_err(ReadError::Io)
+        }
+        Writer::Arrow(compression) => {
+            let frames = frames(game)?;
+            let metadata = crate::export_metadata();
+            atomic::write(output, |file| {
+                crate::write_frames_arrow(file, &frames, ipc::options(compression), metadata)
+            })
+            .map_err(arrow_error)
+        }
+        Writer::Parquet(compression) => {
+            let frames = frames(game)?;
+            let metadata = crate::export_metadata();
+            atomic::write(output, |file| parquet::write(file, &frames, compression, 0, metadata)).map_err(arrow_error)
+        }
+    }
+}
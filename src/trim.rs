@@ -0,0 +1,133 @@
+//! Cutting a replay down to a window of frames
+//!
+//! Clips of a combo, or a recording with the handwarming cut off the front, are still `.slp` files
+//! that Dolphin and every parser can open. Trimming rewrites the event stream keeping everything
+//! before the first frame (Event Payloads, Game Start, Gecko codes), the events of the frames in
+//! the window (including non-frame events recorded among them), and Game End. The metadata is
+//! patched to match: `lastFrame` becomes the window's last frame, and `startAt` moves forward by
+//! the frames cut from the front.
+
+use std::collections::BTreeSet;
+use std::io;
+
+use crate::{FIRST_FRAME, clock, raw};
+
+/// Overwrite the 32-bit integer value of `key` in a UBJSON object, if it's stored as one.
+fn patch_int(tail: &mut [u8], key: &str, value: i32) {
+    let mut needle = vec![b'U', key.len() as u8];
+    needle.extend_from_slice(key.as_bytes());
+    needle.push(b'l');
+    if let Some(at) = tail.windows(needle.len()).position(|w| w == needle.as_slice()) {
+        let at = at + needle.len();
+        if let Some(slot) = tail.get_mut(at..at + 4) {
+            slot.copy_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+/// Number of digits of fractional seconds in an ISO-8601 timestamp.
+fn fraction_digits(timestamp: &str) -> usize {
+    let fraction = timestamp.get(19..).and_then(|t| t.strip_prefix('.')).unwrap_or("");
+    fraction.bytes().take_while(u8::is_ascii_digit).count()
+}
+
+/// Move the timestamp stored as the string value of `key` in a UBJSON object forward by `seconds`,
+/// keeping its fractional digits, if the new one is as long (so the rest of the file keeps its
+/// layout). Timestamps with a UTC offset come out in UTC, so those are left as they were.
+fn patch_time(tail: &mut [u8], key: &str, seconds: f64) {
+    let mut needle = vec![b'U', key.len() as u8];
+    needle.extend_from_slice(key.as_bytes());
+    needle.extend_from_slice(b"SU");
+    let Some(at) = tail.windows(needle.len()).position(|w| w == needle.as_slice()) else {
+        return;
+    };
+    let at = at + needle.len();
+    let Some(&len) = tail.get(at) else {
+        return;
+    };
+    let range = at + 1..at + 1 + len as usize;
+    let time = tail.get(range.clone()).and_then(|t| std::str::from_utf8(t).ok());
+    let parsed = time.and_then(|t| Some((fraction_digits(t), clock::parse(t)?)));
+    let Some((digits, time)) = parsed else {
+        return;
+    };
+    let moved = clock::format_fraction(time + seconds, digits);
+    if moved.len() == len as usize {
+        tail[range].copy_from_slice(moved.as_bytes());
+    }
+}
+
+/// Rewrite a `.slp` file keeping only frames `first` through `last`, returning the new file's bytes
+/// and the number of frames kept.
+pub fn trim(data: &[u8], first: i32, last: i32) -> io::Result<(Vec<u8>, usize)> {
+    if first > last {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("last frame {last} is before first frame {first}"),
+        ));
+    }
+    let container = raw::parse(data)?;
+    let events = raw::events(container.raw)?;
+
+    let mut stream = Vec::with_capacity(container.raw.len());
+    let mut frames = BTreeSet::new();
+    // Frame of the latest frame event, which non-frame events among the frames belong to
+    let mut current = None;
+    for event in &events {
+        let bytes = &container.raw[event.offset..event.offset + event.size];
+        if let Some(frame) = raw::event_frame(bytes) {
+            current = Some(frame);
+        }
+        let keep = match current {
+            _ if event.command == raw::GAME_END => true,
+            None => true,
+            Some(frame) => (first..=last).contains(&frame),
+        };
+        if keep {
+            stream.extend_from_slice(bytes);
+            // Rollback stores a frame more than once; each is counted once.
+            frames.extend(current.filter(|_| event.command != raw::GAME_END));
+        }
+    }
+    let kept = frames.len();
+
+    let mut tail = container.tail.to_vec();
+    if let Some(&end) = frames.last() {
+        patch_int(&mut tail, "lastFrame", end);
+    }
+    let cut = frames.first().map_or(0, |&start| start - FIRST_FRAME);
+    if cut > 0 {
+        patch_time(&mut tail, "startAt", cut as f64 / 60.0);
+    }
+
+    let mut out = Vec::with_capacity(data.len());
+    raw::write(&mut out, &stream, &tail)?;
+    Ok((out, kept))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tail(time: &str) -> Vec<u8> {
+        let mut tail = b"U\x07startAtSU".to_vec();
+        tail.push(time.len() as u8);
+        tail.extend_from_slice(time.as_bytes());
+        tail
+    }
+
+    #[test]
+    fn moves_start_times_keeping_their_precision() {
+        let mut whole = tail("2023-04-01T18:00:00Z");
+        patch_time(&mut whole, "startAt", 90.0);
+        assert_eq!(whole, tail("2023-04-01T18:01:30Z"));
+
+        let mut fractional = tail("2023-04-01T18:00:00.250Z");
+        patch_time(&mut fractional, "startAt", 1.5);
+        assert_eq!(fractional, tail("2023-04-01T18:00:01.750Z"));
+
+        let mut offset = tail("2023-04-01T20:00:00+02:00");
+        patch_time(&mut offset, "startAt", 1.0);
+        assert_eq!(offset, tail("2023-04-01T20:00:00+02:00"));
+    }
+}
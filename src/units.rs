@@ -0,0 +1,147 @@
+//! Unit normalization of exported frames
+//!
+//! Raw frames keep Melee's own representations, which suit replay analysis but make models
+//! sensitive to the stage played on. The [Units] option converts them during export, so every game
+//! in a dataset shares a representation. Percent is always `f32`, whichever version recorded it.
+
+use std::str::FromStr;
+
+use arrow2::array::{Array, BooleanArray, PrimitiveArray, StructArray};
+use arrow2::compute::arity::unary;
+use arrow2::datatypes::{DataType, Field};
+use serde::{Deserialize, Serialize};
+
+use crate::stages;
+
+/// Version of the unit conversions, bumped whenever a change alters converted values.
+pub const VERSION: u32 = 1;
+
+/// How character positions are represented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PositionUnits {
+    /// Game units, as recorded
+    Game,
+    /// Scaled so the stage's blast zones lie at -1 and 1 on each axis
+    Stage,
+}
+
+/// How facing direction is represented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FacingUnits {
+    /// -1 for left and 1 for right, as recorded
+    Sign,
+    /// `true` when facing right
+    Bool,
+}
+
+impl FromStr for PositionUnits {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "game" => Ok(PositionUnits::Game),
+            "stage" => Ok(PositionUnits::Stage),
+            _ => Err(format!("unknown position units: {s}")),
+        }
+    }
+}
+
+impl FromStr for FacingUnits {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sign" => Ok(FacingUnits::Sign),
+            "bool" => Ok(FacingUnits::Bool),
+            _ => Err(format!("unknown facing units: {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Units {
+    pub positions: PositionUnits,
+    pub facing: FacingUnits,
+}
+
+impl Default for Units {
+    fn default() -> Self {
+        Units {
+            positions: PositionUnits::Game,
+            facing: FacingUnits::Sign,
+        }
+    }
+}
+
+/// Convert the frames of a game on `stage` to `units`.
+///
+/// Fails for stage-relative positions on a stage without known geometry.
+pub fn apply(frames: &StructArray, stage: u16, units: Units) -> Result<StructArray, String> {
+    convert(frames, "", stage, units)
+}
+
+/// Convert an [item table](crate::items) of a game on `stage` to `units`, as [apply] converts
+/// characters' positions and facing directions.
+pub fn apply_items(items: &StructArray, stage: u16, units: Units) -> Result<StructArray, String> {
+    // Its columns are flattened already, so named under `item` to match as nested ones would.
+    convert(items, "item", stage, units)
+}
+
+fn convert(array: &StructArray, prefix: &str, stage: u16, units: Units) -> Result<StructArray, String> {
+    let blast_zones = match units.positions {
+        PositionUnits::Game => None,
+        PositionUnits::Stage => Some(
+            stages::geometry(stage)
+                .ok_or_else(|| format!("no geometry for stage {stage}"))?
+                .blast_zones,
+        ),
+    };
+
+    Ok(map_leaves(array, prefix, &|name, array| {
+        // Scale `value` from `[low, high]` onto `[-1, 1]`.
+        let scale = |low: f32, high: f32| -> Option<Box<dyn Array>> {
+            let array = array.as_any().downcast_ref::<PrimitiveArray<f32>>()?;
+            Some(unary(array, |v| (2.0 * v - low - high) / (high - low), DataType::Float32).boxed())
+        };
+        match (blast_zones, units.facing) {
+            (Some(b), _) if name.ends_with(".position.x") => scale(b.left, b.right),
+            (Some(b), _) if name.ends_with(".position.y") => scale(b.bottom, b.top),
+            (_, FacingUnits::Bool) if name.ends_with(".direction") => {
+                let array = array.as_any().downcast_ref::<PrimitiveArray<f32>>()?;
+                let facing: BooleanArray = array.iter().map(|v| v.map(|&d| d > 0.0)).collect();
+                Some(facing.boxed())
+            }
+            _ => None,
+        }
+    }))
+}
+
+/// Rebuild nested struct arrays with each leaf column replaced by `f(name, leaf)`, when that's
+/// `Some`. Leaves are named as by [export::flatten](crate::export::flatten).
+fn map_leaves(
+    array: &StructArray,
+    prefix: &str,
+    f: &dyn Fn(&str, &dyn Array) -> Option<Box<dyn Array>>,
+) -> StructArray {
+    let (fields, values): (Vec<Field>, Vec<Box<dyn Array>>) = array
+        .fields()
+        .iter()
+        .zip(array.values())
+        .map(|(field, values)| {
+            let name = match prefix {
+                "" => field.name.clone(),
+                _ => format!("{prefix}.{}", field.name),
+            };
+            let values = match values.as_any().downcast_ref::<StructArray>() {
+                Some(inner) => map_leaves(inner, &name, f).boxed(),
+                None => f(&name, values.as_ref()).unwrap_or_else(|| values.clone()),
+            };
+            let field = Field::new(field.name.clone(), values.data_type().clone(), field.is_nullable);
+            (field, values)
+        })
+        .unzip();
+    StructArray::new(DataType::Struct(fields), values, array.validity().cloned())
+}
@@ -0,0 +1,162 @@
+//! Warnings about fields a replay is too old to have
+//!
+//! Slippi has added fields to its events over the years, so older replays lack columns (and the
+//! stats computed from them) that newer ones have. Rather than leaving such columns out of a
+//! column selection, or filling the stats derived from them with zeros, they're returned as nulls,
+//! and each game records a warning per missing field: the field, the Slippi version that introduced
+//! it, and the version that recorded the replay.
+//!
+//! Which frame columns a replay would have if it were recent is found by building an empty frame
+//! of the latest supported version for the same ports.
+
+use arrow2::array::{new_null_array, Array, StructArray};
+use arrow2::datatypes::{DataType, Field};
+use peppi::frame::PortOccupancy;
+use peppi::frame::immutable::Frame;
+use peppi::frame::mutable::Frame as MutableFrame;
+use peppi::game::Port;
+use peppi::io::slippi::{MAX_SUPPORTED_VERSION, Version};
+use serde::Serialize;
+
+use crate::{dictionary, profiles};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Warning {
+    /// Dotted path of the missing column (`hits.<column>` for the hit table)
+    pub field: String,
+    /// Slippi version that introduced the field
+    pub required_version: String,
+    /// Slippi version that recorded the replay
+    pub replay_version: String,
+}
+
+pub fn version_string(v: Version) -> String {
+    format!("{}.{}.{}", v.0, v.1, v.2)
+}
+
+fn parse_version(s: &str) -> Version {
+    let mut parts = s.split('.').map(|p| p.parse().unwrap_or(0));
+    let mut next = || parts.next().unwrap_or(0);
+    Version(next(), next(), next())
+}
+
+/// Add null columns for the fields matching `patterns` that a replay recorded by Slippi `version`
+/// lacks, returning the frames with them and a warning for each.
+pub fn fill(frames: &StructArray, version: Version, patterns: &[String]) -> (StructArray, Vec<Warning>) {
+    let ports = port_occupancy(frames);
+    let empty: Frame = MutableFrame::with_capacity(0, MAX_SUPPORTED_VERSION, &ports).into();
+    let latest = empty.into_struct_array(MAX_SUPPORTED_VERSION, &ports);
+    let DataType::Struct(template) = latest.data_type() else {
+        return (frames.clone(), Vec::new());
+    };
+
+    let mut missing = Vec::new();
+    let filled = fill_in(frames, template, &mut Vec::new(), patterns, &mut missing);
+    let warnings = missing
+        .into_iter()
+        .map(|field| Warning {
+            required_version: dictionary::introduced_in(&field).to_string(),
+            replay_version: version_string(version),
+            field,
+        })
+        .collect();
+    (filled, warnings)
+}
+
+fn fill_in<'a>(
+    array: &'a StructArray,
+    template: &'a [Field],
+    path: &mut Vec<&'a str>,
+    patterns: &[String],
+    missing: &mut Vec<String>,
+) -> StructArray {
+    let present = |name: &str| array.fields().iter().position(|f| f.name == name);
+    let mut fields = Vec::new();
+    let mut values = Vec::new();
+    for field in template {
+        path.push(&field.name);
+        match present(&field.name) {
+            Some(i) => {
+                let column = &array.values()[i];
+                let column = match (column.as_any().downcast_ref::<StructArray>(), &field.data_type) {
+                    (Some(inner), DataType::Struct(inner_template)) => {
+                        fill_in(inner, inner_template, path, patterns, missing).boxed()
+                    }
+                    _ => column.clone(),
+                };
+                fields.push(Field::new(field.name.clone(), column.data_type().clone(), array.fields()[i].is_nullable));
+                values.push(column);
+            }
+            // Only fields that are selected, at least in part, are worth a column of nulls.
+            None if patterns.iter().any(|p| profiles::matches(p, path).is_some()) => {
+                leaves(&field.data_type, path, patterns, missing);
+                fields.push(Field::new(field.name.clone(), field.data_type.clone(), true));
+                values.push(new_null_array(field.data_type.clone(), array.len()));
+            }
+            None => {}
+        }
+        path.pop();
+    }
+    // Columns the template doesn't know about, such as derived features, are kept after the rest.
+    for (field, column) in array.fields().iter().zip(array.values()) {
+        if !template.iter().any(|f| f.name == field.name) {
+            fields.push(field.clone());
+            values.push(column.clone());
+        }
+    }
+    StructArray::new(DataType::Struct(fields), values, array.validity().cloned())
+}
+
+/// Record the selected leaves at or below `path` as missing.
+fn leaves<'a>(data_type: &'a DataType, path: &mut Vec<&'a str>, patterns: &[String], missing: &mut Vec<String>) {
+    match data_type {
+        DataType::Struct(fields) => {
+            for field in fields {
+                path.push(&field.name);
+                leaves(&field.data_type, path, patterns, missing);
+                path.pop();
+            }
+        }
+        _ if patterns.iter().any(|p| profiles::matches(p, path) == Some(true)) => missing.push(path.join(".")),
+        _ => {}
+    }
+}
+
+/// Ports (and whether they have followers) of frames as laid out by peppi, from their field names.
+fn port_occupancy(frames: &StructArray) -> Vec<PortOccupancy> {
+    let ports = frames
+        .fields()
+        .iter()
+        .zip(frames.values())
+        .find(|(f, _)| f.name == "ports")
+        .and_then(|(_, ports)| ports.as_any().downcast_ref::<StructArray>());
+    let Some(ports) = ports else {
+        return Vec::new();
+    };
+    ports
+        .fields()
+        .iter()
+        .filter_map(|field| {
+            let index: u8 = field.name.strip_prefix('P')?.parse().ok()?;
+            let port = Port::try_from(index.checked_sub(1)?).ok()?;
+            let follower = match &field.data_type {
+                DataType::Struct(fields) => fields.iter().any(|f| f.name == "follower"),
+                _ => false,
+            };
+            Some(PortOccupancy { port, follower })
+        })
+        .collect()
+}
+
+/// Warnings for the hit table columns a replay recorded by Slippi `version` can't fill.
+pub fn hits(version: Version) -> Vec<Warning> {
+    dictionary::hits()
+        .into_iter()
+        .filter(|column| parse_version(column.introduced) > version)
+        .map(|column| Warning {
+            field: format!("hits.{}", column.name),
+            required_version: column.introduced.to_string(),
+            replay_version: version_string(version),
+        })
+        .collect()
+}
@@ -0,0 +1,150 @@
+//! Per-frame win probability
+//!
+//! Broadcast graphics show who's winning as the game goes, and analysts chart how a game swung.
+//! When enabled, exports gain a `win_probability` struct column with one probability per port (e.g.
+//! `win_probability.P1`), from a logistic model over each player's stocks, percent, and character,
+//! on the game's stage.
+//!
+//! Every player with stocks left gets a score, `stocks * w_stocks + percent * w_percent +
+//! character`, and their probability is their share of the softmax of the scores, which in singles
+//! is the logistic function of the difference between the two. Players out of stocks have
+//! probability 0. The stock and percent weights can be overridden per stage, since stocks are worth
+//! more percent on large stages. Teams aren't modeled: each player is scored on their own.
+//!
+//! The built-in coefficients are a rough hand-tuned starting point. Fitted ones are loaded from a
+//! JSON file of the same shape as [Model], e.g.
+//! `{"stocks": 1.1, "percent": -0.012, "characters": {"2": 0.2}, "stages": {"32": {"stocks": 1.3, "percent": -0.01}}}`,
+//! with characters by internal ID and stages by stage ID.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use arrow2::array::{Array, PrimitiveArray, StructArray};
+use arrow2::datatypes::{DataType, Field};
+use peppi::frame::immutable::Frame;
+use serde::{Deserialize, Serialize};
+
+use crate::features;
+
+/// Version of the win probability computation, bumped whenever a change alters its values.
+pub const VERSION: u32 = 1;
+
+/// Weights of a player's stocks and percent in their score.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Weights {
+    pub stocks: f64,
+    pub percent: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Model {
+    #[serde(flatten)]
+    pub weights: Weights,
+    /// Score added for playing a character, by internal character ID
+    #[serde(default)]
+    pub characters: BTreeMap<u8, f64>,
+    /// Weights used instead of the default ones on a stage, by stage ID
+    #[serde(default)]
+    pub stages: BTreeMap<u16, Weights>,
+}
+
+impl Default for Model {
+    fn default() -> Self {
+        Model {
+            weights: Weights {
+                stocks: 1.0,
+                percent: -0.015,
+            },
+            characters: BTreeMap::new(),
+            stages: BTreeMap::new(),
+        }
+    }
+}
+
+impl Model {
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let file = io::BufReader::new(fs::File::open(path)?);
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    fn score(&self, weights: Weights, stocks: u8, percent: f32, character: u8) -> f64 {
+        let bias = self.characters.get(&character).copied().unwrap_or(0.0);
+        weights.stocks * stocks as f64 + weights.percent * percent as f64 + bias
+    }
+
+    /// Win probability of each port on every frame of `frames` on `stage`, by port and then frame
+    /// (`None` on frames where nobody has stocks left).
+    pub fn score_frames(&self, frames: &Frame, stage: u16) -> Vec<Vec<Option<f32>>> {
+        let weights = self.stages.get(&stage).copied().unwrap_or(self.weights);
+        let len = frames.id.len();
+        let mut probabilities: Vec<Vec<_>> = frames
+            .ports
+            .iter()
+            .map(|_| Vec::with_capacity(len))
+            .collect();
+        let mut scores = vec![None; frames.ports.len()];
+        for i in 0..len {
+            for (score, port) in scores.iter_mut().zip(&frames.ports) {
+                let post = &port.leader.post;
+                let stocks = post.stocks.value(i);
+                *score = (stocks > 0).then(|| {
+                    self.score(
+                        weights,
+                        stocks,
+                        post.percent.value(i),
+                        post.character.value(i),
+                    )
+                });
+            }
+            // Softmax, shifted by the highest score so large ones can't overflow
+            let max = scores.iter().flatten().copied().reduce(f64::max);
+            let total: f64 = scores
+                .iter()
+                .flatten()
+                .map(|s| (s - max.unwrap_or(0.0)).exp())
+                .sum();
+            for (column, score) in probabilities.iter_mut().zip(&scores) {
+                column.push(max.map(|max| score.map_or(0.0, |s| ((s - max).exp() / total) as f32)));
+            }
+        }
+        probabilities
+    }
+}
+
+/// Append the `win_probability` column to `exported`, scored by `model` from the game's raw
+/// `frames` on `stage`.
+pub fn append(
+    exported: &StructArray,
+    frames: &Frame,
+    stage: u16,
+    model: &Model,
+) -> Result<StructArray, String> {
+    let columns = frames
+        .ports
+        .iter()
+        .zip(model.score_frames(frames, stage))
+        .map(|(port, values)| {
+            (
+                format!("{:?}", port.port),
+                PrimitiveArray::<f32>::from(values).boxed(),
+            )
+        })
+        .collect();
+    let column = features::struct_array(columns)?;
+
+    let mut fields = exported.fields().to_vec();
+    let mut values = exported.values().to_vec();
+    fields.push(Field::new(
+        "win_probability",
+        column.data_type().clone(),
+        false,
+    ));
+    values.push(column.boxed());
+    Ok(StructArray::new(
+        DataType::Struct(fields),
+        values,
+        exported.validity().cloned(),
+    ))
+}
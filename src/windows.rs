@@ -0,0 +1,126 @@
+//! Event-aligned windows of frames
+//!
+//! Sequence models are often trained on short clips around events of interest rather than on whole
+//! games. A window export writes one row per event, holding the event itself and, for every
+//! flattened frame column, a fixed-size list of that column's values from `before` frames before
+//! the event to `after` frames after it. Slots past either end of the game are null, so every row
+//! has the same shape.
+
+use std::fs;
+use std::ops::RangeInclusive;
+
+use arrow2::array::{Array, FixedSizeListArray, PrimitiveArray, StructArray};
+use arrow2::chunk::Chunk;
+use arrow2::compute::take::take;
+use arrow2::datatypes::{DataType, Field, Schema};
+use arrow2::error::Result;
+use arrow2::io::ipc::write::FileWriter;
+use peppi::frame::immutable::{Frame, Post};
+use peppi::game::Port;
+
+use crate::{export, grabs, hits, ipc};
+
+/// Tech action states (`Passive` through `PassiveCeil`).
+const TECH_STATES: RangeInclusive<u16> = 199..=204;
+
+/// The frame an event happened on, and the player it happened to.
+#[derive(Debug, Clone, Copy)]
+pub struct Anchor {
+    /// Row of the frame arrays
+    pub row: usize,
+    pub frame: i32,
+    pub port: Port,
+}
+
+/// Anchors of every event of a kind: `hit` (at the damage frame, for the victim), `grab` (at the
+/// first held frame, for the victim), `death` (at the frame a stock is lost), or `tech` (at the
+/// first frame of a tech, wall tech, or ceiling tech).
+pub fn anchors(frames: &Frame, event: &str) -> std::result::Result<Vec<Anchor>, String> {
+    let row = |frame: i32| (frame - frames.id.value(0)) as usize;
+    let anchors = match event {
+        "hit" => hits::detect(frames)
+            .into_iter()
+            .map(|h| Anchor {
+                row: h.index,
+                frame: h.frame,
+                port: h.victim,
+            })
+            .collect(),
+        "grab" => grabs::detect(frames)
+            .into_iter()
+            .map(|g| Anchor {
+                row: row(g.frame),
+                frame: g.frame,
+                port: g.victim,
+            })
+            .collect(),
+        "death" => starts(frames, |post, i| post.stocks.value(i) < post.stocks.value(i - 1)),
+        "tech" => starts(frames, |post, i| {
+            TECH_STATES.contains(&post.state.value(i)) && !TECH_STATES.contains(&post.state.value(i - 1))
+        }),
+        _ => return Err(format!("unknown event: {event}")),
+    };
+    Ok(anchors)
+}
+
+/// Anchors on every row after the first where `starts` holds for a port's leader, ordered by frame
+/// and then by port.
+fn starts(frames: &Frame, starts: impl Fn(&Post, usize) -> bool) -> Vec<Anchor> {
+    let mut anchors = Vec::new();
+    for row in 1..frames.id.len() {
+        for port in &frames.ports {
+            if starts(&port.leader.post, row) {
+                anchors.push(Anchor {
+                    row,
+                    frame: frames.id.value(row),
+                    port: port.port,
+                });
+            }
+        }
+    }
+    anchors
+}
+
+/// Write a window of `before` and `after` frames around every anchor as an Arrow IPC file.
+pub fn write(
+    file: &mut fs::File,
+    frames: &StructArray,
+    anchors: &[Anchor],
+    before: usize,
+    after: usize,
+    policy: export::FollowerPolicy,
+) -> Result<()> {
+    let size = before + after + 1;
+    // Row of each slot of each window, or null past either end of the game
+    let indices: PrimitiveArray<u32> = anchors
+        .iter()
+        .flat_map(|a| (0..size).map(move |i| (a.row + i).checked_sub(before)))
+        .map(|row| row.filter(|&r| r < frames.len()).map(|r| r as u32))
+        .collect();
+
+    let mut fields = vec![
+        Field::new("event_frame", DataType::Int32, false),
+        Field::new("port", DataType::UInt8, false),
+    ];
+    let mut columns: Vec<Box<dyn Array>> = vec![
+        PrimitiveArray::<i32>::from_vec(anchors.iter().map(|a| a.frame).collect()).boxed(),
+        PrimitiveArray::<u8>::from_vec(anchors.iter().map(|a| a.port as u8).collect()).boxed(),
+    ];
+    for (name, array) in export::flatten(frames, policy) {
+        // Per-frame lists (like items) would need a list of lists, so they're left out.
+        if matches!(array.data_type(), DataType::List(_) | DataType::LargeList(_)) {
+            continue;
+        }
+        let values = take(array.as_ref(), &indices)?;
+        let data_type = DataType::FixedSizeList(
+            Box::new(Field::new("item", array.data_type().clone(), true)),
+            size,
+        );
+        fields.push(Field::new(name, data_type.clone(), false));
+        columns.push(FixedSizeListArray::new(data_type, values, None).boxed());
+    }
+
+    let mut writer = FileWriter::try_new(file, Schema::from(fields), None, ipc::write_options())?;
+    writer.write(&Chunk::new(columns), None)?;
+    writer.finish()
+}